@@ -0,0 +1,246 @@
+use anyhow::{bail, Result};
+
+/// A piece of copied text kept by the daemon.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PasteBuffer {
+    pub name: String,
+    pub data: String,
+    /// Automatic buffers are named `bufferN` and dropped past the limit.
+    /// Buffers named with `-b` are kept until deleted.
+    pub automatic: bool,
+}
+
+impl PasteBuffer {
+    /// The start of the buffer on one line, with control characters escaped,
+    /// as `list-buffers` shows it.
+    pub fn sample(&self, max_chars: usize) -> String {
+        let mut sample = String::new();
+        for c in self.data.chars().take(max_chars) {
+            if c.is_control() {
+                sample.extend(c.escape_default());
+            } else {
+                sample.push(c);
+            }
+        }
+        sample
+    }
+}
+
+/// Paste buffers, newest first, like tmux's buffer stack.
+pub struct PasteBuffers {
+    buffers: Vec<PasteBuffer>,
+    next_index: u64,
+    limit: usize,
+}
+
+impl PasteBuffers {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            buffers: Vec::new(),
+            next_index: 0,
+            limit,
+        }
+    }
+
+    /// Change how many automatic buffers are kept, dropping any over it.
+    pub fn set_limit(&mut self, limit: usize) {
+        self.limit = limit;
+        self.trim();
+    }
+
+    /// Add an automatic buffer on top of the stack and return its name.
+    pub fn push(&mut self, data: String) -> String {
+        let name = loop {
+            let name = format!("buffer{}", self.next_index);
+            self.next_index += 1;
+            if self.find(&name).is_none() {
+                break name;
+            }
+        };
+        self.buffers.insert(
+            0,
+            PasteBuffer {
+                name: name.clone(),
+                data,
+                automatic: true,
+            },
+        );
+        self.trim();
+        name
+    }
+
+    /// Set a named buffer, replacing any buffer with that name, and move it
+    /// to the top of the stack.
+    pub fn set(&mut self, name: &str, data: String) {
+        if let Some(idx) = self.find(name) {
+            self.buffers.remove(idx);
+        }
+        self.buffers.insert(
+            0,
+            PasteBuffer {
+                name: name.to_string(),
+                data,
+                automatic: false,
+            },
+        );
+    }
+
+    /// Append to a buffer, or to the newest one when no name is given.
+    /// Appending to a name that doesn't exist creates it.
+    pub fn append(&mut self, name: Option<&str>, data: &str) {
+        let idx = match name {
+            Some(name) => self.find(name),
+            None => (!self.buffers.is_empty()).then_some(0),
+        };
+        match (idx, name) {
+            (Some(idx), _) => self.buffers[idx].data.push_str(data),
+            (None, Some(name)) => self.set(name, data.to_string()),
+            (None, None) => {
+                self.push(data.to_string());
+            }
+        }
+    }
+
+    /// Rename a buffer, or the newest one when no name is given. The
+    /// buffer stops being automatic.
+    pub fn rename(&mut self, name: Option<&str>, new_name: &str) -> Result<()> {
+        let idx = self.index(name)?;
+        if let Some(existing) = self.find(new_name) {
+            if existing != idx {
+                bail!("buffer {} already exists", new_name);
+            }
+        }
+        let buffer = &mut self.buffers[idx];
+        buffer.name = new_name.to_string();
+        buffer.automatic = false;
+        Ok(())
+    }
+
+    /// A buffer by name, or the newest one when no name is given.
+    pub fn get(&self, name: Option<&str>) -> Result<&PasteBuffer> {
+        Ok(&self.buffers[self.index(name)?])
+    }
+
+    /// Remove a buffer by name, or the newest one when no name is given.
+    pub fn delete(&mut self, name: Option<&str>) -> Result<PasteBuffer> {
+        let idx = self.index(name)?;
+        Ok(self.buffers.remove(idx))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &PasteBuffer> {
+        self.buffers.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.buffers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buffers.is_empty()
+    }
+
+    fn find(&self, name: &str) -> Option<usize> {
+        self.buffers.iter().position(|b| b.name == name)
+    }
+
+    fn index(&self, name: Option<&str>) -> Result<usize> {
+        match name {
+            Some(name) => self
+                .find(name)
+                .ok_or_else(|| anyhow::anyhow!("no buffer {}", name)),
+            None if self.buffers.is_empty() => bail!("no buffers"),
+            None => Ok(0),
+        }
+    }
+
+    /// Drop the oldest automatic buffers past the limit.
+    fn trim(&mut self) {
+        let mut automatic = 0;
+        let limit = self.limit;
+        self.buffers.retain(|b| {
+            if !b.automatic {
+                return true;
+            }
+            automatic += 1;
+            automatic <= limit
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(buffers: &PasteBuffers) -> Vec<&str> {
+        buffers.iter().map(|b| b.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_push_names_and_orders_newest_first() {
+        let mut buffers = PasteBuffers::new(50);
+        assert_eq!(buffers.push("a".into()), "buffer0");
+        assert_eq!(buffers.push("b".into()), "buffer1");
+        assert_eq!(names(&buffers), vec!["buffer1", "buffer0"]);
+        assert_eq!(buffers.get(None).unwrap().data, "b");
+        assert_eq!(buffers.get(Some("buffer0")).unwrap().data, "a");
+    }
+
+    #[test]
+    fn test_limit_drops_oldest_automatic_buffers_only() {
+        let mut buffers = PasteBuffers::new(2);
+        buffers.set("keep", "k".into());
+        buffers.push("a".into());
+        buffers.push("b".into());
+        buffers.push("c".into());
+        assert_eq!(names(&buffers), vec!["buffer2", "buffer1", "keep"]);
+
+        buffers.set_limit(1);
+        assert_eq!(names(&buffers), vec!["buffer2", "keep"]);
+    }
+
+    #[test]
+    fn test_set_replaces_and_moves_to_top() {
+        let mut buffers = PasteBuffers::new(50);
+        buffers.set("x", "one".into());
+        buffers.push("a".into());
+        buffers.set("x", "two".into());
+        assert_eq!(names(&buffers), vec!["x", "buffer0"]);
+        assert_eq!(buffers.get(Some("x")).unwrap().data, "two");
+    }
+
+    #[test]
+    fn test_append_rename_and_delete() {
+        let mut buffers = PasteBuffers::new(50);
+        buffers.append(None, "a");
+        buffers.append(None, "b");
+        assert_eq!(buffers.get(None).unwrap().data, "ab");
+        buffers.append(Some("new"), "c");
+        assert_eq!(buffers.get(None).unwrap().name, "new");
+
+        buffers.rename(Some("buffer0"), "kept").unwrap();
+        assert!(!buffers.get(Some("kept")).unwrap().automatic);
+        assert!(buffers.rename(Some("kept"), "new").is_err());
+
+        assert_eq!(buffers.delete(None).unwrap().name, "new");
+        assert!(buffers.delete(Some("missing")).is_err());
+        assert_eq!(buffers.len(), 1);
+    }
+
+    #[test]
+    fn test_push_skips_names_in_use() {
+        let mut buffers = PasteBuffers::new(50);
+        buffers.set("buffer0", "named".into());
+        assert_eq!(buffers.push("a".into()), "buffer1");
+    }
+
+    #[test]
+    fn test_sample_escapes_control_characters() {
+        let buffer = PasteBuffer {
+            name: "b".into(),
+            data: "one\ttwo\nthree".into(),
+            automatic: true,
+        };
+        assert_eq!(buffer.sample(50), "one\\ttwo\\nthree");
+        assert_eq!(buffer.sample(3), "one");
+    }
+}
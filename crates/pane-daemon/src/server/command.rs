@@ -33,6 +33,8 @@ pub enum Command {
         shell: Option<String>,
         /// Tab kind — defaults to Shell when absent.
         kind: Option<TabKind>,
+        /// Scrollback lines — defaults to the configured limit for the kind.
+        history_limit: Option<usize>,
     },
     KillWindow {
         target: Option<TargetWindow>,
@@ -58,6 +60,8 @@ pub enum Command {
         shell: Option<String>,
         /// Tab kind — defaults to Shell when absent.
         kind: Option<TabKind>,
+        /// Scrollback lines — defaults to the configured limit for the kind.
+        history_limit: Option<usize>,
     },
     KillPane {
         target: Option<TargetPane>,
@@ -83,6 +87,13 @@ pub enum Command {
         direction: ResizeDirection,
         amount: u16,
     },
+    /// Give the session a manual size, or with `adjust` go back to sizing
+    /// it to the attached clients.
+    ResizeWindow {
+        width: Option<u16>,
+        height: Option<u16>,
+        adjust: bool,
+    },
 
     // Workspace commands
     CloseWorkspace,
@@ -107,8 +118,40 @@ pub enum Command {
     },
     EqualizeLayout,
     ToggleSync,
+
+    // Paste buffers
+    SetBuffer {
+        name: Option<String>,
+        /// Rename the buffer instead of setting it.
+        new_name: Option<String>,
+        append: bool,
+        data: Option<String>,
+    },
+    ShowBuffer {
+        name: Option<String>,
+    },
+    ListBuffers,
+    DeleteBuffer {
+        name: Option<String>,
+    },
+    SaveBuffer {
+        name: Option<String>,
+        path: String,
+        append: bool,
+    },
+    LoadBuffer {
+        name: Option<String>,
+        path: String,
+    },
     PasteBuffer {
-        text: String,
+        name: Option<String>,
+        target: Option<TargetPane>,
+        /// Delete the buffer after pasting it.
+        delete: bool,
+        /// Wrap the paste in bracketed paste markers if the tab asked for them.
+        bracketed: bool,
+        /// What line feeds are replaced with — carriage return when unset.
+        separator: Option<String>,
     },
 
     // Layout presets
@@ -123,7 +166,20 @@ pub enum Command {
     NewFloat,
 
     // Client commands
-    DetachClient,
+    /// Detach the calling client, another client, or with `others` every
+    /// client but the target.
+    DetachClient {
+        target: Option<u64>,
+        others: bool,
+    },
+    ListClients,
+    /// Show each client's outbound queue.
+    ClientStats,
+    /// Move a client, the calling one by default, to another workspace.
+    SwitchClient {
+        client: Option<u64>,
+        workspace: WorkspaceTarget,
+    },
 
     SetSplitRatio { path: Vec<Side>, ratio: f64 },
 
@@ -150,6 +206,15 @@ pub enum TargetWindow {
     Index(usize),
 }
 
+/// Workspace a client switches to.
+#[derive(Clone, Debug, PartialEq)]
+pub enum WorkspaceTarget {
+    /// An index, or failing that a name
+    Named(String),
+    Next,
+    Previous,
+}
+
 /// Target specifier for a pane.
 #[derive(Clone, Debug, PartialEq)]
 pub enum TargetPane {
@@ -209,23 +274,7 @@ pub fn execute(
         Command::NewWorkspace { window_name, cwd } => {
             // Resolve cwd if provided
             let ws_cwd = cwd.as_ref().map(|path| {
-                let expanded = if path.starts_with("~/") || path == "~" {
-                    if let Some(home) = std::env::var_os("HOME") {
-                        let home = std::path::PathBuf::from(home);
-                        if path == "~" { home } else { home.join(&path[2..]) }
-                    } else {
-                        std::path::PathBuf::from(path)
-                    }
-                } else {
-                    std::path::PathBuf::from(path)
-                };
-                let resolved = if expanded.is_absolute() {
-                    expanded
-                } else if !state.workspaces.is_empty() {
-                    state.active_workspace().cwd.join(&expanded)
-                } else {
-                    std::env::current_dir().unwrap_or_else(|_| std::path::PathBuf::from("/")).join(&expanded)
-                };
+                let resolved = resolve_path(path, state);
                 resolved.canonicalize().unwrap_or(resolved)
             });
 
@@ -269,10 +318,82 @@ pub fn execute(
 
         Command::ReloadConfig => {
             state.config = pane_protocol::config::Config::load();
+            state.buffers.set_limit(state.config.behavior.buffer_limit);
             broadcast_layout(state, broadcast_tx);
             Ok(CommandResult::Ok("config reloaded".to_string()))
         }
 
+        Command::SetBuffer {
+            name,
+            new_name,
+            append,
+            data,
+        } => {
+            if let Some(new_name) = new_name {
+                state.buffers.rename(name.as_deref(), new_name)?;
+                return Ok(CommandResult::Ok(String::new()));
+            }
+            let Some(data) = data else {
+                bail!("no data specified");
+            };
+            match name {
+                _ if *append => state.buffers.append(name.as_deref(), data),
+                Some(name) => state.buffers.set(name, data.clone()),
+                None => {
+                    state.buffers.push(data.clone());
+                }
+            }
+            Ok(CommandResult::Ok(String::new()))
+        }
+
+        Command::ShowBuffer { name } => {
+            let buffer = state.buffers.get(name.as_deref())?;
+            Ok(CommandResult::Ok(buffer.data.clone()))
+        }
+
+        Command::ListBuffers => {
+            let lines: Vec<String> = state
+                .buffers
+                .iter()
+                .map(|b| format!("{}: {} bytes: \"{}\"", b.name, b.data.len(), b.sample(50)))
+                .collect();
+            Ok(CommandResult::Ok(lines.join("\n")))
+        }
+
+        Command::DeleteBuffer { name } => {
+            state.buffers.delete(name.as_deref())?;
+            Ok(CommandResult::Ok(String::new()))
+        }
+
+        Command::SaveBuffer { name, path, append } => {
+            use std::io::Write;
+            let data = state.buffers.get(name.as_deref())?.data.clone();
+            let path = resolve_path(path, state);
+            let mut file = std::fs::OpenOptions::new()
+                .create(true)
+                .write(true)
+                .append(*append)
+                .truncate(!*append)
+                .open(&path)
+                .map_err(|e| anyhow::anyhow!("{}: {}", path.display(), e))?;
+            file.write_all(data.as_bytes())?;
+            Ok(CommandResult::Ok(String::new()))
+        }
+
+        Command::LoadBuffer { name, path } => {
+            let path = resolve_path(path, state);
+            let bytes =
+                std::fs::read(&path).map_err(|e| anyhow::anyhow!("{}: {}", path.display(), e))?;
+            let data = String::from_utf8_lossy(&bytes).into_owned();
+            match name {
+                Some(name) => state.buffers.set(name, data),
+                None => {
+                    state.buffers.push(data);
+                }
+            }
+            Ok(CommandResult::Ok(String::new()))
+        }
+
         // All commands below require at least one workspace.
         _ if state.workspaces.is_empty() => {
             bail!("no workspace — open a project first");
@@ -283,12 +404,19 @@ pub fn execute(
             command,
             shell,
             kind,
+            history_limit,
             ..
         } => {
             let tab_kind = kind.clone().unwrap_or(TabKind::Shell);
             let (cols, rows) = state.active_window_pty_size();
-            let pane_id =
-                state.add_tab_to_active_group(tab_kind, command.clone(), shell.clone(), cols, rows)?;
+            let pane_id = state.add_tab_to_active_group(
+                tab_kind,
+                command.clone(),
+                shell.clone(),
+                *history_limit,
+                cols,
+                rows,
+            )?;
             if let Some(wname) = window_name {
                 let ws = state.active_workspace_mut();
                 if let Some(group) = ws.groups.get_mut(&ws.active_group) {
@@ -393,7 +521,13 @@ pub fn execute(
         }
 
         Command::SplitWindow {
-            horizontal, target, command, shell, kind, ..
+            horizontal,
+            target,
+            command,
+            shell,
+            kind,
+            history_limit,
+            ..
         } => {
             if let Some(target) = target {
                 let group_id = resolve_pane_to_group(target, state, id_map)?;
@@ -406,8 +540,15 @@ pub fn execute(
                 SplitDirection::Vertical
             };
             let (cols, rows) = state.active_window_pty_size();
-            let (new_group_id, new_pane_id) =
-                state.split_active_group(direction, tab_kind, command.clone(), shell.clone(), cols, rows)?;
+            let (new_group_id, new_pane_id) = state.split_active_group(
+                direction,
+                tab_kind,
+                command.clone(),
+                shell.clone(),
+                *history_limit,
+                cols,
+                rows,
+            )?;
             let pane_n = id_map.register_pane(new_pane_id);
             let win_n = id_map.register_window(new_group_id);
             broadcast_layout(state, broadcast_tx);
@@ -575,6 +716,25 @@ pub fn execute(
             Ok(CommandResult::LayoutChanged)
         }
 
+        Command::ResizeWindow {
+            width,
+            height,
+            adjust,
+        } => {
+            if *adjust {
+                // The daemon refits the session to its clients
+                state.manual_size = None;
+                return Ok(CommandResult::Ok(String::new()));
+            }
+            let (w, h) = state.last_size;
+            let size = (width.unwrap_or(w), height.unwrap_or(h));
+            state.manual_size = Some(size);
+            state.last_size = size;
+            state.resize_all_tabs(size.0, size.1);
+            broadcast_layout(state, broadcast_tx);
+            Ok(CommandResult::LayoutChanged)
+        }
+
         Command::CloseWorkspace => {
             state.close_workspace();
             let (w, h) = state.last_size;
@@ -677,13 +837,28 @@ pub fn execute(
             Ok(CommandResult::LayoutChanged)
         }
 
-        Command::PasteBuffer { text } => {
-            let bytes = text.as_bytes().to_vec();
-            if !bytes.is_empty() {
-                let ws = state.active_workspace_mut();
-                if let Some(group) = ws.groups.get_mut(&ws.active_group) {
-                    group.active_tab_mut().write_input(&bytes);
+        Command::PasteBuffer {
+            name,
+            target,
+            delete,
+            bracketed,
+            separator,
+        } => {
+            let data = state.buffers.get(name.as_deref())?.data.clone();
+            let (_group_id, pane_id) = resolve_pane_target(target.as_ref(), state, id_map)?;
+            if let Some(pane) = state.find_tab_mut(pane_id) {
+                let text = data.replace('\n', separator.as_deref().unwrap_or("\r"));
+                let bracketed = *bracketed && pane.screen().bracketed_paste();
+                if bracketed {
+                    pane.write_input(b"\x1b[200~");
                 }
+                pane.write_input(text.as_bytes());
+                if bracketed {
+                    pane.write_input(b"\x1b[201~");
+                }
+            }
+            if *delete {
+                state.buffers.delete(name.as_deref())?;
             }
             Ok(CommandResult::Ok(String::new()))
         }
@@ -798,6 +973,10 @@ pub fn execute(
             let cols = fw_w.saturating_sub(2);
             let rows = fw_h.saturating_sub(2);
             let ws_cwd = state.active_workspace().cwd.clone();
+            let history_limit = state
+                .config
+                .scrollback
+                .history_limit_for(&crate::window::TabKind::Shell);
             let pane = match crate::window::Tab::spawn_with_env(
                 pane_id,
                 crate::window::TabKind::Shell,
@@ -808,6 +987,7 @@ pub fn execute(
                 None,
                 Some(tmux_env),
                 Some(&ws_cwd),
+                history_limit,
             ) {
                 Ok(p) => p,
                 Err(e) => crate::window::Tab::spawn_error(
@@ -838,7 +1018,18 @@ pub fn execute(
             })
         }
 
-        Command::DetachClient => Ok(CommandResult::DetachRequested),
+        Command::DetachClient {
+            target: None,
+            others: false,
+        } => Ok(CommandResult::DetachRequested),
+
+        Command::DetachClient { .. }
+        | Command::ListClients
+        | Command::ClientStats
+        | Command::SwitchClient { .. } => {
+            // The daemon runs these, as they need the attached clients
+            bail!("no attached clients")
+        }
 
         Command::SetSplitRatio { path, ratio } => {
             let ws = state.active_workspace_mut();
@@ -877,6 +1068,33 @@ pub fn execute(
     }
 }
 
+/// Expand `~` and make a relative path relative to the active workspace.
+fn resolve_path(path: &str, state: &ServerState) -> std::path::PathBuf {
+    let expanded = if path.starts_with("~/") || path == "~" {
+        if let Some(home) = std::env::var_os("HOME") {
+            let home = std::path::PathBuf::from(home);
+            if path == "~" {
+                home
+            } else {
+                home.join(&path[2..])
+            }
+        } else {
+            std::path::PathBuf::from(path)
+        }
+    } else {
+        std::path::PathBuf::from(path)
+    };
+    if expanded.is_absolute() {
+        expanded
+    } else if !state.workspaces.is_empty() {
+        state.active_workspace().cwd.join(&expanded)
+    } else {
+        std::env::current_dir()
+            .unwrap_or_else(|_| std::path::PathBuf::from("/"))
+            .join(&expanded)
+    }
+}
+
 /// Broadcast a layout update to all connected clients.
 fn broadcast_layout(state: &ServerState, broadcast_tx: &broadcast::Sender<ServerResponse>) {
     let render_state = render_state_from_server(state);
@@ -1068,6 +1286,11 @@ mod tests {
             last_size: (120, 40),
             next_pane_number: 1,
             drag_state: None,
+            terminal_focused: true,
+            focused_tab: None,
+            clipboard_reads: HashMap::new(),
+            buffers: crate::server::buffers::PasteBuffers::new(50),
+            manual_size: None,
         };
         let mut id_map = IdMap::new();
         id_map.register_window(group_id);
@@ -1123,6 +1346,11 @@ mod tests {
             last_size: (120, 40),
             next_pane_number: 2,
             drag_state: None,
+            terminal_focused: true,
+            focused_tab: None,
+            clipboard_reads: HashMap::new(),
+            buffers: crate::server::buffers::PasteBuffers::new(50),
+            manual_size: None,
         };
         let mut id_map = IdMap::new();
         id_map.register_window(gid1);
@@ -1693,6 +1921,34 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_execute_resize_window() {
+        let (mut state, mut id_map, broadcast_tx, gid1, _gid2) = make_split_state();
+        let cmd = Command::ResizeWindow {
+            width: Some(200),
+            height: None,
+            adjust: false,
+        };
+        let result = execute(&cmd, &mut state, &mut id_map, &broadcast_tx).unwrap();
+        assert!(matches!(result, CommandResult::LayoutChanged));
+        assert_eq!(state.manual_size, Some((200, 40)));
+        assert_eq!(state.last_size, (200, 40));
+        assert_eq!(
+            state.window_size().0,
+            pane_protocol::config::WindowSize::Manual
+        );
+        let tab = state.active_workspace().groups[&gid1].active_tab();
+        assert_eq!(tab.screen().size().1, 96);
+
+        let cmd = Command::ResizeWindow {
+            width: None,
+            height: None,
+            adjust: true,
+        };
+        execute(&cmd, &mut state, &mut id_map, &broadcast_tx).unwrap();
+        assert_eq!(state.manual_size, None);
+    }
+
     #[test]
     fn test_execute_resize_pane_left() {
         let (mut state, mut id_map, broadcast_tx, _gid1, _gid2) = make_split_state();
@@ -1799,6 +2055,7 @@ mod tests {
             command: None,
             shell: None,
             kind: None,
+            history_limit: None,
         };
         let result = execute(&cmd, &mut state, &mut id_map, &broadcast_tx).unwrap();
         match result {
@@ -1823,6 +2080,7 @@ mod tests {
             command: None,
             shell: None,
             kind: None,
+            history_limit: None,
         };
         execute(&cmd, &mut state, &mut id_map, &broadcast_tx).unwrap();
         assert_eq!(state.active_workspace().groups.len(), 2);
@@ -1839,6 +2097,7 @@ mod tests {
             command: None,
             shell: None,
             kind: None,
+            history_limit: None,
         };
         let result = execute(&cmd, &mut state, &mut id_map, &broadcast_tx).unwrap();
         match result {
@@ -1862,6 +2121,7 @@ mod tests {
             command: None,
             shell: None,
             kind: None,
+            history_limit: None,
         };
         execute(&cmd, &mut state, &mut id_map, &broadcast_tx).unwrap();
         let ws = state.active_workspace();
@@ -1869,24 +2129,121 @@ mod tests {
         assert_eq!(group.name, Some("my-named-window".to_string()));
     }
 
+    fn paste(name: Option<&str>, delete: bool) -> Command {
+        Command::PasteBuffer {
+            name: name.map(str::to_string),
+            target: None,
+            delete,
+            bracketed: false,
+            separator: None,
+        }
+    }
+
+    fn ok_output(result: Result<CommandResult>) -> String {
+        match result {
+            Ok(CommandResult::Ok(output)) => output,
+            _ => panic!("expected output"),
+        }
+    }
+
     #[test]
     fn test_execute_paste_buffer() {
         let (mut state, mut id_map, broadcast_tx, _rx) = make_test_state();
-        let cmd = Command::PasteBuffer {
-            text: "hello world".to_string(),
-        };
-        let result = execute(&cmd, &mut state, &mut id_map, &broadcast_tx).unwrap();
+        state.buffers.push("hello world".to_string());
+        let result = execute(&paste(None, false), &mut state, &mut id_map, &broadcast_tx).unwrap();
         assert!(matches!(result, CommandResult::Ok(_)));
+        assert_eq!(state.buffers.len(), 1);
+
+        execute(
+            &paste(Some("buffer0"), true),
+            &mut state,
+            &mut id_map,
+            &broadcast_tx,
+        )
+        .unwrap();
+        assert!(state.buffers.is_empty());
     }
 
     #[test]
     fn test_execute_paste_buffer_empty() {
         let (mut state, mut id_map, broadcast_tx, _rx) = make_test_state();
-        let cmd = Command::PasteBuffer {
-            text: String::new(),
+        assert!(execute(&paste(None, false), &mut state, &mut id_map, &broadcast_tx).is_err());
+        assert!(execute(
+            &paste(Some("nope"), false),
+            &mut state,
+            &mut id_map,
+            &broadcast_tx
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_execute_set_show_and_list_buffers() {
+        let (mut state, mut id_map, broadcast_tx, _rx) = make_test_state();
+        for cmd in [
+            "set-buffer first",
+            "setb -b notes hello",
+            "setb -a -b notes \" there\"",
+        ] {
+            let cmd = crate::server::command_parser::parse(cmd).unwrap();
+            execute(&cmd, &mut state, &mut id_map, &broadcast_tx).unwrap();
+        }
+        let show = Command::ShowBuffer {
+            name: Some("notes".to_string()),
         };
-        let result = execute(&cmd, &mut state, &mut id_map, &broadcast_tx).unwrap();
-        assert!(matches!(result, CommandResult::Ok(_)));
+        assert_eq!(
+            ok_output(execute(&show, &mut state, &mut id_map, &broadcast_tx)),
+            "hello there"
+        );
+        assert_eq!(
+            ok_output(execute(
+                &Command::ListBuffers,
+                &mut state,
+                &mut id_map,
+                &broadcast_tx
+            )),
+            "notes: 11 bytes: \"hello there\"\nbuffer0: 5 bytes: \"first\""
+        );
+
+        let delete = Command::DeleteBuffer { name: None };
+        execute(&delete, &mut state, &mut id_map, &broadcast_tx).unwrap();
+        assert_eq!(state.buffers.get(None).unwrap().name, "buffer0");
+    }
+
+    #[test]
+    fn test_execute_save_and_load_buffer() {
+        let (mut state, mut id_map, broadcast_tx, _rx) = make_test_state();
+        let path = std::env::temp_dir().join(format!("pane-buffer-test-{}", std::process::id()));
+        let path_str = path.to_string_lossy().into_owned();
+        state.buffers.push("saved text".to_string());
+
+        let save = Command::SaveBuffer {
+            name: None,
+            path: path_str.clone(),
+            append: false,
+        };
+        execute(&save, &mut state, &mut id_map, &broadcast_tx).unwrap();
+        let append = Command::SaveBuffer {
+            name: None,
+            path: path_str.clone(),
+            append: true,
+        };
+        execute(&append, &mut state, &mut id_map, &broadcast_tx).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "saved textsaved text"
+        );
+
+        let load = Command::LoadBuffer {
+            name: Some("loaded".to_string()),
+            path: path_str,
+        };
+        execute(&load, &mut state, &mut id_map, &broadcast_tx).unwrap();
+        assert_eq!(
+            state.buffers.get(None).unwrap().data,
+            "saved textsaved text"
+        );
+        let _ = std::fs::remove_file(&path);
     }
 
     #[test]
@@ -2215,7 +2572,10 @@ mod tests {
     #[test]
     fn test_execute_detach_client() {
         let (mut state, mut id_map, broadcast_tx, _rx) = make_test_state();
-        let cmd = Command::DetachClient;
+        let cmd = Command::DetachClient {
+            target: None,
+            others: false,
+        };
         let result = execute(&cmd, &mut state, &mut id_map, &broadcast_tx).unwrap();
         assert!(matches!(result, CommandResult::DetachRequested));
     }
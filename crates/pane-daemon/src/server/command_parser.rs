@@ -39,6 +39,7 @@ pub fn parse(input: &str) -> Result<Command> {
         "send-keys" | "send" => parse_send_keys(args),
         "select-layout" => parse_select_layout(args),
         "resize-pane" | "resizep" => parse_resize_pane(args),
+        "resize-window" | "resizew" => parse_resize_window(args),
         "display-message" | "display" => parse_display_message(args),
         "close-workspace" => Ok(Command::CloseWorkspace),
         "select-workspace" => parse_select_workspace(args),
@@ -49,7 +50,16 @@ pub fn parse(input: &str) -> Result<Command> {
         "equalize-layout" | "equalize" => Ok(Command::EqualizeLayout),
         "toggle-sync" => Ok(Command::ToggleSync),
         "paste-buffer" | "pasteb" => parse_paste_buffer(args),
-        "detach-client" | "detach" => Ok(Command::DetachClient),
+        "set-buffer" | "setb" => parse_set_buffer(args),
+        "show-buffer" | "showb" => parse_show_buffer(args),
+        "list-buffers" | "lsb" => Ok(Command::ListBuffers),
+        "delete-buffer" | "deleteb" => parse_delete_buffer(args),
+        "save-buffer" | "saveb" => parse_save_buffer(args),
+        "load-buffer" | "loadb" => parse_load_buffer(args),
+        "detach-client" | "detach" => parse_detach_client(args),
+        "list-clients" | "lsc" => Ok(Command::ListClients),
+        "client-stats" => Ok(Command::ClientStats),
+        "switch-client" | "switchc" => parse_switch_client(args),
         "toggle-float" | "float" => Ok(Command::ToggleFloat),
         "new-float" => Ok(Command::NewFloat),
         "maximize-focused" | "maximize" => Ok(Command::MaximizeFocused),
@@ -228,12 +238,19 @@ fn parse_kind(s: &str) -> Result<TabKind> {
     }
 }
 
+/// Parse a `-H` value into a scrollback line count.
+fn parse_history_limit(s: &str) -> Result<usize> {
+    s.parse()
+        .map_err(|_| anyhow::anyhow!("invalid history limit: {s}"))
+}
+
 fn parse_new_window(args: &[String]) -> Result<Command> {
     let mut target_session = None;
     let mut window_name = None;
     let mut command = None;
     let mut shell = None;
     let mut kind = None;
+    let mut history_limit = None;
     let mut i = 0;
     while i < args.len() {
         match args[i].as_str() {
@@ -257,6 +274,10 @@ fn parse_new_window(args: &[String]) -> Result<Command> {
                 kind = Some(parse_kind(&args[i + 1])?);
                 i += 2;
             }
+            "-H" if i + 1 < args.len() => {
+                history_limit = Some(parse_history_limit(&args[i + 1])?);
+                i += 2;
+            }
             "-P" | "-F" => {
                 i += 1;
                 if args.get(i).map(|a| !a.starts_with('-')).unwrap_or(false) {
@@ -274,6 +295,7 @@ fn parse_new_window(args: &[String]) -> Result<Command> {
         command,
         shell,
         kind,
+        history_limit,
     })
 }
 
@@ -309,6 +331,7 @@ fn parse_split_window(args: &[String]) -> Result<Command> {
     let mut command = None;
     let mut shell = None;
     let mut kind = None;
+    let mut history_limit = None;
     let mut i = 0;
     while i < args.len() {
         match args[i].as_str() {
@@ -336,6 +359,10 @@ fn parse_split_window(args: &[String]) -> Result<Command> {
                 kind = Some(parse_kind(&args[i + 1])?);
                 i += 2;
             }
+            "-H" if i + 1 < args.len() => {
+                history_limit = Some(parse_history_limit(&args[i + 1])?);
+                i += 2;
+            }
             "-l" if i + 1 < args.len() => {
                 let val = &args[i + 1];
                 if let Some(pct) = val.strip_suffix('%') {
@@ -364,6 +391,7 @@ fn parse_split_window(args: &[String]) -> Result<Command> {
         command,
         shell,
         kind,
+        history_limit,
     })
 }
 
@@ -518,6 +546,30 @@ fn parse_resize_pane(args: &[String]) -> Result<Command> {
     })
 }
 
+fn parse_resize_window(args: &[String]) -> Result<Command> {
+    let flags = Flags::parse("resize-window", args, "xy")?;
+    let dimension = |flag: char| {
+        flags
+            .value(flag)
+            .map(|v| match v.parse::<u16>() {
+                Ok(n) if n > 0 => Ok(n),
+                _ => Err(anyhow::anyhow!("invalid size: {}", v)),
+            })
+            .transpose()
+    };
+    let width = dimension('x')?;
+    let height = dimension('y')?;
+    let adjust = flags.has('A');
+    if !adjust && width.is_none() && height.is_none() {
+        bail!("resize-window requires -x, -y or -A");
+    }
+    Ok(Command::ResizeWindow {
+        width,
+        height,
+        adjust,
+    })
+}
+
 fn parse_display_message(args: &[String]) -> Result<Command> {
     let mut to_stdout = false;
     let mut msg_parts = Vec::new();
@@ -551,6 +603,37 @@ fn parse_select_workspace(args: &[String]) -> Result<Command> {
     Ok(Command::SelectWorkspaceByIndex { index })
 }
 
+fn parse_client_id(id: &str) -> Result<u64> {
+    id.parse()
+        .map_err(|_| anyhow::anyhow!("invalid client: {}", id))
+}
+
+fn parse_detach_client(args: &[String]) -> Result<Command> {
+    let flags = Flags::parse("detach-client", args, "t")?;
+    Ok(Command::DetachClient {
+        target: flags.value('t').map(|t| parse_client_id(&t)).transpose()?,
+        others: flags.has('a'),
+    })
+}
+
+fn parse_switch_client(args: &[String]) -> Result<Command> {
+    let flags = Flags::parse("switch-client", args, "ct")?;
+    let workspace = if flags.has('n') {
+        WorkspaceTarget::Next
+    } else if flags.has('p') {
+        WorkspaceTarget::Previous
+    } else {
+        let target = flags
+            .value('t')
+            .ok_or_else(|| anyhow::anyhow!("switch-client requires -t, -n or -p"))?;
+        WorkspaceTarget::Named(target)
+    };
+    Ok(Command::SwitchClient {
+        client: flags.value('c').map(|c| parse_client_id(&c)).transpose()?,
+        workspace,
+    })
+}
+
 fn parse_move_tab(args: &[String]) -> Result<Command> {
     let mut direction = None;
     for arg in args {
@@ -567,9 +650,140 @@ fn parse_move_tab(args: &[String]) -> Result<Command> {
     Ok(Command::MoveTab { direction })
 }
 
+/// Flags parsed getopt-style: boolean flags, which may be combined as in
+/// `-dp`, flags taking a value, and the arguments after the last flag.
+struct Flags {
+    set: Vec<char>,
+    values: Vec<(char, String)>,
+    rest: Vec<String>,
+}
+
+impl Flags {
+    fn parse(cmd: &str, args: &[String], with_value: &str) -> Result<Self> {
+        let mut flags = Flags {
+            set: Vec::new(),
+            values: Vec::new(),
+            rest: Vec::new(),
+        };
+        let mut i = 0;
+        while i < args.len() {
+            let arg = &args[i];
+            if arg == "--" {
+                i += 1;
+                break;
+            }
+            let Some(letters) = arg.strip_prefix('-').filter(|l| !l.is_empty()) else {
+                break;
+            };
+            for (pos, c) in letters.char_indices() {
+                if with_value.contains(c) {
+                    let inline = &letters[pos + c.len_utf8()..];
+                    let value = if !inline.is_empty() {
+                        inline.to_string()
+                    } else if i + 1 < args.len() {
+                        i += 1;
+                        args[i].clone()
+                    } else {
+                        bail!("{} -{} requires a value", cmd, c);
+                    };
+                    flags.values.push((c, value));
+                    break;
+                }
+                flags.set.push(c);
+            }
+            i += 1;
+        }
+        flags.rest = args[i..].to_vec();
+        Ok(flags)
+    }
+
+    fn has(&self, flag: char) -> bool {
+        self.set.contains(&flag)
+    }
+
+    fn value(&self, flag: char) -> Option<String> {
+        self.values
+            .iter()
+            .rev()
+            .find(|(c, _)| *c == flag)
+            .map(|(_, v)| v.clone())
+    }
+}
+
 fn parse_paste_buffer(args: &[String]) -> Result<Command> {
-    let text = args.join(" ");
-    Ok(Command::PasteBuffer { text })
+    let flags = Flags::parse("paste-buffer", args, "bst")?;
+    let target = flags
+        .value('t')
+        .map(|t| parse_target_pane(&t))
+        .transpose()?;
+    let separator = if flags.has('r') {
+        Some("\n".to_string())
+    } else {
+        flags.value('s')
+    };
+    Ok(Command::PasteBuffer {
+        name: flags.value('b'),
+        target,
+        delete: flags.has('d'),
+        bracketed: flags.has('p'),
+        separator,
+    })
+}
+
+fn parse_set_buffer(args: &[String]) -> Result<Command> {
+    let flags = Flags::parse("set-buffer", args, "bn")?;
+    let new_name = flags.value('n');
+    let data = (!flags.rest.is_empty()).then(|| flags.rest.join(" "));
+    if data.is_none() && new_name.is_none() {
+        bail!("set-buffer requires data");
+    }
+    Ok(Command::SetBuffer {
+        name: flags.value('b'),
+        new_name,
+        append: flags.has('a'),
+        data,
+    })
+}
+
+fn parse_show_buffer(args: &[String]) -> Result<Command> {
+    let flags = Flags::parse("show-buffer", args, "b")?;
+    Ok(Command::ShowBuffer {
+        name: flags.value('b'),
+    })
+}
+
+fn parse_delete_buffer(args: &[String]) -> Result<Command> {
+    let flags = Flags::parse("delete-buffer", args, "b")?;
+    Ok(Command::DeleteBuffer {
+        name: flags.value('b'),
+    })
+}
+
+fn parse_save_buffer(args: &[String]) -> Result<Command> {
+    let flags = Flags::parse("save-buffer", args, "b")?;
+    let path = flags
+        .rest
+        .first()
+        .cloned()
+        .ok_or_else(|| anyhow::anyhow!("save-buffer requires a path"))?;
+    Ok(Command::SaveBuffer {
+        name: flags.value('b'),
+        path,
+        append: flags.has('a'),
+    })
+}
+
+fn parse_load_buffer(args: &[String]) -> Result<Command> {
+    let flags = Flags::parse("load-buffer", args, "b")?;
+    let path = flags
+        .rest
+        .first()
+        .cloned()
+        .ok_or_else(|| anyhow::anyhow!("load-buffer requires a path"))?;
+    Ok(Command::LoadBuffer {
+        name: flags.value('b'),
+        path,
+    })
 }
 
 fn parse_set_split_ratio(args: &[String]) -> Result<Command> {
@@ -677,6 +891,7 @@ mod tests {
                 command: None,
                 shell: None,
                 kind: None,
+                history_limit: None,
             }
         );
     }
@@ -693,6 +908,7 @@ mod tests {
                 command: None,
                 shell: None,
                 kind: None,
+                history_limit: None,
             }
         );
     }
@@ -709,6 +925,7 @@ mod tests {
                 command: None,
                 shell: None,
                 kind: None,
+                history_limit: None,
             }
         );
     }
@@ -725,6 +942,7 @@ mod tests {
                 command: None,
                 shell: None,
                 kind: None,
+                history_limit: None,
             }
         );
     }
@@ -741,6 +959,7 @@ mod tests {
                 command: None,
                 shell: None,
                 kind: None,
+                history_limit: None,
             }
         );
     }
@@ -1124,6 +1343,7 @@ mod tests {
                 command: None,
                 shell: None,
                 kind: None,
+                history_limit: None,
             }
         );
     }
@@ -1141,6 +1361,7 @@ mod tests {
                 command: None,
                 shell: None,
                 kind: None,
+                history_limit: None,
             }
         );
     }
@@ -1157,6 +1378,7 @@ mod tests {
                 command: None,
                 shell: None,
                 kind: None,
+                history_limit: None,
             }
         );
     }
@@ -1174,6 +1396,7 @@ mod tests {
                 command: None,
                 shell: None,
                 kind: None,
+                history_limit: None,
             }
         );
     }
@@ -1190,6 +1413,7 @@ mod tests {
                 command: None,
                 shell: None,
                 kind: None,
+                history_limit: None,
             }
         );
     }
@@ -1223,6 +1447,7 @@ mod tests {
                 command: None,
                 shell: None,
                 kind: None,
+                history_limit: None,
             }
         );
     }
@@ -1239,6 +1464,7 @@ mod tests {
                 command: None,
                 shell: None,
                 kind: None,
+                history_limit: None,
             }
         );
     }
@@ -1446,6 +1672,7 @@ mod tests {
                 command: None,
                 shell: None,
                 kind: None,
+                history_limit: None,
             }
         );
         assert_eq!(
@@ -1473,6 +1700,7 @@ mod tests {
                 command: None,
                 shell: None,
                 kind: None,
+                history_limit: None,
             }
         );
         assert_eq!(parse("killp").unwrap(), Command::KillPane { target: None });
@@ -1516,11 +1744,16 @@ mod tests {
         assert_eq!(parse("prev").unwrap(), Command::PreviousWindow);
         assert_eq!(parse("equalize").unwrap(), Command::EqualizeLayout);
         assert_eq!(
-            parse("pasteb hello world").unwrap(),
+            parse("pasteb -b saved").unwrap(),
             Command::PasteBuffer {
-                text: "hello world".to_string()
+                name: Some("saved".to_string()),
+                target: None,
+                delete: false,
+                bracketed: false,
+                separator: None,
             }
         );
+        assert_eq!(parse("lsb").unwrap(), Command::ListBuffers);
     }
 
     // --- extract_target edge cases ---
@@ -1615,6 +1848,7 @@ mod tests {
                 command: None,
                 shell: None,
                 kind: None,
+                history_limit: None,
             }
         );
     }
@@ -1630,6 +1864,7 @@ mod tests {
                 command: None,
                 shell: None,
                 kind: None,
+                history_limit: None,
             }
         );
     }
@@ -1645,6 +1880,7 @@ mod tests {
                 command: None,
                 shell: None,
                 kind: Some(TabKind::Agent),
+                history_limit: None,
             }
         );
     }
@@ -1661,10 +1897,24 @@ mod tests {
                 command: None,
                 shell: None,
                 kind: Some(TabKind::Nvim),
+                history_limit: None,
             }
         );
     }
 
+    #[test]
+    fn test_parse_history_limit() {
+        match parse("new-window -H 50000").unwrap() {
+            Command::NewWindow { history_limit, .. } => assert_eq!(history_limit, Some(50000)),
+            other => panic!("unexpected {other:?}"),
+        }
+        match parse("split-window -v -H 200").unwrap() {
+            Command::SplitWindow { history_limit, .. } => assert_eq!(history_limit, Some(200)),
+            other => panic!("unexpected {other:?}"),
+        }
+        assert!(parse("new-window -H lots").is_err());
+    }
+
     // --- Misc zero-arg commands ---
 
     #[test]
@@ -1683,9 +1933,159 @@ mod tests {
         assert_eq!(
             cmd,
             Command::PasteBuffer {
-                text: "".to_string()
+                name: None,
+                target: None,
+                delete: false,
+                bracketed: false,
+                separator: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_client_commands() {
+        assert_eq!(
+            parse("detach-client").unwrap(),
+            Command::DetachClient {
+                target: None,
+                others: false,
+            }
+        );
+        assert_eq!(
+            parse("detach -a -t 3").unwrap(),
+            Command::DetachClient {
+                target: Some(3),
+                others: true,
+            }
+        );
+        assert!(parse("detach-client -t me").is_err());
+        assert_eq!(parse("lsc").unwrap(), Command::ListClients);
+        assert_eq!(parse("client-stats").unwrap(), Command::ClientStats);
+        assert_eq!(
+            parse("switch-client -c 2 -t Work").unwrap(),
+            Command::SwitchClient {
+                client: Some(2),
+                workspace: WorkspaceTarget::Named("Work".to_string()),
+            }
+        );
+        assert_eq!(
+            parse("switchc -n").unwrap(),
+            Command::SwitchClient {
+                client: None,
+                workspace: WorkspaceTarget::Next,
+            }
+        );
+        assert!(parse("switch-client").is_err());
+    }
+
+    #[test]
+    fn test_parse_resize_window() {
+        assert_eq!(
+            parse("resize-window -x 200 -y 50").unwrap(),
+            Command::ResizeWindow {
+                width: Some(200),
+                height: Some(50),
+                adjust: false,
+            }
+        );
+        assert_eq!(
+            parse("resizew -A").unwrap(),
+            Command::ResizeWindow {
+                width: None,
+                height: None,
+                adjust: true,
+            }
+        );
+        assert!(parse("resize-window").is_err());
+        assert!(parse("resize-window -x wide").is_err());
+    }
+
+    #[test]
+    fn test_parse_paste_buffer_flags() {
+        assert_eq!(
+            parse("paste-buffer -dpr -b b1 -t %2").unwrap(),
+            Command::PasteBuffer {
+                name: Some("b1".to_string()),
+                target: Some(TargetPane::Id(2)),
+                delete: true,
+                bracketed: true,
+                separator: Some("\n".to_string()),
             }
         );
+        assert_eq!(
+            parse("paste-buffer -s ';'").unwrap(),
+            Command::PasteBuffer {
+                name: None,
+                target: None,
+                delete: false,
+                bracketed: false,
+                separator: Some(";".to_string()),
+            }
+        );
+        assert!(parse("paste-buffer -b").is_err());
+    }
+
+    #[test]
+    fn test_parse_set_buffer() {
+        assert_eq!(
+            parse("setb -a -b notes \"more text\"").unwrap(),
+            Command::SetBuffer {
+                name: Some("notes".to_string()),
+                new_name: None,
+                append: true,
+                data: Some("more text".to_string()),
+            }
+        );
+        // Data may start with a dash after the flags end
+        assert_eq!(
+            parse("set-buffer -- -x").unwrap(),
+            Command::SetBuffer {
+                name: None,
+                new_name: None,
+                append: false,
+                data: Some("-x".to_string()),
+            }
+        );
+        assert_eq!(
+            parse("set-buffer -b old -n new").unwrap(),
+            Command::SetBuffer {
+                name: Some("old".to_string()),
+                new_name: Some("new".to_string()),
+                append: false,
+                data: None,
+            }
+        );
+        assert!(parse("set-buffer").is_err());
+    }
+
+    #[test]
+    fn test_parse_buffer_file_commands() {
+        assert_eq!(
+            parse("saveb -a -b b0 /tmp/out").unwrap(),
+            Command::SaveBuffer {
+                name: Some("b0".to_string()),
+                path: "/tmp/out".to_string(),
+                append: true,
+            }
+        );
+        assert_eq!(
+            parse("loadb ~/notes.txt").unwrap(),
+            Command::LoadBuffer {
+                name: None,
+                path: "~/notes.txt".to_string(),
+            }
+        );
+        assert!(parse("save-buffer").is_err());
+        assert_eq!(
+            parse("showb -b b0").unwrap(),
+            Command::ShowBuffer {
+                name: Some("b0".to_string()),
+            }
+        );
+        assert_eq!(
+            parse("deleteb").unwrap(),
+            Command::DeleteBuffer { name: None }
+        );
     }
 
     #[test]
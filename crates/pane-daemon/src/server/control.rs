@@ -21,6 +21,7 @@ use tokio::sync::{broadcast, Mutex};
 use crate::server::command;
 use crate::server::command_parser;
 use crate::server::id_map::IdMap;
+use pane_protocol::base64;
 use pane_protocol::protocol::ServerResponse;
 use crate::server::state::ServerState;
 
@@ -50,7 +51,7 @@ pub async fn run_control_mode(
             match response {
                 ServerResponse::PaneOutput { pane_id, data } => {
                     // Base64-encode binary data for control mode
-                    let encoded = base64_encode(&data);
+                    let encoded = base64::encode(&data);
                     let _ = writeln!(out, "%output {} {}", pane_id, encoded);
                 }
                 ServerResponse::PaneExited { pane_id } => {
@@ -72,14 +73,14 @@ pub async fn run_control_mode(
                     let _ = out.flush();
                     break;
                 }
-                ServerResponse::Attached => {
+                ServerResponse::Attached | ServerResponse::AttachedV2 { .. } => {
                     let _ = writeln!(out, "%session-changed pane");
                 }
                 ServerResponse::Error(msg) => {
                     let _ = writeln!(out, "%error {}", msg);
                 }
                 ServerResponse::FullScreenDump { pane_id, data } => {
-                    let encoded = base64_encode(&data);
+                    let encoded = base64::encode(&data);
                     let _ = writeln!(out, "%screen-dump {} {}", pane_id, encoded);
                 }
                 ServerResponse::ClientCountChanged(count) => {
@@ -91,6 +92,15 @@ pub async fn run_control_mode(
                 ServerResponse::PluginSegments(_) => {
                     // Skip plugin segments in control mode
                 }
+                ServerResponse::Passthrough { .. } => {
+                    // There's no outer terminal to pass through to
+                }
+                ServerResponse::ClipboardWrite { .. } | ServerResponse::ClipboardRead { .. } => {
+                    // Control mode has no clipboard to offer
+                }
+                ServerResponse::BufferList(_) | ServerResponse::SwitchWorkspace(_) => {
+                    // Only sent to one client, never a control client
+                }
             }
             let _ = out.flush();
         }
@@ -176,68 +186,3 @@ pub async fn run_control_mode(
     notify_task.abort();
     Ok(())
 }
-
-/// Simple base64 encoding for control mode output.
-fn base64_encode(data: &[u8]) -> String {
-    const CHARS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
-    let mut result = String::with_capacity(data.len().div_ceil(3) * 4);
-    let chunks = data.chunks(3);
-
-    for chunk in chunks {
-        let b0 = chunk[0] as u32;
-        let b1 = if chunk.len() > 1 { chunk[1] as u32 } else { 0 };
-        let b2 = if chunk.len() > 2 { chunk[2] as u32 } else { 0 };
-        let triple = (b0 << 16) | (b1 << 8) | b2;
-
-        result.push(CHARS[((triple >> 18) & 0x3F) as usize] as char);
-        result.push(CHARS[((triple >> 12) & 0x3F) as usize] as char);
-        if chunk.len() > 1 {
-            result.push(CHARS[((triple >> 6) & 0x3F) as usize] as char);
-        } else {
-            result.push('=');
-        }
-        if chunk.len() > 2 {
-            result.push(CHARS[(triple & 0x3F) as usize] as char);
-        } else {
-            result.push('=');
-        }
-    }
-
-    result
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn test_base64_encode_empty() {
-        assert_eq!(base64_encode(b""), "");
-    }
-
-    #[test]
-    fn test_base64_encode_hello() {
-        assert_eq!(base64_encode(b"hello"), "aGVsbG8=");
-    }
-
-    #[test]
-    fn test_base64_encode_single_byte() {
-        assert_eq!(base64_encode(b"a"), "YQ==");
-    }
-
-    #[test]
-    fn test_base64_encode_two_bytes() {
-        assert_eq!(base64_encode(b"ab"), "YWI=");
-    }
-
-    #[test]
-    fn test_base64_encode_three_bytes() {
-        assert_eq!(base64_encode(b"abc"), "YWJj");
-    }
-
-    #[test]
-    fn test_base64_roundtrip_concept() {
-        // Just verify our encoding matches expected values
-        assert_eq!(base64_encode(b"Hello, World!"), "SGVsbG8sIFdvcmxkIQ==");
-    }
-}
@@ -5,19 +5,31 @@ use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 
 use anyhow::Result;
-use tokio::net::{UnixListener, UnixStream};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::{TcpListener, UnixListener, UnixStream};
 use tokio::sync::{broadcast, mpsc, Mutex};
 
-use pane_protocol::config::Config;
+use pane_protocol::config::{AllowPassthrough, Config, ServerConfig, WindowSize};
 use pane_protocol::event::AppEvent;
+use pane_protocol::layout::{viewport_origin, TabId};
+use crate::server::command::{Command, WorkspaceTarget};
 use crate::server::command_parser;
-use pane_protocol::framing;
+use pane_protocol::framing::{self, Encoding};
 use crate::server::id_map::IdMap;
+use crate::server::outbound::{FrameKind, OutboundQueue};
+use crate::server::view::{ClientView, THUMBNAIL_INTERVAL};
 use pane_protocol::protocol::{
-    ClientRequest, ClientType, SerializableSystemStats, ServerResponse,
+    negotiate, BufferSummary, Capability, ClientRequest, ClientType, MouseButton, MouseModifiers,
+    SerializableSystemStats, ServerResponse, WheelDirection, SYNC_OUTPUT_TIMEOUT,
 };
-use crate::server::state::{ServerState, render_state_from_server, render_state_for_client};
+use pane_protocol::window_types::WindowId;
+use crate::server::tcp;
+use crate::server::state::{ClientFocus, ServerState, render_state_from_server, render_state_for_client};
 use crate::system_stats;
+use crate::window::replies::ReplyColors;
+
+/// Characters of each paste buffer sent to clients for previews.
+const BUFFER_PREVIEW_CHARS: usize = 2000;
 
 /// Global counter for assigning unique client IDs.
 static NEXT_CLIENT_ID: AtomicU64 = AtomicU64::new(0);
@@ -27,12 +39,52 @@ static NEXT_CLIENT_ID: AtomicU64 = AtomicU64::new(0);
 struct ClientInfo {
     width: u16,
     height: u16,
-    active_workspace: usize,
+    /// The client's own active workspace, windows and tabs.
+    focus: ClientFocus,
     client_type: ClientType,
+    /// Whether the client's own terminal has focus.
+    focused: bool,
+    /// Order of the client's latest input; the highest is the latest.
+    last_active: u64,
+    /// Read-only clients watch; their input is dropped.
+    read_only: bool,
+    /// Whether the client negotiated carrying out clipboard requests.
+    clipboard: bool,
+    attached_at: chrono::DateTime<chrono::Local>,
+    /// Messages for this client alone, sent by its forward task.
+    control: Option<mpsc::UnboundedSender<ClientControl>>,
+    /// Frames waiting to be written to the client.
+    queue: Option<Arc<OutboundQueue>>,
+}
+
+impl ClientInfo {
+    fn new(width: u16, height: u16, focus: ClientFocus, client_type: ClientType) -> Self {
+        Self {
+            width,
+            height,
+            focus,
+            client_type,
+            focused: true,
+            last_active: 0,
+            read_only: false,
+            clipboard: false,
+            attached_at: chrono::Local::now(),
+            control: None,
+            queue: None,
+        }
+    }
+}
+
+/// Something for one client's forward task to do.
+#[derive(Debug)]
+enum ClientControl {
+    Send(ServerResponse),
+    /// Close the connection, which the client takes as being detached.
+    Detach,
 }
 
-/// Registry of connected clients with their terminal sizes and workspace views.
-/// The server uses the smallest dimensions across all clients.
+/// Registry of connected clients with their terminal sizes and focus.
+/// The `window_size` policy picks which of their sizes the server uses.
 #[derive(Clone)]
 struct ClientRegistry {
     inner: Arc<Mutex<HashMap<u64, ClientInfo>>>,
@@ -45,16 +97,96 @@ impl ClientRegistry {
         }
     }
 
-    async fn register(&self, id: u64, width: u16, height: u16, active_workspace: usize, client_type: ClientType) {
-        self.inner.lock().await.insert(
-            id,
-            ClientInfo {
-                width,
-                height,
-                active_workspace,
-                client_type,
-            },
-        );
+    async fn register(&self, id: u64, mut info: ClientInfo) {
+        let mut clients = self.inner.lock().await;
+        info.last_active = Self::next_activity(&clients);
+        clients.insert(id, info);
+    }
+
+    /// Attached clients, oldest first.
+    async fn list(&self) -> Vec<(u64, ClientInfo)> {
+        let mut list: Vec<_> = self
+            .inner
+            .lock()
+            .await
+            .iter()
+            .map(|(id, info)| (*id, info.clone()))
+            .collect();
+        list.sort_by_key(|(id, _)| *id);
+        list
+    }
+
+    /// Hand a message to one client's forward task. Returns whether the
+    /// client is attached.
+    async fn send(&self, id: u64, control: ClientControl) -> bool {
+        match self.inner.lock().await.get(&id) {
+            Some(info) => {
+                if let Some(tx) = &info.control {
+                    let _ = tx.send(control);
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn next_activity(clients: &HashMap<u64, ClientInfo>) -> u64 {
+        clients
+            .values()
+            .map(|c| c.last_active + 1)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Record input from a client. Returns whether it wasn't already the
+    /// latest client to have input.
+    async fn touch(&self, id: u64) -> bool {
+        let mut clients = self.inner.lock().await;
+        let next = Self::next_activity(&clients);
+        match clients.get_mut(&id) {
+            Some(info) if info.last_active + 1 != next => {
+                info.last_active = next;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    async fn set_focused(&self, id: u64, focused: bool) {
+        if let Some(info) = self.inner.lock().await.get_mut(&id) {
+            info.focused = focused;
+        }
+    }
+
+    /// The client that carries out tabs' clipboard requests: of the writable
+    /// clients that can, the latest active, preferring focused terminals.
+    async fn clipboard_client(&self) -> Option<u64> {
+        self.inner
+            .lock()
+            .await
+            .iter()
+            .filter(|(_, c)| c.clipboard && !c.read_only)
+            .max_by_key(|(_, c)| (c.focused, c.last_active))
+            .map(|(id, _)| *id)
+    }
+
+    /// Whether any connected client's terminal has focus. Read-only clients
+    /// only watch, so their focus doesn't count.
+    async fn any_focused(&self) -> bool {
+        self.inner
+            .lock()
+            .await
+            .values()
+            .any(|c| c.focused && !c.read_only)
+    }
+
+    /// A client's terminal size, which its windows are laid out in.
+    async fn size(&self, id: u64) -> Option<(u16, u16)> {
+        self.inner
+            .lock()
+            .await
+            .get(&id)
+            .map(|c| (c.width, c.height))
     }
 
     async fn update_size(&self, id: u64, width: u16, height: u16) {
@@ -64,14 +196,23 @@ impl ClientRegistry {
         }
     }
 
-    async fn set_active_workspace(&self, id: u64, ws: usize) {
+    /// Switch the server state to this client's focus before acting for it.
+    async fn apply_focus(&self, id: u64, state: &mut ServerState) {
+        if let Some(info) = self.inner.lock().await.get(&id) {
+            info.focus.apply(state);
+        }
+    }
+
+    /// Remember the server state's focus as this client's, after acting for
+    /// it. Capture it before releasing the state lock.
+    async fn set_focus(&self, id: u64, focus: ClientFocus) {
         if let Some(info) = self.inner.lock().await.get_mut(&id) {
-            info.active_workspace = ws;
+            info.focus = focus;
         }
     }
 
-    async fn get_active_workspace(&self, id: u64) -> Option<usize> {
-        self.inner.lock().await.get(&id).map(|i| i.active_workspace)
+    async fn get_focus(&self, id: u64) -> Option<ClientFocus> {
+        self.inner.lock().await.get(&id).map(|i| i.focus.clone())
     }
 
     async fn unregister(&self, id: u64) {
@@ -80,34 +221,44 @@ impl ClientRegistry {
 
     /// Return the effective terminal size for the TUI layout engine.
     ///
-    /// TUI clients constrain each other (smallest wins) because they all render
-    /// from the same vt100 screen buffer. NativeApp clients are excluded from
-    /// this calculation — they manage per-pane sizes via `SetPaneSize`.
+    /// TUI clients all render from the same vt100 screen buffer, so the
+    /// `window_size` policy picks one size for all of them: the smallest,
+    /// the largest, the latest client's, or `manual` whatever they are.
+    /// NativeApp clients are excluded from this calculation — they manage
+    /// per-pane sizes via `SetPaneSize`.
     ///
     /// When only NativeApp clients are connected, returns the largest native
-    /// size so the daemon has a reasonable default PTY size.
-    async fn effective_size(&self) -> Option<(u16, u16)> {
+    /// size so the daemon has a reasonable default PTY size. Read-only
+    /// clients are left out: they watch at whatever size the others set.
+    async fn effective_size(&self, policy: WindowSize, manual: (u16, u16)) -> Option<(u16, u16)> {
         let clients = self.inner.lock().await;
+        let clients: Vec<_> = clients.values().filter(|c| !c.read_only).collect();
         if clients.is_empty() {
             return None;
         }
 
-        let tui_sizes: Vec<_> = clients
-            .values()
+        let tui: Vec<_> = clients
+            .iter()
             .filter(|c| c.client_type == ClientType::Tui)
-            .map(|c| (c.width, c.height))
             .collect();
 
-        if !tui_sizes.is_empty() {
-            // TUI clients: use the smallest size across all TUI clients
-            let min_w = tui_sizes.iter().map(|(w, _)| *w).min().unwrap();
-            let min_h = tui_sizes.iter().map(|(_, h)| *h).min().unwrap();
-            return Some((min_w, min_h));
+        if !tui.is_empty() {
+            let widths = tui.iter().map(|c| c.width);
+            let heights = tui.iter().map(|c| c.height);
+            return match policy {
+                WindowSize::Smallest => Some((widths.min()?, heights.min()?)),
+                WindowSize::Largest => Some((widths.max()?, heights.max()?)),
+                WindowSize::Latest => tui
+                    .iter()
+                    .max_by_key(|c| c.last_active)
+                    .map(|c| (c.width, c.height)),
+                WindowSize::Manual => Some(manual),
+            };
         }
 
         // Only NativeApp clients: use the largest size as a reasonable default
-        let max_w = clients.values().map(|c| c.width).max().unwrap_or(80);
-        let max_h = clients.values().map(|c| c.height).max().unwrap_or(24);
+        let max_w = clients.iter().map(|c| c.width).max().unwrap_or(80);
+        let max_h = clients.iter().map(|c| c.height).max().unwrap_or(24);
         Some((max_w, max_h))
     }
 
@@ -191,6 +342,7 @@ pub async fn run_server(config: Config) -> Result<()> {
     }
 
     let auto_suspend_secs = config.behavior.auto_suspend_secs;
+    let tcp_listener = bind_tcp(&config.server).await;
 
     let state = ServerState::new_with_workspace(&event_tx, 80, 24, config)
         .expect("failed to create initial workspace");
@@ -290,6 +442,19 @@ pub async fn run_server(config: Config) -> Result<()> {
         }
     });
 
+    // Accept remote clients when [server] configures a TCP listener
+    if let Some((listener, tls, token)) = tcp_listener {
+        tokio::spawn(accept_tcp(
+            listener,
+            tls,
+            token,
+            Arc::clone(&state),
+            Arc::clone(&id_map),
+            broadcast_tx.clone(),
+            clients.clone(),
+        ));
+    }
+
     // Set up signal handler for graceful shutdown (SIGTERM + Ctrl-C)
     let broadcast_tx_term = broadcast_tx.clone();
     let sock_path_clone = sock_path.clone();
@@ -376,15 +541,32 @@ async fn process_events(
     while let Some(event) = event_rx.recv().await {
         match event {
             AppEvent::PtyOutput { pane_id, bytes } => {
-                let fg_changed = {
+                // Output goes out under the state lock, so a client resyncing
+                // knows which of it its snapshot already holds
+                let (fg_changed, passthrough, clipboard) = {
                     let mut state = state.lock().await;
-                    if let Some(pane) = state.find_tab_mut(pane_id) {
+                    let colors = ReplyColors::from_theme(&state.config.theme);
+                    let allow_passthrough = state.config.behavior.allow_passthrough;
+                    let event_tx = state.event_tx.clone();
+                    let mut output = None;
+                    let mut passthrough = Vec::new();
+                    let mut clipboard = Vec::new();
+                    let changed = if let Some(pane) = state.find_tab_mut(pane_id) {
+                        // Passthrough never reaches the parser; it is sent on
+                        // to clients only if allowed.
+                        let filtered = pane.passthrough.filter(&bytes);
+                        let bytes = filtered.output;
+                        if allow_passthrough != AllowPassthrough::Off {
+                            passthrough = filtered.sequences;
+                        }
+                        // Color queries are answered from the current theme.
+                        pane.vt.callbacks_mut().colors = colors;
                         // Catch panics in vt100 processing so a single pane
                         // can't take down the entire daemon.
                         let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
                             pane.process_output(&bytes)
                         }));
-                        match result {
+                        let changed = match result {
                             Ok(changed) => changed,
                             Err(_) => {
                                 eprintln!(
@@ -393,15 +575,35 @@ async fn process_events(
                                 );
                                 false
                             }
+                        };
+                        // Hold back synchronized frames until they're complete
+                        let synchronized = pane.screen().synchronized_output();
+                        let pushed = pane.frames.push(bytes, synchronized);
+                        if pushed.frame_started {
+                            tokio::spawn(async move {
+                                tokio::time::sleep(SYNC_OUTPUT_TIMEOUT).await;
+                                let _ = event_tx.send(AppEvent::SyncOutputTimeout { pane_id });
+                            });
                         }
+                        output = pushed.output;
+                        clipboard = pane.vt.callbacks_mut().take_clipboard();
+                        changed
                     } else {
                         false
+                    };
+                    state.enforce_scrollback_budget();
+                    if let Some(data) = output.filter(|data| !data.is_empty()) {
+                        let _ = broadcast_tx.send(ServerResponse::PaneOutput { pane_id, data });
                     }
+                    let clipboard = state.clipboard_responses(pane_id, clipboard);
+                    (changed, passthrough, clipboard)
                 };
-                let _ = broadcast_tx.send(ServerResponse::PaneOutput {
-                    pane_id,
-                    data: bytes,
-                });
+                for data in passthrough {
+                    let _ = broadcast_tx.send(ServerResponse::Passthrough { pane_id, data });
+                }
+                for response in clipboard {
+                    let _ = broadcast_tx.send(response);
+                }
                 if fg_changed {
                     let state = state.lock().await;
                     let render_state = render_state_from_server(&state);
@@ -414,6 +616,7 @@ async fn process_events(
                 let should_quit = {
                     let mut state = state.lock().await;
                     let quit = state.handle_pty_exited(pane_id);
+                    state.sync_focus();
                     if !quit {
                         let (w, h) = state.last_size;
                         state.resize_all_tabs(w, h);
@@ -430,6 +633,15 @@ async fn process_events(
                     break;
                 }
             }
+            AppEvent::SyncOutputTimeout { pane_id } => {
+                let mut state = state.lock().await;
+                let output = state
+                    .find_tab_mut(pane_id)
+                    .and_then(|pane| pane.frames.flush_expired());
+                if let Some(data) = output {
+                    let _ = broadcast_tx.send(ServerResponse::PaneOutput { pane_id, data });
+                }
+            }
             AppEvent::SystemStats(stats) => {
                 {
                     let mut state = state.lock().await;
@@ -467,26 +679,149 @@ async fn process_events(
             // These events come from clients, not the internal event loop
             AppEvent::Key(_)
             | AppEvent::MouseDown { .. }
-            | AppEvent::MouseRightDown { .. }
             | AppEvent::MouseDrag { .. }
             | AppEvent::MouseMove { .. }
             | AppEvent::MouseUp { .. }
             | AppEvent::MouseScroll { .. }
+            | AppEvent::FocusChanged { .. }
             | AppEvent::Resize(_, _) => {}
         }
     }
 }
 
+/// Bind the TCP listener when `[server]` asks for one. It stays off without
+/// a token, and never falls back to plain TCP when TLS was asked for but
+/// can't be set up. Plain TCP beyond loopback is allowed with a warning.
+async fn bind_tcp(
+    server: &ServerConfig,
+) -> Option<(TcpListener, Option<tokio_rustls::TlsAcceptor>, Arc<str>)> {
+    let addr = server.tcp_listen.as_ref()?;
+    let Some(token) = server.token.as_deref() else {
+        eprintln!(
+            "pane: [server] tcp_listen needs a token, not listening on {}",
+            addr
+        );
+        return None;
+    };
+    let tls = match (&server.tls_cert, &server.tls_key) {
+        (Some(cert), Some(key)) => match tcp::tls_acceptor(cert, key) {
+            Ok(acceptor) => Some(acceptor),
+            Err(e) => {
+                eprintln!("pane: TLS setup failed, not listening on {}: {}", addr, e);
+                return None;
+            }
+        },
+        (None, None) => None,
+        _ => {
+            eprintln!(
+                "pane: [server] needs both tls_cert and tls_key, not listening on {}",
+                addr
+            );
+            return None;
+        }
+    };
+    match TcpListener::bind(addr).await {
+        Ok(listener) => {
+            let public = listener.local_addr().is_ok_and(|a| !a.ip().is_loopback());
+            if public && tls.is_none() {
+                eprintln!(
+                    "pane: warning: listening on {} without TLS; the token and \
+                     session are sent in the clear",
+                    addr
+                );
+            }
+            Some((listener, tls, Arc::from(token)))
+        }
+        Err(e) => {
+            eprintln!("pane: failed to listen on {}: {}", addr, e);
+            None
+        }
+    }
+}
+
+/// Accept clients on the TCP listener. Each connection is wrapped in TLS when
+/// there is an acceptor, and must authenticate before it reaches `handle_client`.
+async fn accept_tcp(
+    listener: TcpListener,
+    tls: Option<tokio_rustls::TlsAcceptor>,
+    token: Arc<str>,
+    state: Arc<Mutex<ServerState>>,
+    id_map: Arc<Mutex<IdMap>>,
+    broadcast_tx: broadcast::Sender<ServerResponse>,
+    clients: ClientRegistry,
+) {
+    loop {
+        let (stream, addr) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                eprintln!("pane: tcp accept error: {}", e);
+                continue;
+            }
+        };
+        let _ = stream.set_nodelay(true);
+        let tls = tls.clone();
+        let token = Arc::clone(&token);
+        let state = Arc::clone(&state);
+        let id_map = Arc::clone(&id_map);
+        let broadcast_tx = broadcast_tx.clone();
+        let clients = clients.clone();
+        tokio::spawn(async move {
+            let result = match tls {
+                Some(tls) => match tcp::tls_accept(&tls, stream).await {
+                    Ok(stream) => {
+                        serve_tcp(stream, &token, state, id_map, broadcast_tx, clients).await
+                    }
+                    Err(e) => Err(e),
+                },
+                None => serve_tcp(stream, &token, state, id_map, broadcast_tx, clients).await,
+            };
+            if let Err(e) = result {
+                eprintln!("pane: tcp client {}: {}", addr, e);
+            }
+        });
+    }
+}
+
+/// Authenticate a TCP connection, then serve it like a local client.
+async fn serve_tcp<S>(
+    mut stream: S,
+    token: &str,
+    state: Arc<Mutex<ServerState>>,
+    id_map: Arc<Mutex<IdMap>>,
+    broadcast_tx: broadcast::Sender<ServerResponse>,
+    clients: ClientRegistry,
+) -> Result<()>
+where
+    S: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+{
+    tcp::authenticate(&mut stream, token).await?;
+    let broadcast_rx = broadcast_tx.subscribe();
+    let client_id = NEXT_CLIENT_ID.fetch_add(1, Ordering::Relaxed);
+    handle_client(
+        stream,
+        state,
+        id_map,
+        broadcast_tx,
+        broadcast_rx,
+        clients,
+        client_id,
+    )
+    .await
+}
+
 /// Handle a single client connection.
-async fn handle_client(
-    mut stream: UnixStream,
+async fn handle_client<S>(
+    mut stream: S,
     state: Arc<Mutex<ServerState>>,
     id_map: Arc<Mutex<IdMap>>,
     broadcast_tx: broadcast::Sender<ServerResponse>,
     mut broadcast_rx: broadcast::Receiver<ServerResponse>,
     clients: ClientRegistry,
     client_id: u64,
-) -> Result<()> {
+) -> Result<()>
+where
+    S: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+{
     // Read the initial request
     let first_msg: ClientRequest = framing::recv_required(&mut stream).await?;
 
@@ -498,12 +833,19 @@ async fn handle_client(
                 Ok(parsed_cmd) => {
                     let mut state_guard = state.lock().await;
                     let mut id_map_guard = id_map.lock().await;
-                    match crate::server::command::execute(
-                        &parsed_cmd,
-                        &mut state_guard,
-                        &mut id_map_guard,
-                        &broadcast_tx,
-                    ) {
+                    let client_result =
+                        execute_client_command(&parsed_cmd, &mut state_guard, &clients, None).await;
+                    let result = match client_result {
+                        Some(result) => result.map(crate::server::command::CommandResult::Ok),
+                        None => crate::server::command::execute(
+                            &parsed_cmd,
+                            &mut state_guard,
+                            &mut id_map_guard,
+                            &broadcast_tx,
+                        ),
+                    };
+                    state_guard.sync_focus();
+                    match result {
                         Ok(crate::server::command::CommandResult::Ok(output)) => {
                             ServerResponse::CommandOutput {
                                 output,
@@ -570,9 +912,51 @@ async fn handle_client(
         return Ok(());
     }
 
-    let client_type = match &first_msg {
-        ClientRequest::Attach => ClientType::Tui,
-        ClientRequest::AttachV2 { client_type } => client_type.clone(),
+    // Clients that predate negotiation get `Attached` and the features they
+    // could handle
+    let (client_type, read_only, attached, capabilities) = match &first_msg {
+        ClientRequest::Attach => (
+            ClientType::Tui,
+            false,
+            ServerResponse::Attached,
+            Capability::LEGACY.to_vec(),
+        ),
+        ClientRequest::AttachV2 {
+            client_type,
+            read_only,
+            protocol_version: 0,
+            ..
+        } => (
+            client_type.clone(),
+            *read_only,
+            ServerResponse::Attached,
+            Capability::LEGACY.to_vec(),
+        ),
+        ClientRequest::AttachV2 {
+            client_type,
+            read_only,
+            protocol_version,
+            capabilities,
+        } => match negotiate(*protocol_version, capabilities) {
+            Ok(negotiated) => (
+                client_type.clone(),
+                *read_only,
+                ServerResponse::AttachedV2 {
+                    protocol_version: negotiated.protocol_version,
+                    capabilities: negotiated.capabilities.clone(),
+                },
+                negotiated.capabilities,
+            ),
+            Err(reason) => {
+                let message = format!(
+                    "{}; this daemon is pane {}, upgrade the client to match",
+                    reason,
+                    env!("CARGO_PKG_VERSION")
+                );
+                framing::send(&mut stream, &ServerResponse::Error(message)).await?;
+                return Ok(());
+            }
+        },
         _ => {
             framing::send(
                 &mut stream,
@@ -583,78 +967,175 @@ async fn handle_client(
         }
     };
 
-    // Send attached confirmation
-    framing::send(&mut stream, &ServerResponse::Attached).await?;
+    // Send attached confirmation. Everything after it uses the negotiated encoding.
+    framing::send(&mut stream, &attached).await?;
+    let encoding = if capabilities.contains(&Capability::BinaryFrames) {
+        Encoding::Binary
+    } else {
+        Encoding::Json
+    };
 
-    // Register client with default size and current active workspace
-    {
-        let state_guard = state.lock().await;
+    // Register client with default size, starting from the current focus.
+    // Its queue starts with a resync, which sends the layout and the screens
+    // of its tabs so it can render output that arrived before it connected.
+    let (control_tx, mut control_rx) = mpsc::unbounded_channel();
+    let queue = {
+        let mut state_guard = state.lock().await;
         let (w, h) = state_guard.last_size;
-        clients
-            .register(client_id, w, h, state_guard.active_workspace, client_type.clone())
-            .await;
+        let focus = ClientFocus::capture(&state_guard);
+        let limit = state_guard.config.behavior.client_queue_limit_kb * 1024;
+        let queue = Arc::new(OutboundQueue::new(limit));
+        let info = ClientInfo {
+            read_only,
+            clipboard: capabilities.contains(&Capability::Clipboard),
+            control: Some(control_tx),
+            queue: Some(Arc::clone(&queue)),
+            ..ClientInfo::new(w, h, focus, client_type.clone())
+        };
+        clients.register(client_id, info).await;
+        state_guard.terminal_focused = clients.any_focused().await;
+        state_guard.sync_focus();
         let count = clients.count().await as u32;
         let _ = broadcast_tx.send(ServerResponse::ClientCountChanged(count));
-    }
+        queue
+    };
 
-    // Send initial layout state using this client's active workspace
-    {
-        let state = state.lock().await;
-        let client_ws = clients.get_active_workspace(client_id).await.unwrap_or(0);
-        let render_state = render_state_for_client(&state, client_ws);
-        framing::send(&mut stream, &ServerResponse::LayoutChanged { render_state }).await?;
-
-        // Collect current screen content for all panes so the client can render
-        // output that arrived before it connected.
-        let mut screen_dumps: Vec<(pane_protocol::layout::TabId, Vec<u8>)> = Vec::new();
-        for ws in &state.workspaces {
-            for group in ws.groups.values() {
-                for pane in &group.tabs {
-                    let data = pane.screen().state_formatted();
-                    if !data.is_empty() {
-                        screen_dumps.push((pane.id, data));
-                    }
-                }
-            }
-        }
-        drop(state); // release lock before sending
-        for (pane_id, data) in screen_dumps {
-            framing::send(
-                &mut stream,
-                &ServerResponse::FullScreenDump { pane_id, data },
-            )
-            .await?;
-        }
-    }
+    // Clients that negotiated it only get output for the tabs they show
+    let view = capabilities
+        .contains(&Capability::VisibleOutput)
+        .then(|| Arc::new(std::sync::Mutex::new(ClientView::default())));
 
     // Split the stream for bidirectional communication
-    let (read_half, write_half) = stream.into_split();
+    let (read_half, mut write_half) = tokio::io::split(stream);
     let mut read_stream = read_half;
-    let write_stream = Arc::new(Mutex::new(write_half));
 
-    // Spawn a task to forward broadcasts to this client
-    let write_clone = Arc::clone(&write_stream);
+    // Spawn a task to write the queue to the client. Only it waits on the
+    // socket, so a slow client holds up nobody else.
+    let writer_queue = Arc::clone(&queue);
+    let writer_task = tokio::spawn(async move {
+        use tokio::io::AsyncWriteExt;
+        while let Some(body) = writer_queue.pop().await {
+            if framing::write_frame(&mut write_half, &body).await.is_err() {
+                writer_queue.close();
+                return;
+            }
+        }
+        let _ = write_half.shutdown().await;
+    });
+
+    // Spawn a task to queue broadcasts and this client's own messages for
+    // it. Layouts are rendered again with the client's own focus, which also
+    // decides the tabs it gets output for. It also resyncs the client when
+    // its queue asks for it.
+    let forward_queue = Arc::clone(&queue);
+    let forward_state = Arc::clone(&state);
+    let forward_clients = clients.clone();
+    let forward_view = view.clone();
     let forward_task = tokio::spawn(async move {
-        while let Ok(response) = broadcast_rx.recv().await {
-            let mut writer = write_clone.lock().await;
-            // Reassemble a UnixStream-like writer for framing
-            let json = match serde_json::to_vec(&response) {
-                Ok(j) => j,
-                Err(_) => continue,
+        let mut thumbnails = tokio::time::interval(THUMBNAIL_INTERVAL);
+        thumbnails.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        // Broadcasts sent before the last resync's snapshot, whose output
+        // and layouts are already in it
+        let mut snapshotted = 0;
+        loop {
+            if forward_queue.resync_pending() {
+                let state = forward_state.lock().await;
+                // Output is only broadcast under the state lock, so what's
+                // waiting now is exactly what the snapshot already holds
+                snapshotted = broadcast_rx.len();
+                let focus = forward_clients.get_focus(client_id).await;
+                let responses = resync_responses(&state, focus, forward_view.as_deref(), encoding);
+                drop(state); // release lock before encoding
+                forward_queue.push_resync(responses.iter().filter_map(|response| {
+                    Some((encode_frame(response, encoding)?, frame_kind(response)))
+                }));
+            }
+            let response = tokio::select! {
+                response = broadcast_rx.recv() => match response {
+                    Ok(response) if snapshotted > 0 => {
+                        snapshotted -= 1;
+                        match frame_kind(&response) {
+                            FrameKind::Output | FrameKind::Layout => continue,
+                            _ => response,
+                        }
+                    }
+                    Ok(response) => response,
+                    // Missed broadcasts are made up for with fresh screens
+                    Err(broadcast::error::RecvError::Lagged(_)) => {
+                        snapshotted = 0;
+                        forward_queue.request_resync();
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                },
+                Some(control) = control_rx.recv() => match control {
+                    ClientControl::Send(response) => response,
+                    // Closing the queue closes the connection once it's written
+                    ClientControl::Detach => break,
+                },
+                _ = thumbnails.tick(), if forward_view.is_some() => {
+                    let stale = match &forward_view {
+                        Some(view) => view.lock().unwrap().take_stale(),
+                        None => Vec::new(),
+                    };
+                    if !stale.is_empty() {
+                        let state = forward_state.lock().await;
+                        let dumps = screen_dumps(&state, &stale, None);
+                        drop(state); // release lock before encoding
+                        for dump in dumps {
+                            if let Some(body) = encode_frame(&dump, encoding) {
+                                forward_queue.push(body, FrameKind::Output);
+                            }
+                        }
+                    }
+                    continue;
+                }
+                _ = forward_queue.resync_wanted() => continue,
             };
-            let len: u32 = match json.len().try_into() {
-                Ok(l) if l <= pane_protocol::framing::MAX_FRAME_SIZE => l,
-                _ => continue,
+            let mut dumps = Vec::new();
+            let response = match response {
+                // The render state is built once for everyone; each client
+                // only swaps in its own focus
+                ServerResponse::LayoutChanged { mut render_state } => {
+                    if let Some(focus) = forward_clients.get_focus(client_id).await {
+                        focus.apply_to(&mut render_state);
+                    }
+                    if let Some(view) = &forward_view {
+                        let shown = view.lock().unwrap().update(&render_state);
+                        if !shown.is_empty() {
+                            let state = forward_state.lock().await;
+                            dumps = screen_dumps(&state, &shown, Some(encoding));
+                            drop(state); // release lock before encoding
+                        }
+                    }
+                    ServerResponse::LayoutChanged { render_state }
+                }
+                // Hidden tabs are dumped when they come into view, and
+                // thumbnails on the next refresh
+                ServerResponse::PaneOutput { pane_id, .. }
+                | ServerResponse::FullScreenDump { pane_id, .. }
+                    if forward_view
+                        .as_ref()
+                        .is_some_and(|view| !view.lock().unwrap().wants_output(pane_id)) =>
+                {
+                    continue
+                }
+                other if !client_accepts(&capabilities, &other) => continue,
+                // Only one client acts on a tab's clipboard request
+                ServerResponse::ClipboardWrite { .. } | ServerResponse::ClipboardRead { .. }
+                    if forward_clients.clipboard_client().await != Some(client_id) =>
+                {
+                    continue
+                }
+                other => other,
             };
-            use tokio::io::AsyncWriteExt;
-            if writer.write_all(&len.to_be_bytes()).await.is_err() {
-                break;
-            }
-            if writer.write_all(&json).await.is_err() {
-                break;
+            for response in std::iter::once(response).chain(dumps) {
+                if let Some(body) = encode_frame(&response, encoding) {
+                    forward_queue.push(body, frame_kind(&response));
+                }
             }
-            let _ = writer.flush().await;
         }
+        forward_queue.close();
     });
 
     // Read client requests
@@ -673,105 +1154,138 @@ async fn handle_client(
         if read_stream.read_exact(&mut buf).await.is_err() {
             break;
         }
-        let request: ClientRequest = match serde_json::from_slice(&buf) {
+        let request: ClientRequest = match framing::decode(&buf) {
             Ok(r) => r,
             Err(_) => continue,
         };
 
+        if read_only && is_input(&request) {
+            if let ClientRequest::Command(_) = request {
+                let refusal = ServerResponse::Error("client is read-only".to_string());
+                if let Some(body) = encode_frame(&refusal, encoding) {
+                    queue.push(body, FrameKind::Other);
+                }
+            }
+            continue;
+        }
+
         match request {
             ClientRequest::Detach => break,
             ClientRequest::Resize { width, height } => {
                 clients.update_size(client_id, width, height).await;
-                // Use the smallest terminal size across all connected clients
-                let (eff_w, eff_h) = clients.effective_size().await.unwrap_or((width, height));
                 let mut state = state.lock().await;
-                state.last_size = (eff_w, eff_h);
-                state.resize_all_tabs(eff_w, eff_h);
-                // Broadcast uses server's active_workspace; each client re-renders with their own
+                if !fit_to_clients(&mut state, &clients).await {
+                    let (w, h) = state.last_size;
+                    state.resize_all_tabs(w, h);
+                }
+                // Each client's forward task renders this with their own focus
                 let render_state = render_state_from_server(&state);
                 let _ = broadcast_tx.send(ServerResponse::LayoutChanged { render_state });
             }
             ClientRequest::Key(sk) => {
                 let key_event = sk.into();
+                note_activity(&state, &clients, client_id, &broadcast_tx).await;
                 let mut state = state.lock().await;
                 if state.workspaces.is_empty() { continue; }
-                // Set state to this client's active workspace
-                if let Some(cws) = clients.get_active_workspace(client_id).await {
-                    state.active_workspace = cws;
-                }
+                clients.apply_focus(client_id, &mut state).await;
                 // Forward key to the active pane as raw bytes
                 let ws = state.active_workspace_mut();
                 let group = ws.groups.get_mut(&ws.active_group);
                 if let Some(group) = group {
                     let pane = group.active_tab_mut();
                     let app_cursor = pane.screen().application_cursor();
-                    let bytes = pane_protocol::keys::key_to_bytes(key_event, app_cursor);
+                    let flags = pane.screen().keyboard_flags();
+                    let bytes = pane_protocol::keys::encode_key(key_event, app_cursor, flags);
                     if !bytes.is_empty() {
                         pane.write_input(&bytes);
                     }
                 }
             }
-            ClientRequest::MouseDown { x, y } => {
+            ClientRequest::MouseDown {
+                x,
+                y,
+                button,
+                modifiers,
+            } => {
+                note_activity(&state, &clients, client_id, &broadcast_tx).await;
                 let mut state = state.lock().await;
                 if state.workspaces.is_empty() { continue; }
-                if let Some(cws) = clients.get_active_workspace(client_id).await {
-                    state.active_workspace = cws;
-                }
-                handle_mouse_down_server(&mut state, x, y);
-                let cws = state.active_workspace;
-                let render_state = render_state_for_client(&state, cws);
+                clients.apply_focus(client_id, &mut state).await;
+                let view = clients.size(client_id).await.unwrap_or(state.last_size);
+                handle_mouse_down_server(&mut state, view, x, y, button, modifiers);
+                clients
+                    .set_focus(client_id, ClientFocus::capture(&state))
+                    .await;
+                let render_state = render_state_from_server(&state);
                 let _ = broadcast_tx.send(ServerResponse::LayoutChanged { render_state });
             }
-            ClientRequest::MouseDrag { x, y } => {
+            ClientRequest::MouseDrag {
+                x,
+                y,
+                button,
+                modifiers,
+            } => {
                 let mut state = state.lock().await;
                 if state.workspaces.is_empty() { continue; }
-                if let Some(cws) = clients.get_active_workspace(client_id).await {
-                    state.active_workspace = cws;
-                }
+                clients.apply_focus(client_id, &mut state).await;
                 let had_drag = state.drag_state.is_some();
                 handle_mouse_drag_server(&mut state, x, y);
                 if had_drag {
-                    let cws = state.active_workspace;
-                    let render_state = render_state_for_client(&state, cws);
+                    let render_state = render_state_from_server(&state);
                     let _ = broadcast_tx.send(ServerResponse::LayoutChanged { render_state });
                 } else {
-                    // Forward drag to PTY if the process wants button-motion events
-                    forward_mouse_to_pty(&mut state, 32, x, y, true);
+                    // Drags stay with the window the button went down in
+                    let active = state.active_workspace().active_group;
+                    let code = mouse_button_code(button, modifiers) + 32;
+                    let view = clients.size(client_id).await.unwrap_or(state.last_size);
+                    forward_mouse_to_pty(&mut state, view, active, code, MouseReport::Drag, x, y);
                 }
             }
-            ClientRequest::MouseMove { x, y } => {
-                // Forward motion to PTY if the process wants any-motion events
+            ClientRequest::MouseMove { x, y, modifiers } => {
                 let mut state = state.lock().await;
                 if state.workspaces.is_empty() { continue; }
-                if let Some(cws) = clients.get_active_workspace(client_id).await {
-                    state.active_workspace = cws;
+                clients.apply_focus(client_id, &mut state).await;
+                let view = clients.size(client_id).await.unwrap_or(state.last_size);
+                if let Some(id) = window_at(&state, view, x, y) {
+                    let code = with_modifiers(35, modifiers);
+                    forward_mouse_to_pty(&mut state, view, id, code, MouseReport::Motion, x, y);
                 }
-                forward_mouse_to_pty(&mut state, 35, x, y, true);
             }
-            ClientRequest::MouseUp { x, y } => {
+            ClientRequest::MouseUp {
+                x,
+                y,
+                button,
+                modifiers,
+            } => {
                 let mut state = state.lock().await;
                 if state.workspaces.is_empty() { continue; }
-                if let Some(cws) = clients.get_active_workspace(client_id).await {
-                    state.active_workspace = cws;
-                }
+                clients.apply_focus(client_id, &mut state).await;
                 let had_drag = state.drag_state.is_some();
                 if had_drag {
                     state.drag_state = None;
                     let (w, h) = state.last_size;
                     state.resize_all_tabs(w, h);
-                    let cws = state.active_workspace;
-                    let render_state = render_state_for_client(&state, cws);
+                    let render_state = render_state_from_server(&state);
                     let _ = broadcast_tx.send(ServerResponse::LayoutChanged { render_state });
                 } else {
-                    forward_mouse_to_pty(&mut state, 0, x, y, false);
+                    let active = state.active_workspace().active_group;
+                    let code = mouse_button_code(button, modifiers);
+                    let view = clients.size(client_id).await.unwrap_or(state.last_size);
+                    forward_mouse_to_pty(
+                        &mut state,
+                        view,
+                        active,
+                        code,
+                        MouseReport::Release,
+                        x,
+                        y,
+                    );
                 }
             }
             ClientRequest::MouseScroll { up } => {
                 let mut state = state.lock().await;
                 if state.workspaces.is_empty() { continue; }
-                if let Some(cws) = clients.get_active_workspace(client_id).await {
-                    state.active_workspace = cws;
-                }
+                clients.apply_focus(client_id, &mut state).await;
                 // Forward scroll to PTY if the process wants mouse events
                 let mouse_mode = state
                     .active_workspace()
@@ -795,12 +1309,27 @@ async fn handle_client(
                     state.scroll_active_tab(|p| p.scroll_down(3));
                 }
             }
+            ClientRequest::MouseWheel {
+                x,
+                y,
+                direction,
+                modifiers,
+            } => {
+                let mut state = state.lock().await;
+                if state.workspaces.is_empty() {
+                    continue;
+                }
+                clients.apply_focus(client_id, &mut state).await;
+                let view = clients.size(client_id).await.unwrap_or(state.last_size);
+                if let Some(id) = window_at(&state, view, x, y) {
+                    handle_mouse_wheel_server(&mut state, view, id, x, y, direction, modifiers);
+                }
+            }
             ClientRequest::Paste(text) => {
+                note_activity(&state, &clients, client_id, &broadcast_tx).await;
                 let mut state = state.lock().await;
                 if state.workspaces.is_empty() { continue; }
-                if let Some(cws) = clients.get_active_workspace(client_id).await {
-                    state.active_workspace = cws;
-                }
+                clients.apply_focus(client_id, &mut state).await;
                 let bytes = text.into_bytes();
                 if !bytes.is_empty() {
                     let ws = state.active_workspace_mut();
@@ -810,6 +1339,7 @@ async fn handle_client(
                 }
             }
             ClientRequest::Command(cmd) => {
+                note_activity(&state, &clients, client_id, &broadcast_tx).await;
                 if handle_command(&cmd, &state, &id_map, &broadcast_tx, &clients, client_id).await {
                     break;
                 }
@@ -817,21 +1347,19 @@ async fn handle_client(
             ClientRequest::FocusWindow { id } => {
                 let mut state = state.lock().await;
                 if state.workspaces.is_empty() { continue; }
-                if let Some(cws) = clients.get_active_workspace(client_id).await {
-                    state.active_workspace = cws;
-                }
+                clients.apply_focus(client_id, &mut state).await;
                 let bar_h = state.workspace_bar_height();
                 state.focus_group(id, bar_h);
-                let cws = state.active_workspace;
-                let render_state = render_state_for_client(&state, cws);
+                clients
+                    .set_focus(client_id, ClientFocus::capture(&state))
+                    .await;
+                let render_state = render_state_from_server(&state);
                 let _ = broadcast_tx.send(ServerResponse::LayoutChanged { render_state });
             }
             ClientRequest::SelectTab { window_id, tab_index } => {
                 let mut state = state.lock().await;
                 if state.workspaces.is_empty() { continue; }
-                if let Some(cws) = clients.get_active_workspace(client_id).await {
-                    state.active_workspace = cws;
-                }
+                clients.apply_focus(client_id, &mut state).await;
                 let bar_h = state.workspace_bar_height();
                 state.focus_group(window_id, bar_h);
                 let ws = state.active_workspace_mut();
@@ -840,12 +1368,14 @@ async fn handle_client(
                         group.active_tab = tab_index;
                     }
                 }
-                let cws = state.active_workspace;
-                let render_state = render_state_for_client(&state, cws);
+                clients
+                    .set_focus(client_id, ClientFocus::capture(&state))
+                    .await;
+                let render_state = render_state_from_server(&state);
                 let _ = broadcast_tx.send(ServerResponse::LayoutChanged { render_state });
             }
-            ClientRequest::Attach | ClientRequest::AttachV2 { .. } => {
-                // Already attached, ignore
+            ClientRequest::Auth { .. } | ClientRequest::Attach | ClientRequest::AttachV2 { .. } => {
+                // Already authenticated and attached, ignore
             }
             ClientRequest::CommandSync(_) => {
                 // CommandSync is handled before attach; ignore if received mid-session
@@ -855,11 +1385,10 @@ async fn handle_client(
                 // Used by the native macOS app where ghostty has already
                 // processed the key into terminal byte sequences.
                 if bytes.is_empty() { continue; }
+                note_activity(&state, &clients, client_id, &broadcast_tx).await;
                 let mut state = state.lock().await;
                 if state.workspaces.is_empty() { continue; }
-                if let Some(cws) = clients.get_active_workspace(client_id).await {
-                    state.active_workspace = cws;
-                }
+                clients.apply_focus(client_id, &mut state).await;
                 let ws = state.active_workspace_mut();
                 if let Some(group) = ws.groups.get_mut(&ws.active_group) {
                     group.active_tab_mut().write_input(&bytes);
@@ -873,10 +1402,56 @@ async fn handle_client(
                     tab.resize_pty_with_pixels(cols, rows, pixel_width, pixel_height);
                 }
             }
+            ClientRequest::FocusChanged { focused } => {
+                clients.set_focused(client_id, focused).await;
+                let mut state = state.lock().await;
+                state.terminal_focused = clients.any_focused().await;
+            }
+            ClientRequest::SetBuffer(text) => {
+                if !text.is_empty() {
+                    state.lock().await.buffers.push(text);
+                }
+            }
+            ClientRequest::OverviewChanged { open } => {
+                if let Some(view) = &view {
+                    view.lock().unwrap().set_overview(open);
+                    // A fresh layout has the forward task dump the tabs now in view
+                    let render_state = render_state_from_server(&*state.lock().await);
+                    let response = ServerResponse::LayoutChanged { render_state };
+                    clients.send(client_id, ClientControl::Send(response)).await;
+                }
+            }
+            ClientRequest::ListBuffers => {
+                let summaries = {
+                    let state = state.lock().await;
+                    state
+                        .buffers
+                        .iter()
+                        .map(|b| BufferSummary {
+                            name: b.name.clone(),
+                            size: b.data.len(),
+                            preview: b.data.chars().take(BUFFER_PREVIEW_CHARS).collect(),
+                        })
+                        .collect()
+                };
+                let response = ServerResponse::BufferList(summaries);
+                if let Some(body) = encode_frame(&response, encoding) {
+                    queue.push(body, FrameKind::Other);
+                }
+            }
+            ClientRequest::ClipboardReply { pane_id, data, .. } => {
+                // The tab gets the selection it asked for, whatever the
+                // client says
+                state.lock().await.answer_clipboard_read(pane_id, &data);
+            }
         }
+
+        // Let tabs know if the request moved keyboard focus
+        state.lock().await.sync_focus();
     }
 
     forward_task.abort();
+    writer_task.abort();
 
     // Client disconnected: unregister and recalculate effective size
     clients.unregister(client_id).await;
@@ -884,10 +1459,14 @@ async fn handle_client(
         let count = clients.count().await as u32;
         let _ = broadcast_tx.send(ServerResponse::ClientCountChanged(count));
     }
-    if let Some((eff_w, eff_h)) = clients.effective_size().await {
+    {
+        // With nobody left looking, the focused tab loses focus
         let mut state = state.lock().await;
-        state.last_size = (eff_w, eff_h);
-        state.resize_all_tabs(eff_w, eff_h);
+        state.terminal_focused = clients.any_focused().await;
+        state.sync_focus();
+    }
+    let mut state = state.lock().await;
+    if fit_to_clients(&mut state, &clients).await {
         let render_state = render_state_from_server(&state);
         let _ = broadcast_tx.send(ServerResponse::LayoutChanged { render_state });
     }
@@ -895,12 +1474,212 @@ async fn handle_client(
     Ok(())
 }
 
-/// Handle mouse down events server-side (pane focus changes).
-fn handle_mouse_down_server(state: &mut ServerState, x: u16, y: u16) {
+/// Requests that change the session or write to tabs, which read-only
+/// clients may not send. Reporting their own terminal size and looking
+/// around are allowed, but sizing tabs isn't.
+fn is_input(request: &ClientRequest) -> bool {
+    matches!(
+        request,
+        ClientRequest::Key(_)
+            | ClientRequest::RawInput(_)
+            | ClientRequest::Paste(_)
+            | ClientRequest::Command(_)
+            | ClientRequest::MouseDown { .. }
+            | ClientRequest::MouseDrag { .. }
+            | ClientRequest::MouseMove { .. }
+            | ClientRequest::MouseUp { .. }
+            | ClientRequest::MouseScroll { .. }
+            | ClientRequest::MouseWheel { .. }
+            | ClientRequest::ClipboardReply { .. }
+            | ClientRequest::SetBuffer(_)
+            | ClientRequest::SetPaneSize { .. }
+    )
+}
+
+/// Run the commands that manage attached clients, which need the registry
+/// rather than just the session. `caller` is the client running it, if
+/// attached. Returns `None` for any other command.
+async fn execute_client_command(
+    cmd: &Command,
+    state: &mut ServerState,
+    clients: &ClientRegistry,
+    caller: Option<u64>,
+) -> Option<Result<String>> {
+    match cmd {
+        Command::ListClients => {
+            let lines: Vec<String> = clients
+                .list()
+                .await
+                .into_iter()
+                .map(|(id, info)| {
+                    let kind = match info.client_type {
+                        ClientType::Tui => "tui",
+                        ClientType::NativeApp => "native",
+                    };
+                    let ws = info
+                        .focus
+                        .active_workspace
+                        .min(state.workspaces.len().saturating_sub(1));
+                    let ws_name = state.workspaces.get(ws).map_or("", |ws| ws.name.as_str());
+                    format!(
+                        "{}: {} [{}x{}] workspace {} ({}) attached {}{}",
+                        id,
+                        kind,
+                        info.width,
+                        info.height,
+                        ws,
+                        ws_name,
+                        info.attached_at.format("%Y-%m-%d %H:%M:%S"),
+                        if info.read_only { " (read-only)" } else { "" }
+                    )
+                })
+                .collect();
+            Some(Ok(lines.join("\n")))
+        }
+        Command::ClientStats => {
+            let lines: Vec<String> = clients
+                .list()
+                .await
+                .into_iter()
+                .filter_map(|(id, info)| {
+                    let stats = info.queue?.stats();
+                    Some(format!(
+                        "{}: {} queued ({} KiB of {} KiB), {} output frames dropped, {} resyncs",
+                        id,
+                        stats.frames,
+                        stats.bytes.div_ceil(1024),
+                        stats.limit / 1024,
+                        stats.dropped,
+                        stats.resyncs
+                    ))
+                })
+                .collect();
+            Some(Ok(lines.join("\n")))
+        }
+        Command::DetachClient { target, others } if target.is_some() || *others => {
+            let ids: Vec<u64> = clients.list().await.into_iter().map(|(id, _)| id).collect();
+            if let Some(target) = target {
+                if !ids.contains(target) {
+                    return Some(Err(anyhow::anyhow!("no client {}", target)));
+                }
+            }
+            let keep = target.or(caller);
+            for id in ids {
+                let detach = if *others {
+                    Some(id) != keep
+                } else {
+                    Some(id) == *target
+                };
+                if detach {
+                    clients.send(id, ClientControl::Detach).await;
+                }
+            }
+            Some(Ok(String::new()))
+        }
+        Command::SwitchClient { client, workspace } => {
+            let Some(id) = client.or(caller) else {
+                return Some(Err(anyhow::anyhow!("no current client")));
+            };
+            let Some(mut focus) = clients.get_focus(id).await else {
+                return Some(Err(anyhow::anyhow!("no client {}", id)));
+            };
+            let count = state.workspaces.len();
+            if count == 0 {
+                return Some(Err(anyhow::anyhow!("no workspaces")));
+            }
+            let current = focus.active_workspace.min(count - 1);
+            let index = match workspace {
+                WorkspaceTarget::Next => (current + 1) % count,
+                WorkspaceTarget::Previous => (current + count - 1) % count,
+                WorkspaceTarget::Named(name) => {
+                    let found = name
+                        .parse::<usize>()
+                        .ok()
+                        .filter(|i| *i < count)
+                        .or_else(|| state.workspaces.iter().position(|ws| ws.name == *name));
+                    match found {
+                        Some(index) => index,
+                        None => return Some(Err(anyhow::anyhow!("no workspace {}", name))),
+                    }
+                }
+            };
+            if Some(id) == caller {
+                // The caller's focus is captured from the state afterwards
+                state.active_workspace = index;
+            } else {
+                focus.active_workspace = index;
+                clients.set_focus(id, focus).await;
+            }
+            let response = ServerResponse::SwitchWorkspace(index);
+            clients.send(id, ClientControl::Send(response)).await;
+            Some(Ok(String::new()))
+        }
+        _ => None,
+    }
+}
+
+/// Resize every tab to the size the `window_size` policy picks from the
+/// attached clients. Returns whether the size changed.
+async fn fit_to_clients(state: &mut ServerState, clients: &ClientRegistry) -> bool {
+    let (policy, manual) = state.window_size();
+    match clients.effective_size(policy, manual).await {
+        Some(size) if size != state.last_size => {
+            state.last_size = size;
+            state.resize_all_tabs(size.0, size.1);
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Record input from a client. Under the `latest` policy, input from a
+/// different client than last time resizes the session to it.
+async fn note_activity(
+    state: &Arc<Mutex<ServerState>>,
+    clients: &ClientRegistry,
+    client_id: u64,
+    broadcast_tx: &broadcast::Sender<ServerResponse>,
+) {
+    if !clients.touch(client_id).await {
+        return;
+    }
+    let mut state = state.lock().await;
+    if state.window_size().0 == WindowSize::Latest && fit_to_clients(&mut state, clients).await {
+        let render_state = render_state_from_server(&state);
+        let _ = broadcast_tx.send(ServerResponse::LayoutChanged { render_state });
+    }
+}
+
+/// Handle mouse down events server-side (pane focus changes). `view` is the
+/// size of the client's terminal, which its windows are laid out in.
+fn handle_mouse_down_server(
+    state: &mut ServerState,
+    view: (u16, u16),
+    x: u16,
+    y: u16,
+    button: MouseButton,
+    modifiers: MouseModifiers,
+) {
     state.drag_state = None;
+    let code = mouse_button_code(button, modifiers);
+
+    // Floating windows sit above the layout, and only left clicks on the
+    // layout resize splits, unfold windows or switch tabs
+    if let Some(id) = window_at(state, view, x, y) {
+        let floating = state
+            .active_workspace()
+            .floating_windows
+            .iter()
+            .any(|fw| fw.id == id);
+        if floating || button != MouseButton::Left {
+            state.active_workspace_mut().active_group = id;
+            forward_mouse_to_pty(state, view, id, code, MouseReport::Press, x, y);
+            return;
+        }
+    }
 
     let bar_h = state.workspace_bar_height();
-    let (w, h) = state.last_size;
+    let (w, h) = view;
     let body_height = h.saturating_sub(1 + bar_h);
     let body = ratatui::layout::Rect::new(0, bar_h, w, body_height);
 
@@ -965,12 +1744,14 @@ fn handle_mouse_down_server(state: &mut ServerState, x: u16, y: u16) {
                                     }
                                 }
                                 crate::tab_bar::TabBarClick::NewTab => {
+                                    let (w, h) = state.last_size;
                                     let cols = w.saturating_sub(2);
                                     let rows = h.saturating_sub(1 + bar_h + 2);
                                     let _ = state.add_tab_to_active_group(
                                         crate::window::TabKind::Shell,
                                         None,
                                         None,
+                                        None,
                                         cols,
                                         rows,
                                     );
@@ -983,20 +1764,7 @@ fn handle_mouse_down_server(state: &mut ServerState, x: u16, y: u16) {
                 let group_id = *group_id;
                 state.active_workspace_mut().active_group = group_id;
 
-                // Forward mouse press to PTY if the process wants mouse events
-                if let Some(content_rect) = window_content_rect(*rect) {
-                    if let Some(group) = state.active_workspace().groups.get(&group_id) {
-                        let tab = group.active_tab();
-                        let mode = tab.screen().mouse_protocol_mode();
-                        if mode != vt100::MouseProtocolMode::None {
-                            let encoding = tab.screen().mouse_protocol_encoding();
-                            let local_x = x.saturating_sub(content_rect.x);
-                            let local_y = y.saturating_sub(content_rect.y);
-                            let bytes = encode_mouse_sgr(0, local_x, local_y, true, encoding);
-                            state.active_workspace_mut().active_group_mut().active_tab_mut().write_input(&bytes);
-                        }
-                    }
-                }
+                forward_mouse_to_pty(state, view, group_id, code, MouseReport::Press, x, y);
 
                 return;
             }
@@ -1004,38 +1772,175 @@ fn handle_mouse_down_server(state: &mut ServerState, x: u16, y: u16) {
     }
 }
 
-/// Forward a mouse event to the active tab's PTY, translating coordinates to the content area.
-/// `button`: SGR button code (0=left, 1=mid, 2=right, 32=motion+left, 35=motion, 64/65=scroll)
-fn forward_mouse_to_pty(state: &mut ServerState, button: u8, x: u16, y: u16, pressed: bool) {
+/// Handle a wheel event over a window: the active tab gets it when it reports
+/// mouse events, and is scrolled otherwise.
+fn handle_mouse_wheel_server(
+    state: &mut ServerState,
+    view: (u16, u16),
+    id: WindowId,
+    x: u16,
+    y: u16,
+    direction: WheelDirection,
+    modifiers: MouseModifiers,
+) {
+    let code = with_modifiers(wheel_button_code(direction), modifiers);
+    if forward_mouse_to_pty(state, view, id, code, MouseReport::Press, x, y) {
+        return;
+    }
+    if let Some(group) = state.active_workspace_mut().groups.get_mut(&id) {
+        match direction {
+            WheelDirection::Up => group.active_tab_mut().scroll_up(3),
+            WheelDirection::Down => group.active_tab_mut().scroll_down(3),
+            WheelDirection::Left | WheelDirection::Right => {}
+        }
+    }
+}
+
+/// Windows of the active workspace and where a client with a `view` sized
+/// terminal draws them, topmost first.
+fn window_rects(state: &ServerState, view: (u16, u16)) -> Vec<(WindowId, ratatui::layout::Rect)> {
     let bar_h = state.workspace_bar_height();
-    let (w, h) = state.last_size;
+    let (w, h) = view;
     let body_height = h.saturating_sub(1 + bar_h);
     let body = ratatui::layout::Rect::new(0, bar_h, w, body_height);
 
-    let active_group_id = state.active_workspace().active_group;
     let ws = state.active_workspace();
-    let resolved = ws.layout.resolve_with_folds(body, &ws.folded_windows);
-    for rp in &resolved {
-        if let pane_protocol::layout::ResolvedPane::Visible { id, rect, .. } = rp {
-            if *id == active_group_id {
-                if let Some(content_rect) = window_content_rect(*rect) {
-                    if let Some(group) = state.active_workspace().groups.get(id) {
-                        let tab = group.active_tab();
-                        let mode = tab.screen().mouse_protocol_mode();
-                        if mode == vt100::MouseProtocolMode::None {
-                            return;
-                        }
-                        let encoding = tab.screen().mouse_protocol_encoding();
-                        let local_x = x.saturating_sub(content_rect.x);
-                        let local_y = y.saturating_sub(content_rect.y);
-                        let bytes = encode_mouse_sgr(button, local_x, local_y, pressed, encoding);
-                        state.active_workspace_mut().active_group_mut().active_tab_mut().write_input(&bytes);
-                    }
-                }
-                return;
+    let mut rects: Vec<_> = ws
+        .floating_windows
+        .iter()
+        .rev()
+        .map(|fw| {
+            (
+                fw.id,
+                ratatui::layout::Rect::new(fw.x, fw.y, fw.width, fw.height),
+            )
+        })
+        .collect();
+    if let Some(zoomed) = ws.zoomed_window {
+        rects.push((zoomed, body));
+    } else {
+        for rp in ws.layout.resolve_with_folds(body, &ws.folded_windows) {
+            if let pane_protocol::layout::ResolvedPane::Visible { id, rect, .. } = rp {
+                rects.push((id, rect));
             }
         }
     }
+    rects
+}
+
+/// The topmost window under (x, y).
+fn window_at(state: &ServerState, view: (u16, u16), x: u16, y: u16) -> Option<WindowId> {
+    window_rects(state, view)
+        .into_iter()
+        .find(|(_, rect)| rect.contains(ratatui::layout::Position { x, y }))
+        .map(|(id, _)| id)
+}
+
+/// Which kind of event a mouse report is, to check against the mode a tab
+/// asked for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum MouseReport {
+    Press,
+    Release,
+    Drag,
+    Motion,
+}
+
+impl MouseReport {
+    fn wanted_by(self, mode: vt100::MouseProtocolMode) -> bool {
+        use vt100::MouseProtocolMode as Mode;
+        match self {
+            MouseReport::Press => mode != Mode::None,
+            MouseReport::Release => {
+                matches!(
+                    mode,
+                    Mode::PressRelease | Mode::ButtonMotion | Mode::AnyMotion
+                )
+            }
+            MouseReport::Drag => matches!(mode, Mode::ButtonMotion | Mode::AnyMotion),
+            MouseReport::Motion => mode == Mode::AnyMotion,
+        }
+    }
+}
+
+/// Button code of a mouse button with the modifiers held.
+fn mouse_button_code(button: MouseButton, modifiers: MouseModifiers) -> u8 {
+    let code = match button {
+        MouseButton::Left => 0,
+        MouseButton::Middle => 1,
+        MouseButton::Right => 2,
+    };
+    with_modifiers(code, modifiers)
+}
+
+fn wheel_button_code(direction: WheelDirection) -> u8 {
+    match direction {
+        WheelDirection::Up => 64,
+        WheelDirection::Down => 65,
+        WheelDirection::Left => 66,
+        WheelDirection::Right => 67,
+    }
+}
+
+/// Add the modifier bits to a button code: shift 4, alt 8, ctrl 16.
+fn with_modifiers(code: u8, modifiers: MouseModifiers) -> u8 {
+    code | if modifiers.shift { 4 } else { 0 }
+        | if modifiers.alt { 8 } else { 0 }
+        | if modifiers.ctrl { 16 } else { 0 }
+}
+
+/// Forward a mouse event to a window's active tab, translating coordinates to
+/// its content area and to the part of the screen shown there. Returns false
+/// when the tab doesn't report this kind of event. `code` is the SGR button
+/// code, see [`encode_mouse_sgr`].
+fn forward_mouse_to_pty(
+    state: &mut ServerState,
+    view: (u16, u16),
+    id: WindowId,
+    code: u8,
+    report: MouseReport,
+    x: u16,
+    y: u16,
+) -> bool {
+    let Some(content_rect) = window_rects(state, view)
+        .into_iter()
+        .find(|(window, _)| *window == id)
+        .and_then(|(_, rect)| window_content_rect(rect))
+    else {
+        return false;
+    };
+    let Some(group) = state.active_workspace_mut().groups.get_mut(&id) else {
+        return false;
+    };
+    let tab = group.active_tab_mut();
+    let mode = tab.screen().mouse_protocol_mode();
+    if !report.wanted_by(mode) {
+        return false;
+    }
+    // X10 mode reports no modifiers
+    let code = if mode == vt100::MouseProtocolMode::Press {
+        code & !0b1_1100
+    } else {
+        code
+    };
+    let encoding = tab.screen().mouse_protocol_encoding();
+    // A screen bigger than the content area is shown around its cursor
+    let (origin_row, origin_col) = viewport_origin(
+        tab.screen().size(),
+        tab.screen().cursor_position(),
+        (content_rect.height, content_rect.width),
+    );
+    let local_x = origin_col + x.saturating_sub(content_rect.x);
+    let local_y = origin_row + y.saturating_sub(content_rect.y);
+    let bytes = encode_mouse_sgr(
+        code,
+        local_x,
+        local_y,
+        report != MouseReport::Release,
+        encoding,
+    );
+    tab.write_input(&bytes);
+    true
 }
 
 /// Compute the content area (where terminal output is rendered) within a window rect.
@@ -1059,7 +1964,8 @@ fn window_content_rect(rect: ratatui::layout::Rect) -> Option<ratatui::layout::R
 }
 
 /// Encode a mouse event for forwarding to the PTY.
-/// `button`: 0=left, 1=middle, 2=right, 64=scroll-up, 65=scroll-down
+/// `button`: 0=left, 1=middle, 2=right, 64-67=wheel up/down/left/right, plus
+/// 32 for motion and the modifier bits from [`with_modifiers`]
 /// `pressed`: true for press, false for release
 fn encode_mouse_sgr(
     button: u8,
@@ -1076,7 +1982,11 @@ fn encode_mouse_sgr(
         }
         _ => {
             // Default/UTF-8 encoding: \x1b[M CbCxCy (all +32, release=3)
-            let cb = if pressed { button + 32 } else { 3 + 32 };
+            let cb = if pressed {
+                button
+            } else {
+                3 | (button & 0b1_1100)
+            } + 32;
             let cx = (x as u8).saturating_add(33);
             let cy = (y as u8).saturating_add(33);
             vec![0x1b, b'[', b'M', cb, cx, cy]
@@ -1112,6 +2022,125 @@ fn handle_mouse_drag_server(state: &mut ServerState, x: u16, y: u16) {
     ws.layout.set_ratio_at_path(&drag.split_path, new_ratio);
 }
 
+/// Whether a client that negotiated `capabilities` understands `response`.
+/// Anything it doesn't is left out rather than sent as an unknown message.
+fn client_accepts(capabilities: &[Capability], response: &ServerResponse) -> bool {
+    let needed = match response {
+        ServerResponse::Passthrough { .. } => Capability::ImagePassthrough,
+        ServerResponse::ClipboardWrite { .. } | ServerResponse::ClipboardRead { .. } => {
+            Capability::Clipboard
+        }
+        ServerResponse::SwitchWorkspace(_) => Capability::ClientFocus,
+        _ => return true,
+    };
+    capabilities.contains(&needed)
+}
+
+/// Encode one response for a client's queue in its encoding. Responses too
+/// big for a frame are logged and skipped.
+fn encode_frame(response: &ServerResponse, encoding: Encoding) -> Option<Vec<u8>> {
+    let body = framing::encode_response(response, encoding).ok()?;
+    if body.len() > pane_protocol::framing::MAX_FRAME_SIZE as usize {
+        eprintln!(
+            "pane: dropping {} byte {:?} frame, over the frame size limit",
+            body.len(),
+            frame_kind(response)
+        );
+        return None;
+    }
+    Some(body)
+}
+
+/// The most screen dump data that is sure to fit in one frame once encoded.
+/// Binary frames carry it raw after a 17 byte header; JSON writes each byte
+/// as a number of up to three digits and a comma.
+fn dump_budget(encoding: Encoding) -> usize {
+    let max = pane_protocol::framing::MAX_FRAME_SIZE as usize;
+    match encoding {
+        Encoding::Binary => max - 1024,
+        Encoding::Json => max / 4 - 1024,
+    }
+}
+
+/// How a client's queue treats a response when the client falls behind.
+fn frame_kind(response: &ServerResponse) -> FrameKind {
+    match response {
+        ServerResponse::PaneOutput { .. } | ServerResponse::FullScreenDump { .. } => {
+            FrameKind::Output
+        }
+        ServerResponse::LayoutChanged { .. } => FrameKind::Layout,
+        ServerResponse::StatsUpdate(_) => FrameKind::Stats,
+        _ => FrameKind::Other,
+    }
+}
+
+/// Responses that bring a client up to date from scratch: its layout with
+/// its own focus, then the screen of every tab it shows, or of every tab
+/// when it doesn't track a view.
+fn resync_responses(
+    state: &ServerState,
+    focus: Option<ClientFocus>,
+    view: Option<&std::sync::Mutex<ClientView>>,
+    encoding: Encoding,
+) -> Vec<ServerResponse> {
+    let render_state = match focus {
+        Some(focus) => render_state_for_client(state, &focus),
+        None => render_state_from_server(state),
+    };
+    let tabs: Vec<TabId> = state
+        .workspaces
+        .iter()
+        .flat_map(|ws| ws.groups.values())
+        .flat_map(|group| group.tabs.iter().map(|tab| tab.id))
+        .collect();
+    let scrollback = view.is_some().then_some(encoding);
+    let tabs = match view {
+        Some(view) => {
+            let mut view = view.lock().unwrap();
+            view.update(&render_state);
+            tabs.into_iter().filter(|id| view.shows(*id)).collect()
+        }
+        None => tabs,
+    };
+    let mut responses = vec![ServerResponse::LayoutChanged { render_state }];
+    responses.extend(screen_dumps(state, &tabs, scrollback));
+    responses
+}
+
+/// Screen dumps of the given tabs, skipping blank ones. With `scrollback`,
+/// the encoding of the client they're for, they carry each tab's history
+/// too, for a client that rebuilds the tab from its dump. A tab whose
+/// history wouldn't fit in a frame is dumped without it.
+fn screen_dumps(
+    state: &ServerState,
+    tabs: &[TabId],
+    scrollback: Option<Encoding>,
+) -> Vec<ServerResponse> {
+    tabs.iter()
+        .filter_map(|&pane_id| {
+            let screen = state.find_tab(pane_id)?.screen();
+            let data = match scrollback {
+                Some(encoding) => {
+                    let data = screen.state_formatted_with_scrollback();
+                    if data.len() > dump_budget(encoding) {
+                        eprintln!(
+                            "pane: {} byte dump of tab {} is too big for a frame, \
+                             sending it without scrollback",
+                            data.len(),
+                            pane_id
+                        );
+                        screen.state_formatted()
+                    } else {
+                        data
+                    }
+                }
+                None => screen.state_formatted(),
+            };
+            (!data.is_empty()).then_some(ServerResponse::FullScreenDump { pane_id, data })
+        })
+        .collect()
+}
+
 /// Handle string commands from the command protocol.
 /// Returns `true` if the client should detach (break the read loop).
 async fn handle_command(
@@ -1125,17 +2154,29 @@ async fn handle_command(
     match command_parser::parse(cmd) {
         Ok(parsed_cmd) => {
             let mut state = state.lock().await;
-            // Set active workspace to this client's view
-            if let Some(cws) = clients.get_active_workspace(client_id).await {
-                state.active_workspace = cws;
-            }
+            clients.apply_focus(client_id, &mut state).await;
             let mut id_map = id_map.lock().await;
             let result =
-                crate::server::command::execute(&parsed_cmd, &mut state, &mut id_map, broadcast_tx);
-            // Sync back: command may have changed active_workspace (e.g. new-workspace, next-workspace)
+                match execute_client_command(&parsed_cmd, &mut state, clients, Some(client_id))
+                    .await
+                {
+                    Some(result) => result.map(crate::server::command::CommandResult::Ok),
+                    None => crate::server::command::execute(
+                        &parsed_cmd,
+                        &mut state,
+                        &mut id_map,
+                        broadcast_tx,
+                    ),
+                };
+            // Keep what the command focused (split, close, next-workspace, ...)
             clients
-                .set_active_workspace(client_id, state.active_workspace)
+                .set_focus(client_id, ClientFocus::capture(&state))
                 .await;
+            // Commands like resize-window and reload-config can change the size
+            if fit_to_clients(&mut state, clients).await {
+                let render_state = render_state_from_server(&state);
+                let _ = broadcast_tx.send(ServerResponse::LayoutChanged { render_state });
+            }
             match result {
                 Ok(crate::server::command::CommandResult::Ok(output)) => {
                     if !output.is_empty() {
@@ -1332,7 +2373,7 @@ pub async fn send_keys(keys: &str) -> Result<()> {
     // Drain the handshake responses (Attached + LayoutChanged + screen dumps)
     let _: ServerResponse = framing::recv_required(&mut stream).await?;
     // Read LayoutChanged, ignore
-    let _ = framing::recv::<ServerResponse>(&mut stream).await;
+    let _ = framing::recv::<_, ServerResponse>(&mut stream).await;
 
     // Parse keys: simple text for now, each character as a key event
     for ch in keys.chars() {
@@ -1354,6 +2395,7 @@ pub async fn send_keys(keys: &str) -> Result<()> {
 mod tests {
     use super::*;
     use pane_protocol::config::Config;
+    use pane_protocol::protocol::PROTOCOL_VERSION;
 
     /// Start a mini server on a Unix socket pair and return the client stream
     /// and a handle to drive the server side.
@@ -1383,11 +2425,9 @@ mod tests {
             while let Some(event) = event_rx.recv().await {
                 match event {
                     AppEvent::PtyOutput { pane_id, bytes } => {
-                        {
-                            let mut s = state_clone.lock().await;
-                            if let Some(pane) = s.find_tab_mut(pane_id) {
-                                let _ = pane.process_output(&bytes);
-                            }
+                        let mut s = state_clone.lock().await;
+                        if let Some(pane) = s.find_tab_mut(pane_id) {
+                            let _ = pane.process_output(&bytes);
                         }
                         let _ = btx_clone.send(ServerResponse::PaneOutput {
                             pane_id,
@@ -1465,6 +2505,398 @@ mod tests {
             .unwrap();
     }
 
+    /// Helper: start a TCP listener on a loopback port with the given token
+    /// and optional TLS certificate, returning the address it listens on.
+    async fn setup_tcp_server(token: &str, tls: bool) -> String {
+        let (event_tx, mut event_rx) = mpsc::unbounded_channel();
+        tokio::spawn(async move { while event_rx.recv().await.is_some() {} });
+        let state = ServerState::new_with_workspace(&event_tx, 80, 24, Config::default()).unwrap();
+        let (broadcast_tx, _) = broadcast::channel::<ServerResponse>(256);
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let tls = tls.then(|| {
+            let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/server/testdata");
+            tcp::tls_acceptor(&dir.join("localhost.pem"), &dir.join("localhost-key.pem")).unwrap()
+        });
+        tokio::spawn(accept_tcp(
+            listener,
+            tls,
+            Arc::from(token),
+            Arc::new(Mutex::new(state)),
+            Arc::new(Mutex::new(IdMap::new())),
+            broadcast_tx,
+            ClientRegistry::new(),
+        ));
+        addr
+    }
+
+    #[tokio::test]
+    async fn test_tcp_client_attaches_with_token() {
+        let addr = setup_tcp_server("s3cret", false).await;
+        let (mut reader, mut writer) = tcp::connect(&addr, "s3cret", None).await.unwrap();
+
+        framing::send(&mut writer, &ClientRequest::Attach)
+            .await
+            .unwrap();
+        let resp: ServerResponse = tokio::time::timeout(
+            std::time::Duration::from_secs(5),
+            framing::recv_required(&mut reader),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert!(matches!(resp, ServerResponse::Attached), "got {:?}", resp);
+        framing::send(&mut writer, &ClientRequest::Detach)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_tcp_client_with_wrong_token_is_refused() {
+        let addr = setup_tcp_server("s3cret", false).await;
+        let (mut reader, mut writer) = tcp::connect(&addr, "guess", None).await.unwrap();
+
+        framing::send(&mut writer, &ClientRequest::Attach)
+            .await
+            .unwrap();
+        let resp: ServerResponse = framing::recv_required(&mut reader).await.unwrap();
+        assert!(
+            matches!(&resp, ServerResponse::Error(msg) if msg == "authentication failed"),
+            "got {:?}",
+            resp
+        );
+        let eof: Option<ServerResponse> = framing::recv(&mut reader).await.unwrap_or(None);
+        assert!(eof.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_tcp_client_must_authenticate_before_attach() {
+        let addr = setup_tcp_server("s3cret", false).await;
+        let mut stream = tokio::net::TcpStream::connect(&addr).await.unwrap();
+
+        framing::send(&mut stream, &ClientRequest::Attach)
+            .await
+            .unwrap();
+        let resp: ServerResponse = framing::recv_required(&mut stream).await.unwrap();
+        assert!(matches!(resp, ServerResponse::Error(_)), "got {:?}", resp);
+    }
+
+    #[tokio::test]
+    async fn test_tls_client_attaches_with_token() {
+        let addr = setup_tcp_server("s3cret", true).await;
+        let cert = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/server/testdata/localhost.pem");
+        let (mut reader, mut writer) = tcp::connect(&addr, "s3cret", Some(&cert)).await.unwrap();
+
+        framing::send(&mut writer, &ClientRequest::Attach)
+            .await
+            .unwrap();
+        let resp: ServerResponse = tokio::time::timeout(
+            std::time::Duration::from_secs(5),
+            framing::recv_required(&mut reader),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert!(matches!(resp, ServerResponse::Attached), "got {:?}", resp);
+        framing::send(&mut writer, &ClientRequest::Detach)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_attach_negotiates_protocol_and_capabilities() {
+        let (mut client, handle, _state) = setup_test_server().await;
+
+        framing::send(
+            &mut client,
+            &ClientRequest::AttachV2 {
+                client_type: ClientType::Tui,
+                read_only: false,
+                protocol_version: PROTOCOL_VERSION + 1,
+                capabilities: vec![Capability::Clipboard, Capability::Unknown],
+            },
+        )
+        .await
+        .unwrap();
+        let resp: ServerResponse = framing::recv_required(&mut client).await.unwrap();
+        match resp {
+            ServerResponse::AttachedV2 {
+                protocol_version,
+                capabilities,
+            } => {
+                assert_eq!(protocol_version, PROTOCOL_VERSION);
+                assert_eq!(capabilities, vec![Capability::Clipboard]);
+            }
+            other => panic!("expected AttachedV2, got {:?}", other),
+        }
+
+        framing::send(&mut client, &ClientRequest::Detach)
+            .await
+            .unwrap();
+        let _ = tokio::time::timeout(std::time::Duration::from_secs(5), handle).await;
+    }
+
+    #[tokio::test]
+    async fn test_binary_frames_after_negotiation() {
+        let (mut client, handle, _state) = setup_test_server().await;
+
+        framing::send(
+            &mut client,
+            &ClientRequest::AttachV2 {
+                client_type: ClientType::Tui,
+                read_only: false,
+                protocol_version: PROTOCOL_VERSION,
+                capabilities: vec![Capability::BinaryFrames],
+            },
+        )
+        .await
+        .unwrap();
+        // The answer itself is JSON, so a client can read it before it knows
+        let body = framing::read_frame(&mut client).await.unwrap().unwrap();
+        assert_eq!(body.first(), Some(&b'{'));
+
+        let body = framing::read_frame(&mut client).await.unwrap().unwrap();
+        assert_ne!(body.first(), Some(&b'{'));
+        let resp = framing::decode_response(&body).unwrap();
+        assert!(
+            matches!(resp, ServerResponse::LayoutChanged { .. }),
+            "got {:?}",
+            resp
+        );
+
+        // Requests may come in either encoding
+        let resize = ClientRequest::Resize {
+            width: 100,
+            height: 30,
+        };
+        let body = framing::encode(&resize, Encoding::Binary).unwrap();
+        framing::write_frame(&mut client, &body).await.unwrap();
+        loop {
+            let body = tokio::time::timeout(
+                std::time::Duration::from_secs(5),
+                framing::read_frame(&mut client),
+            )
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+            if let ServerResponse::LayoutChanged { .. } = framing::decode_response(&body).unwrap() {
+                break;
+            }
+        }
+
+        framing::send(&mut client, &ClientRequest::Detach)
+            .await
+            .unwrap();
+        let _ = tokio::time::timeout(std::time::Duration::from_secs(5), handle).await;
+    }
+
+    /// Helper: serve one client with a broadcast channel of the given
+    /// capacity that the test sends on itself.
+    async fn setup_broadcast_server(
+        capacity: usize,
+    ) -> (
+        UnixStream,
+        tokio::task::JoinHandle<Result<()>>,
+        Arc<Mutex<ServerState>>,
+        ClientRegistry,
+        broadcast::Sender<ServerResponse>,
+    ) {
+        let (server_stream, client) = UnixStream::pair().unwrap();
+        let (event_tx, _event_rx) = mpsc::unbounded_channel();
+        let state = ServerState::new_with_workspace(&event_tx, 80, 24, Config::default()).unwrap();
+        let state = Arc::new(Mutex::new(state));
+        let clients = ClientRegistry::new();
+        let (broadcast_tx, broadcast_rx) = broadcast::channel::<ServerResponse>(capacity);
+        let handle = tokio::spawn(handle_client(
+            server_stream,
+            Arc::clone(&state),
+            Arc::new(Mutex::new(IdMap::new())),
+            broadcast_tx.clone(),
+            broadcast_rx,
+            clients.clone(),
+            0,
+        ));
+        (client, handle, state, clients, broadcast_tx)
+    }
+
+    #[tokio::test]
+    async fn test_lagging_client_is_resynced() {
+        let (mut client, handle, state, clients, broadcast_tx) = setup_broadcast_server(16).await;
+        attach_and_consume_initial(&mut client).await;
+
+        // More output than the channel holds, before the client's task runs
+        for _ in 0..64 {
+            let _ = broadcast_tx.send(ServerResponse::PaneOutput {
+                pane_id: TabId::new_v4(),
+                data: b"x".to_vec(),
+            });
+        }
+
+        loop {
+            let resp: ServerResponse = tokio::time::timeout(
+                std::time::Duration::from_secs(5),
+                framing::recv_required(&mut client),
+            )
+            .await
+            .unwrap()
+            .unwrap();
+            if let ServerResponse::LayoutChanged { .. } = resp {
+                break;
+            }
+        }
+        let stats = {
+            let mut state = state.lock().await;
+            execute_client_command(&Command::ClientStats, &mut state, &clients, None)
+                .await
+                .unwrap()
+                .unwrap()
+        };
+        assert!(stats.starts_with("0: "), "{}", stats);
+        assert!(stats.ends_with(", 1 resyncs"), "{}", stats);
+
+        framing::send(&mut client, &ClientRequest::Detach)
+            .await
+            .unwrap();
+        let _ = tokio::time::timeout(std::time::Duration::from_secs(5), handle).await;
+    }
+
+    #[tokio::test]
+    async fn test_output_only_for_shown_tabs() {
+        let (mut client, handle, _state, _clients, broadcast_tx) =
+            setup_broadcast_server(256).await;
+        framing::send(
+            &mut client,
+            &ClientRequest::AttachV2 {
+                client_type: ClientType::Tui,
+                read_only: false,
+                protocol_version: PROTOCOL_VERSION,
+                capabilities: vec![Capability::VisibleOutput],
+            },
+        )
+        .await
+        .unwrap();
+        let mut shown = None;
+        loop {
+            let resp: ServerResponse = framing::recv_required(&mut client).await.unwrap();
+            match resp {
+                ServerResponse::LayoutChanged { render_state } => {
+                    let ws = &render_state.workspaces[render_state.active_workspace];
+                    let group = ws.groups.iter().find(|g| g.id == ws.active_group).unwrap();
+                    shown = Some(group.tabs[group.active_tab].id);
+                }
+                ServerResponse::ClientCountChanged(_) => break,
+                _ => {}
+            }
+        }
+        let shown = shown.expect("initial layout");
+
+        for pane_id in [TabId::new_v4(), shown] {
+            let _ = broadcast_tx.send(ServerResponse::PaneOutput {
+                pane_id,
+                data: b"x".to_vec(),
+            });
+        }
+        loop {
+            let resp: ServerResponse = tokio::time::timeout(
+                std::time::Duration::from_secs(5),
+                framing::recv_required(&mut client),
+            )
+            .await
+            .unwrap()
+            .unwrap();
+            if let ServerResponse::PaneOutput { pane_id, .. } = resp {
+                assert_eq!(pane_id, shown);
+                break;
+            }
+        }
+
+        // Opening the overview sends a fresh layout
+        framing::send(&mut client, &ClientRequest::OverviewChanged { open: true })
+            .await
+            .unwrap();
+        loop {
+            let resp: ServerResponse = tokio::time::timeout(
+                std::time::Duration::from_secs(5),
+                framing::recv_required(&mut client),
+            )
+            .await
+            .unwrap()
+            .unwrap();
+            if let ServerResponse::LayoutChanged { .. } = resp {
+                break;
+            }
+        }
+
+        framing::send(&mut client, &ClientRequest::Detach)
+            .await
+            .unwrap();
+        let _ = tokio::time::timeout(std::time::Duration::from_secs(5), handle).await;
+    }
+
+    #[test]
+    fn test_client_accepts_only_negotiated_messages() {
+        let passthrough = ServerResponse::Passthrough {
+            pane_id: TabId::new_v4(),
+            data: vec![0x1b],
+        };
+        assert!(client_accepts(Capability::ALL, &passthrough));
+        assert!(!client_accepts(&[Capability::Clipboard], &passthrough));
+        assert!(!client_accepts(&[], &ServerResponse::SwitchWorkspace(1)));
+        assert!(client_accepts(&[], &ServerResponse::ClientCountChanged(2)));
+    }
+
+    #[tokio::test]
+    async fn test_read_only_client_cannot_run_commands() {
+        let (mut client, handle, state) = setup_test_server().await;
+
+        framing::send(
+            &mut client,
+            &ClientRequest::AttachV2 {
+                client_type: ClientType::Tui,
+                read_only: true,
+                protocol_version: 0,
+                capabilities: Vec::new(),
+            },
+        )
+        .await
+        .unwrap();
+        loop {
+            let resp: ServerResponse = framing::recv_required(&mut client).await.unwrap();
+            if matches!(resp, ServerResponse::ClientCountChanged(_)) {
+                break;
+            }
+        }
+
+        let before = state.lock().await.active_workspace().groups.len();
+        framing::send(
+            &mut client,
+            &ClientRequest::Command("split-window".to_string()),
+        )
+        .await
+        .unwrap();
+
+        let resp: ServerResponse = tokio::time::timeout(
+            std::time::Duration::from_secs(5),
+            framing::recv_required(&mut client),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert!(
+            matches!(&resp, ServerResponse::Error(msg) if msg == "client is read-only"),
+            "expected read-only refusal, got {:?}",
+            resp
+        );
+        assert_eq!(state.lock().await.active_workspace().groups.len(), before);
+
+        framing::send(&mut client, &ClientRequest::Detach)
+            .await
+            .unwrap();
+        let _ = tokio::time::timeout(std::time::Duration::from_secs(5), handle).await;
+    }
+
     #[tokio::test]
     async fn test_resize_triggers_layout_update() {
         let (mut client, handle, _state) = setup_test_server().await;
@@ -1614,25 +3046,166 @@ mod tests {
     #[tokio::test]
     async fn test_client_registry_effective_size_tui() {
         let registry = ClientRegistry::new();
-        assert_eq!(registry.effective_size().await, None);
+        assert_eq!(
+            registry
+                .effective_size(WindowSize::Smallest, (80, 24))
+                .await,
+            None
+        );
 
-        registry.register(1, 120, 40, 0, ClientType::Tui).await;
-        assert_eq!(registry.effective_size().await, Some((120, 40)));
+        registry
+            .register(
+                1,
+                ClientInfo::new(120, 40, ClientFocus::default(), ClientType::Tui),
+            )
+            .await;
+        assert_eq!(
+            registry
+                .effective_size(WindowSize::Smallest, (80, 24))
+                .await,
+            Some((120, 40))
+        );
 
-        registry.register(2, 80, 24, 0, ClientType::Tui).await;
-        assert_eq!(registry.effective_size().await, Some((80, 24)));
+        registry
+            .register(
+                2,
+                ClientInfo::new(80, 24, ClientFocus::default(), ClientType::Tui),
+            )
+            .await;
+        assert_eq!(
+            registry
+                .effective_size(WindowSize::Smallest, (80, 24))
+                .await,
+            Some((80, 24))
+        );
 
-        registry.register(3, 100, 30, 0, ClientType::Tui).await;
-        assert_eq!(registry.effective_size().await, Some((80, 24)));
+        registry
+            .register(
+                3,
+                ClientInfo::new(100, 30, ClientFocus::default(), ClientType::Tui),
+            )
+            .await;
+        assert_eq!(
+            registry
+                .effective_size(WindowSize::Smallest, (80, 24))
+                .await,
+            Some((80, 24))
+        );
 
         registry.unregister(2).await;
-        assert_eq!(registry.effective_size().await, Some((100, 30)));
+        assert_eq!(
+            registry
+                .effective_size(WindowSize::Smallest, (80, 24))
+                .await,
+            Some((100, 30))
+        );
 
         registry.unregister(1).await;
-        assert_eq!(registry.effective_size().await, Some((100, 30)));
+        assert_eq!(
+            registry
+                .effective_size(WindowSize::Smallest, (80, 24))
+                .await,
+            Some((100, 30))
+        );
+
+        registry.unregister(3).await;
+        assert_eq!(
+            registry
+                .effective_size(WindowSize::Smallest, (80, 24))
+                .await,
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_client_registry_focus() {
+        let registry = ClientRegistry::new();
+        assert!(!registry.any_focused().await);
+
+        registry
+            .register(
+                1,
+                ClientInfo::new(80, 24, ClientFocus::default(), ClientType::Tui),
+            )
+            .await;
+        registry
+            .register(
+                2,
+                ClientInfo::new(80, 24, ClientFocus::default(), ClientType::Tui),
+            )
+            .await;
+        assert!(registry.any_focused().await);
+
+        registry.set_focused(1, false).await;
+        assert!(registry.any_focused().await);
+        registry.set_focused(2, false).await;
+        assert!(!registry.any_focused().await);
+
+        registry.set_focused(1, true).await;
+        registry.unregister(1).await;
+        assert!(!registry.any_focused().await);
+    }
+
+    #[tokio::test]
+    async fn test_read_only_clients_neither_size_nor_focus() {
+        let registry = ClientRegistry::new();
+        let watcher = ClientInfo {
+            read_only: true,
+            ..ClientInfo::new(60, 20, ClientFocus::default(), ClientType::Tui)
+        };
+        registry.register(1, watcher).await;
+        assert_eq!(
+            registry
+                .effective_size(WindowSize::Smallest, (80, 24))
+                .await,
+            None
+        );
+        assert!(!registry.any_focused().await);
+
+        registry
+            .register(
+                2,
+                ClientInfo::new(100, 30, ClientFocus::default(), ClientType::Tui),
+            )
+            .await;
+        assert_eq!(
+            registry
+                .effective_size(WindowSize::Smallest, (80, 24))
+                .await,
+            Some((100, 30))
+        );
+        registry.set_focused(2, false).await;
+        assert!(!registry.any_focused().await);
+        assert!(is_input(&ClientRequest::SetPaneSize {
+            tab_id: TabId::new_v4(),
+            cols: 80,
+            rows: 24,
+            pixel_width: 0,
+            pixel_height: 0,
+        }));
+    }
+
+    #[tokio::test]
+    async fn test_clipboard_goes_to_one_writable_client() {
+        let registry = ClientRegistry::new();
+        let info = |read_only| ClientInfo {
+            read_only,
+            clipboard: true,
+            ..ClientInfo::new(80, 24, ClientFocus::default(), ClientType::Tui)
+        };
+        registry.register(1, info(false)).await;
+        registry.register(2, info(true)).await;
+        assert_eq!(registry.clipboard_client().await, Some(1));
 
+        // The latest client wins, unless only an older one has focus
+        registry.register(3, info(false)).await;
+        assert_eq!(registry.clipboard_client().await, Some(3));
+        registry.set_focused(3, false).await;
+        assert_eq!(registry.clipboard_client().await, Some(1));
+
+        registry.unregister(1).await;
         registry.unregister(3).await;
-        assert_eq!(registry.effective_size().await, None);
+        assert_eq!(registry.clipboard_client().await, None);
     }
 
     #[tokio::test]
@@ -1640,11 +3213,31 @@ mod tests {
         let registry = ClientRegistry::new();
 
         // Native clients use max() instead of min()
-        registry.register(1, 80, 24, 0, ClientType::NativeApp).await;
-        assert_eq!(registry.effective_size().await, Some((80, 24)));
+        registry
+            .register(
+                1,
+                ClientInfo::new(80, 24, ClientFocus::default(), ClientType::NativeApp),
+            )
+            .await;
+        assert_eq!(
+            registry
+                .effective_size(WindowSize::Smallest, (80, 24))
+                .await,
+            Some((80, 24))
+        );
 
-        registry.register(2, 120, 40, 0, ClientType::NativeApp).await;
-        assert_eq!(registry.effective_size().await, Some((120, 40)));
+        registry
+            .register(
+                2,
+                ClientInfo::new(120, 40, ClientFocus::default(), ClientType::NativeApp),
+            )
+            .await;
+        assert_eq!(
+            registry
+                .effective_size(WindowSize::Smallest, (80, 24))
+                .await,
+            Some((120, 40))
+        );
     }
 
     #[tokio::test]
@@ -1652,26 +3245,119 @@ mod tests {
         let registry = ClientRegistry::new();
 
         // TUI clients constrain size; native clients are ignored
-        registry.register(1, 120, 40, 0, ClientType::Tui).await;
-        registry.register(2, 200, 60, 0, ClientType::NativeApp).await;
+        registry
+            .register(
+                1,
+                ClientInfo::new(120, 40, ClientFocus::default(), ClientType::Tui),
+            )
+            .await;
+        registry
+            .register(
+                2,
+                ClientInfo::new(200, 60, ClientFocus::default(), ClientType::NativeApp),
+            )
+            .await;
         // Only TUI sizes matter when TUI clients exist
-        assert_eq!(registry.effective_size().await, Some((120, 40)));
+        assert_eq!(
+            registry
+                .effective_size(WindowSize::Smallest, (80, 24))
+                .await,
+            Some((120, 40))
+        );
 
-        registry.register(3, 80, 24, 0, ClientType::Tui).await;
-        assert_eq!(registry.effective_size().await, Some((80, 24)));
+        registry
+            .register(
+                3,
+                ClientInfo::new(80, 24, ClientFocus::default(), ClientType::Tui),
+            )
+            .await;
+        assert_eq!(
+            registry
+                .effective_size(WindowSize::Smallest, (80, 24))
+                .await,
+            Some((80, 24))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_client_registry_window_size_policies() {
+        let registry = ClientRegistry::new();
+        registry
+            .register(
+                1,
+                ClientInfo::new(200, 60, ClientFocus::default(), ClientType::Tui),
+            )
+            .await;
+        registry
+            .register(
+                2,
+                ClientInfo::new(80, 24, ClientFocus::default(), ClientType::Tui),
+            )
+            .await;
+        let manual = (100, 30);
+
+        assert_eq!(
+            registry.effective_size(WindowSize::Smallest, manual).await,
+            Some((80, 24))
+        );
+        assert_eq!(
+            registry.effective_size(WindowSize::Largest, manual).await,
+            Some((200, 60))
+        );
+        assert_eq!(
+            registry.effective_size(WindowSize::Manual, manual).await,
+            Some((100, 30))
+        );
+
+        // The client that attached or typed last wins
+        assert_eq!(
+            registry.effective_size(WindowSize::Latest, manual).await,
+            Some((80, 24))
+        );
+        assert!(registry.touch(1).await);
+        assert!(!registry.touch(1).await);
+        assert_eq!(
+            registry.effective_size(WindowSize::Latest, manual).await,
+            Some((200, 60))
+        );
+        registry.unregister(1).await;
+        assert_eq!(
+            registry.effective_size(WindowSize::Latest, manual).await,
+            Some((80, 24))
+        );
     }
 
     #[tokio::test]
     async fn test_client_registry_update_size() {
         let registry = ClientRegistry::new();
 
-        registry.register(1, 120, 40, 0, ClientType::Tui).await;
-        registry.register(2, 80, 24, 0, ClientType::Tui).await;
-        assert_eq!(registry.effective_size().await, Some((80, 24)));
+        registry
+            .register(
+                1,
+                ClientInfo::new(120, 40, ClientFocus::default(), ClientType::Tui),
+            )
+            .await;
+        registry
+            .register(
+                2,
+                ClientInfo::new(80, 24, ClientFocus::default(), ClientType::Tui),
+            )
+            .await;
+        assert_eq!(
+            registry
+                .effective_size(WindowSize::Smallest, (80, 24))
+                .await,
+            Some((80, 24))
+        );
 
         // Client 2 resizes larger
         registry.update_size(2, 200, 50).await;
-        assert_eq!(registry.effective_size().await, Some((120, 40)));
+        assert_eq!(
+            registry
+                .effective_size(WindowSize::Smallest, (80, 24))
+                .await,
+            Some((120, 40))
+        );
     }
 
     // --- CommandSync protocol tests ---
@@ -2067,4 +3753,127 @@ mod tests {
         assert_eq!(parser.screen().cell(0, 0).unwrap().contents(), "$");
         assert_eq!(parser.screen().cell(0, 2).unwrap().contents(), "h");
     }
+
+    #[tokio::test]
+    async fn test_oversized_dump_drops_scrollback() {
+        let (_client, _handle, state) = setup_test_server().await;
+
+        let mut s = state.lock().await;
+        let ws = s.active_workspace_mut();
+        let group = ws.groups.get_mut(&ws.active_group).unwrap();
+        let tab = group.active_tab_mut();
+        let pane_id = tab.id;
+        tab.vt = crate::window::replies::new_parser(24, 80, 100_000);
+        // Every cell in its own color: several MiB of history
+        let mut output = Vec::new();
+        for row in 0..12000 {
+            for col in 0..80 {
+                output.extend_from_slice(format!("\x1b[3{}mx", (row + col) % 8).as_bytes());
+            }
+            output.extend_from_slice(b"\r\n");
+        }
+        tab.process_output(&output);
+        let screen = tab.screen();
+        let full = screen.state_formatted_with_scrollback();
+        let visible = screen.state_formatted();
+        assert!(full.len() > dump_budget(Encoding::Json));
+        assert!(full.len() <= dump_budget(Encoding::Binary));
+
+        // A JSON client gets the screen without history, in one frame
+        let dumps = screen_dumps(&s, &[pane_id], Some(Encoding::Json));
+        assert!(
+            matches!(&dumps[..], [ServerResponse::FullScreenDump { data, .. }] if *data == visible)
+        );
+        assert!(encode_frame(&dumps[0], Encoding::Json).is_some());
+        // A binary client still gets all of it
+        let dumps = screen_dumps(&s, &[pane_id], Some(Encoding::Binary));
+        assert!(
+            matches!(&dumps[..], [ServerResponse::FullScreenDump { data, .. }] if *data == full)
+        );
+        assert!(encode_frame(&dumps[0], Encoding::Binary).is_some());
+    }
+
+    #[test]
+    fn test_encode_mouse_buttons_and_modifiers() {
+        let sgr = vt100::MouseProtocolEncoding::Sgr;
+        let shift_ctrl = MouseModifiers {
+            shift: true,
+            alt: false,
+            ctrl: true,
+        };
+        let right = mouse_button_code(MouseButton::Right, shift_ctrl);
+        assert_eq!(right, 22);
+        assert_eq!(encode_mouse_sgr(right, 4, 2, true, sgr), b"\x1b[<22;5;3M");
+        assert_eq!(encode_mouse_sgr(right, 4, 2, false, sgr), b"\x1b[<22;5;3m");
+        let wheel = wheel_button_code(WheelDirection::Right);
+        assert_eq!(encode_mouse_sgr(wheel, 0, 0, true, sgr), b"\x1b[<67;1;1M");
+
+        // Default encoding reports which button came up as 3, keeping modifiers
+        let default = vt100::MouseProtocolEncoding::Default;
+        let middle = mouse_button_code(MouseButton::Middle, MouseModifiers::default());
+        assert_eq!(encode_mouse_sgr(middle, 0, 0, true, default), b"\x1b[M!!!");
+        assert_eq!(encode_mouse_sgr(right, 0, 0, false, default), b"\x1b[M7!!");
+    }
+
+    #[test]
+    fn test_mouse_reports_follow_mode() {
+        use vt100::MouseProtocolMode as Mode;
+        assert!(!MouseReport::Press.wanted_by(Mode::None));
+        assert!(MouseReport::Press.wanted_by(Mode::Press));
+        assert!(!MouseReport::Release.wanted_by(Mode::Press));
+        assert!(MouseReport::Release.wanted_by(Mode::PressRelease));
+        assert!(!MouseReport::Drag.wanted_by(Mode::PressRelease));
+        assert!(MouseReport::Drag.wanted_by(Mode::ButtonMotion));
+        assert!(!MouseReport::Motion.wanted_by(Mode::ButtonMotion));
+        assert!(MouseReport::Motion.wanted_by(Mode::AnyMotion));
+    }
+
+    #[tokio::test]
+    async fn test_wheel_goes_to_window_under_pointer() {
+        let (_client, _handle, state) = setup_test_server().await;
+        let mut s = state.lock().await;
+        let first = s.active_workspace().active_group;
+        let view = s.last_size;
+        let (rect, content) = {
+            let rects = window_rects(&s, s.last_size);
+            let rect = rects.iter().find(|(id, _)| *id == first).unwrap().1;
+            (rect, window_content_rect(rect).unwrap())
+        };
+        assert_eq!(window_at(&s, s.last_size, rect.x, rect.y), Some(first));
+        assert_eq!(window_at(&s, s.last_size, 0, s.last_size.1 - 1), None);
+
+        // Without mouse reporting the wheel scrolls the tab instead
+        assert!(!forward_mouse_to_pty(
+            &mut s,
+            view,
+            first,
+            64,
+            MouseReport::Press,
+            content.x,
+            content.y
+        ));
+
+        let group = s.active_workspace_mut().groups.get_mut(&first).unwrap();
+        group
+            .active_tab_mut()
+            .process_output(b"\x1b[?1000h\x1b[?1006h");
+        assert!(forward_mouse_to_pty(
+            &mut s,
+            view,
+            first,
+            64,
+            MouseReport::Press,
+            content.x,
+            content.y
+        ));
+        assert!(!forward_mouse_to_pty(
+            &mut s,
+            view,
+            first,
+            35,
+            MouseReport::Motion,
+            content.x,
+            content.y
+        ));
+    }
 }
@@ -1,10 +1,15 @@
+pub mod buffers;
 pub mod command;
 pub mod command_parser;
 pub mod control;
 pub mod daemon;
 pub mod id_map;
+pub mod outbound;
+pub mod proxy;
 pub mod state;
+pub mod tcp;
 pub mod tmux_shim;
+pub mod view;
 
 // Re-export protocol and framing from pane-protocol for convenience
 pub use pane_protocol::framing;
@@ -0,0 +1,352 @@
+//! Per-client outbound queues.
+//!
+//! Every attached client gets its own queue of encoded frames between the
+//! shared broadcast channel and its socket. Filling a queue never waits on
+//! the socket, so a slow or suspended client can't hold up the PTY readers or
+//! the other clients. Only the latest layout and stats are kept. When a
+//! client's queue goes over its byte limit, the queued output, layouts and
+//! stats are dropped and the client is resynchronized with a fresh layout and
+//! screen dumps. A client still over the limit after that is disconnected.
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use tokio::sync::Notify;
+
+/// What a queued frame holds, which decides what becomes of it when the
+/// client falls behind.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FrameKind {
+    /// Pane output, which a resync's screen dumps replace.
+    Output,
+    /// A layout, replaced by a newer one or by a resync's.
+    Layout,
+    /// System stats, replaced by newer ones.
+    Stats,
+    /// Anything else, which is always delivered.
+    Other,
+}
+
+/// A snapshot of a queue, for `client-stats`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct QueueStats {
+    pub frames: usize,
+    pub bytes: usize,
+    pub limit: usize,
+    /// Output frames dropped because the client fell behind.
+    pub dropped: u64,
+    pub resyncs: u64,
+}
+
+#[derive(Debug)]
+struct Entry {
+    body: Vec<u8>,
+    kind: FrameKind,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    entries: VecDeque<Entry>,
+    bytes: usize,
+    /// A resync is due. Output and layouts are dropped until it's queued.
+    resync: bool,
+    closed: bool,
+    dropped: u64,
+    resyncs: u64,
+}
+
+#[derive(Debug)]
+pub struct OutboundQueue {
+    inner: Mutex<Inner>,
+    /// Wakes the writer for frames.
+    notify: Notify,
+    /// Wakes the forwarder for a resync.
+    resync_notify: Notify,
+    limit: usize,
+}
+
+impl OutboundQueue {
+    /// A queue holding up to `limit` bytes of frames. It starts out wanting
+    /// a resync, which sends the new client its layout and the screens it
+    /// missed before attaching.
+    pub fn new(limit: usize) -> Self {
+        Self {
+            inner: Mutex::new(Inner {
+                resync: true,
+                ..Inner::default()
+            }),
+            notify: Notify::new(),
+            resync_notify: Notify::new(),
+            limit,
+        }
+    }
+
+    /// Queue a frame. Output and layouts are dropped rather than queued
+    /// while a resync is pending, and a layout or stats frame replaces the
+    /// one already queued. A frame that doesn't fit makes room by dropping
+    /// everything a resync replaces.
+    pub fn push(&self, body: Vec<u8>, kind: FrameKind) {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.closed {
+            return;
+        }
+        match kind {
+            FrameKind::Output if inner.resync => {
+                inner.dropped += 1;
+                return;
+            }
+            FrameKind::Layout if inner.resync => return,
+            _ => {}
+        }
+        if matches!(kind, FrameKind::Layout | FrameKind::Stats) {
+            // Replaced in place, so a tab it adds still reaches the client
+            // before that tab's output does
+            if let Some(entry) = inner.entries.iter_mut().find(|e| e.kind == kind) {
+                let grown = body.len() as isize - entry.body.len() as isize;
+                entry.body = body;
+                inner.bytes = inner.bytes.saturating_add_signed(grown);
+                return;
+            }
+        }
+        // A frame bigger than the limit still goes to a client that's caught up
+        if inner.bytes > 0 && inner.bytes + body.len() > self.limit {
+            // Fallen behind: replace everything it hasn't seen with a resync
+            inner.start_resync();
+            self.resync_notify.notify_one();
+            match kind {
+                FrameKind::Output => inner.dropped += 1,
+                FrameKind::Layout => {}
+                FrameKind::Stats | FrameKind::Other
+                    if inner.bytes > 0 && inner.bytes + body.len() > self.limit =>
+                {
+                    // Too far behind even without output: disconnect
+                    inner.entries.clear();
+                    inner.bytes = 0;
+                    inner.closed = true;
+                }
+                FrameKind::Stats | FrameKind::Other => inner.push(body, kind),
+            }
+        } else {
+            inner.push(body, kind);
+        }
+        drop(inner);
+        self.notify.notify_one();
+    }
+
+    /// Ask for a resync, e.g. when the client missed broadcasts.
+    pub fn request_resync(&self) {
+        self.inner.lock().unwrap().start_resync();
+        self.resync_notify.notify_one();
+    }
+
+    /// Whether a resync is due.
+    pub fn resync_pending(&self) -> bool {
+        self.inner.lock().unwrap().resync
+    }
+
+    /// Wait until a resync may be due.
+    pub async fn resync_wanted(&self) {
+        self.resync_notify.notified().await;
+    }
+
+    /// Queue a resync's frames and let output back in. They go in even
+    /// past the limit, since they're what the client needs to catch up.
+    pub fn push_resync(&self, frames: impl IntoIterator<Item = (Vec<u8>, FrameKind)>) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.resync = false;
+        if inner.closed {
+            return;
+        }
+        for (body, kind) in frames {
+            inner.push(body, kind);
+        }
+        drop(inner);
+        self.notify.notify_one();
+    }
+
+    /// Stop taking frames. The writer still drains what's queued.
+    pub fn close(&self) {
+        self.inner.lock().unwrap().closed = true;
+        self.notify.notify_one();
+    }
+
+    /// Wait for the next frame to send. Returns `None` once the queue is
+    /// closed and drained.
+    pub async fn pop(&self) -> Option<Vec<u8>> {
+        loop {
+            {
+                let mut inner = self.inner.lock().unwrap();
+                if let Some(entry) = inner.entries.pop_front() {
+                    inner.bytes -= entry.body.len();
+                    return Some(entry.body);
+                }
+                if inner.closed {
+                    return None;
+                }
+            }
+            self.notify.notified().await;
+        }
+    }
+
+    pub fn stats(&self) -> QueueStats {
+        let inner = self.inner.lock().unwrap();
+        QueueStats {
+            frames: inner.entries.len(),
+            bytes: inner.bytes,
+            limit: self.limit,
+            dropped: inner.dropped,
+            resyncs: inner.resyncs,
+        }
+    }
+}
+
+impl Inner {
+    fn push(&mut self, body: Vec<u8>, kind: FrameKind) {
+        self.bytes += body.len();
+        self.entries.push_back(Entry { body, kind });
+    }
+
+    /// Drop the queued frames a resync replaces and make one due.
+    fn start_resync(&mut self) {
+        let output = self
+            .entries
+            .iter()
+            .filter(|e| e.kind == FrameKind::Output)
+            .count();
+        self.dropped += output as u64;
+        self.entries.retain(|e| e.kind == FrameKind::Other);
+        self.bytes = self.entries.iter().map(|e| e.body.len()).sum();
+        if !self.resync {
+            self.resync = true;
+            self.resyncs += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A queue whose initial resync has gone out.
+    fn caught_up(limit: usize) -> OutboundQueue {
+        let queue = OutboundQueue::new(limit);
+        assert!(queue.resync_pending());
+        queue.push_resync([]);
+        queue
+    }
+
+    #[tokio::test]
+    async fn test_frames_come_out_in_order() {
+        let queue = caught_up(1024);
+        queue.push(b"one".to_vec(), FrameKind::Output);
+        queue.push(b"two".to_vec(), FrameKind::Other);
+        assert_eq!(queue.stats().bytes, 6);
+        assert_eq!(queue.pop().await, Some(b"one".to_vec()));
+        assert_eq!(queue.pop().await, Some(b"two".to_vec()));
+        assert_eq!(queue.stats().bytes, 0);
+    }
+
+    #[tokio::test]
+    async fn test_only_latest_layout_and_stats_are_kept() {
+        let queue = caught_up(1024);
+        queue.push(b"l1".to_vec(), FrameKind::Layout);
+        queue.push(b"out".to_vec(), FrameKind::Output);
+        queue.push(b"new l2".to_vec(), FrameKind::Layout);
+        queue.push(b"s1".to_vec(), FrameKind::Stats);
+        queue.push(b"s2".to_vec(), FrameKind::Stats);
+        assert_eq!(queue.stats().frames, 3);
+        assert_eq!(queue.stats().bytes, 11);
+        assert_eq!(queue.pop().await, Some(b"new l2".to_vec()));
+        assert_eq!(queue.pop().await, Some(b"out".to_vec()));
+        assert_eq!(queue.pop().await, Some(b"s2".to_vec()));
+    }
+
+    #[tokio::test]
+    async fn test_overflow_drops_output_and_resyncs() {
+        let queue = caught_up(10);
+        queue.push(vec![0; 4], FrameKind::Output);
+        queue.push(b"abc".to_vec(), FrameKind::Other);
+        queue.push(b"lay".to_vec(), FrameKind::Layout);
+        // Doesn't fit: queued output and layouts are dropped, other frames
+        // are kept
+        queue.push(vec![0; 6], FrameKind::Output);
+        // Output and layouts are dropped until the resync is queued
+        queue.push(vec![0; 1], FrameKind::Output);
+        queue.push(b"lay".to_vec(), FrameKind::Layout);
+
+        let stats = queue.stats();
+        assert_eq!(stats.frames, 1);
+        assert_eq!(stats.bytes, 3);
+        assert_eq!(stats.dropped, 3);
+        assert_eq!(stats.resyncs, 1);
+
+        assert!(queue.resync_pending());
+        queue.push_resync([(b"dump".to_vec(), FrameKind::Output)]);
+        queue.push(b"new".to_vec(), FrameKind::Output);
+        assert_eq!(queue.pop().await, Some(b"abc".to_vec()));
+        assert_eq!(queue.pop().await, Some(b"dump".to_vec()));
+        assert_eq!(queue.pop().await, Some(b"new".to_vec()));
+    }
+
+    #[tokio::test]
+    async fn test_resync_drops_stale_layout_and_stats() {
+        let queue = caught_up(1024);
+        queue.push(b"layout".to_vec(), FrameKind::Layout);
+        queue.push(b"stats".to_vec(), FrameKind::Stats);
+        queue.push(b"other".to_vec(), FrameKind::Other);
+        queue.request_resync();
+        queue.push_resync([(b"fresh".to_vec(), FrameKind::Layout)]);
+        assert_eq!(queue.pop().await, Some(b"other".to_vec()));
+        assert_eq!(queue.pop().await, Some(b"fresh".to_vec()));
+        assert_eq!(queue.stats().frames, 0);
+    }
+
+    #[tokio::test]
+    async fn test_too_far_behind_disconnects() {
+        let queue = caught_up(10);
+        // A single big frame still goes through
+        queue.push(vec![0; 16], FrameKind::Other);
+        assert_eq!(queue.stats().frames, 1);
+        // More that can't be dropped doesn't
+        queue.push(vec![0; 4], FrameKind::Other);
+        assert_eq!(queue.stats().frames, 0);
+        queue.push(b"late".to_vec(), FrameKind::Other);
+        assert_eq!(queue.pop().await, None);
+    }
+
+    #[tokio::test]
+    async fn test_close_drains_then_ends() {
+        let queue = OutboundQueue::new(1024);
+        queue.push(b"last".to_vec(), FrameKind::Other);
+        queue.close();
+        queue.push(b"ignored".to_vec(), FrameKind::Other);
+        assert_eq!(queue.pop().await, Some(b"last".to_vec()));
+        assert_eq!(queue.pop().await, None);
+    }
+
+    #[tokio::test]
+    async fn test_pop_waits_for_push() {
+        let queue = std::sync::Arc::new(caught_up(1024));
+        let waiter = {
+            let queue = std::sync::Arc::clone(&queue);
+            tokio::spawn(async move { queue.pop().await })
+        };
+        tokio::task::yield_now().await;
+        queue.push(b"frame".to_vec(), FrameKind::Other);
+        assert_eq!(waiter.await.unwrap(), Some(b"frame".to_vec()));
+    }
+
+    #[tokio::test]
+    async fn test_resync_request_wakes_forwarder() {
+        let queue = std::sync::Arc::new(caught_up(1024));
+        let waiter = {
+            let queue = std::sync::Arc::clone(&queue);
+            tokio::spawn(async move { queue.resync_wanted().await })
+        };
+        tokio::task::yield_now().await;
+        queue.request_resync();
+        waiter.await.unwrap();
+        assert!(queue.resync_pending());
+        assert_eq!(queue.stats().resyncs, 1);
+    }
+}
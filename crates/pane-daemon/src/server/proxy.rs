@@ -0,0 +1,114 @@
+//! Stdio bridge to the daemon socket.
+//!
+//! `pane proxy` forwards length-prefixed frames between its stdin/stdout and
+//! the local daemon, so a TUI on another host can attach with
+//! `pane attach --via 'ssh host pane proxy'`. Only framed messages cross the
+//! link; rendering and key handling stay on the client side.
+
+use anyhow::Result;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::UnixStream;
+
+use crate::server::daemon::socket_path;
+use crate::server::framing;
+
+/// Connect to the running daemon and bridge it to stdin/stdout.
+pub async fn run() -> Result<()> {
+    let path = socket_path();
+    if !path.exists() {
+        anyhow::bail!("no running daemon");
+    }
+    let (daemon_read, daemon_write) = UnixStream::connect(&path).await?.into_split();
+    relay(
+        tokio::io::stdin(),
+        tokio::io::stdout(),
+        daemon_read,
+        daemon_write,
+    )
+    .await
+}
+
+/// Forward frames in both directions until either side closes.
+pub async fn relay<CR, CW, DR, DW>(
+    client_read: CR,
+    client_write: CW,
+    daemon_read: DR,
+    daemon_write: DW,
+) -> Result<()>
+where
+    CR: AsyncRead + Unpin,
+    CW: AsyncWrite + Unpin,
+    DR: AsyncRead + Unpin,
+    DW: AsyncWrite + Unpin,
+{
+    tokio::select! {
+        result = forward(client_read, daemon_write) => result,
+        result = forward(daemon_read, client_write) => result,
+    }
+}
+
+/// Copy whole frames from one stream to the other, flushing after each so
+/// a key press is never held back waiting for more input.
+async fn forward<R, W>(mut from: R, mut to: W) -> Result<()>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    while let Some(frame) = framing::read_frame(&mut from).await? {
+        framing::write_frame(&mut to, &frame).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pane_protocol::protocol::{ClientRequest, ServerResponse};
+
+    #[tokio::test]
+    async fn test_relay_forwards_frames_both_ways() {
+        // Client side: a plain pipe pair standing in for stdin/stdout.
+        let (mut client, proxy_client) = tokio::io::duplex(1024);
+        let (proxy_read, proxy_write) = tokio::io::split(proxy_client);
+        // Daemon side: a socket pair standing in for the daemon socket.
+        let (mut daemon, proxy_daemon) = UnixStream::pair().unwrap();
+        let (daemon_read, daemon_write) = proxy_daemon.into_split();
+
+        let relay = tokio::spawn(relay(proxy_read, proxy_write, daemon_read, daemon_write));
+
+        framing::send(&mut client, &ClientRequest::Attach).await.unwrap();
+        let req: ClientRequest = framing::recv_required(&mut daemon).await.unwrap();
+        assert!(matches!(req, ClientRequest::Attach));
+
+        framing::send(&mut daemon, &ServerResponse::Attached).await.unwrap();
+        let resp: ServerResponse = framing::recv_required(&mut client).await.unwrap();
+        assert!(matches!(resp, ServerResponse::Attached));
+
+        // Closing the client side ends the relay and the daemon sees EOF.
+        drop(client);
+        tokio::time::timeout(std::time::Duration::from_secs(5), relay)
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        let eof: Option<ClientRequest> = framing::recv(&mut daemon).await.unwrap();
+        assert!(eof.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_relay_ends_when_daemon_closes() {
+        let (client, proxy_client) = tokio::io::duplex(1024);
+        let (proxy_read, proxy_write) = tokio::io::split(proxy_client);
+        let (daemon, proxy_daemon) = UnixStream::pair().unwrap();
+        let (daemon_read, daemon_write) = proxy_daemon.into_split();
+
+        let relay = tokio::spawn(relay(proxy_read, proxy_write, daemon_read, daemon_write));
+        drop(daemon);
+        tokio::time::timeout(std::time::Duration::from_secs(5), relay)
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        drop(client);
+    }
+}
@@ -1,10 +1,15 @@
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use tokio::sync::mpsc;
 
-use pane_protocol::config::Config;
+use pane_protocol::base64;
+use pane_protocol::config::{ClipboardPolicy, Config, WindowSize};
 use pane_protocol::event::AppEvent;
 use pane_protocol::layout::{ResolvedPane, Side, SplitDirection, TabId};
+use pane_protocol::protocol::ServerResponse;
 use pane_protocol::system_stats::SystemStats;
+use crate::server::buffers::PasteBuffers;
+use crate::window::replies::ClipboardRequest;
 use crate::window::{Tab, TabKind, Window, WindowId};
 use crate::workspace::Workspace;
 
@@ -30,6 +35,17 @@ pub struct ServerState {
     pub next_pane_number: u32,
     /// Active drag state for split border resizing.
     pub drag_state: Option<DragState>,
+    /// Whether any attached client's terminal has focus.
+    pub terminal_focused: bool,
+    /// The tab last sent a focus-in event, if any.
+    pub focused_tab: Option<TabId>,
+    /// Tabs waiting for a client to answer an OSC 52 clipboard read, with
+    /// the selection each asked for.
+    pub clipboard_reads: HashMap<TabId, String>,
+    /// Copied text kept for pasting, newest first.
+    pub buffers: PasteBuffers,
+    /// Size set with `resize-window`, overriding the `window_size` policy.
+    pub manual_size: Option<(u16, u16)>,
 }
 
 /// Auto-name a workspace based on the git repo name, then folder name, with
@@ -159,6 +175,11 @@ impl ServerState {
         shell.or_else(|| self.config.behavior.default_shell.clone())
     }
 
+    /// Resolve the scrollback limit for a new tab: explicit limit > per-kind config > global config.
+    fn resolve_history_limit(&self, kind: &TabKind, history_limit: Option<usize>) -> usize {
+        history_limit.unwrap_or_else(|| self.config.scrollback.history_limit_for(kind))
+    }
+
     pub fn active_workspace(&self) -> &Workspace {
         let idx = self.active_workspace.min(self.workspaces.len().saturating_sub(1));
         &self.workspaces[idx]
@@ -196,6 +217,31 @@ impl ServerState {
         None
     }
 
+    /// Keep the combined scrollback of all tabs within the configured memory
+    /// budget. The tab holding the most history gives up its oldest lines
+    /// first, so short histories survive a single noisy tab.
+    /// Returns the number of lines dropped.
+    pub fn enforce_scrollback_budget(&mut self) -> usize {
+        let Some(budget) = self.config.scrollback.memory_limit_bytes() else {
+            return 0;
+        };
+        let mut tabs: Vec<&mut Tab> = self
+            .workspaces
+            .iter_mut()
+            .flat_map(|ws| ws.groups.values_mut())
+            .flat_map(|group| group.tabs.iter_mut())
+            .collect();
+        let screens = tabs.iter_mut().map(|t| t.vt.screen_mut());
+        let dropped = vt100::trim_scrollback_to_budget(screens, budget);
+        if dropped > 0 {
+            // Trimming can pull a scrolled-back view closer to the bottom
+            for tab in tabs {
+                tab.scroll_offset = tab.vt.screen().scrollback();
+            }
+        }
+        dropped
+    }
+
     /// Find which workspace/group a pane belongs to.
     pub fn find_tab_location(&self, pane_id: TabId) -> Option<(usize, WindowId)> {
         for (ws_idx, ws) in self.workspaces.iter().enumerate() {
@@ -220,12 +266,17 @@ impl ServerState {
         Self {
             workspaces: Vec::new(),
             active_workspace: 0,
+            buffers: PasteBuffers::new(config.behavior.buffer_limit),
             config,
             system_stats: SystemStats::default(),
             event_tx: event_tx.clone(),
             last_size: (cols.saturating_add(2), rows.saturating_add(3)),
             next_pane_number: 0,
             drag_state: None,
+            terminal_focused: true,
+            focused_tab: None,
+            clipboard_reads: HashMap::new(),
+            manual_size: None,
         }
     }
 
@@ -349,11 +400,13 @@ impl ServerState {
         kind: TabKind,
         command: Option<String>,
         shell: Option<String>,
+        history_limit: Option<usize>,
         cols: u16,
         rows: u16,
     ) -> anyhow::Result<TabId> {
         let pane_id = TabId::new_v4();
         let resolved_shell = self.resolve_shell(shell);
+        let history_limit = self.resolve_history_limit(&kind, history_limit);
         let tmux_env = self.next_tmux_env();
         let ws_cwd = self.active_workspace().cwd.clone();
         let pane = match Tab::spawn_with_env(
@@ -366,6 +419,7 @@ impl ServerState {
             resolved_shell,
             Some(tmux_env),
             Some(&ws_cwd),
+            history_limit,
         ) {
             Ok(p) => p,
             Err(e) => Tab::spawn_error(pane_id, kind, &e.to_string()),
@@ -382,18 +436,21 @@ impl ServerState {
     }
 
     /// Split the active group and return (new_group_id, new_pane_id) for the created pane.
+    #[allow(clippy::too_many_arguments)]
     pub fn split_active_group(
         &mut self,
         direction: SplitDirection,
         kind: TabKind,
         command: Option<String>,
         shell: Option<String>,
+        history_limit: Option<usize>,
         cols: u16,
         rows: u16,
     ) -> anyhow::Result<(WindowId, TabId)> {
         let new_group_id = WindowId::new_v4();
         let pane_id = TabId::new_v4();
         let resolved_shell = self.resolve_shell(shell);
+        let history_limit = self.resolve_history_limit(&kind, history_limit);
         let tmux_env = self.next_tmux_env();
         let ws_cwd = self.active_workspace().cwd.clone();
 
@@ -407,6 +464,7 @@ impl ServerState {
             resolved_shell,
             Some(tmux_env),
             Some(&ws_cwd),
+            history_limit,
         ) {
             Ok(p) => p,
             Err(e) => Tab::spawn_error(pane_id, kind, &e.to_string()),
@@ -438,6 +496,7 @@ impl ServerState {
         });
 
         let resolved_shell = self.resolve_shell(None);
+        let history_limit = self.resolve_history_limit(&TabKind::Shell, None);
         let pane = match Tab::spawn_with_env(
             pane_id,
             TabKind::Shell,
@@ -448,6 +507,7 @@ impl ServerState {
             resolved_shell,
             Some(tmux_env),
             Some(&cwd),
+            history_limit,
         ) {
             Ok(p) => p,
             Err(e) => Tab::spawn_error(pane_id, TabKind::Shell, &e.to_string()),
@@ -482,7 +542,7 @@ impl ServerState {
 
     pub fn restart_active_tab(&mut self, cols: u16, rows: u16) -> anyhow::Result<()> {
         let active_group_id = self.active_workspace().active_group;
-        let (exited, kind, command, id) = {
+        let (exited, kind, command, id, history_limit) = {
             let ws = self.active_workspace();
             if let Some(group) = ws.groups.get(&active_group_id) {
                 let pane = group.active_tab();
@@ -491,6 +551,7 @@ impl ServerState {
                     pane.kind.clone(),
                     pane.command.clone(),
                     pane.id,
+                    pane.screen().scrollback_limit(),
                 )
             } else {
                 return Ok(());
@@ -502,6 +563,9 @@ impl ServerState {
         }
 
         let resolved_shell = self.resolve_shell(None);
+        // Keep the limit the tab was created with; error tabs have none.
+        let history_limit =
+            self.resolve_history_limit(&kind, Some(history_limit).filter(|&n| n > 0));
         let tmux_env = self.next_tmux_env();
         let ws_cwd = self.active_workspace().cwd.clone();
         let new_pane = match Tab::spawn_with_env(
@@ -514,6 +578,7 @@ impl ServerState {
             resolved_shell,
             Some(tmux_env),
             Some(&ws_cwd),
+            history_limit,
         ) {
             Ok(p) => p,
             Err(e) => Tab::spawn_error(id, kind, &e.to_string()),
@@ -529,6 +594,17 @@ impl ServerState {
         Ok(())
     }
 
+    /// The sizing policy in force and the size `manual` uses.
+    pub fn window_size(&self) -> (WindowSize, (u16, u16)) {
+        match self.manual_size {
+            Some(size) => (WindowSize::Manual, size),
+            None => (
+                self.config.behavior.window_size,
+                self.config.behavior.default_size,
+            ),
+        }
+    }
+
     pub fn resize_all_tabs(&mut self, w: u16, h: u16) {
         let overhead = 1 + self.workspace_bar_height();
         let body_height = h.saturating_sub(overhead);
@@ -591,6 +667,80 @@ impl ServerState {
         (cols.max(1), rows.max(1))
     }
 
+    /// The tab that has keyboard focus: the active tab of the active window,
+    /// as long as some client's terminal is focused.
+    fn focus_target(&self) -> Option<TabId> {
+        if !self.terminal_focused || self.workspaces.is_empty() {
+            return None;
+        }
+        let ws = self.active_workspace();
+        ws.groups.get(&ws.active_group).map(|g| g.active_tab().id)
+    }
+
+    /// Send focus out/in events when keyboard focus has moved to another
+    /// tab, or away from all of them, since the last call.
+    pub fn sync_focus(&mut self) {
+        let target = self.focus_target();
+        if target == self.focused_tab {
+            return;
+        }
+        if let Some(tab) = self.focused_tab.and_then(|id| self.find_tab_mut(id)) {
+            tab.report_focus(false);
+        }
+        if let Some(tab) = target.and_then(|id| self.find_tab_mut(id)) {
+            tab.report_focus(true);
+        }
+        self.focused_tab = target;
+    }
+
+    /// Turn a tab's OSC 52 requests into messages for clients, dropping the
+    /// ones the config denies. Writes are also kept as paste buffers.
+    pub fn clipboard_responses(
+        &mut self,
+        pane_id: TabId,
+        requests: Vec<ClipboardRequest>,
+    ) -> Vec<ServerResponse> {
+        let behavior = &self.config.behavior;
+        let (write, read) = (behavior.clipboard_write, behavior.clipboard_read);
+        let mut responses = Vec::new();
+        for request in requests {
+            match request {
+                ClipboardRequest::Write { data } if write != ClipboardPolicy::Deny => {
+                    // Writes that aren't valid base64 go nowhere
+                    if let Some(bytes) = base64::decode(&data) {
+                        self.buffers
+                            .push(String::from_utf8_lossy(&bytes).into_owned());
+                        responses.push(ServerResponse::ClipboardWrite { pane_id, data });
+                    }
+                }
+                ClipboardRequest::Read { selection } if read != ClipboardPolicy::Deny => {
+                    self.clipboard_reads.insert(pane_id, selection.clone());
+                    responses.push(ServerResponse::ClipboardRead { pane_id, selection });
+                }
+                _ => {}
+            }
+        }
+        responses
+    }
+
+    /// Answer a tab's pending OSC 52 read with a client's base64 `data`,
+    /// for the selection the tab asked about. Replies for tabs with no read
+    /// pending, and data that isn't strict base64, are ignored. Returns
+    /// whether the reply was written.
+    pub fn answer_clipboard_read(&mut self, pane_id: TabId, data: &str) -> bool {
+        if base64::decode(data).is_none() {
+            return false;
+        }
+        let Some(selection) = self.clipboard_reads.remove(&pane_id) else {
+            return false;
+        };
+        if let Some(tab) = self.find_tab_mut(pane_id) {
+            let reply = format!("\x1b]52;{selection};{data}\x1b\\");
+            tab.write_input(reply.as_bytes());
+        }
+        true
+    }
+
     pub fn scroll_active_tab(&mut self, f: impl FnOnce(&mut Tab)) {
         let ws = self.active_workspace_mut();
         if let Some(group) = ws.groups.get_mut(&ws.active_group) {
@@ -607,10 +757,78 @@ use pane_protocol::protocol::{
     FloatingWindowSnapshot, RenderState, TabSnapshot, WindowSnapshot, WorkspaceSnapshot,
 };
 
-/// Build a RenderState for a specific client, using their active_workspace.
-pub fn render_state_for_client(state: &ServerState, active_workspace: usize) -> RenderState {
+/// One client's own focus. Workspaces, windows and tabs are shared, but each
+/// client has its own active workspace, active window in each workspace and
+/// active tab in each window. The daemon applies a client's focus to the
+/// server state before handling its requests and captures it again after.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ClientFocus {
+    pub active_workspace: usize,
+    /// The active window of each workspace.
+    pub windows: HashSet<WindowId>,
+    /// The active tab of each window. Tabs are kept by id so that opening or
+    /// closing tabs before it doesn't move the focus.
+    pub tabs: HashMap<WindowId, TabId>,
+}
+
+impl ClientFocus {
+    /// The focus the server state currently has.
+    pub fn capture(state: &ServerState) -> Self {
+        Self {
+            active_workspace: state.active_workspace,
+            windows: state.workspaces.iter().map(|ws| ws.active_group).collect(),
+            tabs: state
+                .workspaces
+                .iter()
+                .flat_map(|ws| ws.groups.iter())
+                .filter_map(|(id, group)| Some((*id, group.tabs.get(group.active_tab)?.id)))
+                .collect(),
+        }
+    }
+
+    /// Make this the server state's focus. Windows whose focused window or
+    /// tab has gone since it was captured keep the focus the state already
+    /// has.
+    pub fn apply(&self, state: &mut ServerState) {
+        state.active_workspace = self
+            .active_workspace
+            .min(state.workspaces.len().saturating_sub(1));
+        for ws in &mut state.workspaces {
+            if let Some(id) = ws.groups.keys().find(|id| self.windows.contains(id)) {
+                ws.active_group = *id;
+            }
+            for (id, group) in &mut ws.groups {
+                let tab = self.tabs.get(id);
+                if let Some(index) = group.tabs.iter().position(|t| Some(&t.id) == tab) {
+                    group.active_tab = index;
+                }
+            }
+        }
+    }
+
+    /// Show a render state with this focus.
+    pub fn apply_to(&self, rs: &mut RenderState) {
+        rs.active_workspace = self
+            .active_workspace
+            .min(rs.workspaces.len().saturating_sub(1));
+        for ws in &mut rs.workspaces {
+            if let Some(group) = ws.groups.iter().find(|g| self.windows.contains(&g.id)) {
+                ws.active_group = group.id;
+            }
+            for group in &mut ws.groups {
+                let tab = self.tabs.get(&group.id);
+                if let Some(index) = group.tabs.iter().position(|t| Some(&t.id) == tab) {
+                    group.active_tab = index;
+                }
+            }
+        }
+    }
+}
+
+/// Build a RenderState for a specific client, with their own focus.
+pub fn render_state_for_client(state: &ServerState, focus: &ClientFocus) -> RenderState {
     let mut rs = render_state_from_server(state);
-    rs.active_workspace = active_workspace.min(rs.workspaces.len().saturating_sub(1));
+    focus.apply_to(&mut rs);
     rs
 }
 
@@ -650,6 +868,7 @@ pub fn render_state_from_server(state: &ServerState) -> RenderState {
                                 cwd: pane.cwd.to_string_lossy().to_string(),
                                 cols,
                                 rows,
+                                history_limit: pane.screen().scrollback_limit(),
                             }
                         })
                         .collect(),
@@ -712,6 +931,11 @@ mod tests {
             last_size: (120, 40),
             next_pane_number: 1,
             drag_state: None,
+            terminal_focused: true,
+            focused_tab: None,
+            clipboard_reads: HashMap::new(),
+            buffers: PasteBuffers::new(50),
+            manual_size: None,
         };
         (state, rx)
     }
@@ -760,6 +984,11 @@ mod tests {
             last_size: (120, 40),
             next_pane_number: 2,
             drag_state: None,
+            terminal_focused: true,
+            focused_tab: None,
+            clipboard_reads: HashMap::new(),
+            buffers: PasteBuffers::new(50),
+            manual_size: None,
         };
         (state, gid1, gid2, rx)
     }
@@ -1080,6 +1309,33 @@ mod tests {
         assert_eq!(state.workspaces[0].active_group, gid1);
     }
 
+    // ---- sync_focus ----
+
+    #[test]
+    fn test_sync_focus_follows_active_tab() {
+        let (mut state, gid1, gid2, _rx) = make_split_state();
+        let left = state.workspaces[0].groups[&gid1].active_tab().id;
+        let right = state.workspaces[0].groups[&gid2].active_tab().id;
+        state.sync_focus();
+        assert_eq!(state.focused_tab, Some(left));
+        state.focus_group(gid2, 1);
+        state.sync_focus();
+        assert_eq!(state.focused_tab, Some(right));
+    }
+
+    #[test]
+    fn test_sync_focus_clears_when_terminal_unfocused() {
+        let (mut state, _rx) = make_test_state();
+        state.sync_focus();
+        assert!(state.focused_tab.is_some());
+        state.terminal_focused = false;
+        state.sync_focus();
+        assert_eq!(state.focused_tab, None);
+        state.terminal_focused = true;
+        state.sync_focus();
+        assert!(state.focused_tab.is_some());
+    }
+
     // ---- move_tab_to_neighbor ----
 
     #[test]
@@ -1139,7 +1395,7 @@ mod tests {
         assert_eq!(state.active_workspace().groups[&gid].tab_count(), 1);
 
         state
-            .add_tab_to_active_group(TabKind::Shell, None, None, 78, 22)
+            .add_tab_to_active_group(TabKind::Shell, None, None, None, 78, 22)
             .unwrap();
         assert_eq!(state.active_workspace().groups[&gid].tab_count(), 2);
     }
@@ -1153,7 +1409,15 @@ mod tests {
         assert_eq!(state.active_workspace().groups.len(), 1);
 
         let (new_gid, _new_pid) = state
-            .split_active_group(SplitDirection::Horizontal, TabKind::Shell, None, None, 40, 22)
+            .split_active_group(
+                SplitDirection::Horizontal,
+                TabKind::Shell,
+                None,
+                None,
+                None,
+                40,
+                22,
+            )
             .unwrap();
         assert_eq!(state.active_workspace().groups.len(), 2);
         assert!(state.active_workspace().groups.contains_key(&new_gid));
@@ -1473,6 +1737,60 @@ mod tests {
         assert!(state.active_workspace().folded_windows.contains(&gid1));
     }
 
+    // ---- scrollback limits ----
+
+    #[test]
+    fn test_resolve_history_limit() {
+        let (mut state, _rx) = make_test_state();
+        state.config.scrollback.history_limit = 5000;
+        state
+            .config
+            .scrollback
+            .kind_limits
+            .insert("server".to_string(), 100_000);
+        assert_eq!(state.resolve_history_limit(&TabKind::Shell, None), 5000);
+        assert_eq!(
+            state.resolve_history_limit(&TabKind::DevServer, None),
+            100_000
+        );
+        assert_eq!(
+            state.resolve_history_limit(&TabKind::DevServer, Some(10)),
+            10
+        );
+    }
+
+    #[test]
+    fn test_enforce_scrollback_budget_trims_largest_history() {
+        let (mut state, gid1, gid2, _rx) = make_split_state();
+        state.config.scrollback.memory_limit_mb = 1;
+        for (gid, lines) in [(gid1, 200), (gid2, 20_000)] {
+            let tab = state
+                .active_workspace_mut()
+                .groups
+                .get_mut(&gid)
+                .unwrap()
+                .active_tab_mut();
+            tab.vt = crate::window::replies::new_parser(3, 80, 100_000);
+            for i in 0..lines {
+                tab.vt.process(format!("line {}\r\n", i).as_bytes());
+            }
+        }
+
+        assert!(state.enforce_scrollback_budget() > 0);
+        let ws = state.active_workspace();
+        let small = ws.groups[&gid1].active_tab().screen();
+        let big = ws.groups[&gid2].active_tab().screen();
+        assert!(small.scrollback_bytes() + big.scrollback_bytes() <= 1024 * 1024);
+        // The small history is untouched; the big one lost its oldest lines
+        assert_eq!(small.scrollback_rows(), 198);
+        assert!(big.scrollback_rows() < 19_998);
+        let last = big.history_len() - 1;
+        assert_eq!(big.history_cell(last, 5).unwrap().contents(), "1");
+
+        // Nothing left to do once under budget
+        assert_eq!(state.enforce_scrollback_budget(), 0);
+    }
+
     // ---- scroll_active_tab ----
 
     #[test]
@@ -1483,7 +1801,7 @@ mod tests {
         {
             let ws = state.active_workspace_mut();
             let tab = ws.groups.get_mut(&gid).unwrap().active_tab_mut();
-            tab.vt = vt100::Parser::new(3, 80, 1000);
+            tab.vt = crate::window::replies::new_parser(3, 80, 1000);
             for i in 0..20 {
                 tab.vt.process(format!("line {}\r\n", i).as_bytes());
             }
@@ -1524,10 +1842,69 @@ mod tests {
     #[test]
     fn test_render_state_for_client_clamps_index() {
         let (state, _rx) = make_test_state();
-        let rs = render_state_for_client(&state, 999);
+        let focus = ClientFocus {
+            active_workspace: 999,
+            ..ClientFocus::default()
+        };
+        let rs = render_state_for_client(&state, &focus);
         assert_eq!(rs.active_workspace, 0); // clamped to valid range
     }
 
+    #[test]
+    fn test_client_focus_is_independent() {
+        let (mut state, gid1, gid2, _rx) = make_split_state();
+        state.active_workspace_mut().active_group = gid1;
+        let first = ClientFocus::capture(&state);
+        state.active_workspace_mut().active_group = gid2;
+        let second = ClientFocus::capture(&state);
+
+        first.apply(&mut state);
+        assert_eq!(state.active_workspace().active_group, gid1);
+        let rs = render_state_for_client(&state, &second);
+        assert_eq!(rs.workspaces[0].active_group, gid2);
+        second.apply(&mut state);
+        assert_eq!(state.active_workspace().active_group, gid2);
+    }
+
+    #[test]
+    fn test_client_focus_keeps_its_tab_when_tabs_open_before_it() {
+        let (mut state, gid1, _gid2, _rx) = make_split_state();
+        let focused = state.active_workspace().groups[&gid1].tabs[0].id;
+        let focus = ClientFocus::capture(&state);
+        let group = state.active_workspace_mut().groups.get_mut(&gid1).unwrap();
+        let tab = Tab::spawn_error(TabId::new_v4(), TabKind::Shell, "new");
+        group.tabs.insert(0, tab);
+        group.active_tab = 0;
+
+        focus.apply(&mut state);
+        let group = &state.active_workspace().groups[&gid1];
+        assert_eq!(group.tabs[group.active_tab].id, focused);
+        let rs = render_state_for_client(&state, &focus);
+        assert_eq!(
+            rs.workspaces[0]
+                .groups
+                .iter()
+                .find(|g| g.id == gid1)
+                .unwrap()
+                .active_tab,
+            1
+        );
+    }
+
+    #[test]
+    fn test_client_focus_ignores_closed_windows() {
+        let (mut state, gid1, gid2, _rx) = make_split_state();
+        state.active_workspace_mut().active_group = gid2;
+        let stale = ClientFocus::capture(&state);
+        let ws = state.active_workspace_mut();
+        ws.layout.close_pane(gid2);
+        ws.groups.remove(&gid2);
+        ws.active_group = gid1;
+
+        stale.apply(&mut state);
+        assert_eq!(state.active_workspace().active_group, gid1);
+    }
+
     #[test]
     fn test_render_state_captures_folded_windows() {
         let (mut state, gid1, _gid2, _rx) = make_split_state();
@@ -1551,4 +1928,64 @@ mod tests {
         let rs = render_state_from_server(&state);
         assert_eq!(rs.workspaces[0].zoomed_window, Some(gid1));
     }
+
+    #[test]
+    fn test_clipboard_responses_follow_policy() {
+        let (mut state, _rx) = make_test_state();
+        let pane_id = TabId::new_v4();
+        let requests = || {
+            vec![
+                ClipboardRequest::Write {
+                    data: "aGk=".into(),
+                },
+                ClipboardRequest::Read {
+                    selection: "c".into(),
+                },
+            ]
+        };
+
+        // Reads are denied by default
+        let responses = state.clipboard_responses(pane_id, requests());
+        assert!(matches!(
+            responses.as_slice(),
+            [ServerResponse::ClipboardWrite { data, .. }] if data == "aGk="
+        ));
+        assert!(state.clipboard_reads.is_empty());
+        assert_eq!(state.buffers.get(None).unwrap().data, "hi");
+
+        state.config.behavior.clipboard_write = ClipboardPolicy::Deny;
+        state.config.behavior.clipboard_read = ClipboardPolicy::Ask;
+        let responses = state.clipboard_responses(pane_id, requests());
+        assert!(matches!(
+            responses.as_slice(),
+            [ServerResponse::ClipboardRead { selection, .. }] if selection == "c"
+        ));
+        assert_eq!(
+            state.clipboard_reads.get(&pane_id).map(String::as_str),
+            Some("c")
+        );
+
+        // Writes that aren't base64 are dropped
+        state.config.behavior.clipboard_write = ClipboardPolicy::Allow;
+        let bad = vec![ClipboardRequest::Write {
+            data: "aGk;\x1b]".into(),
+        }];
+        assert!(state.clipboard_responses(pane_id, bad).is_empty());
+    }
+
+    #[test]
+    fn test_clipboard_reply_needs_pending_read() {
+        let (mut state, _rx) = make_test_state();
+        let pane_id = TabId::new_v4();
+        assert!(!state.answer_clipboard_read(pane_id, "aGk="));
+
+        state.clipboard_reads.insert(pane_id, "c".into());
+        // Anything but strict base64 could end the sequence early
+        assert!(!state.answer_clipboard_read(pane_id, "aGk=\x1b\\\x1b]0;x"));
+        assert!(!state.answer_clipboard_read(pane_id, "aGk"));
+        assert!(state.answer_clipboard_read(pane_id, "aGk="));
+        // Only the first answer is heard
+        assert!(!state.answer_clipboard_read(pane_id, "aGk="));
+        assert!(state.clipboard_reads.is_empty());
+    }
 }
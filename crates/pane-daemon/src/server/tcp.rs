@@ -0,0 +1,162 @@
+//! Optional TCP listener for remote clients.
+//!
+//! Off unless `[server] tcp_listen` and `token` are both set. Each connection
+//! must open with `ClientRequest::Auth` carrying the shared token before the
+//! usual `Attach`/`AttachV2`. With `tls_cert` and `tls_key` the connection is
+//! wrapped in TLS before anything else is read.
+
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpStream;
+use tokio_rustls::rustls;
+use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName};
+use tokio_rustls::{TlsAcceptor, TlsConnector};
+
+use crate::server::framing;
+use pane_protocol::protocol::{ClientRequest, ServerResponse};
+
+/// How long a new connection has to authenticate before it is dropped.
+const AUTH_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long a new connection has to finish the TLS handshake.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Read side of a client connection to a remote daemon.
+pub type RemoteReader = Box<dyn AsyncRead + Send + Unpin>;
+/// Write side of a client connection to a remote daemon.
+pub type RemoteWriter = Box<dyn AsyncWrite + Send + Unpin>;
+
+/// Build the TLS acceptor for the listener from PEM files.
+pub fn tls_acceptor(cert: &Path, key: &Path) -> Result<TlsAcceptor> {
+    let certs = load_certs(cert)?;
+    let key = load_key(key)?;
+    let config = rustls::ServerConfig::builder_with_provider(Arc::new(
+        rustls::crypto::ring::default_provider(),
+    ))
+    .with_safe_default_protocol_versions()?
+    .with_no_client_auth()
+    .with_single_cert(certs, key)?;
+    Ok(TlsAcceptor::from(Arc::new(config)))
+}
+
+/// Wrap an accepted connection in TLS. A peer that stalls the handshake is
+/// dropped rather than holding the connection open.
+pub async fn tls_accept(
+    acceptor: &TlsAcceptor,
+    stream: TcpStream,
+) -> Result<tokio_rustls::server::TlsStream<TcpStream>> {
+    let stream = tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream))
+        .await
+        .context("TLS handshake timed out")??;
+    Ok(stream)
+}
+
+/// Wait for the client's `Auth` message and check its token. A client that
+/// sends anything else, or the wrong token, gets an error and is refused.
+pub async fn authenticate<S>(stream: &mut S, token: &str) -> Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let request: ClientRequest = tokio::time::timeout(AUTH_TIMEOUT, framing::recv_required(stream))
+        .await
+        .context("authentication timed out")??;
+    match request {
+        ClientRequest::Auth { token: offered } if tokens_match(&offered, token) => Ok(()),
+        _ => {
+            let refusal = ServerResponse::Error("authentication failed".to_string());
+            let _ = framing::send(stream, &refusal).await;
+            anyhow::bail!("authentication failed")
+        }
+    }
+}
+
+/// Connect to a daemon's TCP listener and authenticate. With `tls_cert`, the
+/// connection uses TLS and trusts only that certificate.
+pub async fn connect(
+    addr: &str,
+    token: &str,
+    tls_cert: Option<&Path>,
+) -> Result<(RemoteReader, RemoteWriter)> {
+    let tcp = TcpStream::connect(addr)
+        .await
+        .with_context(|| format!("failed to connect to {}", addr))?;
+    tcp.set_nodelay(true)?;
+    let auth = ClientRequest::Auth {
+        token: token.to_string(),
+    };
+
+    match tls_cert {
+        Some(cert) => {
+            let mut roots = rustls::RootCertStore::empty();
+            for cert in load_certs(cert)? {
+                roots.add(cert)?;
+            }
+            let config = rustls::ClientConfig::builder_with_provider(Arc::new(
+                rustls::crypto::ring::default_provider(),
+            ))
+            .with_safe_default_protocol_versions()?
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+            let host = addr
+                .rsplit_once(':')
+                .map_or(addr, |(host, _)| host)
+                .trim_start_matches('[')
+                .trim_end_matches(']');
+            let name = ServerName::try_from(host.to_string())
+                .with_context(|| format!("invalid server name: {}", host))?;
+            let mut tls = TlsConnector::from(Arc::new(config))
+                .connect(name, tcp)
+                .await?;
+            framing::send(&mut tls, &auth).await?;
+            let (reader, writer) = tokio::io::split(tls);
+            Ok((Box::new(reader), Box::new(writer)))
+        }
+        None => {
+            let (reader, mut writer) = tcp.into_split();
+            framing::send(&mut writer, &auth).await?;
+            Ok((Box::new(reader), Box::new(writer)))
+        }
+    }
+}
+
+/// Compare tokens without bailing out at the first differing byte.
+fn tokens_match(offered: &str, expected: &str) -> bool {
+    let (offered, expected) = (offered.as_bytes(), expected.as_bytes());
+    offered.len() == expected.len()
+        && offered
+            .iter()
+            .zip(expected)
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+fn load_certs(path: &Path) -> Result<Vec<CertificateDer<'static>>> {
+    let pem = std::fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+    let certs = rustls_pemfile::certs(&mut pem.as_slice()).collect::<Result<Vec<_>, _>>()?;
+    if certs.is_empty() {
+        anyhow::bail!("no certificates in {}", path.display());
+    }
+    Ok(certs)
+}
+
+fn load_key(path: &Path) -> Result<PrivateKeyDer<'static>> {
+    let pem = std::fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+    rustls_pemfile::private_key(&mut pem.as_slice())?
+        .with_context(|| format!("no private key in {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokens_match() {
+        assert!(tokens_match("s3cret", "s3cret"));
+        assert!(!tokens_match("s3cre", "s3cret"));
+        assert!(!tokens_match("s3creT", "s3cret"));
+        assert!(!tokens_match("", "s3cret"));
+    }
+}
@@ -0,0 +1,224 @@
+//! What each client shows, so tabs it can't see don't cost it anything.
+//!
+//! Clients that negotiate `VisibleOutput` get pane output only for the tabs
+//! on their screen: the active tab of each window in their workspace,
+//! including floating windows. A hidden tab gets a screen dump when it comes
+//! into view instead of everything it printed meanwhile. In the overview,
+//! each workspace's active tab is a thumbnail, refreshed with screen dumps at
+//! a lower rate rather than streamed.
+
+use std::collections::HashSet;
+
+use pane_protocol::layout::TabId;
+use pane_protocol::protocol::{RenderState, WorkspaceSnapshot};
+
+/// How often overview thumbnails are refreshed.
+pub const THUMBNAIL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(500);
+
+#[derive(Debug, Default)]
+pub struct ClientView {
+    overview: bool,
+    /// Tabs whose output is sent as it arrives.
+    live: HashSet<TabId>,
+    /// Overview thumbnails.
+    thumbnails: HashSet<TabId>,
+    /// Thumbnails with output since their last dump.
+    stale: HashSet<TabId>,
+}
+
+impl ClientView {
+    pub fn set_overview(&mut self, open: bool) {
+        self.overview = open;
+    }
+
+    /// Recompute the tabs shown from the client's own render state. Returns
+    /// the tabs that just came into view, which need a screen dump.
+    pub fn update(&mut self, render_state: &RenderState) -> Vec<TabId> {
+        let (live, thumbnails) = if self.overview {
+            let tiles = render_state.workspaces.iter().filter_map(active_tab);
+            (HashSet::new(), tiles.collect::<HashSet<_>>())
+        } else {
+            let ws = render_state.workspaces.get(render_state.active_workspace);
+            (ws.map(shown_tabs).unwrap_or_default(), HashSet::new())
+        };
+        let mut shown: Vec<TabId> = live.difference(&self.live).copied().collect();
+        shown.extend(
+            thumbnails
+                .iter()
+                .filter(|id| !self.thumbnails.contains(id) && !self.live.contains(id))
+                .copied(),
+        );
+        self.stale.retain(|id| thumbnails.contains(id));
+        self.live = live;
+        self.thumbnails = thumbnails;
+        shown
+    }
+
+    /// Whether a tab is on the client's screen, live or as a thumbnail.
+    pub fn shows(&self, tab: TabId) -> bool {
+        self.live.contains(&tab) || self.thumbnails.contains(&tab)
+    }
+
+    /// Whether to send output for a tab now. Output for a thumbnail marks it
+    /// for the next refresh instead.
+    pub fn wants_output(&mut self, tab: TabId) -> bool {
+        if self.thumbnails.contains(&tab) {
+            self.stale.insert(tab);
+        }
+        self.live.contains(&tab)
+    }
+
+    /// Thumbnails due a screen dump.
+    pub fn take_stale(&mut self) -> Vec<TabId> {
+        self.stale.drain().collect()
+    }
+}
+
+fn active_tab(ws: &WorkspaceSnapshot) -> Option<TabId> {
+    let group = ws.groups.iter().find(|g| g.id == ws.active_group)?;
+    group.tabs.get(group.active_tab).map(|t| t.id)
+}
+
+/// The active tab of each window drawn in a workspace: the zoomed window or
+/// the unfolded tiled ones, and the floating ones above them.
+fn shown_tabs(ws: &WorkspaceSnapshot) -> HashSet<TabId> {
+    let mut windows = match ws.zoomed_window {
+        Some(id) => vec![id],
+        None => ws
+            .layout
+            .group_ids()
+            .into_iter()
+            .filter(|id| !ws.folded_windows.contains(id))
+            .collect(),
+    };
+    windows.extend(ws.floating_windows.iter().map(|fw| fw.id));
+    ws.groups
+        .iter()
+        .filter(|g| windows.contains(&g.id))
+        .filter_map(|g| g.tabs.get(g.active_tab).map(|t| t.id))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pane_protocol::layout::{LayoutNode, SplitDirection};
+    use pane_protocol::window_types::TabKind;
+    use pane_protocol::protocol::{TabSnapshot, WindowSnapshot};
+
+    fn tab(id: TabId) -> TabSnapshot {
+        TabSnapshot {
+            id,
+            kind: TabKind::Shell,
+            title: String::new(),
+            exited: false,
+            foreground_process: None,
+            cwd: String::new(),
+            cols: 80,
+            rows: 24,
+            history_limit: 1000,
+        }
+    }
+
+    /// A workspace of two side-by-side windows, each with two tabs and the
+    /// first one active.
+    fn workspace() -> WorkspaceSnapshot {
+        let (w1, w2) = (TabId::new_v4(), TabId::new_v4());
+        let window = |id| WindowSnapshot {
+            id,
+            tabs: vec![tab(TabId::new_v4()), tab(TabId::new_v4())],
+            active_tab: 0,
+            name: None,
+        };
+        WorkspaceSnapshot {
+            name: "ws".to_string(),
+            cwd: String::new(),
+            layout: LayoutNode::Split {
+                direction: SplitDirection::Horizontal,
+                ratio: 0.5,
+                first: Box::new(LayoutNode::Leaf(w1)),
+                second: Box::new(LayoutNode::Leaf(w2)),
+            },
+            groups: vec![window(w1), window(w2)],
+            active_group: w1,
+            sync_panes: false,
+            folded_windows: HashSet::new(),
+            zoomed_window: None,
+            floating_windows: Vec::new(),
+        }
+    }
+
+    fn render_state(workspaces: Vec<WorkspaceSnapshot>) -> RenderState {
+        RenderState {
+            workspaces,
+            active_workspace: 0,
+        }
+    }
+
+    #[test]
+    fn test_streams_active_tabs_of_active_workspace() {
+        let (ws, other) = (workspace(), workspace());
+        let state = render_state(vec![ws.clone(), other.clone()]);
+        let mut view = ClientView::default();
+        assert_eq!(view.update(&state).len(), 2);
+
+        assert!(view.wants_output(ws.groups[0].tabs[0].id));
+        assert!(view.wants_output(ws.groups[1].tabs[0].id));
+        assert!(!view.wants_output(ws.groups[0].tabs[1].id));
+        assert!(!view.wants_output(other.groups[0].tabs[0].id));
+
+        // Nothing new to dump when the layout is sent again
+        assert!(view.update(&state).is_empty());
+    }
+
+    #[test]
+    fn test_switching_tab_dumps_it() {
+        let mut ws = workspace();
+        let mut view = ClientView::default();
+        view.update(&render_state(vec![ws.clone()]));
+
+        ws.groups[0].active_tab = 1;
+        let shown = view.update(&render_state(vec![ws.clone()]));
+        assert_eq!(shown, vec![ws.groups[0].tabs[1].id]);
+        assert!(!view.shows(ws.groups[0].tabs[0].id));
+    }
+
+    #[test]
+    fn test_zoom_and_fold_hide_windows() {
+        let mut ws = workspace();
+        let w2 = ws.groups[1].id;
+        ws.folded_windows.insert(w2);
+        let mut view = ClientView::default();
+        view.update(&render_state(vec![ws.clone()]));
+        assert!(view.shows(ws.groups[0].tabs[0].id));
+        assert!(!view.shows(ws.groups[1].tabs[0].id));
+
+        ws.folded_windows.clear();
+        ws.zoomed_window = Some(w2);
+        view.update(&render_state(vec![ws.clone()]));
+        assert!(!view.shows(ws.groups[0].tabs[0].id));
+        assert!(view.shows(ws.groups[1].tabs[0].id));
+    }
+
+    #[test]
+    fn test_overview_refreshes_thumbnails() {
+        let (ws, other) = (workspace(), workspace());
+        let state = render_state(vec![ws.clone(), other.clone()]);
+        let mut view = ClientView::default();
+        view.update(&state);
+
+        view.set_overview(true);
+        let shown = view.update(&state);
+        // The current workspace's tile was already live
+        assert_eq!(shown, vec![other.groups[0].tabs[0].id]);
+
+        let tile = other.groups[0].tabs[0].id;
+        assert!(!view.wants_output(tile));
+        assert!(!view.wants_output(ws.groups[1].tabs[0].id));
+        assert_eq!(view.take_stale(), vec![tile]);
+        assert!(view.take_stale().is_empty());
+
+        view.set_overview(false);
+        assert_eq!(view.update(&state).len(), 2);
+    }
+}
@@ -0,0 +1,150 @@
+//! Holding back output of synchronized frames (DECSET 2026).
+//!
+//! Programs wrap a redraw in `CSI ? 2026 h` ... `CSI ? 2026 l` so that it is
+//! shown all at once. The daemon keeps such a frame to itself until it ends,
+//! so clients never render half of one. A frame that doesn't end within
+//! [`SYNC_OUTPUT_TIMEOUT`] is released anyway.
+
+use pane_protocol::protocol::SYNC_OUTPUT_TIMEOUT;
+use std::time::Instant;
+
+/// The sequence ending a synchronized frame.
+const FRAME_END: &[u8] = b"\x1b[?2026l";
+
+/// Result of [`FrameBuffer::push`].
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Pushed {
+    /// Output ready to send to clients.
+    pub output: Option<Vec<u8>>,
+    /// A new frame started buffering; it should be flushed with
+    /// [`FrameBuffer::flush_expired`] once the timeout passes.
+    pub frame_started: bool,
+}
+
+/// Output held back while a tab draws a synchronized frame.
+#[derive(Debug, Default)]
+pub struct FrameBuffer {
+    pending: Vec<u8>,
+    since: Option<Instant>,
+}
+
+impl FrameBuffer {
+    /// Take a chunk of output the tab's parser has already processed.
+    /// `synchronized` is whether the screen is mid-frame after the chunk.
+    pub fn push(&mut self, mut bytes: Vec<u8>, synchronized: bool) -> Pushed {
+        if !synchronized {
+            self.since = None;
+            return Pushed {
+                output: Some(self.take_with(bytes)),
+                frame_started: false,
+            };
+        }
+        // A frame ended in this chunk and the next one began: release
+        // everything up to the end of the last complete frame.
+        if let Some(end) = last_frame_end(&bytes) {
+            let rest = bytes.split_off(end);
+            let output = self.take_with(bytes);
+            self.pending = rest;
+            self.since = Some(Instant::now());
+            return Pushed {
+                output: Some(output),
+                frame_started: true,
+            };
+        }
+        if self.expired() {
+            return Pushed {
+                output: Some(self.take_with(bytes)),
+                frame_started: false,
+            };
+        }
+        let frame_started = self.since.is_none();
+        self.since.get_or_insert_with(Instant::now);
+        self.pending.extend_from_slice(&bytes);
+        Pushed {
+            output: None,
+            frame_started,
+        }
+    }
+
+    /// Release a frame that has been held back longer than the timeout.
+    pub fn flush_expired(&mut self) -> Option<Vec<u8>> {
+        if self.expired() && !self.pending.is_empty() {
+            Some(std::mem::take(&mut self.pending))
+        } else {
+            None
+        }
+    }
+
+    fn expired(&self) -> bool {
+        self.since
+            .is_some_and(|since| since.elapsed() >= SYNC_OUTPUT_TIMEOUT)
+    }
+
+    fn take_with(&mut self, bytes: Vec<u8>) -> Vec<u8> {
+        if self.pending.is_empty() {
+            return bytes;
+        }
+        let mut output = std::mem::take(&mut self.pending);
+        output.extend_from_slice(&bytes);
+        output
+    }
+}
+
+/// Offset just past the last frame end sequence in `bytes`.
+fn last_frame_end(bytes: &[u8]) -> Option<usize> {
+    bytes
+        .windows(FRAME_END.len())
+        .rposition(|w| w == FRAME_END)
+        .map(|pos| pos + FRAME_END.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unsynchronized_output_passes_through() {
+        let mut frames = FrameBuffer::default();
+        let pushed = frames.push(b"hello".to_vec(), false);
+        assert_eq!(pushed.output, Some(b"hello".to_vec()));
+        assert!(!pushed.frame_started);
+    }
+
+    #[test]
+    fn test_frame_is_held_until_it_ends() {
+        let mut frames = FrameBuffer::default();
+        let pushed = frames.push(b"\x1b[?2026hab".to_vec(), true);
+        assert_eq!(pushed.output, None);
+        assert!(pushed.frame_started);
+        let pushed = frames.push(b"cd".to_vec(), true);
+        assert_eq!(pushed.output, None);
+        assert!(!pushed.frame_started);
+        let pushed = frames.push(b"\x1b[?2026l".to_vec(), false);
+        assert_eq!(pushed.output, Some(b"\x1b[?2026habcd\x1b[?2026l".to_vec()));
+    }
+
+    #[test]
+    fn test_back_to_back_frames_release_the_finished_one() {
+        let mut frames = FrameBuffer::default();
+        frames.push(b"\x1b[?2026hone".to_vec(), true);
+        let pushed = frames.push(b"\x1b[?2026l\x1b[?2026htwo".to_vec(), true);
+        assert_eq!(pushed.output, Some(b"\x1b[?2026hone\x1b[?2026l".to_vec()));
+        assert!(pushed.frame_started);
+        let pushed = frames.push(b"\x1b[?2026l".to_vec(), false);
+        assert_eq!(pushed.output, Some(b"\x1b[?2026htwo\x1b[?2026l".to_vec()));
+    }
+
+    #[test]
+    fn test_unfinished_frame_is_released_after_timeout() {
+        let mut frames = FrameBuffer::default();
+        frames.push(b"\x1b[?2026hab".to_vec(), true);
+        assert_eq!(frames.flush_expired(), None);
+
+        frames.since = Some(Instant::now() - SYNC_OUTPUT_TIMEOUT);
+        assert_eq!(frames.flush_expired(), Some(b"\x1b[?2026hab".to_vec()));
+        assert_eq!(frames.flush_expired(), None);
+        // Further output of the overdue frame isn't held back
+        let pushed = frames.push(b"cd".to_vec(), true);
+        assert_eq!(pushed.output, Some(b"cd".to_vec()));
+    }
+}
@@ -1,4 +1,7 @@
+pub mod frames;
+pub mod passthrough;
 pub mod pty;
+pub mod replies;
 
 // Re-export shared types from pane-protocol
 pub use pane_protocol::window_types::{TabKind, WindowId};
@@ -129,7 +132,11 @@ pub struct Tab {
     pub id: TabId,
     pub kind: TabKind,
     pub title: String,
-    pub vt: vt100::Parser,
+    pub vt: vt100::Parser<replies::TerminalReplies>,
+    /// Output held back while the program draws a synchronized frame.
+    pub frames: frames::FrameBuffer,
+    /// Takes `DCS tmux;` passthrough out of the output before the parser.
+    pub passthrough: passthrough::PassthroughFilter,
     pub exited: bool,
     pub command: Option<String>,
     pub cwd: PathBuf,
@@ -155,6 +162,7 @@ impl Tab {
         shell: Option<String>,
         tmux_env: Option<pty::TmuxEnv>,
         cwd: Option<&std::path::Path>,
+        history_limit: usize,
     ) -> anyhow::Result<Self> {
         // vt100 panics on zero dimensions
         let cols = cols.max(1);
@@ -223,13 +231,15 @@ impl Tab {
 
         let pty_handle = pty::spawn_pty(cmd, &args, size, event_tx, id, Some(&cwd), tmux_env)?;
         let shell_pid = pty_handle.shell_pid;
-        let vt = vt100::Parser::new(rows, cols, 1000);
+        let vt = replies::new_parser(rows, cols, history_limit);
 
         Ok(Self {
             id,
             kind,
             title,
             vt,
+            frames: frames::FrameBuffer::default(),
+            passthrough: passthrough::PassthroughFilter::default(),
             exited: false,
             command,
             cwd,
@@ -244,13 +254,15 @@ impl Tab {
 
     /// Create a pane that shows an error message instead of a PTY.
     pub fn spawn_error(id: TabId, kind: TabKind, error_msg: &str) -> Self {
-        let mut vt = vt100::Parser::new(24, 80, 0);
+        let mut vt = replies::new_parser(24, 80, 0);
         vt.process(format!("error: {}\r\n", error_msg).as_bytes());
         Self {
             id,
             kind: kind.clone(),
             title: format!("{}: {}", kind.label(), error_msg),
             vt,
+            frames: frames::FrameBuffer::default(),
+            passthrough: passthrough::PassthroughFilter::default(),
             exited: true,
             command: None,
             cwd: PathBuf::from("/"),
@@ -270,6 +282,13 @@ impl Tab {
         }
     }
 
+    /// Send a focus in/out event if the program asked for them (DECSET 1004).
+    pub fn report_focus(&mut self, focused: bool) {
+        if self.screen().focus_reporting() {
+            self.write_input(if focused { b"\x1b[I" } else { b"\x1b[O" });
+        }
+    }
+
     #[cfg(test)]
     pub fn is_scrolled(&self) -> bool {
         self.scroll_offset > 0
@@ -360,15 +379,27 @@ impl Tab {
         self.scroll_offset = self.vt.screen().scrollback();
     }
 
+    /// Drop up to `lines` of the oldest scrollback, keeping the scroll
+    /// position in range. Returns the number of lines dropped.
+    pub fn trim_scrollback(&mut self, lines: usize) -> usize {
+        let dropped = self.vt.screen_mut().trim_scrollback(lines);
+        self.scroll_offset = self.vt.screen().scrollback();
+        dropped
+    }
+
     pub fn scroll_to_bottom(&mut self) {
         self.scroll_offset = 0;
         self.vt.screen_mut().set_scrollback(0);
     }
 
-    /// Process PTY output bytes.
+    /// Process PTY output bytes, answering any terminal queries in them.
     /// Returns `true` if the foreground process changed (caller should broadcast layout).
     pub fn process_output(&mut self, bytes: &[u8]) -> bool {
         self.vt.process(bytes);
+        let replies = self.vt.callbacks_mut().take();
+        if !replies.is_empty() {
+            self.write_input(&replies);
+        }
         if self.scroll_offset > 0 {
             self.scroll_offset = self.vt.screen().scrollback();
         }
@@ -399,6 +430,11 @@ impl Tab {
             self.scroll_to_bottom();
         }
         self.vt.screen_mut().set_size(rows, cols);
+        if pixel_width > 0 && pixel_height > 0 {
+            self.vt
+                .screen_mut()
+                .set_cell_pixel_size(pixel_width / cols, pixel_height / rows);
+        }
         if let Some(master) = &self.pty_master {
             let _ = master.resize(PtySize {
                 rows,
@@ -481,7 +517,7 @@ mod tests {
     #[test]
     fn test_process_output_updates_screen() {
         let mut pane = Tab::spawn_error(TabId::new_v4(), TabKind::Shell, "");
-        pane.vt = vt100::Parser::new(24, 80, 0);
+        pane.vt = replies::new_parser(24, 80, 0);
         pane.process_output(b"hello world");
         let content = pane.screen().contents();
         assert!(content.contains("hello world"));
@@ -490,7 +526,7 @@ mod tests {
     #[test]
     fn test_process_output_osc_title_update() {
         let mut pane = Tab::spawn_error(TabId::new_v4(), TabKind::Shell, "");
-        pane.vt = vt100::Parser::new(24, 80, 0);
+        pane.vt = replies::new_parser(24, 80, 0);
         pane.process_output(b"\x1b]0;my-custom-title\x07");
         assert_eq!(pane.title, "my-custom-title");
     }
@@ -499,11 +535,20 @@ mod tests {
     fn test_process_output_empty_osc_title_keeps_existing() {
         let mut pane = Tab::spawn_error(TabId::new_v4(), TabKind::Shell, "");
         pane.title = "original".to_string();
-        pane.vt = vt100::Parser::new(24, 80, 0);
+        pane.vt = replies::new_parser(24, 80, 0);
         pane.process_output(b"some output");
         assert_eq!(pane.title, "original");
     }
 
+    #[test]
+    fn test_process_output_drains_query_replies() {
+        let mut pane = Tab::spawn_error(TabId::new_v4(), TabKind::Shell, "");
+        pane.vt = replies::new_parser(24, 80, 0);
+        pane.process_output(b"\x1b[6n");
+        // The reply was handed to the PTY writer rather than left queued
+        assert!(pane.vt.callbacks_mut().take().is_empty());
+    }
+
     #[test]
     fn test_resize_pty_updates_vt_size() {
         let mut pane = Tab::spawn_error(TabId::new_v4(), TabKind::Shell, "");
@@ -574,7 +619,7 @@ mod tests {
     #[test]
     fn test_scroll_up_no_scrollback() {
         let mut pane = Tab::spawn_error(TabId::new_v4(), TabKind::Shell, "");
-        pane.vt = vt100::Parser::new(3, 80, 1000);
+        pane.vt = replies::new_parser(3, 80, 1000);
         pane.scroll_up(5);
         assert_eq!(pane.scroll_offset, 0);
         assert!(!pane.is_scrolled());
@@ -583,7 +628,7 @@ mod tests {
     #[test]
     fn test_scroll_up_with_scrollback() {
         let mut pane = Tab::spawn_error(TabId::new_v4(), TabKind::Shell, "");
-        pane.vt = vt100::Parser::new(3, 80, 1000);
+        pane.vt = replies::new_parser(3, 80, 1000);
         for i in 0..20 {
             pane.vt.process(format!("line {}\r\n", i).as_bytes());
         }
@@ -595,7 +640,7 @@ mod tests {
     #[test]
     fn test_scroll_to_bottom() {
         let mut pane = Tab::spawn_error(TabId::new_v4(), TabKind::Shell, "");
-        pane.vt = vt100::Parser::new(3, 80, 1000);
+        pane.vt = replies::new_parser(3, 80, 1000);
         for i in 0..20 {
             pane.vt.process(format!("line {}\r\n", i).as_bytes());
         }
@@ -609,7 +654,7 @@ mod tests {
     #[test]
     fn test_resize_resets_scroll() {
         let mut pane = Tab::spawn_error(TabId::new_v4(), TabKind::Shell, "");
-        pane.vt = vt100::Parser::new(3, 80, 1000);
+        pane.vt = replies::new_parser(3, 80, 1000);
         for i in 0..20 {
             pane.vt.process(format!("line {}\r\n", i).as_bytes());
         }
@@ -850,7 +895,7 @@ mod tests {
     #[test]
     fn test_scroll_down_below_zero() {
         let mut pane = Tab::spawn_error(TabId::new_v4(), TabKind::Shell, "");
-        pane.vt = vt100::Parser::new(3, 80, 1000);
+        pane.vt = replies::new_parser(3, 80, 1000);
         // Scroll down without any scroll offset should stay at 0
         pane.scroll_down(5);
         assert_eq!(pane.scroll_offset, 0);
@@ -859,7 +904,7 @@ mod tests {
     #[test]
     fn test_scroll_up_then_down() {
         let mut pane = Tab::spawn_error(TabId::new_v4(), TabKind::Shell, "");
-        pane.vt = vt100::Parser::new(3, 80, 1000);
+        pane.vt = replies::new_parser(3, 80, 1000);
         for i in 0..20 {
             pane.vt.process(format!("line {}\r\n", i).as_bytes());
         }
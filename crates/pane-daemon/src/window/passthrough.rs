@@ -0,0 +1,202 @@
+//! Unwrapping `DCS tmux; ... ST` passthrough sequences.
+//!
+//! Programs that want to reach the outer terminal through a multiplexer wrap
+//! the sequence in `ESC P tmux;` and `ESC \`, doubling every ESC inside it.
+//! The vt100 parser would end the DCS at the first of those ESCs, so
+//! passthrough is taken out of the output before the parser sees it.
+//!
+//! Output that might be the start of a passthrough is passed on right away
+//! rather than held for the next chunk. If it turns out to be one, a CAN
+//! cancels the part the parser already saw.
+
+/// Start of a passthrough sequence.
+const PREFIX: &[u8] = b"\x1bPtmux;";
+
+/// Cancels an escape sequence the parser is partway through.
+const CAN: u8 = 0x18;
+
+/// Passthrough sequences longer than this are dropped.
+const MAX_LEN: usize = 4 * 1024 * 1024;
+
+/// Result of [`PassthroughFilter::filter`].
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Filtered {
+    /// Output with passthrough sequences removed.
+    pub output: Vec<u8>,
+    /// Unwrapped sequences to write to the outer terminal, in order.
+    pub sequences: Vec<Vec<u8>>,
+}
+
+/// Removes passthrough sequences from a tab's output, across chunks.
+#[derive(Debug, Default)]
+pub struct PassthroughFilter {
+    /// Bytes at the end of the last chunk that may begin a passthrough,
+    /// already passed on.
+    partial: Vec<u8>,
+    /// The passthrough being read, if inside one.
+    payload: Option<Vec<u8>>,
+    /// Last byte of the passthrough was an ESC.
+    escaped: bool,
+    /// The passthrough grew past [`MAX_LEN`] and will be dropped.
+    overflowed: bool,
+}
+
+impl PassthroughFilter {
+    /// Take a chunk of PTY output.
+    pub fn filter(&mut self, bytes: &[u8]) -> Filtered {
+        let mut filtered = Filtered::default();
+        if self.payload.is_none() && self.partial.is_empty() && !bytes.contains(&0x1b) {
+            filtered.output = bytes.to_vec();
+            return filtered;
+        }
+        let mut input = std::mem::take(&mut self.partial);
+        let sent = input.len();
+        input.extend_from_slice(bytes);
+        // Pass on input[from..to], skipping what went out with the last chunk
+        let emit = |output: &mut Vec<u8>, from: usize, to: usize| {
+            output.extend_from_slice(&input[from.max(sent).min(to)..to]);
+        };
+
+        let mut i = 0;
+        while i < input.len() {
+            if self.payload.is_some() {
+                i += self.read_payload(&input[i..], &mut filtered.sequences);
+                continue;
+            }
+            let Some(esc) = input[i..].iter().position(|&b| b == 0x1b) else {
+                emit(&mut filtered.output, i, input.len());
+                break;
+            };
+            let esc = i + esc;
+            emit(&mut filtered.output, i, esc);
+            let rest = &input[esc..];
+            if rest.starts_with(PREFIX) {
+                if esc < sent {
+                    filtered.output.push(CAN);
+                }
+                self.payload = Some(Vec::new());
+                i = esc + PREFIX.len();
+            } else if PREFIX.starts_with(rest) {
+                // Might be a passthrough split across chunks
+                emit(&mut filtered.output, esc, input.len());
+                self.partial = rest.to_vec();
+                break;
+            } else {
+                emit(&mut filtered.output, esc, esc + 1);
+                i = esc + 1;
+            }
+        }
+        filtered
+    }
+
+    /// Consume passthrough bytes up to and including its end. Returns how
+    /// many bytes were used.
+    fn read_payload(&mut self, bytes: &[u8], sequences: &mut Vec<Vec<u8>>) -> usize {
+        for (n, &b) in bytes.iter().enumerate() {
+            if self.escaped {
+                self.escaped = false;
+                if b == b'\\' {
+                    let payload = self.payload.take().unwrap_or_default();
+                    if !std::mem::take(&mut self.overflowed) {
+                        sequences.push(payload);
+                    }
+                    return n + 1;
+                }
+                // ESC ESC is a literal ESC; keep anything else as it came
+                self.push(0x1b);
+                if b != 0x1b {
+                    self.push(b);
+                }
+            } else if b == 0x1b {
+                self.escaped = true;
+            } else {
+                self.push(b);
+            }
+        }
+        bytes.len()
+    }
+
+    fn push(&mut self, b: u8) {
+        let Some(payload) = self.payload.as_mut() else {
+            return;
+        };
+        if payload.len() >= MAX_LEN {
+            self.overflowed = true;
+            payload.clear();
+        }
+        if !self.overflowed {
+            payload.push(b);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plain_output_passes_through() {
+        let mut filter = PassthroughFilter::default();
+        let filtered = filter.filter(b"hello \x1b[31mworld\x1b[m");
+        assert_eq!(filtered.output, b"hello \x1b[31mworld\x1b[m");
+        assert!(filtered.sequences.is_empty());
+    }
+
+    #[test]
+    fn test_passthrough_is_unwrapped() {
+        let mut filter = PassthroughFilter::default();
+        let filtered = filter.filter(b"a\x1bPtmux;\x1b\x1b]1337;File=:aGk=\x07\x1b\\b");
+        assert_eq!(filtered.output, b"ab");
+        assert_eq!(filtered.sequences, vec![b"\x1b]1337;File=:aGk=\x07".to_vec()]);
+    }
+
+    #[test]
+    fn test_passthrough_split_across_chunks() {
+        let mut filter = PassthroughFilter::default();
+        let filtered = filter.filter(b"a\x1bPtm");
+        assert_eq!(filtered.output, b"a\x1bPtm");
+        // The parser saw the start, so it's cancelled
+        let filtered = filter.filter(b"ux;\x1b");
+        assert_eq!(filtered.output, b"\x18");
+        assert!(filtered.sequences.is_empty());
+        let filtered = filter.filter(b"\x1b]9;hi\x1b\x1b\\\x1b\\b");
+        assert_eq!(filtered.output, b"b");
+        assert_eq!(filtered.sequences, vec![b"\x1b]9;hi\x1b\\".to_vec()]);
+    }
+
+    #[test]
+    fn test_cancelled_prefix_leaves_parser_clean() {
+        let mut filter = PassthroughFilter::default();
+        let mut parser = vt100::Parser::new(2, 10, 0);
+        for chunk in [&b"a\x1b"[..], b"Ptmux", b";\x1b\x1b]2;t\x07\x1b\\b"] {
+            parser.process(&filter.filter(chunk).output);
+        }
+        assert_eq!(parser.screen().contents(), "ab");
+    }
+
+    #[test]
+    fn test_other_dcs_is_left_alone() {
+        let mut filter = PassthroughFilter::default();
+        let filtered = filter.filter(b"\x1bP$qm\x1b\\");
+        assert_eq!(filtered.output, b"\x1bP$qm\x1b\\");
+        // A trailing ESC isn't held back waiting for the next chunk
+        let filtered = filter.filter(b"x\x1b");
+        assert_eq!(filtered.output, b"x\x1b");
+        let filtered = filter.filter(b"[H");
+        assert_eq!(filtered.output, b"[H");
+        let filtered = filter.filter(b"\x1bP");
+        assert_eq!(filtered.output, b"\x1bP");
+        let filtered = filter.filter(b"$qm\x1b\\");
+        assert_eq!(filtered.output, b"$qm\x1b\\");
+    }
+
+    #[test]
+    fn test_oversized_passthrough_is_dropped() {
+        let mut filter = PassthroughFilter::default();
+        filter.filter(PREFIX);
+        filter.filter(&vec![b'x'; MAX_LEN + 1]);
+        let filtered = filter.filter(b"\x1b\\after");
+        assert_eq!(filtered.output, b"after");
+        assert!(filtered.sequences.is_empty());
+    }
+}
@@ -0,0 +1,372 @@
+//! Answers to terminal queries sent by programs running in a tab.
+//!
+//! The daemon's vt100 parser is the only place that sees every byte a tab
+//! writes, so it answers device attribute, status report, version, mode,
+//! keyboard protocol, window size, color and kitty graphics queries itself.
+//! Clients only re-render output and never reply.
+
+use pane_protocol::config::Theme;
+
+/// Reported in XTVERSION replies.
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+type Rgb = (u8, u8, u8);
+
+/// Colors reported for OSC 10/11 queries.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ReplyColors {
+    pub fg: Rgb,
+    pub bg: Rgb,
+}
+
+impl ReplyColors {
+    pub fn from_theme(theme: &Theme) -> Self {
+        let (fg, bg) = theme.terminal_colors();
+        Self { fg, bg }
+    }
+}
+
+impl Default for ReplyColors {
+    fn default() -> Self {
+        Self {
+            fg: (229, 229, 229),
+            bg: (0, 0, 0),
+        }
+    }
+}
+
+/// An OSC 52 clipboard request from a tab.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ClipboardRequest {
+    /// Set the clipboard to base64 encoded `data`.
+    Write { data: String },
+    /// Report the clipboard back to the tab.
+    Read { selection: String },
+}
+
+/// vt100 callbacks that queue replies to terminal queries. The owning tab
+/// drains them with [`take`](Self::take) after each chunk of output and
+/// writes them back to the PTY. Clipboard requests are queued separately,
+/// since only clients can carry them out.
+#[derive(Debug, Default)]
+pub struct TerminalReplies {
+    pub colors: ReplyColors,
+    pending: Vec<u8>,
+    clipboard: Vec<ClipboardRequest>,
+}
+
+impl TerminalReplies {
+    /// Take all replies queued since the last call.
+    pub fn take(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.pending)
+    }
+
+    /// Take all clipboard requests queued since the last call.
+    pub fn take_clipboard(&mut self) -> Vec<ClipboardRequest> {
+        std::mem::take(&mut self.clipboard)
+    }
+
+    fn push_str(&mut self, s: &str) {
+        self.pending.extend_from_slice(s.as_bytes());
+    }
+
+    fn push_color(&mut self, osc: &str, rgb: Rgb) {
+        let (r, g, b) = rgb;
+        // Colors are reported with 16 bits per channel, like xterm.
+        self.push_str(&format!(
+            "\x1b]{osc};rgb:{r:02x}{r:02x}/{g:02x}{g:02x}/{b:02x}{b:02x}\x1b\\"
+        ));
+    }
+
+    /// DECRQM state for a DEC private mode: 1 = set, 2 = reset, 3 =
+    /// permanently set, 0 = unknown.
+    fn private_mode_state(screen: &vt100::Screen, mode: u16) -> u8 {
+        use vt100::{MouseProtocolEncoding, MouseProtocolMode};
+        let set = match mode {
+            1 => screen.application_cursor(),
+            25 => !screen.hide_cursor(),
+            47 | 1047 | 1049 => screen.alternate_screen(),
+            9 => screen.mouse_protocol_mode() == MouseProtocolMode::Press,
+            1000 => screen.mouse_protocol_mode() == MouseProtocolMode::PressRelease,
+            1002 => screen.mouse_protocol_mode() == MouseProtocolMode::ButtonMotion,
+            1003 => screen.mouse_protocol_mode() == MouseProtocolMode::AnyMotion,
+            1004 => screen.focus_reporting(),
+            1005 => screen.mouse_protocol_encoding() == MouseProtocolEncoding::Utf8,
+            1006 => screen.mouse_protocol_encoding() == MouseProtocolEncoding::Sgr,
+            2004 => screen.bracketed_paste(),
+            2026 => screen.synchronized_output(),
+            // Grapheme clustering is on unless a program turns it off, so
+            // it's reported the way terminals that always cluster do
+            2027 if screen.grapheme_clusters() => return 3,
+            2027 => false,
+            _ => return 0,
+        };
+        if set {
+            1
+        } else {
+            2
+        }
+    }
+}
+
+/// First value of the first parameter, treating a missing one as 0.
+fn first_param(params: &[&[u16]]) -> u16 {
+    params.first().and_then(|p| p.first()).copied().unwrap_or(0)
+}
+
+impl vt100::Callbacks for TerminalReplies {
+    fn unhandled_csi(
+        &mut self,
+        screen: &mut vt100::Screen,
+        i1: Option<u8>,
+        i2: Option<u8>,
+        params: &[&[u16]],
+        c: char,
+    ) {
+        match (i1, i2, c) {
+            // DA1: VT220 with sixel graphics and ANSI color
+            (None, None, 'c') if first_param(params) == 0 => self.push_str("\x1b[?62;4;22c"),
+            // DA2: identify like tmux, which programs already special-case
+            (Some(b'>'), None, 'c') if first_param(params) == 0 => self.push_str("\x1b[>84;0;0c"),
+            // DSR: operating status and cursor position
+            (None | Some(b'?'), None, 'n') => match first_param(params) {
+                5 if i1.is_none() => self.push_str("\x1b[0n"),
+                6 => {
+                    let (rows, cols) = screen.size();
+                    let (row, col) = screen.cursor_position();
+                    let row = row.min(rows.saturating_sub(1)) + 1;
+                    let col = col.min(cols.saturating_sub(1)) + 1;
+                    let private = if i1.is_some() { "?" } else { "" };
+                    self.push_str(&format!("\x1b[{private}{row};{col}R"));
+                }
+                _ => {}
+            },
+            // XTWINOPS: text area and cell size in pixels
+            (None, None, 't') if matches!(first_param(params), 14 | 16) => {
+                let (rows, cols) = screen.size();
+                let (width, height) = screen.cell_pixel_size();
+                let (width, height) = (u32::from(width), u32::from(height));
+                if first_param(params) == 14 {
+                    let (rows, cols) = (u32::from(rows), u32::from(cols));
+                    self.push_str(&format!("\x1b[4;{};{}t", rows * height, cols * width));
+                } else {
+                    self.push_str(&format!("\x1b[6;{height};{width}t"));
+                }
+            }
+            // XTVERSION
+            (Some(b'>'), None, 'q') if first_param(params) == 0 => {
+                self.push_str(&format!("\x1bP>|pane({VERSION})\x1b\\"));
+            }
+            // Kitty keyboard protocol flags
+            (Some(b'?'), None, 'u') => {
+                self.push_str(&format!("\x1b[?{}u", screen.keyboard_flags()));
+            }
+            // DECRQM for DEC private modes
+            (Some(b'?'), Some(b'$'), 'p') => {
+                let mode = first_param(params);
+                let state = Self::private_mode_state(screen, mode);
+                self.push_str(&format!("\x1b[?{mode};{state}$y"));
+            }
+            // DECRQM for ANSI modes, none of which are tracked
+            (Some(b'$'), None, 'p') => {
+                let mode = first_param(params);
+                self.push_str(&format!("\x1b[{mode};0$y"));
+            }
+            _ => {}
+        }
+    }
+
+    fn copy_to_clipboard(&mut self, _: &mut vt100::Screen, _ty: &[u8], data: &[u8]) {
+        self.clipboard.push(ClipboardRequest::Write {
+            data: String::from_utf8_lossy(data).into_owned(),
+        });
+    }
+
+    fn paste_from_clipboard(&mut self, _: &mut vt100::Screen, ty: &[u8]) {
+        // An empty selection means the default, `s0`, like xterm
+        let selection = if ty.is_empty() { b"s0" } else { ty };
+        self.clipboard.push(ClipboardRequest::Read {
+            selection: String::from_utf8_lossy(selection).into_owned(),
+        });
+    }
+
+    fn kitty_graphics_reply(&mut self, _: &mut vt100::Screen, id: u32, error: Option<&str>) {
+        let message = error.unwrap_or("OK");
+        self.push_str(&format!("\x1b_Gi={id};{message}\x1b\\"));
+    }
+
+    fn unhandled_osc(&mut self, _: &mut vt100::Screen, params: &[&[u8]]) {
+        let Some((&first, rest)) = params.split_first() else {
+            return;
+        };
+        match first {
+            // OSC 10/11: each `?` queries the next dynamic color in turn
+            b"10" | b"11" => {
+                let start = if first == b"10" { 10 } else { 11 };
+                for (osc, value) in (start..).zip(rest) {
+                    if *value != b"?" {
+                        continue;
+                    }
+                    match osc {
+                        10 => self.push_color("10", self.colors.fg),
+                        11 => self.push_color("11", self.colors.bg),
+                        _ => {}
+                    }
+                }
+            }
+            // OSC 4: index;? pairs for palette entries
+            b"4" => {
+                for pair in rest.chunks(2) {
+                    let [index, b"?"] = pair else { continue };
+                    let Some(index) = std::str::from_utf8(index)
+                        .ok()
+                        .and_then(|s| s.parse::<u8>().ok())
+                    else {
+                        continue;
+                    };
+                    self.push_color(&format!("4;{index}"), Theme::xterm_palette(index));
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Create a parser for a tab whose terminal queries get answered.
+pub fn new_parser(rows: u16, cols: u16, scrollback: usize) -> vt100::Parser<TerminalReplies> {
+    vt100::Parser::new_with_callbacks(rows, cols, scrollback, TerminalReplies::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reply(input: &[u8]) -> String {
+        let mut parser = new_parser(24, 80, 0);
+        parser.process(input);
+        String::from_utf8(parser.callbacks_mut().take()).unwrap()
+    }
+
+    #[test]
+    fn test_clipboard_requests_are_queued() {
+        let mut parser = new_parser(24, 80, 0);
+        parser.process(b"\x1b]52;c;aGVsbG8=\x07\x1b]52;;?\x1b\\\x1b]52;p;?\x07");
+        assert_eq!(
+            parser.callbacks_mut().take_clipboard(),
+            vec![
+                ClipboardRequest::Write {
+                    data: "aGVsbG8=".to_string()
+                },
+                ClipboardRequest::Read {
+                    selection: "s0".to_string()
+                },
+                ClipboardRequest::Read {
+                    selection: "p".to_string()
+                },
+            ]
+        );
+        // Nothing is written back to the tab until a client answers
+        assert!(parser.callbacks_mut().take().is_empty());
+        assert!(parser.callbacks_mut().take_clipboard().is_empty());
+    }
+
+    #[test]
+    fn test_device_attributes() {
+        assert_eq!(reply(b"\x1b[c"), "\x1b[?62;4;22c");
+        assert_eq!(reply(b"\x1b[0c"), "\x1b[?62;4;22c");
+        assert_eq!(reply(b"\x1b[>c"), "\x1b[>84;0;0c");
+    }
+
+    #[test]
+    fn test_status_and_cursor_position_reports() {
+        assert_eq!(reply(b"\x1b[5n"), "\x1b[0n");
+        assert_eq!(reply(b"\x1b[3;7H\x1b[6n"), "\x1b[3;7R");
+        assert_eq!(reply(b"\x1b[3;7H\x1b[?6n"), "\x1b[?3;7R");
+    }
+
+    #[test]
+    fn test_cursor_position_report_is_in_order() {
+        // The position is taken when the query is seen, not at the end of
+        // the chunk.
+        assert_eq!(reply(b"ab\x1b[6ncdef"), "\x1b[1;3R");
+    }
+
+    #[test]
+    fn test_xtversion() {
+        assert_eq!(reply(b"\x1b[>q"), format!("\x1bP>|pane({VERSION})\x1b\\"));
+    }
+
+    #[test]
+    fn test_decrqm() {
+        assert_eq!(reply(b"\x1b[?2004$p"), "\x1b[?2004;2$y");
+        assert_eq!(reply(b"\x1b[?2004h\x1b[?2004$p"), "\x1b[?2004;1$y");
+        assert_eq!(reply(b"\x1b[?1049h\x1b[?1049$p"), "\x1b[?1049;1$y");
+        assert_eq!(reply(b"\x1b[?2026$p"), "\x1b[?2026;2$y");
+        assert_eq!(reply(b"\x1b[?2027$p"), "\x1b[?2027;3$y");
+        assert_eq!(reply(b"\x1b[?2027l\x1b[?2027$p"), "\x1b[?2027;2$y");
+        assert_eq!(reply(b"\x1b[?9999$p"), "\x1b[?9999;0$y");
+        assert_eq!(reply(b"\x1b[4$p"), "\x1b[4;0$y");
+    }
+
+    #[test]
+    fn test_keyboard_flags_query() {
+        assert_eq!(reply(b"\x1b[?u"), "\x1b[?0u");
+        assert_eq!(reply(b"\x1b[>5u\x1b[?u"), "\x1b[?5u");
+    }
+
+    #[test]
+    fn test_window_size_reports() {
+        assert_eq!(reply(b"\x1b[16t"), "\x1b[6;20;10t");
+        assert_eq!(reply(b"\x1b[14t"), "\x1b[4;480;800t");
+    }
+
+    #[test]
+    fn test_kitty_graphics_replies() {
+        assert_eq!(
+            reply(b"\x1b_Gi=31,s=1,v=1,a=q,t=d,f=24;AAAA\x1b\\"),
+            "\x1b_Gi=31;OK\x1b\\"
+        );
+        assert_eq!(
+            reply(b"\x1b_Gi=32,a=q,t=f;AAAA\x1b\\"),
+            "\x1b_Gi=32;EINVAL:only direct transmission is supported\x1b\\"
+        );
+        // Quiet commands and ones without an id get no reply
+        assert_eq!(reply(b"\x1b_Ga=T,f=24,s=1,v=1;AAAA\x1b\\"), "");
+        assert_eq!(reply(b"\x1b_Ga=T,i=5,q=2,f=24,s=1,v=1;AAAA\x1b\\"), "");
+    }
+
+    #[test]
+    fn test_color_queries() {
+        let mut parser = new_parser(24, 80, 0);
+        parser.callbacks_mut().colors = ReplyColors {
+            fg: (0xf8, 0xf8, 0xf2),
+            bg: (0x28, 0x2a, 0x36),
+        };
+        parser.process(b"\x1b]11;?\x07\x1b]10;?\x1b\\");
+        assert_eq!(
+            String::from_utf8(parser.callbacks_mut().take()).unwrap(),
+            "\x1b]11;rgb:2828/2a2a/3636\x1b\\\x1b]10;rgb:f8f8/f8f8/f2f2\x1b\\"
+        );
+        // Both at once
+        parser.process(b"\x1b]10;?;?\x07");
+        assert_eq!(
+            String::from_utf8(parser.callbacks_mut().take()).unwrap(),
+            "\x1b]10;rgb:f8f8/f8f8/f2f2\x1b\\\x1b]11;rgb:2828/2a2a/3636\x1b\\"
+        );
+    }
+
+    #[test]
+    fn test_palette_queries() {
+        assert_eq!(
+            reply(b"\x1b]4;1;?;196;?\x07"),
+            "\x1b]4;1;rgb:cdcd/0000/0000\x1b\\\x1b]4;196;rgb:ffff/0000/0000\x1b\\"
+        );
+        // Setting a color is not a query
+        assert_eq!(reply(b"\x1b]4;1;#ff0000\x07"), "");
+    }
+
+    #[test]
+    fn test_plain_output_has_no_replies() {
+        assert_eq!(reply(b"hello \x1b[31mworld\x1b[m\r\n"), "");
+    }
+}
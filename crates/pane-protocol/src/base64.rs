@@ -0,0 +1,293 @@
+//! Standard base64, as used by OSC 52 clipboard requests, kitty graphics
+//! and control mode output.
+
+const CHARS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+pub fn encode(data: &[u8]) -> String {
+    let mut result = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let b0 = chunk[0] as u32;
+        let b1 = if chunk.len() > 1 { chunk[1] as u32 } else { 0 };
+        let b2 = if chunk.len() > 2 { chunk[2] as u32 } else { 0 };
+        let triple = (b0 << 16) | (b1 << 8) | b2;
+        result.push(CHARS[((triple >> 18) & 0x3F) as usize] as char);
+        result.push(CHARS[((triple >> 12) & 0x3F) as usize] as char);
+        if chunk.len() > 1 {
+            result.push(CHARS[((triple >> 6) & 0x3F) as usize] as char);
+        } else {
+            result.push('=');
+        }
+        if chunk.len() > 2 {
+            result.push(CHARS[(triple & 0x3F) as usize] as char);
+        } else {
+            result.push('=');
+        }
+    }
+    result
+}
+
+/// Decode padded base64. Anything else, including whitespace and missing
+/// padding, is `None`, so a decoded payload is safe to echo back inside an
+/// escape sequence.
+pub fn decode(data: &str) -> Option<Vec<u8>> {
+    fn value(c: u8) -> Option<u32> {
+        match c {
+            b'A'..=b'Z' => Some((c - b'A') as u32),
+            b'a'..=b'z' => Some((c - b'a' + 26) as u32),
+            b'0'..=b'9' => Some((c - b'0' + 52) as u32),
+            b'+' => Some(62),
+            b'/' => Some(63),
+            _ => None,
+        }
+    }
+    let data = data.as_bytes();
+    if !data.len().is_multiple_of(4) {
+        return None;
+    }
+    let groups = data.len() / 4;
+    let mut result = Vec::with_capacity(groups * 3);
+    for (i, chunk) in data.chunks_exact(4).enumerate() {
+        let pad = chunk.iter().rev().take_while(|&&c| c == b'=').count();
+        if pad > 2 || (pad > 0 && i + 1 < groups) {
+            return None;
+        }
+        let mut triple = 0u32;
+        for &c in &chunk[..4 - pad] {
+            triple = (triple << 6) | value(c)?;
+        }
+        triple <<= 6 * pad;
+        result.extend_from_slice(&triple.to_be_bytes()[1..4 - pad]);
+    }
+    Some(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_empty() {
+        assert_eq!(encode(b""), "");
+    }
+
+    #[test]
+    fn test_encode_hello() {
+        assert_eq!(encode(b"hello"), "aGVsbG8=");
+    }
+
+    #[test]
+    fn test_encode_padding() {
+        assert_eq!(encode(b"a"), "YQ==");
+        assert_eq!(encode(b"ab"), "YWI=");
+        assert_eq!(encode(b"abc"), "YWJj");
+    }
+
+    #[test]
+    fn test_encode_longer() {
+        assert_eq!(encode(b"Hello, World!"), "SGVsbG8sIFdvcmxkIQ==");
+    }
+
+    #[test]
+    fn test_encode_single_byte() {
+        // 1 byte → 2 chars + "==" padding
+        assert_eq!(encode(b"\x00"), "AA==");
+        assert_eq!(encode(b"\xff"), "/w==");
+    }
+
+    #[test]
+    fn test_encode_exactly_3_bytes() {
+        // 3 bytes = exactly one chunk, no padding
+        assert_eq!(encode(b"abc"), "YWJj");
+        assert_eq!(encode(b"\x00\x00\x00"), "AAAA");
+    }
+
+    #[test]
+    fn test_encode_non_ascii_bytes() {
+        // 0x80, 0xFF, 0xFE
+        assert_eq!(encode(&[0x80, 0xFF, 0xFE]), "gP/+");
+        // High bytes with padding
+        assert_eq!(encode(&[0xDE, 0xAD]), "3q0=");
+        assert_eq!(encode(&[0xCA, 0xFE, 0xBA, 0xBE]), "yv66vg==");
+    }
+
+    #[test]
+    fn test_encode_6_bytes_no_padding() {
+        // 6 bytes = exactly 2 chunks, no padding
+        assert_eq!(encode(b"abcdef"), "YWJjZGVm");
+    }
+
+    #[test]
+    fn test_encode_4_bytes_has_padding() {
+        // 4 bytes = 1 full chunk + 1 byte remainder → "==" padding on last group
+        let result = encode(b"abcd");
+        assert_eq!(result, "YWJjZA==");
+        assert!(result.ends_with("=="));
+    }
+
+    #[test]
+    fn test_encode_5_bytes_has_single_pad() {
+        // 5 bytes = 1 full chunk + 2 byte remainder → "=" padding on last group
+        let result = encode(b"abcde");
+        assert_eq!(result, "YWJjZGU=");
+        assert!(result.ends_with('='));
+        assert!(!result.ends_with("=="));
+    }
+
+    // --- Binary data: all byte values 0-255 ---
+
+    #[test]
+    fn test_encode_all_bytes() {
+        let data: Vec<u8> = (0..=255).collect();
+        let result = encode(&data);
+
+        // 256 bytes → ceil(256/3) = 86 groups, but 256 % 3 = 1,
+        // so 85 full groups + 1 partial = 86 groups → 86 * 4 = 344 chars
+        assert_eq!(result.len(), 344);
+
+        // Verify it's valid base64 characters
+        for c in result.chars() {
+            assert!(
+                c.is_ascii_alphanumeric() || c == '+' || c == '/' || c == '=',
+                "invalid base64 char: {:?}",
+                c
+            );
+        }
+
+        // Verify padding: 256 % 3 = 1 → 2 pad chars ("==")
+        assert!(result.ends_with("=="));
+    }
+
+    #[test]
+    fn test_encode_all_bytes_first_128() {
+        let data: Vec<u8> = (0..128).collect();
+        let result = encode(&data);
+
+        // 128 bytes → 128 % 3 = 2, so single "=" pad
+        assert!(result.ends_with('='));
+        assert!(!result.ends_with("=="));
+
+        for c in result.chars() {
+            assert!(
+                c.is_ascii_alphanumeric() || c == '+' || c == '/' || c == '=',
+                "invalid base64 char: {:?}",
+                c
+            );
+        }
+    }
+
+    #[test]
+    fn test_encode_binary_roundtrip_length() {
+        // For N input bytes, output length = ceil(N/3) * 4
+        for n in 0..=50 {
+            let data: Vec<u8> = (0..n).map(|i| i as u8).collect();
+            let result = encode(&data);
+            let expected_len = if n == 0 {
+                0
+            } else {
+                (n as usize).div_ceil(3) * 4
+            };
+            assert_eq!(
+                result.len(),
+                expected_len,
+                "wrong length for {} input bytes",
+                n
+            );
+        }
+    }
+
+    // --- Very long strings ---
+
+    #[test]
+    fn test_encode_long_string() {
+        let input = "A".repeat(10_000);
+        let result = encode(input.as_bytes());
+
+        // 10000 bytes → ceil(10000/3) * 4 = 3334 * 4 = 13336
+        // 10000 % 3 = 1 → "==" padding
+        assert_eq!(result.len(), 13336);
+        assert!(result.ends_with("=="));
+
+        // Verify the content is consistent (all 'A' bytes = 0x41)
+        // "AAA" → base64 "QUFB" (repeating pattern)
+        // First 4 chars should be "QUFB"
+        assert!(result.starts_with("QUFB"));
+    }
+
+    #[test]
+    fn test_encode_long_mixed() {
+        let input: Vec<u8> = (0..5000).map(|i| (i % 256) as u8).collect();
+        let result = encode(&input);
+
+        // 5000 % 3 = 2 → single "=" pad
+        assert!(result.ends_with('='));
+        assert!(!result.ends_with("=="));
+
+        let expected_len = 5000_usize.div_ceil(3) * 4;
+        assert_eq!(result.len(), expected_len);
+    }
+
+    #[test]
+    fn test_encode_exactly_divisible_by_3() {
+        // 999 bytes / 3 = 333 groups → no padding
+        let data: Vec<u8> = (0..999).map(|i| (i % 256) as u8).collect();
+        let result = encode(&data);
+        assert!(!result.ends_with('='), "no padding for length divisible by 3");
+        assert_eq!(result.len(), 333 * 4);
+    }
+
+    // --- Edge cases ---
+
+    #[test]
+    fn test_encode_newlines() {
+        let result = encode(b"\n\n\n");
+        assert_eq!(result, "CgoK");
+    }
+
+    #[test]
+    fn test_encode_null_bytes() {
+        let result = encode(b"\x00\x00\x00\x00\x00\x00");
+        assert_eq!(result, "AAAAAAAA");
+    }
+
+    #[test]
+    fn test_encode_max_bytes() {
+        let result = encode(b"\xff\xff\xff");
+        assert_eq!(result, "////");
+    }
+
+    #[test]
+    fn test_encode_unicode_string() {
+        // UTF-8 encoding of emoji
+        let result = encode("🦀".as_bytes());
+        // 🦀 = 4 bytes (F0 9F A6 80), 4 % 3 = 1 → "==" padding
+        assert_eq!(result.len(), 8);
+        assert!(result.ends_with("=="));
+    }
+
+    #[test]
+    fn test_decode_roundtrip() {
+        for n in 0..=20 {
+            let data: Vec<u8> = (0..n).map(|i| (i * 37) as u8).collect();
+            assert_eq!(decode(&encode(&data)), Some(data));
+        }
+        assert_eq!(decode("aGVsbG8="), Some(b"hello".to_vec()));
+    }
+
+    #[test]
+    fn test_decode_rejects_garbage() {
+        assert_eq!(decode("a"), None);
+        assert_eq!(decode("a*bc"), None);
+    }
+
+    #[test]
+    fn test_decode_is_strict() {
+        // Padding is required, and only at the end
+        assert_eq!(decode("aGVsbG8"), None);
+        assert_eq!(decode("aGk"), None);
+        assert_eq!(decode("YQ==YQ=="), None);
+        assert_eq!(decode("Y==="), None);
+        assert_eq!(decode("aGVs\nbG8="), None);
+        assert_eq!(decode("aGVs;\x1b]"), None);
+        assert_eq!(decode(""), Some(Vec::new()));
+    }
+}
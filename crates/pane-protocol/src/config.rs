@@ -41,6 +41,7 @@ pub enum Action {
     ScrollMode,
     CopyMode,
     PasteClipboard,
+    ChooseBuffer,
     SelectLayout(String),
     ToggleSyncPanes,
     CommandPalette,
@@ -167,7 +168,23 @@ impl Theme {
     /// Dim a color by a factor (0.0–1.0). Named ANSI colors are mapped to RGB
     /// first. `Color::Reset` and `Color::Indexed` pass through unchanged.
     pub fn dim_color(color: Color, factor: f32) -> Color {
-        let (r, g, b) = match color {
+        if matches!(color, Color::Indexed(_)) {
+            return color;
+        }
+        let Some((r, g, b)) = Self::rgb(color) else {
+            return color;
+        };
+        Color::Rgb(
+            (r as f32 * factor) as u8,
+            (g as f32 * factor) as u8,
+            (b as f32 * factor) as u8,
+        )
+    }
+
+    /// RGB value of a color, using the xterm palette for named and indexed
+    /// colors. Returns `None` for `Color::Reset`.
+    pub fn rgb(color: Color) -> Option<(u8, u8, u8)> {
+        Some(match color {
             Color::Rgb(r, g, b) => (r, g, b),
             Color::Black => (0, 0, 0),
             Color::Red => (205, 0, 0),
@@ -185,12 +202,57 @@ impl Theme {
             Color::LightBlue => (92, 92, 255),
             Color::LightMagenta => (255, 0, 255),
             Color::LightCyan => (0, 255, 255),
-            _ => return color,
+            Color::Indexed(i) => Self::xterm_palette(i),
+            Color::Reset => return None,
+        })
+    }
+
+    /// RGB value of an entry in the default xterm 256-color palette.
+    pub fn xterm_palette(index: u8) -> (u8, u8, u8) {
+        const ANSI: [(u8, u8, u8); 16] = [
+            (0, 0, 0),
+            (205, 0, 0),
+            (0, 205, 0),
+            (205, 205, 0),
+            (0, 0, 238),
+            (205, 0, 205),
+            (0, 205, 205),
+            (229, 229, 229),
+            (127, 127, 127),
+            (255, 0, 0),
+            (0, 255, 0),
+            (255, 255, 0),
+            (92, 92, 255),
+            (255, 0, 255),
+            (0, 255, 255),
+            (255, 255, 255),
+        ];
+        match index {
+            0..=15 => ANSI[index as usize],
+            16..=231 => {
+                let level = |v: u8| if v == 0 { 0 } else { 55 + v * 40 };
+                let i = index - 16;
+                (level(i / 36), level(i / 6 % 6), level(i % 6))
+            }
+            _ => {
+                let v = 8 + (index - 232) * 10;
+                (v, v, v)
+            }
+        }
+    }
+
+    /// Default foreground and background reported to programs that query
+    /// the terminal (OSC 10/11). `Reset` falls back to white on black, or
+    /// black on white when the outer terminal has a light background.
+    pub fn terminal_colors(&self) -> ((u8, u8, u8), (u8, u8, u8)) {
+        let (fg, bg) = if is_light_terminal() {
+            ((0, 0, 0), (255, 255, 255))
+        } else {
+            ((229, 229, 229), (0, 0, 0))
         };
-        Color::Rgb(
-            (r as f32 * factor) as u8,
-            (g as f32 * factor) as u8,
-            (b as f32 * factor) as u8,
+        (
+            Self::rgb(self.fg).unwrap_or(fg),
+            Self::rgb(self.bg).unwrap_or(bg),
         )
     }
 }
@@ -211,6 +273,120 @@ pub struct Behavior {
     pub terminal_title_format: Option<String>,
     /// Whether the terminal font supports Nerd Font glyphs.
     pub nerd_fonts: bool,
+    /// Key bindings used in copy mode.
+    pub copy_mode_keys: CopyModeKeys,
+    /// Which tabs may write to the outer terminal with `DCS tmux;`.
+    pub allow_passthrough: AllowPassthrough,
+    /// Whether tabs may set the clipboard with OSC 52.
+    pub clipboard_write: ClipboardPolicy,
+    /// Whether tabs may read the clipboard with OSC 52.
+    pub clipboard_read: ClipboardPolicy,
+    /// Automatic paste buffers kept before the oldest is dropped.
+    pub buffer_limit: usize,
+    /// How tabs are sized when clients of different sizes are attached.
+    pub window_size: WindowSize,
+    /// Size used by the `manual` policy, as columns and rows.
+    pub default_size: (u16, u16),
+    /// KiB of frames queued for a client before its output is dropped
+    /// and it is resynced.
+    pub client_queue_limit_kb: usize,
+}
+
+/// Key binding style for copy mode, like tmux's `mode-keys`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CopyModeKeys {
+    #[default]
+    Vi,
+    Emacs,
+}
+
+impl CopyModeKeys {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "vi" | "vim" => Some(Self::Vi),
+            "emacs" => Some(Self::Emacs),
+            _ => None,
+        }
+    }
+}
+
+/// Whether `DCS tmux; ... ST` passthrough reaches the outer terminal, like
+/// tmux's `allow-passthrough`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AllowPassthrough {
+    /// Passthrough is discarded.
+    #[default]
+    Off,
+    /// Only tabs currently on screen may use passthrough.
+    On,
+    /// Any tab may use passthrough, even when it isn't shown.
+    All,
+}
+
+impl AllowPassthrough {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "off" | "false" => Some(Self::Off),
+            "on" | "true" => Some(Self::On),
+            "all" => Some(Self::All),
+            _ => None,
+        }
+    }
+}
+
+/// Whether a tab's OSC 52 clipboard requests are carried out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ClipboardPolicy {
+    Allow,
+    Deny,
+    /// Each client asks its user first.
+    Ask,
+}
+
+impl ClipboardPolicy {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "allow" | "on" | "true" => Some(Self::Allow),
+            "deny" | "off" | "false" => Some(Self::Deny),
+            "ask" | "prompt" => Some(Self::Ask),
+            _ => None,
+        }
+    }
+}
+
+/// Which attached clients decide the session size, like tmux's `window-size`.
+/// Native app clients size their tabs themselves and never count.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum WindowSize {
+    /// The smallest client, so every client sees all of each tab.
+    #[default]
+    Smallest,
+    /// The largest client; smaller clients see part of each tab.
+    Largest,
+    /// The client that most recently had input.
+    Latest,
+    /// `default_size`, whatever size the clients are.
+    Manual,
+}
+
+impl WindowSize {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "smallest" => Some(Self::Smallest),
+            "largest" => Some(Self::Largest),
+            "latest" => Some(Self::Latest),
+            "manual" => Some(Self::Manual),
+            _ => None,
+        }
+    }
+}
+
+/// Parse a `WIDTHxHEIGHT` size such as `"200x50"`.
+pub fn parse_size(s: &str) -> Option<(u16, u16)> {
+    let (w, h) = s.trim().split_once(['x', 'X'])?;
+    let w: u16 = w.trim().parse().ok()?;
+    let h: u16 = h.trim().parse().ok()?;
+    (w > 0 && h > 0).then_some((w, h))
 }
 
 impl Default for Behavior {
@@ -223,10 +399,81 @@ impl Default for Behavior {
             auto_suspend_secs: 86400,
             terminal_title_format: Some("{session} - {workspace}".to_string()),
             nerd_fonts: false,
+            copy_mode_keys: CopyModeKeys::default(),
+            allow_passthrough: AllowPassthrough::default(),
+            clipboard_write: ClipboardPolicy::Allow,
+            clipboard_read: ClipboardPolicy::Deny,
+            buffer_limit: 50,
+            window_size: WindowSize::default(),
+            default_size: (80, 24),
+            client_queue_limit_kb: 4096,
         }
     }
 }
 
+// ---------------------------------------------------------------------------
+// ScrollbackConfig
+// ---------------------------------------------------------------------------
+
+#[derive(Clone, Debug)]
+pub struct ScrollbackConfig {
+    /// Lines of history kept per tab unless overridden.
+    pub history_limit: usize,
+    /// Per tab kind overrides, keyed by kind name ("shell", "agent", "nvim", "server").
+    pub kind_limits: HashMap<String, usize>,
+    /// Memory budget in MiB for the scrollback of all tabs combined (0 = unlimited).
+    pub memory_limit_mb: usize,
+}
+
+impl Default for ScrollbackConfig {
+    fn default() -> Self {
+        Self {
+            history_limit: 1000,
+            kind_limits: HashMap::new(),
+            memory_limit_mb: 256,
+        }
+    }
+}
+
+impl ScrollbackConfig {
+    /// History limit for a new tab of the given kind.
+    pub fn history_limit_for(&self, kind: &crate::window_types::TabKind) -> usize {
+        let name = match kind {
+            crate::window_types::TabKind::Shell => "shell",
+            crate::window_types::TabKind::Agent => "agent",
+            crate::window_types::TabKind::Nvim => "nvim",
+            crate::window_types::TabKind::DevServer => "server",
+        };
+        self.kind_limits
+            .get(name)
+            .copied()
+            .unwrap_or(self.history_limit)
+    }
+
+    /// Memory budget in bytes, or `None` when unlimited.
+    pub fn memory_limit_bytes(&self) -> Option<usize> {
+        (self.memory_limit_mb > 0).then(|| self.memory_limit_mb.saturating_mul(1024 * 1024))
+    }
+}
+
+// ---------------------------------------------------------------------------
+// ServerConfig
+// ---------------------------------------------------------------------------
+
+#[derive(Clone, Debug, Default)]
+pub struct ServerConfig {
+    /// Address for the optional TCP listener, e.g. "127.0.0.1:7474".
+    /// `None` keeps the daemon on its Unix socket only.
+    pub tcp_listen: Option<String>,
+    /// Shared secret TCP clients must send before attaching. The listener
+    /// stays off without one.
+    pub token: Option<String>,
+    /// PEM certificate chain; with `tls_key`, the TCP listener speaks TLS.
+    pub tls_cert: Option<std::path::PathBuf>,
+    /// PEM private key for `tls_cert`.
+    pub tls_key: Option<std::path::PathBuf>,
+}
+
 // ---------------------------------------------------------------------------
 // PaneDecoration — per-process visual overrides
 // ---------------------------------------------------------------------------
@@ -683,6 +930,8 @@ pub struct Config {
     pub keys: KeyMap,
     pub normal_keys: KeyMap,
     pub status_bar: StatusBarConfig,
+    pub scrollback: ScrollbackConfig,
+    pub server: ServerConfig,
     pub decorations: Vec<PaneDecoration>,
     pub leader: LeaderConfig,
     pub plugins: Vec<crate::plugin::PluginConfig>,
@@ -697,6 +946,8 @@ impl Default for Config {
             keys: KeyMap::from_defaults(),
             normal_keys: KeyMap::from_pairs(crate::default_keys::normal_defaults()),
             status_bar: StatusBarConfig::default(),
+            scrollback: ScrollbackConfig::default(),
+            server: ServerConfig::default(),
             decorations: PaneDecoration::defaults(),
             leader: LeaderConfig::default(),
             plugins: Vec::new(),
@@ -846,6 +1097,38 @@ impl Config {
             if let Some(v) = b.nerd_fonts {
                 config.behavior.nerd_fonts = v;
             }
+            if let Some(v) = b.copy_mode_keys.as_deref().and_then(CopyModeKeys::parse) {
+                config.behavior.copy_mode_keys = v;
+            }
+            if let Some(v) = b
+                .allow_passthrough
+                .as_deref()
+                .and_then(AllowPassthrough::parse)
+            {
+                config.behavior.allow_passthrough = v;
+            }
+            if let Some(v) = b
+                .clipboard_write
+                .as_deref()
+                .and_then(ClipboardPolicy::parse)
+            {
+                config.behavior.clipboard_write = v;
+            }
+            if let Some(v) = b.clipboard_read.as_deref().and_then(ClipboardPolicy::parse) {
+                config.behavior.clipboard_read = v;
+            }
+            if let Some(v) = b.buffer_limit {
+                config.behavior.buffer_limit = v;
+            }
+            if let Some(v) = b.window_size.as_deref().and_then(WindowSize::parse) {
+                config.behavior.window_size = v;
+            }
+            if let Some(v) = b.default_size.as_deref().and_then(parse_size) {
+                config.behavior.default_size = v;
+            }
+            if let Some(v) = b.client_queue_limit_kb.filter(|&v| v > 0) {
+                config.behavior.client_queue_limit_kb = v;
+            }
         }
 
         // Keys
@@ -883,6 +1166,34 @@ impl Config {
             }
         }
 
+        // Scrollback
+        if let Some(sb) = raw.scrollback {
+            if let Some(v) = sb.history_limit {
+                config.scrollback.history_limit = v;
+            }
+            if let Some(v) = sb.memory_limit_mb {
+                config.scrollback.memory_limit_mb = v;
+            }
+            if let Some(kinds) = sb.kinds {
+                for (name, limit) in kinds {
+                    let name = match name.to_lowercase().as_str() {
+                        "claude" => "agent".to_string(),
+                        "devserver" | "dev-server" => "server".to_string(),
+                        other => other.to_string(),
+                    };
+                    config.scrollback.kind_limits.insert(name, limit);
+                }
+            }
+        }
+
+        // Server
+        if let Some(server) = raw.server {
+            config.server.tcp_listen = server.tcp_listen.filter(|s| !s.is_empty());
+            config.server.token = server.token.filter(|s| !s.is_empty());
+            config.server.tls_cert = server.tls_cert.map(std::path::PathBuf::from);
+            config.server.tls_key = server.tls_key.map(std::path::PathBuf::from);
+        }
+
         // Leader
         if let Some(ref leader) = raw.leader {
             if let Some(ref key_str) = leader.key {
@@ -960,6 +1271,8 @@ struct RawConfig {
     keys: Option<HashMap<String, String>>,
     normal_keys: Option<HashMap<String, String>>,
     status_bar: Option<RawStatusBar>,
+    scrollback: Option<RawScrollback>,
+    server: Option<RawServer>,
     decorations: Option<Vec<RawDecoration>>,
     leader: Option<RawLeader>,
     leader_keys: Option<HashMap<String, String>>,
@@ -1022,6 +1335,14 @@ struct RawBehavior {
     auto_suspend_secs: Option<u64>,
     terminal_title_format: Option<String>,
     nerd_fonts: Option<bool>,
+    copy_mode_keys: Option<String>,
+    allow_passthrough: Option<String>,
+    clipboard_write: Option<String>,
+    clipboard_read: Option<String>,
+    buffer_limit: Option<usize>,
+    window_size: Option<String>,
+    default_size: Option<String>,
+    client_queue_limit_kb: Option<usize>,
 }
 
 #[derive(Deserialize, Default)]
@@ -1035,6 +1356,21 @@ struct RawStatusBar {
     right: Option<String>,
 }
 
+#[derive(Deserialize, Default)]
+struct RawScrollback {
+    history_limit: Option<usize>,
+    memory_limit_mb: Option<usize>,
+    kinds: Option<HashMap<String, usize>>,
+}
+
+#[derive(Deserialize, Default)]
+struct RawServer {
+    tcp_listen: Option<String>,
+    token: Option<String>,
+    tls_cert: Option<String>,
+    tls_key: Option<String>,
+}
+
 // ---------------------------------------------------------------------------
 // parse_key: "ctrl+shift+d" → crossterm KeyEvent
 // ---------------------------------------------------------------------------
@@ -1392,6 +1728,131 @@ min_pane_width = 80
         assert_eq!(config.behavior.fold_bar_size, 1);
     }
 
+    #[test]
+    fn test_config_copy_mode_keys() {
+        assert_eq!(Config::default().behavior.copy_mode_keys, CopyModeKeys::Vi);
+
+        let raw: RawConfig = toml::from_str("[behavior]\ncopy_mode_keys = \"emacs\"\n").unwrap();
+        let config = Config::from_raw(raw);
+        assert_eq!(config.behavior.copy_mode_keys, CopyModeKeys::Emacs);
+
+        let raw: RawConfig = toml::from_str("[behavior]\ncopy_mode_keys = \"bogus\"\n").unwrap();
+        let config = Config::from_raw(raw);
+        assert_eq!(config.behavior.copy_mode_keys, CopyModeKeys::Vi);
+    }
+
+    #[test]
+    fn test_config_clipboard_policies() {
+        let config = Config::default();
+        assert_eq!(config.behavior.clipboard_write, ClipboardPolicy::Allow);
+        assert_eq!(config.behavior.clipboard_read, ClipboardPolicy::Deny);
+
+        let raw: RawConfig =
+            toml::from_str("[behavior]\nclipboard_write = \"ask\"\nclipboard_read = \"allow\"\n")
+                .unwrap();
+        let config = Config::from_raw(raw);
+        assert_eq!(config.behavior.clipboard_write, ClipboardPolicy::Ask);
+        assert_eq!(config.behavior.clipboard_read, ClipboardPolicy::Allow);
+    }
+
+    #[test]
+    fn test_config_window_size() {
+        let config = Config::default();
+        assert_eq!(config.behavior.window_size, WindowSize::Smallest);
+        assert_eq!(config.behavior.default_size, (80, 24));
+
+        let raw: RawConfig =
+            toml::from_str("[behavior]\nwindow_size = \"latest\"\ndefault_size = \"200x50\"\n")
+                .unwrap();
+        let config = Config::from_raw(raw);
+        assert_eq!(config.behavior.window_size, WindowSize::Latest);
+        assert_eq!(config.behavior.default_size, (200, 50));
+    }
+
+    #[test]
+    fn test_config_client_queue_limit() {
+        assert_eq!(Config::default().behavior.client_queue_limit_kb, 4096);
+
+        let raw: RawConfig = toml::from_str("[behavior]\nclient_queue_limit_kb = 256\n").unwrap();
+        assert_eq!(Config::from_raw(raw).behavior.client_queue_limit_kb, 256);
+
+        let raw: RawConfig = toml::from_str("[behavior]\nclient_queue_limit_kb = 0\n").unwrap();
+        assert_eq!(Config::from_raw(raw).behavior.client_queue_limit_kb, 4096);
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("120x40"), Some((120, 40)));
+        assert_eq!(parse_size(" 80 X 24 "), Some((80, 24)));
+        assert_eq!(parse_size("0x24"), None);
+        assert_eq!(parse_size("wide"), None);
+    }
+
+    #[test]
+    fn test_config_allow_passthrough() {
+        assert_eq!(
+            Config::default().behavior.allow_passthrough,
+            AllowPassthrough::Off
+        );
+
+        let raw: RawConfig = toml::from_str("[behavior]\nallow_passthrough = \"on\"\n").unwrap();
+        let config = Config::from_raw(raw);
+        assert_eq!(config.behavior.allow_passthrough, AllowPassthrough::On);
+
+        let raw: RawConfig = toml::from_str("[behavior]\nallow_passthrough = \"all\"\n").unwrap();
+        let config = Config::from_raw(raw);
+        assert_eq!(config.behavior.allow_passthrough, AllowPassthrough::All);
+    }
+
+    #[test]
+    fn test_config_scrollback() {
+        use crate::window_types::TabKind;
+
+        let config = Config::default();
+        assert_eq!(config.scrollback.history_limit_for(&TabKind::Shell), 1000);
+        assert_eq!(
+            config.scrollback.memory_limit_bytes(),
+            Some(256 * 1024 * 1024)
+        );
+
+        let raw: RawConfig = toml::from_str(
+            "[scrollback]\nhistory_limit = 5000\nmemory_limit_mb = 0\n\n[scrollback.kinds]\nserver = 100000\nclaude = 20000\n",
+        )
+        .unwrap();
+        let config = Config::from_raw(raw);
+        assert_eq!(config.scrollback.history_limit_for(&TabKind::Shell), 5000);
+        assert_eq!(
+            config.scrollback.history_limit_for(&TabKind::DevServer),
+            100000
+        );
+        assert_eq!(config.scrollback.history_limit_for(&TabKind::Agent), 20000);
+        assert_eq!(config.scrollback.memory_limit_bytes(), None);
+    }
+
+    #[test]
+    fn test_config_server() {
+        let config = Config::default();
+        assert!(config.server.tcp_listen.is_none());
+        assert!(config.server.token.is_none());
+
+        let raw: RawConfig = toml::from_str(
+            "[server]\ntcp_listen = \"127.0.0.1:7474\"\ntoken = \"s3cret\"\ntls_cert = \"/etc/pane/cert.pem\"\ntls_key = \"/etc/pane/key.pem\"\n",
+        )
+        .unwrap();
+        let config = Config::from_raw(raw);
+        assert_eq!(config.server.tcp_listen.as_deref(), Some("127.0.0.1:7474"));
+        assert_eq!(config.server.token.as_deref(), Some("s3cret"));
+        assert_eq!(
+            config.server.tls_cert,
+            Some(std::path::PathBuf::from("/etc/pane/cert.pem"))
+        );
+
+        let raw: RawConfig = toml::from_str("[server]\ntcp_listen = \"\"\ntoken = \"\"\n").unwrap();
+        let config = Config::from_raw(raw);
+        assert!(config.server.tcp_listen.is_none());
+        assert!(config.server.token.is_none());
+    }
+
     // --- LeaderConfig ---
 
     #[test]
@@ -1984,4 +2445,22 @@ accent = "#ff0000"
         let dimmed = Theme::dim_color(Color::Green, 0.65);
         assert!(matches!(dimmed, Color::Rgb(_, _, _)));
     }
+
+    #[test]
+    fn test_xterm_palette() {
+        assert_eq!(Theme::xterm_palette(1), (205, 0, 0));
+        assert_eq!(Theme::xterm_palette(16), (0, 0, 0));
+        assert_eq!(Theme::xterm_palette(196), (255, 0, 0));
+        assert_eq!(Theme::xterm_palette(231), (255, 255, 255));
+        assert_eq!(Theme::xterm_palette(232), (8, 8, 8));
+        assert_eq!(Theme::xterm_palette(255), (238, 238, 238));
+        assert_eq!(Theme::rgb(Color::Indexed(21)), Some((0, 0, 255)));
+        assert_eq!(Theme::rgb(Color::Reset), None);
+    }
+
+    #[test]
+    fn test_terminal_colors_use_theme() {
+        let theme = Theme::preset("dracula").unwrap();
+        assert_eq!(theme.terminal_colors(), ((248, 248, 242), (40, 42, 54)));
+    }
 }
@@ -75,6 +75,7 @@ pub fn normal_defaults() -> Vec<(&'static str, Action)> {
         // ── Tools ───────────────────────────────────────────────────────
         ("c", Action::CopyMode),         // Enter copy mode to select and copy text
         ("p", Action::PasteClipboard),   // Paste from system clipboard
+        ("shift+p", Action::ChooseBuffer), // Pick a paste buffer to paste
         (":", Action::CommandPalette),   // Open the command palette
         // ── Quit ────────────────────────────────────────────────────────
         ("q", Action::Quit),             // Quit pane
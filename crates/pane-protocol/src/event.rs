@@ -1,20 +1,47 @@
 use crate::layout::TabId;
+use crate::protocol::{MouseButton, MouseModifiers, WheelDirection};
 use crate::system_stats::SystemStats;
 use crossterm::event::KeyEvent;
 
 #[derive(Debug)]
 pub enum AppEvent {
     Key(KeyEvent),
-    MouseDown { x: u16, y: u16 },
-    MouseRightDown { x: u16, y: u16 },
-    MouseDrag { x: u16, y: u16 },
-    MouseMove { x: u16, y: u16 },
-    MouseUp { x: u16, y: u16 },
-    MouseScroll { up: bool },
+    MouseDown {
+        x: u16,
+        y: u16,
+        button: MouseButton,
+        modifiers: MouseModifiers,
+    },
+    MouseDrag {
+        x: u16,
+        y: u16,
+        button: MouseButton,
+        modifiers: MouseModifiers,
+    },
+    MouseMove {
+        x: u16,
+        y: u16,
+        modifiers: MouseModifiers,
+    },
+    MouseUp {
+        x: u16,
+        y: u16,
+        button: MouseButton,
+        modifiers: MouseModifiers,
+    },
+    MouseScroll {
+        x: u16,
+        y: u16,
+        direction: WheelDirection,
+        modifiers: MouseModifiers,
+    },
+    FocusChanged { focused: bool },
     Resize(u16, u16),
     Tick,
     PtyOutput { pane_id: TabId, bytes: Vec<u8> },
     PtyExited { pane_id: TabId },
+    /// A tab's synchronized frame may have outlived its timeout.
+    SyncOutputTimeout { pane_id: TabId },
     SystemStats(SystemStats),
     ForegroundPoll,
 }
@@ -44,11 +71,17 @@ mod tests {
 
     #[test]
     fn mouse_down_fields() {
-        let event = AppEvent::MouseDown { x: 42, y: 13 };
+        let event = AppEvent::MouseDown {
+            x: 42,
+            y: 13,
+            button: MouseButton::Left,
+            modifiers: MouseModifiers::default(),
+        };
         match event {
-            AppEvent::MouseDown { x, y } => {
+            AppEvent::MouseDown { x, y, button, .. } => {
                 assert_eq!(x, 42);
                 assert_eq!(y, 13);
+                assert_eq!(button, MouseButton::Left);
             }
             _ => panic!("Expected MouseDown"),
         }
@@ -56,21 +89,41 @@ mod tests {
 
     #[test]
     fn mouse_right_down_fields() {
-        let event = AppEvent::MouseRightDown { x: 100, y: 200 };
+        let event = AppEvent::MouseDown {
+            x: 100,
+            y: 200,
+            button: MouseButton::Right,
+            modifiers: MouseModifiers {
+                shift: true,
+                ..MouseModifiers::default()
+            },
+        };
         match event {
-            AppEvent::MouseRightDown { x, y } => {
+            AppEvent::MouseDown {
+                x,
+                y,
+                button,
+                modifiers,
+            } => {
                 assert_eq!(x, 100);
                 assert_eq!(y, 200);
+                assert_eq!(button, MouseButton::Right);
+                assert!(modifiers.shift);
             }
-            _ => panic!("Expected MouseRightDown"),
+            _ => panic!("Expected MouseDown"),
         }
     }
 
     #[test]
     fn mouse_drag_fields() {
-        let event = AppEvent::MouseDrag { x: 5, y: 10 };
+        let event = AppEvent::MouseDrag {
+            x: 5,
+            y: 10,
+            button: MouseButton::Left,
+            modifiers: MouseModifiers::default(),
+        };
         match event {
-            AppEvent::MouseDrag { x, y } => {
+            AppEvent::MouseDrag { x, y, .. } => {
                 assert_eq!(x, 5);
                 assert_eq!(y, 10);
             }
@@ -80,9 +133,13 @@ mod tests {
 
     #[test]
     fn mouse_move_fields() {
-        let event = AppEvent::MouseMove { x: 0, y: 0 };
+        let event = AppEvent::MouseMove {
+            x: 0,
+            y: 0,
+            modifiers: MouseModifiers::default(),
+        };
         match event {
-            AppEvent::MouseMove { x, y } => {
+            AppEvent::MouseMove { x, y, .. } => {
                 assert_eq!(x, 0);
                 assert_eq!(y, 0);
             }
@@ -92,9 +149,14 @@ mod tests {
 
     #[test]
     fn mouse_up_fields() {
-        let event = AppEvent::MouseUp { x: 99, y: 50 };
+        let event = AppEvent::MouseUp {
+            x: 99,
+            y: 50,
+            button: MouseButton::Left,
+            modifiers: MouseModifiers::default(),
+        };
         match event {
-            AppEvent::MouseUp { x, y } => {
+            AppEvent::MouseUp { x, y, .. } => {
                 assert_eq!(x, 99);
                 assert_eq!(y, 50);
             }
@@ -104,18 +166,33 @@ mod tests {
 
     #[test]
     fn mouse_scroll_up() {
-        let event = AppEvent::MouseScroll { up: true };
+        let event = AppEvent::MouseScroll {
+            x: 1,
+            y: 2,
+            direction: WheelDirection::Up,
+            modifiers: MouseModifiers::default(),
+        };
         match event {
-            AppEvent::MouseScroll { up } => assert!(up),
+            AppEvent::MouseScroll { direction, .. } => assert_eq!(direction, WheelDirection::Up),
             _ => panic!("Expected MouseScroll"),
         }
     }
 
     #[test]
     fn mouse_scroll_down() {
-        let event = AppEvent::MouseScroll { up: false };
+        let event = AppEvent::MouseScroll {
+            x: 1,
+            y: 2,
+            direction: WheelDirection::Down,
+            modifiers: MouseModifiers::default(),
+        };
         match event {
-            AppEvent::MouseScroll { up } => assert!(!up),
+            AppEvent::MouseScroll {
+                x, y, direction, ..
+            } => {
+                assert_eq!((x, y), (1, 2));
+                assert_eq!(direction, WheelDirection::Down);
+            }
             _ => panic!("Expected MouseScroll"),
         }
     }
@@ -189,9 +266,11 @@ mod tests {
         let event = AppEvent::MouseDown {
             x: u16::MAX,
             y: u16::MAX,
+            button: MouseButton::Middle,
+            modifiers: MouseModifiers::default(),
         };
         match event {
-            AppEvent::MouseDown { x, y } => {
+            AppEvent::MouseDown { x, y, .. } => {
                 assert_eq!(x, u16::MAX);
                 assert_eq!(y, u16::MAX);
             }
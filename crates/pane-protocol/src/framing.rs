@@ -1,14 +1,108 @@
 use anyhow::{bail, Result};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
-use tokio::net::UnixStream;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::layout::TabId;
+use crate::protocol::ServerResponse;
 
 /// Maximum frame size: 16 MiB. Prevents memory exhaustion from bad data.
 pub const MAX_FRAME_SIZE: u32 = 16 * 1024 * 1024;
 
+/// How a connection encodes frame bodies.
+///
+/// JSON is the default and what the tmux shim and debugging tools speak.
+/// Binary is negotiated at attach with `Capability::BinaryFrames`. Decoding
+/// doesn't depend on it: a JSON body always starts with `{` or `"`, and a
+/// binary body with one of the tag bytes below.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Encoding {
+    #[default]
+    Json,
+    Binary,
+}
+
+/// Binary body: a MessagePack-encoded message.
+const TAG_MSGPACK: u8 = 0x01;
+/// Binary body: `PaneOutput`, as the 16-byte pane id followed by the raw
+/// output bytes.
+const TAG_PANE_OUTPUT: u8 = 0x02;
+/// Binary body: `FullScreenDump`, laid out like `PaneOutput`.
+const TAG_SCREEN_DUMP: u8 = 0x03;
+/// Binary body: `Passthrough`, laid out like `PaneOutput`.
+const TAG_PASSTHROUGH: u8 = 0x04;
+
+/// Encode a message body.
+pub fn encode<T: Serialize>(msg: &T, encoding: Encoding) -> Result<Vec<u8>> {
+    match encoding {
+        Encoding::Json => Ok(serde_json::to_vec(msg)?),
+        Encoding::Binary => {
+            let mut body = vec![TAG_MSGPACK];
+            rmp_serde::encode::write_named(&mut body, msg)?;
+            Ok(body)
+        }
+    }
+}
+
+/// Decode a message body in either encoding.
+pub fn decode<T: DeserializeOwned>(body: &[u8]) -> Result<T> {
+    match body.first() {
+        Some(&TAG_MSGPACK) => Ok(rmp_serde::from_slice(&body[1..])?),
+        _ => Ok(serde_json::from_slice(body)?),
+    }
+}
+
+/// Encode a server response. In binary mode the responses carrying terminal
+/// bytes skip serde and go out raw: pane output is most of the traffic, and
+/// screen dumps and passthrough are the biggest frames, which MessagePack
+/// would otherwise write as an array of integers.
+pub fn encode_response(response: &ServerResponse, encoding: Encoding) -> Result<Vec<u8>> {
+    let raw = match (encoding, response) {
+        (Encoding::Binary, ServerResponse::PaneOutput { pane_id, data }) => {
+            Some((TAG_PANE_OUTPUT, pane_id, data))
+        }
+        (Encoding::Binary, ServerResponse::FullScreenDump { pane_id, data }) => {
+            Some((TAG_SCREEN_DUMP, pane_id, data))
+        }
+        (Encoding::Binary, ServerResponse::Passthrough { pane_id, data }) => {
+            Some((TAG_PASSTHROUGH, pane_id, data))
+        }
+        _ => None,
+    };
+    match raw {
+        Some((tag, pane_id, data)) => {
+            let mut body = Vec::with_capacity(1 + 16 + data.len());
+            body.push(tag);
+            body.extend_from_slice(pane_id.as_bytes());
+            body.extend_from_slice(data);
+            Ok(body)
+        }
+        None => encode(response, encoding),
+    }
+}
+
+/// Decode a server response in either encoding.
+pub fn decode_response(body: &[u8]) -> Result<ServerResponse> {
+    let Some(&tag @ (TAG_PANE_OUTPUT | TAG_SCREEN_DUMP | TAG_PASSTHROUGH)) = body.first() else {
+        return decode(body);
+    };
+    let Some(id) = body.get(1..17) else {
+        bail!("truncated pane output frame");
+    };
+    let pane_id = TabId::from_slice(id)?;
+    let data = body[17..].to_vec();
+    Ok(match tag {
+        TAG_PANE_OUTPUT => ServerResponse::PaneOutput { pane_id, data },
+        TAG_SCREEN_DUMP => ServerResponse::FullScreenDump { pane_id, data },
+        _ => ServerResponse::Passthrough { pane_id, data },
+    })
+}
+
 /// Write a length-prefixed frame to the stream.
-pub async fn write_frame(stream: &mut UnixStream, data: &[u8]) -> std::io::Result<()> {
+pub async fn write_frame<S: AsyncWrite + Unpin>(
+    stream: &mut S,
+    data: &[u8],
+) -> std::io::Result<()> {
     let len: u32 = data.len().try_into().map_err(|_| {
         std::io::Error::new(
             std::io::ErrorKind::InvalidInput,
@@ -29,7 +123,7 @@ pub async fn write_frame(stream: &mut UnixStream, data: &[u8]) -> std::io::Resul
 
 /// Read a length-prefixed frame from the stream.
 /// Returns `Ok(None)` on clean EOF.
-pub async fn read_frame(stream: &mut UnixStream) -> std::io::Result<Option<Vec<u8>>> {
+pub async fn read_frame<S: AsyncRead + Unpin>(stream: &mut S) -> std::io::Result<Option<Vec<u8>>> {
     let mut len_buf = [0u8; 4];
     match stream.read_exact(&mut len_buf).await {
         Ok(_) => {}
@@ -49,18 +143,18 @@ pub async fn read_frame(stream: &mut UnixStream) -> std::io::Result<Option<Vec<u
 }
 
 /// Serialize a message as JSON and write it as a length-prefixed frame.
-pub async fn send<T: Serialize>(stream: &mut UnixStream, msg: &T) -> Result<()> {
+pub async fn send<S: AsyncWrite + Unpin, T: Serialize>(stream: &mut S, msg: &T) -> Result<()> {
     let json = serde_json::to_vec(msg)?;
     write_frame(stream, &json).await?;
     Ok(())
 }
 
-/// Read a length-prefixed frame and deserialize it from JSON.
+/// Read a length-prefixed frame and deserialize it from either encoding.
 /// Returns `Ok(None)` on clean EOF.
-pub async fn recv<T: DeserializeOwned>(stream: &mut UnixStream) -> Result<Option<T>> {
+pub async fn recv<S: AsyncRead + Unpin, T: DeserializeOwned>(stream: &mut S) -> Result<Option<T>> {
     match read_frame(stream).await? {
         Some(data) => {
-            let msg = serde_json::from_slice(&data)?;
+            let msg = decode(&data)?;
             Ok(Some(msg))
         }
         None => Ok(None),
@@ -68,7 +162,7 @@ pub async fn recv<T: DeserializeOwned>(stream: &mut UnixStream) -> Result<Option
 }
 
 /// Read a length-prefixed frame and deserialize, returning an error on EOF.
-pub async fn recv_required<T: DeserializeOwned>(stream: &mut UnixStream) -> Result<T> {
+pub async fn recv_required<S: AsyncRead + Unpin, T: DeserializeOwned>(stream: &mut S) -> Result<T> {
     match recv(stream).await? {
         Some(msg) => Ok(msg),
         None => bail!("connection closed unexpectedly"),
@@ -78,6 +172,7 @@ pub async fn recv_required<T: DeserializeOwned>(stream: &mut UnixStream) -> Resu
 #[cfg(test)]
 mod tests {
     use super::*;
+    use tokio::net::UnixStream;
 
     #[tokio::test]
     async fn test_frame_roundtrip() {
@@ -194,6 +289,75 @@ mod tests {
         assert_eq!(json1, json2);
     }
 
+    #[test]
+    fn test_binary_pane_output_is_raw_bytes() {
+        let pane_id = TabId::new_v4();
+        let data = b"\x1b[31mcompiling\x1b[0m\r\n".to_vec();
+        let response = ServerResponse::PaneOutput {
+            pane_id,
+            data: data.clone(),
+        };
+
+        let body = encode_response(&response, Encoding::Binary).unwrap();
+        assert_eq!(body.len(), 1 + 16 + data.len());
+        assert!(body.ends_with(&data));
+        let json = encode_response(&response, Encoding::Json).unwrap();
+        assert!(json.len() > 2 * body.len());
+
+        match decode_response(&body).unwrap() {
+            ServerResponse::PaneOutput {
+                pane_id: id,
+                data: restored,
+            } => {
+                assert_eq!(id, pane_id);
+                assert_eq!(restored, data);
+            }
+            other => panic!("expected PaneOutput, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_binary_dump_and_passthrough_are_raw_bytes() {
+        let pane_id = TabId::new_v4();
+        let dump = b"\x1b[H\x1b[2Jprompt$ ".to_vec();
+        let passthrough = b"\x1b_Gf=100;AAAA\x1b\\".to_vec();
+        let responses = [
+            ServerResponse::FullScreenDump {
+                pane_id,
+                data: dump.clone(),
+            },
+            ServerResponse::Passthrough {
+                pane_id,
+                data: passthrough.clone(),
+            },
+        ];
+
+        for (response, data) in responses.iter().zip([&dump, &passthrough]) {
+            let body = encode_response(response, Encoding::Binary).unwrap();
+            assert_eq!(body.len(), 1 + 16 + data.len());
+            assert!(body.ends_with(data));
+            let restored = decode_response(&body).unwrap();
+            assert_eq!(
+                serde_json::to_string(&restored).unwrap(),
+                serde_json::to_string(response).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_decode_accepts_either_encoding() {
+        use crate::protocol::ClientRequest;
+
+        let req = ClientRequest::Paste("hello".to_string());
+        for encoding in [Encoding::Json, Encoding::Binary] {
+            let body = encode(&req, encoding).unwrap();
+            let restored: ClientRequest = decode(&body).unwrap();
+            assert!(matches!(restored, ClientRequest::Paste(ref s) if s == "hello"));
+        }
+        assert!(decode_response(&[TAG_PANE_OUTPUT, 1, 2]).is_err());
+        assert!(decode_response(&[TAG_SCREEN_DUMP]).is_err());
+    }
+
     #[tokio::test]
     async fn test_write_frame_rejects_oversized() {
         let (mut a, mut _b) = UnixStream::pair().unwrap();
@@ -1,10 +1,32 @@
-use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+
+/// Kitty keyboard protocol flags, as pushed by `CSI > flags u`.
+pub const KITTY_DISAMBIGUATE: u8 = 0b1;
+pub const KITTY_REPORT_EVENT_TYPES: u8 = 0b10;
+pub const KITTY_REPORT_ALTERNATE_KEYS: u8 = 0b100;
+pub const KITTY_REPORT_ALL_KEYS: u8 = 0b1000;
+pub const KITTY_REPORT_TEXT: u8 = 0b1_0000;
+
+/// Convert a key event to bytes for a PTY whose program has enabled the
+/// given kitty keyboard protocol flags. With no flags enabled this is
+/// [`key_to_bytes`].
+pub fn encode_key(key: KeyEvent, application_cursor: bool, keyboard_flags: u8) -> Vec<u8> {
+    if keyboard_flags == 0 {
+        key_to_bytes(key, application_cursor)
+    } else {
+        kitty_key(key, application_cursor, keyboard_flags)
+    }
+}
 
 /// Convert a crossterm KeyEvent to bytes suitable for writing to a PTY.
 ///
 /// When `application_cursor` is true, unmodified arrow keys use SS3 (`\x1bO`)
 /// sequences instead of CSI (`\x1b[`), matching DEC application cursor mode.
+/// Key releases have no legacy encoding and produce no bytes.
 pub fn key_to_bytes(key: KeyEvent, application_cursor: bool) -> Vec<u8> {
+    if key.kind == KeyEventKind::Release {
+        return vec![];
+    }
     let mods = key.modifiers;
 
     match key.code {
@@ -137,6 +159,126 @@ fn f_key(n: u8, mods: KeyModifiers) -> Vec<u8> {
     }
 }
 
+/// Encode a key with the kitty keyboard protocol.
+///
+/// Keys that still have an unambiguous legacy encoding under `flags` are sent
+/// the legacy way, so shells keep working after a program that enabled the
+/// protocol exits without disabling it.
+fn kitty_key(key: KeyEvent, application_cursor: bool, flags: u8) -> Vec<u8> {
+    let all_keys = flags & KITTY_REPORT_ALL_KEYS != 0;
+    let event = match key.kind {
+        KeyEventKind::Press => 1,
+        KeyEventKind::Repeat if flags & KITTY_REPORT_EVENT_TYPES != 0 => 2,
+        KeyEventKind::Repeat => 1,
+        KeyEventKind::Release if flags & KITTY_REPORT_EVENT_TYPES != 0 => 3,
+        KeyEventKind::Release => return vec![],
+    };
+    let mut mods = kitty_modifiers(key.modifiers);
+    let legacy = mods == 0 && event == 1 && !all_keys;
+
+    let (number, terminator) = match key.code {
+        KeyCode::Char(c) => {
+            let text = mods & !KITTY_SHIFT == 0;
+            if text && event != 3 && !all_keys {
+                return key_to_bytes(key, application_cursor);
+            }
+            let base = unshifted(c);
+            let mut seq = format!("\x1b[{}", base as u32);
+            if flags & KITTY_REPORT_ALTERNATE_KEYS != 0 && base != c {
+                seq.push_str(&format!(":{}", c as u32));
+            }
+            let text = (text && event != 3 && flags & KITTY_REPORT_TEXT != 0).then_some(c);
+            push_kitty_params(&mut seq, mods, event, text);
+            seq.push('u');
+            return seq.into_bytes();
+        }
+        // Without modifiers these stay legacy (and never report releases)
+        // unless every key is reported as an escape code.
+        KeyCode::Enter | KeyCode::Tab | KeyCode::Backspace if mods == 0 && !all_keys => {
+            return if event == 3 {
+                vec![]
+            } else {
+                key_to_bytes(key, application_cursor)
+            };
+        }
+        KeyCode::Enter => (13, 'u'),
+        KeyCode::Tab => (9, 'u'),
+        KeyCode::BackTab => {
+            mods |= KITTY_SHIFT;
+            (9, 'u')
+        }
+        KeyCode::Backspace => (127, 'u'),
+        KeyCode::Esc => (27, 'u'),
+        _ if legacy => return key_to_bytes(key, application_cursor),
+        KeyCode::Up => (1, 'A'),
+        KeyCode::Down => (1, 'B'),
+        KeyCode::Right => (1, 'C'),
+        KeyCode::Left => (1, 'D'),
+        KeyCode::Home => (1, 'H'),
+        KeyCode::End => (1, 'F'),
+        KeyCode::Insert => (2, '~'),
+        KeyCode::Delete => (3, '~'),
+        KeyCode::PageUp => (5, '~'),
+        KeyCode::PageDown => (6, '~'),
+        KeyCode::F(1) => (1, 'P'),
+        KeyCode::F(2) => (1, 'Q'),
+        // F3 avoids `CSI 1;mods R`, which reads as a cursor position report
+        KeyCode::F(3) => (13, '~'),
+        KeyCode::F(4) => (1, 'S'),
+        KeyCode::F(n @ 5..=12) => ([15, 17, 18, 19, 20, 21, 23, 24][usize::from(n - 5)], '~'),
+        _ => return vec![],
+    };
+
+    let mut seq = String::from("\x1b[");
+    let has_params = mods != 0 || event != 1;
+    if number != 1 || terminator == 'u' || terminator == '~' || has_params {
+        seq.push_str(&number.to_string());
+    }
+    push_kitty_params(&mut seq, mods, event, None);
+    seq.push(terminator);
+    seq.into_bytes()
+}
+
+/// Append `;modifiers[:event][;text]`, leaving out trailing defaults.
+fn push_kitty_params(seq: &mut String, mods: u8, event: u8, text: Option<char>) {
+    if mods != 0 || event != 1 || text.is_some() {
+        seq.push_str(&format!(";{}", mods + 1));
+    }
+    if event != 1 {
+        seq.push_str(&format!(":{event}"));
+    }
+    if let Some(c) = text {
+        seq.push_str(&format!(";{}", c as u32));
+    }
+}
+
+const KITTY_SHIFT: u8 = 0b1;
+
+/// Kitty modifier bits: Shift=1, Alt=2, Ctrl=4, Super=8, Hyper=16, Meta=32.
+fn kitty_modifiers(mods: KeyModifiers) -> u8 {
+    [
+        (KeyModifiers::SHIFT, KITTY_SHIFT),
+        (KeyModifiers::ALT, 0b10),
+        (KeyModifiers::CONTROL, 0b100),
+        (KeyModifiers::SUPER, 0b1000),
+        (KeyModifiers::HYPER, 0b1_0000),
+        (KeyModifiers::META, 0b10_0000),
+    ]
+    .into_iter()
+    .filter(|(m, _)| mods.contains(*m))
+    .fold(0, |bits, (_, bit)| bits | bit)
+}
+
+/// The key a character is typed with when shift isn't held, as reported in
+/// kitty key codes.
+fn unshifted(c: char) -> char {
+    let mut lower = c.to_lowercase();
+    match (lower.next(), lower.next()) {
+        (Some(l), None) => l,
+        _ => c,
+    }
+}
+
 /// Compute the xterm-style modifier parameter (0 = none).
 ///
 /// The parameter value is `1 + bitmask` where Shift=1, Alt=2, Ctrl=4.
@@ -157,7 +299,7 @@ fn modifier_param(mods: KeyModifiers) -> u8 {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
 
     fn key(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
         KeyEvent::new(code, modifiers)
@@ -499,6 +641,141 @@ mod tests {
         );
     }
 
+    // --- Kitty keyboard protocol ---
+
+    fn kitty(ev: KeyEvent, flags: u8) -> String {
+        String::from_utf8(encode_key(ev, false, flags)).unwrap()
+    }
+
+    fn with_kind(mut ev: KeyEvent, kind: KeyEventKind) -> KeyEvent {
+        ev.kind = kind;
+        ev
+    }
+
+    #[test]
+    fn kitty_disabled_is_legacy() {
+        let ev = key(KeyCode::Char('i'), KeyModifiers::CONTROL);
+        assert_eq!(encode_key(ev, false, 0), vec![b'\t']);
+    }
+
+    #[test]
+    fn kitty_disambiguates_ctrl_keys() {
+        let d = KITTY_DISAMBIGUATE;
+        assert_eq!(
+            kitty(key(KeyCode::Char('i'), KeyModifiers::CONTROL), d),
+            "\x1b[105;5u"
+        );
+        assert_eq!(kitty(plain(KeyCode::Tab), d), "\t");
+        assert_eq!(
+            kitty(key(KeyCode::Enter, KeyModifiers::SHIFT), d),
+            "\x1b[13;2u"
+        );
+        assert_eq!(kitty(plain(KeyCode::Enter), d), "\r");
+        assert_eq!(kitty(plain(KeyCode::Esc), d), "\x1b[27u");
+        assert_eq!(
+            kitty(key(KeyCode::Char('x'), KeyModifiers::ALT), d),
+            "\x1b[120;3u"
+        );
+        assert_eq!(
+            kitty(key(KeyCode::BackTab, KeyModifiers::SHIFT), d),
+            "\x1b[9;2u"
+        );
+    }
+
+    #[test]
+    fn kitty_text_keys_stay_text() {
+        let d = KITTY_DISAMBIGUATE;
+        assert_eq!(kitty(plain(KeyCode::Char('a')), d), "a");
+        assert_eq!(kitty(key(KeyCode::Char('A'), KeyModifiers::SHIFT), d), "A");
+    }
+
+    #[test]
+    fn kitty_functional_keys() {
+        let d = KITTY_DISAMBIGUATE;
+        assert_eq!(kitty(plain(KeyCode::Up), d), "\x1b[A");
+        assert_eq!(encode_key(plain(KeyCode::Up), true, d), b"\x1bOA".to_vec());
+        assert_eq!(
+            kitty(key(KeyCode::Up, KeyModifiers::CONTROL), d),
+            "\x1b[1;5A"
+        );
+        assert_eq!(
+            kitty(key(KeyCode::Delete, KeyModifiers::SHIFT), d),
+            "\x1b[3;2~"
+        );
+        assert_eq!(
+            kitty(key(KeyCode::F(3), KeyModifiers::SHIFT), d),
+            "\x1b[13;2~"
+        );
+        assert_eq!(
+            kitty(key(KeyCode::F(12), KeyModifiers::ALT), d),
+            "\x1b[24;3~"
+        );
+    }
+
+    #[test]
+    fn kitty_event_types() {
+        let flags = KITTY_DISAMBIGUATE | KITTY_REPORT_EVENT_TYPES;
+        let a = plain(KeyCode::Char('a'));
+        assert_eq!(kitty(a, flags), "a");
+        assert_eq!(kitty(with_kind(a, KeyEventKind::Repeat), flags), "a");
+        assert_eq!(
+            kitty(with_kind(a, KeyEventKind::Release), flags),
+            "\x1b[97;1:3u"
+        );
+        let up = key(KeyCode::Up, KeyModifiers::NONE);
+        assert_eq!(
+            kitty(with_kind(up, KeyEventKind::Release), flags),
+            "\x1b[1;1:3A"
+        );
+        // Plain Enter never reports a release unless all keys are escape codes
+        let enter = with_kind(plain(KeyCode::Enter), KeyEventKind::Release);
+        assert_eq!(kitty(enter, flags), "");
+        // Without event types, releases are dropped and repeats are presses
+        let d = KITTY_DISAMBIGUATE;
+        assert_eq!(kitty(with_kind(a, KeyEventKind::Release), d), "");
+        let ctrl_a = key(KeyCode::Char('a'), KeyModifiers::CONTROL);
+        assert_eq!(
+            kitty(with_kind(ctrl_a, KeyEventKind::Repeat), d),
+            "\x1b[97;5u"
+        );
+    }
+
+    #[test]
+    fn kitty_release_without_kitty_is_dropped() {
+        let ev = with_kind(plain(KeyCode::Char('a')), KeyEventKind::Release);
+        assert!(encode_key(ev, false, 0).is_empty());
+    }
+
+    #[test]
+    fn kitty_all_keys_as_escape_codes() {
+        let flags = KITTY_DISAMBIGUATE | KITTY_REPORT_ALL_KEYS;
+        assert_eq!(kitty(plain(KeyCode::Char('a')), flags), "\x1b[97u");
+        assert_eq!(kitty(plain(KeyCode::Enter), flags), "\x1b[13u");
+        assert_eq!(kitty(plain(KeyCode::Up), flags), "\x1b[A");
+        assert_eq!(
+            kitty(key(KeyCode::Char('A'), KeyModifiers::SHIFT), flags),
+            "\x1b[97;2u"
+        );
+    }
+
+    #[test]
+    fn kitty_alternate_keys_and_text() {
+        let flags = KITTY_DISAMBIGUATE
+            | KITTY_REPORT_ALTERNATE_KEYS
+            | KITTY_REPORT_ALL_KEYS
+            | KITTY_REPORT_TEXT;
+        assert_eq!(
+            kitty(key(KeyCode::Char('A'), KeyModifiers::SHIFT), flags),
+            "\x1b[97:65;2;65u"
+        );
+        assert_eq!(kitty(plain(KeyCode::Char('a')), flags), "\x1b[97;1;97u");
+        // Keys with ctrl or alt produce no text
+        assert_eq!(
+            kitty(key(KeyCode::Char('a'), KeyModifiers::CONTROL), flags),
+            "\x1b[97;5u"
+        );
+    }
+
     // --- Unmapped keycodes ---
 
     #[test]
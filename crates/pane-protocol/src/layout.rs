@@ -913,6 +913,24 @@ enum NeighborResult {
     NeedFromParent,
 }
 
+/// The cell of a screen shown at the top left of a smaller view, as a row
+/// and column. Sizes and positions are (rows, cols). A screen bigger than the
+/// view, as when the session is sized to a bigger client, is shown from
+/// wherever keeps `cursor` in view.
+pub fn viewport_origin(size: (u16, u16), cursor: (u16, u16), view: (u16, u16)) -> (u16, u16) {
+    let offset = |cursor: u16, size: u16, view: u16| {
+        if view == 0 || size <= view {
+            0
+        } else {
+            cursor.saturating_sub(view - 1).min(size - view)
+        }
+    };
+    (
+        offset(cursor.0, size.0, view.0),
+        offset(cursor.1, size.1, view.1),
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1,4 +1,5 @@
 pub mod app;
+pub mod base64;
 pub mod config;
 pub mod default_keys;
 pub mod event;
@@ -15,6 +15,10 @@ use crate::window_types::{TabKind, WindowId};
 pub struct SerializableKeyEvent {
     pub code: SerializableKeyCode,
     pub modifiers: u8,
+    /// Repeat and release events only arrive when the client's terminal
+    /// supports the kitty keyboard protocol.
+    #[serde(default)]
+    pub kind: SerializableKeyEventKind,
 }
 
 impl From<KeyEvent> for SerializableKeyEvent {
@@ -22,6 +26,7 @@ impl From<KeyEvent> for SerializableKeyEvent {
         Self {
             code: SerializableKeyCode::from(key.code),
             modifiers: key.modifiers.bits(),
+            kind: SerializableKeyEventKind::from(key.kind),
         }
     }
 }
@@ -31,12 +36,40 @@ impl From<SerializableKeyEvent> for KeyEvent {
         KeyEvent {
             code: sk.code.into(),
             modifiers: KeyModifiers::from_bits_truncate(sk.modifiers),
-            kind: KeyEventKind::Press,
+            kind: sk.kind.into(),
             state: KeyEventState::NONE,
         }
     }
 }
 
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SerializableKeyEventKind {
+    #[default]
+    Press,
+    Repeat,
+    Release,
+}
+
+impl From<KeyEventKind> for SerializableKeyEventKind {
+    fn from(kind: KeyEventKind) -> Self {
+        match kind {
+            KeyEventKind::Press => SerializableKeyEventKind::Press,
+            KeyEventKind::Repeat => SerializableKeyEventKind::Repeat,
+            KeyEventKind::Release => SerializableKeyEventKind::Release,
+        }
+    }
+}
+
+impl From<SerializableKeyEventKind> for KeyEventKind {
+    fn from(kind: SerializableKeyEventKind) -> Self {
+        match kind {
+            SerializableKeyEventKind::Press => KeyEventKind::Press,
+            SerializableKeyEventKind::Repeat => KeyEventKind::Repeat,
+            SerializableKeyEventKind::Release => KeyEventKind::Release,
+        }
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum SerializableKeyCode {
     Char(char),
@@ -109,10 +142,125 @@ impl From<SerializableKeyCode> for KeyCode {
     }
 }
 
+// ---------------------------------------------------------------------------
+// Mouse
+// ---------------------------------------------------------------------------
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MouseButton {
+    #[default]
+    Left,
+    Middle,
+    Right,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WheelDirection {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+/// Modifier keys held during a mouse event.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MouseModifiers {
+    pub shift: bool,
+    pub alt: bool,
+    pub ctrl: bool,
+}
+
+impl From<KeyModifiers> for MouseModifiers {
+    fn from(modifiers: KeyModifiers) -> Self {
+        Self {
+            shift: modifiers.contains(KeyModifiers::SHIFT),
+            alt: modifiers.contains(KeyModifiers::ALT),
+            ctrl: modifiers.contains(KeyModifiers::CONTROL),
+        }
+    }
+}
+
 // ---------------------------------------------------------------------------
 // Client type — declared at attach time
 // ---------------------------------------------------------------------------
 
+/// Version of the client/daemon protocol. Bump it when a change would make
+/// an older peer misread messages; additive features go in [`Capability`].
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Oldest protocol version the daemon still serves and the client accepts.
+pub const MIN_PROTOCOL_VERSION: u32 = 1;
+
+/// An optional feature a peer understands. Client and daemon each send the
+/// set they support when attaching, and only what both support gets used.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Capability {
+    /// Frame bodies use the compact binary encoding, with pane output as raw
+    /// bytes. See [`crate::framing::Encoding`].
+    BinaryFrames,
+    /// Each client keeps its own active window and tab, and can be moved by
+    /// `switch-client`.
+    ClientFocus,
+    /// Image and DCS passthrough sequences forwarded to the outer terminal.
+    ImagePassthrough,
+    /// OSC 52 clipboard requests routed to clients.
+    Clipboard,
+    /// Output is sent only for the tabs the client shows, with a screen dump
+    /// when a hidden tab comes into view. The client reports the overview
+    /// with [`ClientRequest::OverviewChanged`].
+    VisibleOutput,
+    /// A capability from a newer peer that this build doesn't know.
+    #[serde(other)]
+    Unknown,
+}
+
+impl Capability {
+    /// Everything this build supports.
+    pub const ALL: &'static [Capability] = &[
+        Capability::BinaryFrames,
+        Capability::ClientFocus,
+        Capability::ImagePassthrough,
+        Capability::Clipboard,
+        Capability::VisibleOutput,
+    ];
+
+    /// What clients from before negotiation handle.
+    pub const LEGACY: &'static [Capability] = &[
+        Capability::ClientFocus,
+        Capability::ImagePassthrough,
+        Capability::Clipboard,
+    ];
+}
+
+/// The outcome of attach negotiation: the protocol version both sides speak
+/// and the capabilities both support.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Negotiated {
+    pub protocol_version: u32,
+    pub capabilities: Vec<Capability>,
+}
+
+/// Negotiate with a peer that announced `protocol_version` and
+/// `capabilities`. Returns a message for the user when the peer is too old
+/// to talk to.
+pub fn negotiate(protocol_version: u32, capabilities: &[Capability]) -> Result<Negotiated, String> {
+    if protocol_version < MIN_PROTOCOL_VERSION {
+        return Err(format!(
+            "peer speaks pane protocol v{}, but v{} or newer is needed",
+            protocol_version, MIN_PROTOCOL_VERSION
+        ));
+    }
+    Ok(Negotiated {
+        protocol_version: protocol_version.min(PROTOCOL_VERSION),
+        capabilities: Capability::ALL
+            .iter()
+            .copied()
+            .filter(|c| capabilities.contains(c))
+            .collect(),
+    })
+}
+
 /// Identifies the type of client connecting to the daemon.
 /// Controls how the daemon handles sizing and input for this connection.
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
@@ -132,6 +280,10 @@ pub enum ClientType {
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum ClientRequest {
+    /// Shared-secret handshake, sent before `Attach` on TCP connections.
+    Auth {
+        token: String,
+    },
     Attach,
     Detach,
     Resize {
@@ -142,22 +294,46 @@ pub enum ClientRequest {
     MouseDown {
         x: u16,
         y: u16,
+        #[serde(default)]
+        button: MouseButton,
+        #[serde(default)]
+        modifiers: MouseModifiers,
     },
     MouseDrag {
         x: u16,
         y: u16,
+        #[serde(default)]
+        button: MouseButton,
+        #[serde(default)]
+        modifiers: MouseModifiers,
     },
     MouseMove {
         x: u16,
         y: u16,
+        #[serde(default)]
+        modifiers: MouseModifiers,
     },
     MouseUp {
         x: u16,
         y: u16,
+        #[serde(default)]
+        button: MouseButton,
+        #[serde(default)]
+        modifiers: MouseModifiers,
     },
+    /// Scroll the active tab, as if the wheel turned over it.
     MouseScroll {
         up: bool,
     },
+    /// A wheel event at a position. Goes to the tab under the pointer when
+    /// it reports mouse events, and scrolls it otherwise.
+    MouseWheel {
+        x: u16,
+        y: u16,
+        direction: WheelDirection,
+        #[serde(default)]
+        modifiers: MouseModifiers,
+    },
     Command(String),
     /// Paste text directly to the active PTY, bypassing command parsing.
     Paste(String),
@@ -168,12 +344,45 @@ pub enum ClientRequest {
     FocusWindow { id: WindowId },
     /// Focus a window and switch to a specific tab by index.
     SelectTab { window_id: WindowId, tab_index: usize },
+    /// The client's own terminal gained or lost focus.
+    FocusChanged { focused: bool },
+    /// Clipboard contents for a tab's OSC 52 read, base64 encoded; empty if
+    /// the read was denied. The server answers for the selection the tab
+    /// asked about, whatever `selection` says.
+    ClipboardReply {
+        pane_id: TabId,
+        selection: String,
+        data: String,
+    },
+    /// Text copied in the client, kept as the newest paste buffer.
+    SetBuffer(String),
+    /// Ask for the paste buffers; answered with [`ServerResponse::BufferList`]
+    /// to this client only.
+    ListBuffers,
+    /// The client opened or closed the workspace overview, which shows the
+    /// active tab of every workspace.
+    OverviewChanged {
+        open: bool,
+    },
 
     // -- V2 variants for native app clients --
 
     /// Attach with client metadata. The daemon accepts both `Attach` (defaults
     /// to Tui) and `AttachV2` as the first message on a new connection.
-    AttachV2 { client_type: ClientType },
+    /// Read-only clients can watch but their input is dropped.
+    ///
+    /// Clients that send a `protocol_version` get [`ServerResponse::AttachedV2`]
+    /// back with the negotiated version and capabilities. Without one (0),
+    /// the daemon answers `Attached` and assumes [`Capability::LEGACY`].
+    AttachV2 {
+        client_type: ClientType,
+        #[serde(default)]
+        read_only: bool,
+        #[serde(default)]
+        protocol_version: u32,
+        #[serde(default)]
+        capabilities: Vec<Capability>,
+    },
 
     /// Raw PTY input bytes, already encoded by the client's terminal emulator
     /// (e.g. GhosttyKit). Written directly to the active PTY without UTF-8
@@ -197,9 +406,19 @@ pub enum ClientRequest {
 // Server → Client messages
 // ---------------------------------------------------------------------------
 
+/// How long a synchronized frame (DECSET 2026) may stay unfinished before the
+/// daemon sends its output and clients redraw anyway.
+pub const SYNC_OUTPUT_TIMEOUT: std::time::Duration = std::time::Duration::from_millis(200);
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum ServerResponse {
     Attached,
+    /// Answer to an `AttachV2` that carried a protocol version: the version
+    /// and capabilities both sides share.
+    AttachedV2 {
+        protocol_version: u32,
+        capabilities: Vec<Capability>,
+    },
     PaneOutput {
         pane_id: TabId,
         data: Vec<u8>,
@@ -213,13 +432,39 @@ pub enum ServerResponse {
     StatsUpdate(SerializableSystemStats),
     PluginSegments(Vec<Vec<crate::plugin::PluginSegment>>),
     SessionEnded,
-    /// Full screen dump for a pane, sent when a client attaches mid-session.
+    /// Full screen dump for a pane, sent when a client attaches mid-session
+    /// or resyncs. With [`Capability::VisibleOutput`] it's also sent when a
+    /// tab comes into view or its thumbnail is refreshed, and the client
+    /// replaces its copy of the tab with it. Those dumps carry the tab's
+    /// scrollback too, except for thumbnails.
     FullScreenDump {
         pane_id: TabId,
         data: Vec<u8>,
     },
+    /// A sequence a tab wrapped in `DCS tmux;` for the outer terminal,
+    /// already unwrapped.
+    Passthrough {
+        pane_id: TabId,
+        data: Vec<u8>,
+    },
+    /// A tab set the clipboard with OSC 52; `data` is base64 encoded.
+    ClipboardWrite {
+        pane_id: TabId,
+        data: String,
+    },
+    /// A tab asked for the clipboard with OSC 52. The first client to send
+    /// a [`ClientRequest::ClipboardReply`] answers it.
+    ClipboardRead {
+        pane_id: TabId,
+        selection: String,
+    },
+    /// The paste buffers, newest first, in answer to
+    /// [`ClientRequest::ListBuffers`].
+    BufferList(Vec<BufferSummary>),
     /// Notify clients when the number of connected clients changes.
     ClientCountChanged(u32),
+    /// `switch-client` moved this client to another workspace.
+    SwitchWorkspace(usize),
     Error(String),
     /// Synchronous command result: output text, optional pane/window IDs, and success flag.
     CommandOutput {
@@ -230,6 +475,16 @@ pub enum ServerResponse {
     },
 }
 
+/// A paste buffer as listed for the buffer picker.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BufferSummary {
+    pub name: String,
+    /// Size of the whole buffer in bytes.
+    pub size: usize,
+    /// The start of the buffer, for previews.
+    pub preview: String,
+}
+
 // ---------------------------------------------------------------------------
 // RenderState: serializable snapshot for client rendering
 // ---------------------------------------------------------------------------
@@ -289,6 +544,9 @@ pub struct TabSnapshot {
     pub cols: u16,
     #[serde(default = "default_pty_rows")]
     pub rows: u16,
+    /// Scrollback lines kept for this tab, so client mirrors match the daemon.
+    #[serde(default = "default_history_limit")]
+    pub history_limit: usize,
 }
 
 fn default_pty_cols() -> u16 {
@@ -299,6 +557,10 @@ fn default_pty_rows() -> u16 {
     24
 }
 
+fn default_history_limit() -> usize {
+    1000
+}
+
 // ---------------------------------------------------------------------------
 // SystemStats serde wrapper
 // ---------------------------------------------------------------------------
@@ -390,11 +652,40 @@ mod tests {
                 height: 40,
             },
             ClientRequest::Key(make_key(KeyCode::Char('a'), KeyModifiers::NONE).into()),
-            ClientRequest::MouseDown { x: 10, y: 5 },
-            ClientRequest::MouseDrag { x: 15, y: 8 },
-            ClientRequest::MouseMove { x: 20, y: 3 },
-            ClientRequest::MouseUp { x: 10, y: 5 },
+            ClientRequest::MouseDown {
+                x: 10,
+                y: 5,
+                button: MouseButton::Left,
+                modifiers: MouseModifiers::default(),
+            },
+            ClientRequest::MouseDrag {
+                x: 15,
+                y: 8,
+                button: MouseButton::Middle,
+                modifiers: MouseModifiers::default(),
+            },
+            ClientRequest::MouseMove {
+                x: 20,
+                y: 3,
+                modifiers: MouseModifiers::default(),
+            },
+            ClientRequest::MouseUp {
+                x: 10,
+                y: 5,
+                button: MouseButton::Right,
+                modifiers: MouseModifiers {
+                    shift: true,
+                    alt: false,
+                    ctrl: true,
+                },
+            },
             ClientRequest::MouseScroll { up: true },
+            ClientRequest::MouseWheel {
+                x: 4,
+                y: 7,
+                direction: WheelDirection::Left,
+                modifiers: MouseModifiers::default(),
+            },
             ClientRequest::Command("list-panes".to_string()),
             ClientRequest::Paste("hello world".to_string()),
         ];
@@ -407,6 +698,27 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_mouse_request_defaults() {
+        // Clients that predate buttons and modifiers send a plain left press
+        let req: ClientRequest = serde_json::from_str(r#"{"MouseDown":{"x":3,"y":4}}"#).unwrap();
+        match req {
+            ClientRequest::MouseDown {
+                x,
+                y,
+                button,
+                modifiers,
+            } => {
+                assert_eq!((x, y), (3, 4));
+                assert_eq!(button, MouseButton::Left);
+                assert_eq!(modifiers, MouseModifiers::default());
+            }
+            _ => panic!("Expected MouseDown"),
+        }
+        let modifiers = MouseModifiers::from(KeyModifiers::SHIFT | KeyModifiers::CONTROL);
+        assert!(modifiers.shift && modifiers.ctrl && !modifiers.alt);
+    }
+
     #[test]
     fn test_server_response_serialization() {
         let responses = vec![
@@ -465,6 +777,7 @@ mod tests {
                         cwd: "/tmp".to_string(),
                         cols: 80,
                         rows: 24,
+                        history_limit: 1000,
                     }],
                     active_tab: 0,
                     name: None,
@@ -634,6 +947,7 @@ mod tests {
             cwd: "/home/user/code".to_string(),
             cols: 120,
             rows: 40,
+            history_limit: 1000,
         };
         let json = serde_json::to_string(&tab).unwrap();
         let restored: TabSnapshot = serde_json::from_str(&json).unwrap();
@@ -653,6 +967,7 @@ mod tests {
             cwd: "/tmp".to_string(),
             cols: 80,
             rows: 24,
+            history_limit: 1000,
         };
         let json = serde_json::to_string(&tab).unwrap();
         let restored: TabSnapshot = serde_json::from_str(&json).unwrap();
@@ -671,6 +986,7 @@ mod tests {
             cwd: "/tmp".to_string(),
             cols: 80,
             rows: 24,
+            history_limit: 1000,
         };
         let mut json_val: serde_json::Value = serde_json::to_value(&tab).unwrap();
         let obj = json_val.as_object_mut().unwrap();
@@ -692,6 +1008,7 @@ mod tests {
             cwd: "/tmp".to_string(),
             cols: 80,
             rows: 24,
+            history_limit: 1000,
         };
         let json = serde_json::to_string(&tab).unwrap();
         let restored: TabSnapshot = serde_json::from_str(&json).unwrap();
@@ -710,6 +1027,7 @@ mod tests {
                 cwd: "/tmp".to_string(),
                 cols: 80,
                 rows: 24,
+                history_limit: 1000,
             };
             let json = serde_json::to_string(&tab).unwrap();
             let restored: TabSnapshot = serde_json::from_str(&json).unwrap();
@@ -729,17 +1047,59 @@ mod tests {
                 height: 40,
             },
             ClientRequest::Key(make_key(KeyCode::Char('x'), KeyModifiers::CONTROL).into()),
-            ClientRequest::MouseDown { x: 10, y: 5 },
-            ClientRequest::MouseDrag { x: 15, y: 8 },
-            ClientRequest::MouseMove { x: 20, y: 3 },
-            ClientRequest::MouseUp { x: 10, y: 5 },
+            ClientRequest::MouseDown {
+                x: 10,
+                y: 5,
+                button: MouseButton::Left,
+                modifiers: MouseModifiers::default(),
+            },
+            ClientRequest::MouseDrag {
+                x: 15,
+                y: 8,
+                button: MouseButton::Middle,
+                modifiers: MouseModifiers::default(),
+            },
+            ClientRequest::MouseMove {
+                x: 20,
+                y: 3,
+                modifiers: MouseModifiers::default(),
+            },
+            ClientRequest::MouseUp {
+                x: 10,
+                y: 5,
+                button: MouseButton::Right,
+                modifiers: MouseModifiers {
+                    shift: true,
+                    alt: false,
+                    ctrl: true,
+                },
+            },
             ClientRequest::MouseScroll { up: true },
+            ClientRequest::MouseWheel {
+                x: 4,
+                y: 7,
+                direction: WheelDirection::Left,
+                modifiers: MouseModifiers::default(),
+            },
             ClientRequest::MouseScroll { up: false },
             ClientRequest::Command("list-panes".to_string()),
             ClientRequest::Paste("pasted text with spaces\nnewlines".to_string()),
             ClientRequest::CommandSync("split -h".to_string()),
-            ClientRequest::AttachV2 { client_type: ClientType::Tui },
-            ClientRequest::AttachV2 { client_type: ClientType::NativeApp },
+            ClientRequest::Auth {
+                token: "s3cret".to_string(),
+            },
+            ClientRequest::AttachV2 {
+                client_type: ClientType::Tui,
+                read_only: true,
+                protocol_version: PROTOCOL_VERSION,
+                capabilities: Capability::ALL.to_vec(),
+            },
+            ClientRequest::AttachV2 {
+                client_type: ClientType::NativeApp,
+                read_only: false,
+                protocol_version: 0,
+                capabilities: Vec::new(),
+            },
             ClientRequest::RawInput(vec![0x1b, b'[', b'A']),
             ClientRequest::RawInput(vec![]),
             ClientRequest::SetPaneSize {
@@ -749,6 +1109,14 @@ mod tests {
                 pixel_width: 960,
                 pixel_height: 640,
             },
+            ClientRequest::ClipboardReply {
+                pane_id: TabId::new_v4(),
+                selection: "c".to_string(),
+                data: "aGk=".to_string(),
+            },
+            ClientRequest::SetBuffer("copied\ntext".to_string()),
+            ClientRequest::ListBuffers,
+            ClientRequest::OverviewChanged { open: true },
         ];
 
         for req in &requests {
@@ -756,6 +1124,11 @@ mod tests {
             let restored: ClientRequest = serde_json::from_str(&json).unwrap();
             let json2 = serde_json::to_string(&restored).unwrap();
             assert_eq!(json, json2, "Failed round-trip for: {:?}", req);
+
+            let body = crate::framing::encode(req, crate::framing::Encoding::Binary).unwrap();
+            let restored: ClientRequest = crate::framing::decode(&body).unwrap();
+            let json2 = serde_json::to_string(&restored).unwrap();
+            assert_eq!(json, json2, "Failed binary round-trip for: {:?}", req);
         }
     }
 
@@ -782,6 +1155,7 @@ mod tests {
                         cwd: "/tmp".to_string(),
                         cols: 80,
                         rows: 24,
+                        history_limit: 1000,
                     }],
                     active_tab: 0,
                     name: None,
@@ -826,7 +1200,29 @@ mod tests {
                 pane_id,
                 data: vec![0x1b, b'[', b'2', b'J'],
             },
+            ServerResponse::Passthrough {
+                pane_id,
+                data: b"\x1b]9;done\x07".to_vec(),
+            },
+            ServerResponse::ClipboardWrite {
+                pane_id,
+                data: "aGk=".to_string(),
+            },
+            ServerResponse::ClipboardRead {
+                pane_id,
+                selection: "c".to_string(),
+            },
+            ServerResponse::BufferList(vec![BufferSummary {
+                name: "buffer0".to_string(),
+                size: 5,
+                preview: "hello".to_string(),
+            }]),
             ServerResponse::ClientCountChanged(3),
+            ServerResponse::SwitchWorkspace(2),
+            ServerResponse::AttachedV2 {
+                protocol_version: PROTOCOL_VERSION,
+                capabilities: vec![Capability::ClientFocus],
+            },
             ServerResponse::Error("test error".to_string()),
             ServerResponse::CommandOutput {
                 output: "ok".to_string(),
@@ -847,11 +1243,35 @@ mod tests {
             let restored: ServerResponse = serde_json::from_str(&json).unwrap();
             let json2 = serde_json::to_string(&restored).unwrap();
             assert_eq!(json, json2, "Failed round-trip for: {:?}", resp);
+
+            let body =
+                crate::framing::encode_response(resp, crate::framing::Encoding::Binary).unwrap();
+            let restored = crate::framing::decode_response(&body).unwrap();
+            let json2 = serde_json::to_string(&restored).unwrap();
+            assert_eq!(json, json2, "Failed binary round-trip for: {:?}", resp);
         }
     }
 
     // --- Key event edge cases ---
 
+    #[test]
+    fn test_key_event_kind_round_trip() {
+        let mut key = make_key(KeyCode::Char('a'), KeyModifiers::NONE);
+        key.kind = KeyEventKind::Release;
+        let json = serde_json::to_string(&SerializableKeyEvent::from(key)).unwrap();
+        let restored: KeyEvent = serde_json::from_str::<SerializableKeyEvent>(&json)
+            .unwrap()
+            .into();
+        assert_eq!(restored.kind, KeyEventKind::Release);
+
+        // Events from older clients have no kind and are presses
+        let json = r#"{"code":{"Char":"a"},"modifiers":0}"#;
+        let restored: KeyEvent = serde_json::from_str::<SerializableKeyEvent>(json)
+            .unwrap()
+            .into();
+        assert_eq!(restored.kind, KeyEventKind::Press);
+    }
+
     #[test]
     fn test_key_event_with_combined_modifiers() {
         let key = make_key(KeyCode::Char('a'), KeyModifiers::CONTROL | KeyModifiers::SHIFT);
@@ -977,6 +1397,7 @@ mod tests {
                     cwd: "/tmp".to_string(),
                     cols: 80,
                     rows: 24,
+                    history_limit: 1000,
                 },
                 TabSnapshot {
                     id: TabId::new_v4(),
@@ -987,6 +1408,7 @@ mod tests {
                     cwd: "/home".to_string(),
                     cols: 120,
                     rows: 40,
+                    history_limit: 1000,
                 },
                 TabSnapshot {
                     id: TabId::new_v4(),
@@ -997,6 +1419,7 @@ mod tests {
                     cwd: "/app".to_string(),
                     cols: 80,
                     rows: 24,
+                    history_limit: 1000,
                 },
             ],
             active_tab: 1,
@@ -1047,11 +1470,25 @@ mod tests {
 
     #[test]
     fn test_attach_v2_tui() {
-        let req = ClientRequest::AttachV2 { client_type: ClientType::Tui };
+        let req = ClientRequest::AttachV2 {
+            client_type: ClientType::Tui,
+            read_only: true,
+            protocol_version: PROTOCOL_VERSION,
+            capabilities: vec![Capability::Clipboard],
+        };
         let json = serde_json::to_string(&req).unwrap();
         let restored: ClientRequest = serde_json::from_str(&json).unwrap();
-        if let ClientRequest::AttachV2 { client_type } = restored {
+        if let ClientRequest::AttachV2 {
+            client_type,
+            read_only,
+            protocol_version,
+            capabilities,
+        } = restored
+        {
             assert_eq!(client_type, ClientType::Tui);
+            assert!(read_only);
+            assert_eq!(protocol_version, PROTOCOL_VERSION);
+            assert_eq!(capabilities, vec![Capability::Clipboard]);
         } else {
             panic!("Expected AttachV2");
         }
@@ -1059,16 +1496,69 @@ mod tests {
 
     #[test]
     fn test_attach_v2_native_app() {
-        let req = ClientRequest::AttachV2 { client_type: ClientType::NativeApp };
+        let req = ClientRequest::AttachV2 {
+            client_type: ClientType::NativeApp,
+            read_only: false,
+            protocol_version: 0,
+            capabilities: Vec::new(),
+        };
         let json = serde_json::to_string(&req).unwrap();
         let restored: ClientRequest = serde_json::from_str(&json).unwrap();
-        if let ClientRequest::AttachV2 { client_type } = restored {
+        if let ClientRequest::AttachV2 { client_type, .. } = restored {
             assert_eq!(client_type, ClientType::NativeApp);
         } else {
             panic!("Expected AttachV2");
         }
     }
 
+    #[test]
+    fn test_attach_v2_defaults_to_read_write() {
+        // Clients that predate read-only attach don't send the field
+        let json = r#"{"AttachV2":{"client_type":"Tui"}}"#;
+        let restored: ClientRequest = serde_json::from_str(json).unwrap();
+        assert!(matches!(
+            restored,
+            ClientRequest::AttachV2 {
+                read_only: false,
+                protocol_version: 0,
+                ..
+            }
+        ));
+    }
+
+    // --- Negotiation ---
+
+    #[test]
+    fn test_unknown_capability_decodes_as_unknown() {
+        let json = r#"["clipboard","teleport"]"#;
+        let caps: Vec<Capability> = serde_json::from_str(json).unwrap();
+        assert_eq!(caps, vec![Capability::Clipboard, Capability::Unknown]);
+    }
+
+    #[test]
+    fn test_negotiate_keeps_shared_capabilities() {
+        let negotiated = negotiate(
+            PROTOCOL_VERSION,
+            &[Capability::Clipboard, Capability::Unknown],
+        )
+        .unwrap();
+        assert_eq!(negotiated.protocol_version, PROTOCOL_VERSION);
+        assert_eq!(negotiated.capabilities, vec![Capability::Clipboard]);
+    }
+
+    #[test]
+    fn test_negotiate_downgrades_to_own_version() {
+        let negotiated = negotiate(PROTOCOL_VERSION + 5, Capability::ALL).unwrap();
+        assert_eq!(negotiated.protocol_version, PROTOCOL_VERSION);
+        assert_eq!(negotiated.capabilities, Capability::ALL.to_vec());
+    }
+
+    #[test]
+    fn test_negotiate_rejects_old_peer() {
+        let err = negotiate(MIN_PROTOCOL_VERSION - 1, Capability::ALL).unwrap_err();
+        assert!(err.contains("or newer"), "{}", err);
+    }
+
     // --- RawInput ---
 
     #[test]
@@ -387,6 +387,14 @@ pub fn action_registry() -> &'static [ActionMeta] {
             palette_visible: true,
             action: PasteClipboard,
         },
+        ActionMeta {
+            name: "choose_buffer",
+            display_name: "Choose Paste Buffer",
+            description: "Pick a copied paste buffer to paste into the focused pane",
+            category: Tools,
+            palette_visible: true,
+            action: ChooseBuffer,
+        },
         ActionMeta {
             name: "reload_config",
             display_name: "Reload Config",
@@ -679,6 +687,7 @@ mod tests {
             "scroll_mode",
             "copy_mode",
             "paste_clipboard",
+            "choose_buffer",
             "toggle_sync_panes",
             "rename_window",
             "reload_config",
@@ -1,33 +1,88 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::sync::Arc;
 
 use anyhow::Result;
-use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
 use ratatui::layout::Rect;
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite};
 use tokio::net::UnixStream;
+use tokio::process::Child;
 use tokio::sync::Mutex;
 
 use crate::event::AppEvent;
 use pane_protocol::app::{LeaderState, ResizeBorder, ResizeState};
 use crate::clipboard;
-use pane_protocol::config::{self, Action, Config};
+use crate::graphics::{self, GraphicsSupport, ImageDraw};
+use pane_protocol::config::{self, Action, AllowPassthrough, ClipboardPolicy, Config};
 use pane_protocol::window_types::WindowId;
 use crate::copy_mode::{CopyModeAction, CopyModeState};
 use pane_protocol::layout::{Side, SplitDirection, TabId};
 use pane_daemon::server::daemon;
-use pane_protocol::framing;
+use pane_protocol::framing::{self, Encoding};
 use pane_protocol::protocol::{
-    ClientRequest, RenderState, SerializableKeyEvent, ServerResponse, WorkspaceSnapshot,
+    negotiate, Capability, ClientRequest, ClientType, MouseButton, MouseModifiers, RenderState,
+    SerializableKeyEvent, ServerResponse, WheelDirection, WorkspaceSnapshot, PROTOCOL_VERSION,
+    SYNC_OUTPUT_TIMEOUT,
 };
 use pane_protocol::system_stats::SystemStats;
-use crate::tui::Tui;
+use crate::tui::{CursorStyle, Tui};
 use crate::ui;
 use crate::ui::context_menu::ContextMenuState;
+use crate::ui::buffer_picker::BufferPickerState;
 use crate::ui::palette::UnifiedPaletteState;
 use crate::ui::tab_picker::{TabPickerEntry, TabPickerState};
 use crate::ui::widget_picker::{WidgetPickerMode, WidgetPickerState};
 
+/// How the client reaches the daemon.
+pub enum Connection {
+    /// The local daemon socket.
+    Local,
+    /// A shell command speaking the framed protocol on its stdin/stdout,
+    /// e.g. `ssh host pane proxy`.
+    Via(String),
+    /// A daemon's TCP listener, authenticated with `token`. With `tls_cert`
+    /// the connection uses TLS and trusts only that certificate.
+    Tcp {
+        addr: String,
+        token: String,
+        tls_cert: Option<std::path::PathBuf>,
+    },
+}
+
+/// Why attaching failed before the TUI started.
+#[derive(Debug)]
+pub enum HandshakeError {
+    /// The daemon didn't answer, or dropped the connection.
+    NoResponse(String),
+    /// The daemon speaks a protocol this client can't use.
+    Incompatible(String),
+}
+
+impl std::fmt::Display for HandshakeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HandshakeError::NoResponse(reason) | HandshakeError::Incompatible(reason) => {
+                f.write_str(reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for HandshakeError {}
+
+/// Read side of the connection to the daemon: the local socket, the
+/// stdout of a `--via` command, or a TCP stream.
+type ServerReader = Box<dyn AsyncRead + Send + Unpin>;
+/// Raw write side of the connection to the daemon.
+type ServerWriteHalf = Box<dyn AsyncWrite + Send + Unpin>;
+
+/// Write side of the connection to the daemon, with the encoding agreed on
+/// at attach.
+struct ServerWriter {
+    stream: ServerWriteHalf,
+    encoding: Encoding,
+}
+
 /// Result of hit-testing a tab bar click.
 enum TabBarHit {
     /// Clicked on a specific tab within a window.
@@ -36,27 +91,73 @@ enum TabBarHit {
     Plus { group_id: WindowId },
 }
 
+/// A passthrough sequence with the outer terminal position (column, row) to
+/// write it at, if its tab is shown.
+pub type PassthroughWrite = (Option<(u16, u16)>, Vec<u8>);
+
+/// Images to write after a draw, from [`Client::take_image_output`].
+pub struct ImageOutput {
+    /// Areas of the outer terminal whose cells have to be written again
+    /// first, to remove sixel images that can't be deleted otherwise.
+    pub repaint: Vec<Rect>,
+    pub bytes: Vec<u8>,
+}
+
 /// TUI client that connects to a pane daemon via Unix socket.
 pub struct Client {
     // Local rendering state (received from server)
     pub focus: Focus,
     pub render_state: RenderState,
     pub screens: HashMap<TabId, vt100::Parser>,
+    /// Tabs partway through a synchronized frame (DECSET 2026), with when
+    /// the frame started.
+    frames_pending: HashMap<TabId, std::time::Instant>,
+    /// Cursor style of the focused tab as of the last render.
+    pub cursor_style: CursorStyle,
+    /// Content area of each tab shown in the last render.
+    pub visible_tabs: HashMap<TabId, Rect>,
+    /// Passthrough sequences waiting for the next draw.
+    pub passthrough_pending: Vec<(TabId, Vec<u8>)>,
+    /// Image protocols the outer terminal understands.
+    pub graphics: GraphicsSupport,
+    /// Images to draw over their placeholders as of the last render.
+    pub image_draws: Vec<ImageDraw>,
+    /// Images currently drawn on the outer terminal.
+    images_drawn: Vec<ImageDraw>,
     pub system_stats: SystemStats,
     pub config: Config,
     pub client_count: u32,
+    /// Attached with `--read-only`: the daemon drops this client's input.
+    pub read_only: bool,
     pub plugin_segments: Vec<Vec<pane_protocol::plugin::PluginSegment>>,
 
     // Client-only UI state
     pub leader_state: Option<LeaderState>,
     pub leader_entered_at: Option<tokio::time::Instant>,
     pub palette_state: Option<UnifiedPaletteState>,
+    pub buffer_picker_state: Option<BufferPickerState>,
     pub copy_mode_state: Option<CopyModeState>,
+    /// Text being selected with the mouse, in copy mode.
+    mouse_selection: Option<MouseSelection>,
+    /// Time, position and count of the last left click, to tell double and
+    /// triple clicks.
+    last_click: Option<(std::time::Instant, u16, u16, u8)>,
     pub system_programs: Vec<TabPickerEntry>,
     pub favorites: HashSet<String>,
     pub tab_picker_state: Option<TabPickerState>,
     pub context_menu_state: Option<ContextMenuState>,
     pub pending_confirm_action: Option<Action>,
+    /// Clipboard request the confirm dialog is asking about.
+    pending_clipboard: Option<ClipboardPrompt>,
+    /// Clipboard requests that arrived while a confirm dialog was open, to
+    /// ask about next.
+    queued_clipboard: VecDeque<(ClipboardPrompt, &'static str)>,
+    /// Clipboard reads from tabs waiting to be answered, and whether they
+    /// were allowed. Denied reads are answered empty so the tab stops
+    /// waiting.
+    clipboard_reads: Vec<(TabId, String, bool)>,
+    /// Text copied since the last event, to keep as paste buffers.
+    copied: Vec<String>,
     pub confirm_message: Option<String>,
     pub resize_state: Option<ResizeState>,
     focus_stack: Vec<Focus>,
@@ -70,6 +171,25 @@ pub struct Client {
     pub widget_picker_state: Option<WidgetPickerState>,
     /// Selected workspace index in overview mode.
     pub overview_selected: usize,
+    /// Whether the daemon was last told the overview is open.
+    overview_reported: bool,
+}
+
+/// A mouse drag selecting text in a tab.
+struct MouseSelection {
+    tab: TabId,
+    /// Content area of the tab when the selection started.
+    area: Rect,
+    /// Last pointer position, to keep scrolling while it's past an edge.
+    last: (u16, u16),
+    /// The press started copy mode, so a click without a selection ends it.
+    started_copy_mode: bool,
+}
+
+/// An OSC 52 request from a tab, held while the user is asked about it.
+enum ClipboardPrompt {
+    Write { data: String },
+    Read { pane_id: TabId, selection: String },
 }
 
 /// Unified focus state: replaces the old Mode + FocusLocation + focused_widget.
@@ -94,6 +214,7 @@ pub enum Focus {
 
     // Modals (pushed onto focus_stack)
     Palette,
+    BufferPicker,
     Leader,
     Confirm,
     Rename,
@@ -437,20 +558,35 @@ impl Client {
                 active_workspace: 0,
             },
             screens: HashMap::new(),
+            frames_pending: HashMap::new(),
+            cursor_style: CursorStyle::default(),
+            visible_tabs: HashMap::new(),
+            passthrough_pending: Vec::new(),
+            graphics: GraphicsSupport::detect(),
+            image_draws: Vec::new(),
+            images_drawn: Vec::new(),
             system_stats: SystemStats::default(),
             config,
             client_count: 1,
+            read_only: false,
             plugin_segments: Vec::new(),
 
             leader_state: None,
             leader_entered_at: None,
             palette_state: None,
+            buffer_picker_state: None,
             copy_mode_state: None,
+            mouse_selection: None,
+            last_click: None,
             system_programs: crate::ui::tab_picker::scan_system_programs(),
             favorites: crate::ui::tab_picker::load_favorites(),
             tab_picker_state: None,
             context_menu_state: None,
             pending_confirm_action: None,
+            pending_clipboard: None,
+            queued_clipboard: VecDeque::new(),
+            clipboard_reads: Vec::new(),
+            copied: Vec::new(),
             confirm_message: None,
             resize_state: None,
             focus_stack: Vec::new(),
@@ -462,6 +598,7 @@ impl Client {
             new_workspace_input: None,
             widget_picker_state: None,
             overview_selected: 0,
+            overview_reported: false,
         }
     }
 
@@ -475,20 +612,35 @@ impl Client {
                 active_workspace: 0,
             },
             screens: HashMap::new(),
+            frames_pending: HashMap::new(),
+            cursor_style: CursorStyle::default(),
+            visible_tabs: HashMap::new(),
+            passthrough_pending: Vec::new(),
+            graphics: GraphicsSupport::default(),
+            image_draws: Vec::new(),
+            images_drawn: Vec::new(),
             system_stats: SystemStats::default(),
             config,
             client_count: 1,
+            read_only: false,
             plugin_segments: Vec::new(),
 
             leader_state: None,
             leader_entered_at: None,
             palette_state: None,
+            buffer_picker_state: None,
             copy_mode_state: None,
+            mouse_selection: None,
+            last_click: None,
             system_programs: Vec::new(),
             favorites: HashSet::new(),
             tab_picker_state: None,
             context_menu_state: None,
             pending_confirm_action: None,
+            pending_clipboard: None,
+            queued_clipboard: VecDeque::new(),
+            clipboard_reads: Vec::new(),
+            copied: Vec::new(),
             confirm_message: None,
             resize_state: None,
             focus_stack: Vec::new(),
@@ -500,9 +652,27 @@ impl Client {
             new_workspace_input: None,
             widget_picker_state: None,
             overview_selected: 0,
+            overview_reported: false,
         }
     }
 
+    /// Whether a tab on screen is partway through a synchronized frame that
+    /// hasn't timed out, so redrawing now could show half of it. Tabs that
+    /// aren't shown can't hold up the redraw.
+    fn frame_pending(&self) -> bool {
+        self.frames_pending.iter().any(|(id, since)| {
+            self.visible_tabs.contains_key(id) && since.elapsed() < SYNC_OUTPUT_TIMEOUT
+        })
+    }
+
+    /// Record the tabs a render showed. Tabs that left the view get no more
+    /// output, so a frame one was drawing is dropped with it.
+    pub fn set_visible_tabs(&mut self, visible_tabs: HashMap<TabId, Rect>) {
+        self.frames_pending
+            .retain(|id, _| visible_tabs.contains_key(id));
+        self.visible_tabs = visible_tabs;
+    }
+
     /// Save current focus before entering a modal.
     fn push_focus(&mut self) {
         self.focus_stack.push(self.focus.clone());
@@ -524,35 +694,83 @@ impl Client {
     }
 
     /// Connect to a daemon and run the TUI event loop.
-    pub async fn run(config: Config) -> Result<()> {
-        let sock = daemon::socket_path();
-        if !sock.exists() {
-            anyhow::bail!("no running daemon. Start one with: pane");
-        }
-
-        let mut stream = UnixStream::connect(&sock).await?;
+    pub async fn run(config: Config, read_only: bool, connection: Connection) -> Result<()> {
+        let local = matches!(connection, Connection::Local);
+        let (mut reader, mut writer, mut via_child) = match connect(connection).await {
+            Ok(connected) => connected,
+            Err(e) if local => return Err(HandshakeError::NoResponse(e.to_string()).into()),
+            Err(e) => return Err(e),
+        };
 
         // Attach with timeout — if the daemon is stuck, don't hang forever
         let handshake = async {
-            framing::send(&mut stream, &ClientRequest::Attach).await?;
-
-            let resp: ServerResponse = framing::recv_required(&mut stream).await?;
-            match resp {
-                ServerResponse::Attached => {}
+            // PANE_JSON_FRAMES keeps the connection on JSON, for debugging
+            let json_only = std::env::var_os("PANE_JSON_FRAMES").is_some();
+            let attach = ClientRequest::AttachV2 {
+                client_type: ClientType::Tui,
+                read_only,
+                protocol_version: PROTOCOL_VERSION,
+                capabilities: Capability::ALL
+                    .iter()
+                    .copied()
+                    .filter(|c| !(json_only && *c == Capability::BinaryFrames))
+                    .collect(),
+            };
+            let no_response = |e: anyhow::Error| HandshakeError::NoResponse(e.to_string());
+            framing::send(&mut writer, &attach)
+                .await
+                .map_err(no_response)?;
+
+            let resp: ServerResponse = framing::recv_required(&mut reader)
+                .await
+                .map_err(no_response)?;
+            let encoding = match resp {
+                ServerResponse::AttachedV2 {
+                    protocol_version,
+                    capabilities,
+                } => {
+                    let negotiated =
+                        negotiate(protocol_version, &capabilities).map_err(|reason| {
+                            HandshakeError::Incompatible(format!(
+                                "{}; the daemon is older than this client, restart it with `pane kill`",
+                                reason
+                            ))
+                        })?;
+                    if negotiated.capabilities.contains(&Capability::BinaryFrames) {
+                        Encoding::Binary
+                    } else {
+                        Encoding::Json
+                    }
+                }
+                ServerResponse::Attached => {
+                    return Err(HandshakeError::Incompatible(
+                        "the daemon predates protocol negotiation; restart it with `pane kill`"
+                            .to_string(),
+                    )
+                    .into())
+                }
                 ServerResponse::Error(e) => anyhow::bail!("server error: {}", e),
                 _ => anyhow::bail!("unexpected response: {:?}", resp),
             };
 
-            let resp: ServerResponse = framing::recv_required(&mut stream).await?;
-            Ok::<_, anyhow::Error>(resp)
+            let body = framing::read_frame(&mut reader)
+                .await
+                .map_err(|e| no_response(e.into()))?
+                .ok_or_else(|| no_response(anyhow::anyhow!("connection closed unexpectedly")))?;
+            let resp = framing::decode_response(&body)?;
+            Ok::<_, anyhow::Error>((resp, encoding))
         };
 
-        let resp = tokio::time::timeout(std::time::Duration::from_secs(5), handshake)
+        let (resp, encoding) = tokio::time::timeout(std::time::Duration::from_secs(5), handshake)
             .await
-            .map_err(|_| anyhow::anyhow!("daemon handshake timed out — is the daemon healthy?"))?
-            ?;
+            .map_err(|_| {
+                HandshakeError::NoResponse(
+                    "daemon handshake timed out — is the daemon healthy?".to_string(),
+                )
+            })??;
 
         let mut client = Client::new(config);
+        client.read_only = read_only;
 
         // Apply initial LayoutChanged
         if let ServerResponse::LayoutChanged { render_state } = resp {
@@ -566,7 +784,7 @@ impl Client {
         // Send initial resize
         let size = tui.size()?;
         framing::send(
-            &mut stream,
+            &mut writer,
             &ClientRequest::Resize {
                 width: size.width,
                 height: size.height,
@@ -574,9 +792,11 @@ impl Client {
         )
         .await?;
 
-        // Split stream
-        let (read_half, write_half) = stream.into_split();
-        let writer = Arc::new(Mutex::new(write_half));
+        let read_half = reader;
+        let writer = Arc::new(Mutex::new(ServerWriter {
+            stream: writer,
+            encoding,
+        }));
 
         // Event loop
         let (event_tx, mut event_rx) = tokio::sync::mpsc::unbounded_channel::<ServerEvent>();
@@ -611,7 +831,7 @@ impl Client {
                 if reader.read_exact(&mut buf).await.is_err() {
                     break;
                 }
-                let response: ServerResponse = match serde_json::from_slice(&buf) {
+                let response = match framing::decode_response(&buf) {
                     Ok(r) => r,
                     Err(_) => continue,
                 };
@@ -624,9 +844,23 @@ impl Client {
         });
 
         loop {
-            if client.needs_redraw {
+            if client.needs_redraw && !client.frame_pending() {
                 client.needs_redraw = false;
                 tui.draw(|frame| ui::render_client(&mut client, frame))?;
+                tui.set_cursor_style(&client.cursor_style)?;
+                if let Some(images) = client.take_image_output() {
+                    if !images.repaint.is_empty() {
+                        tui.repaint(&images.repaint, |frame| {
+                            ui::render_client(&mut client, frame)
+                        })?;
+                    }
+                    tui.write_images(&images.bytes)?;
+                }
+            }
+            // Passthrough doesn't wait for a redraw, which a synchronized
+            // frame can hold back
+            for (position, data) in client.take_passthrough() {
+                tui.write_passthrough(position, &data)?;
             }
 
             if let Some(event) = event_rx.recv().await {
@@ -642,6 +876,7 @@ impl Client {
             if client.should_quit {
                 break;
             }
+            client.report_overview(&writer).await;
         }
 
         // Clean up
@@ -652,6 +887,11 @@ impl Client {
             let _ = send_request(&mut w, &ClientRequest::Detach).await;
         };
         let _ = tokio::time::timeout(std::time::Duration::from_millis(500), detach).await;
+        if let Some(child) = via_child.as_mut() {
+            // Closing the pipe lets the remote proxy see EOF and exit
+            drop(writer);
+            let _ = tokio::time::timeout(std::time::Duration::from_millis(500), child.wait()).await;
+        }
 
         // Restore terminal before printing
         tui.exit();
@@ -662,6 +902,17 @@ impl Client {
         Ok(())
     }
 
+    /// Tell the daemon when the overview opens or closes, so it sends output
+    /// for the tabs it shows.
+    async fn report_overview(&mut self, writer: &Arc<Mutex<ServerWriter>>) {
+        let open = self.focus == Focus::Overview;
+        if open != self.overview_reported {
+            self.overview_reported = open;
+            let mut w = writer.lock().await;
+            let _ = send_request(&mut w, &ClientRequest::OverviewChanged { open }).await;
+        }
+    }
+
     fn apply_layout(&mut self, render_state: RenderState) {
         // Preserve our own active_workspace across broadcasts from other clients.
         // On first layout (no workspaces yet), accept the server's value.
@@ -684,7 +935,10 @@ impl Client {
                     let parser = self
                         .screens
                         .entry(pane.id)
-                        .or_insert_with(|| vt100::Parser::new(rows, cols, 1000));
+                        .or_insert_with(|| vt100::Parser::new(rows, cols, pane.history_limit));
+                    if parser.screen().scrollback_limit() != pane.history_limit {
+                        parser.screen_mut().set_scrollback_limit(pane.history_limit);
+                    }
                     // Resize existing parsers when dimensions change
                     let (cur_rows, cur_cols) = parser.screen().size();
                     if cur_rows != rows || cur_cols != cols {
@@ -695,6 +949,8 @@ impl Client {
         }
         // Remove screens for panes that no longer exist
         self.screens.retain(|id, _| live_pane_ids.contains(id));
+        self.frames_pending
+            .retain(|id, _| live_pane_ids.contains(id));
         self.render_state = render_state;
         self.render_state.active_workspace = preserved_ws;
 
@@ -716,7 +972,7 @@ impl Client {
         &mut self,
         event: ServerEvent,
         tui: &Tui,
-        writer: &Arc<Mutex<tokio::net::unix::OwnedWriteHalf>>,
+        writer: &Arc<Mutex<ServerWriter>>,
     ) -> Result<()> {
         match event {
             ServerEvent::Terminal(AppEvent::Tick) => {
@@ -724,6 +980,14 @@ impl Client {
                 if self.tab_picker_state.is_some() || self.focus == Focus::Interact {
                     self.needs_redraw = true;
                 }
+                // A selection dragged past the top or bottom keeps scrolling
+                if let Some(sel) = &self.mouse_selection {
+                    let (x, y) = sel.last;
+                    if y < sel.area.y || y >= sel.area.bottom() {
+                        self.drag_mouse_selection(x, y);
+                        self.needs_redraw = true;
+                    }
+                }
                 // Leader key timeout → open command palette after 300ms
                 if self.focus == Focus::Leader {
                     if let Some(entered) = self.leader_entered_at {
@@ -742,10 +1006,16 @@ impl Client {
             }
             ServerEvent::Terminal(app_event) => {
                 self.handle_terminal_event(app_event, tui, writer).await?;
+                self.send_clipboard_reads(writer).await;
+                for text in std::mem::take(&mut self.copied) {
+                    let mut w = writer.lock().await;
+                    let _ = send_request(&mut w, &ClientRequest::SetBuffer(text)).await;
+                }
                 self.needs_redraw = true;
             }
             ServerEvent::Server(response) => {
                 self.handle_server_response(response);
+                self.send_clipboard_reads(writer).await;
                 self.needs_redraw = true;
             }
             ServerEvent::Disconnected => {
@@ -760,12 +1030,38 @@ impl Client {
             ServerResponse::PaneOutput { pane_id, data } => {
                 if let Some(parser) = self.screens.get_mut(&pane_id) {
                     parser.process(&data);
+                    if parser.screen().synchronized_output() {
+                        self.frames_pending
+                            .entry(pane_id)
+                            .or_insert_with(std::time::Instant::now);
+                    } else {
+                        self.frames_pending.remove(&pane_id);
+                    }
                 }
+                enforce_scrollback_budget(
+                    &mut self.screens,
+                    self.config.scrollback.memory_limit_bytes(),
+                );
             }
+            // A dump replaces the tab, scrollback included, since the tab
+            // may have printed more than its screen while it was hidden
             ServerResponse::FullScreenDump { pane_id, data } => {
                 if let Some(parser) = self.screens.get_mut(&pane_id) {
+                    let (rows, cols) = parser.screen().size();
+                    let limit = parser.screen().scrollback_limit();
+                    *parser = vt100::Parser::new(rows, cols, limit);
                     parser.process(&data);
+                    if parser.screen().synchronized_output() {
+                        self.frames_pending
+                            .insert(pane_id, std::time::Instant::now());
+                    } else {
+                        self.frames_pending.remove(&pane_id);
+                    }
                 }
+                enforce_scrollback_budget(
+                    &mut self.screens,
+                    self.config.scrollback.memory_limit_bytes(),
+                );
             }
             ServerResponse::LayoutChanged { render_state } => {
                 self.apply_layout(render_state);
@@ -781,23 +1077,139 @@ impl Client {
             ServerResponse::SessionEnded => {
                 self.should_quit = true;
             }
+            ServerResponse::Passthrough { pane_id, data } => {
+                if self.config.behavior.allow_passthrough != AllowPassthrough::Off {
+                    self.passthrough_pending.push((pane_id, data));
+                }
+            }
+            ServerResponse::ClipboardWrite { data, .. } => {
+                match self.config.behavior.clipboard_write {
+                    ClipboardPolicy::Allow => {
+                        let _ = clipboard::copy_base64_to_clipboard(&data);
+                    }
+                    ClipboardPolicy::Ask => self.ask_clipboard(
+                        ClipboardPrompt::Write { data },
+                        "Allow a tab to set the clipboard?",
+                    ),
+                    ClipboardPolicy::Deny => {}
+                }
+            }
+            ServerResponse::ClipboardRead { pane_id, selection } => {
+                match self.config.behavior.clipboard_read {
+                    ClipboardPolicy::Allow => self.clipboard_reads.push((pane_id, selection, true)),
+                    ClipboardPolicy::Ask => self.ask_clipboard(
+                        ClipboardPrompt::Read { pane_id, selection },
+                        "Allow a tab to read the clipboard?",
+                    ),
+                    ClipboardPolicy::Deny => self.clipboard_reads.push((pane_id, selection, false)),
+                }
+            }
+            ServerResponse::BufferList(buffers) => {
+                if self.focus != Focus::BufferPicker {
+                    self.push_focus();
+                    self.focus = Focus::BufferPicker;
+                }
+                self.buffer_picker_state = Some(BufferPickerState::new(buffers));
+            }
             ServerResponse::ClientCountChanged(count) => {
                 self.client_count = count;
             }
+            ServerResponse::SwitchWorkspace(index) => {
+                if index < self.render_state.workspaces.len() {
+                    self.render_state.active_workspace = index;
+                    self.update_terminal_title();
+                }
+            }
             ServerResponse::PluginSegments(segments) => {
                 self.plugin_segments = segments;
             }
             ServerResponse::Error(_)
             | ServerResponse::Attached
+            | ServerResponse::AttachedV2 { .. }
             | ServerResponse::CommandOutput { .. } => {}
         }
     }
 
+    /// Put a clipboard request to the user. If another confirm dialog is
+    /// open, it waits its turn.
+    fn ask_clipboard(&mut self, prompt: ClipboardPrompt, message: &'static str) {
+        if self.focus == Focus::Confirm {
+            self.queued_clipboard.push_back((prompt, message));
+            return;
+        }
+        self.pending_clipboard = Some(prompt);
+        self.confirm_message = Some(message.into());
+        self.push_focus();
+        self.focus = Focus::Confirm;
+    }
+
+    /// Ask about the next queued clipboard request once the confirm dialog
+    /// is closed.
+    fn ask_queued_clipboard(&mut self) {
+        if self.focus != Focus::Confirm {
+            if let Some((prompt, message)) = self.queued_clipboard.pop_front() {
+                self.ask_clipboard(prompt, message);
+            }
+        }
+    }
+
+    /// Answer clipboard reads with this client's clipboard, or with nothing
+    /// if they were denied.
+    async fn send_clipboard_reads(&mut self, writer: &Arc<Mutex<ServerWriter>>) {
+        self.ask_queued_clipboard();
+        for (pane_id, selection, allowed) in std::mem::take(&mut self.clipboard_reads) {
+            let data = if allowed {
+                clipboard::clipboard_as_base64()
+            } else {
+                String::new()
+            };
+            let mut w = writer.lock().await;
+            let _ = send_request(
+                &mut w,
+                &ClientRequest::ClipboardReply {
+                    pane_id,
+                    selection,
+                    data,
+                },
+            )
+            .await;
+        }
+    }
+
+    /// Carry out what the confirm dialog asked about.
+    async fn accept_confirm(&mut self, writer: &Arc<Mutex<ServerWriter>>) {
+        if let Some(action) = self.pending_confirm_action.take() {
+            if let Some(cmd) = action_to_command(&action) {
+                let mut w = writer.lock().await;
+                let _ = send_request(&mut w, &ClientRequest::Command(cmd)).await;
+            }
+        }
+        match self.pending_clipboard.take() {
+            Some(ClipboardPrompt::Write { data }) => {
+                let _ = clipboard::copy_base64_to_clipboard(&data);
+            }
+            Some(ClipboardPrompt::Read { pane_id, selection }) => {
+                self.clipboard_reads.push((pane_id, selection, true));
+                self.send_clipboard_reads(writer).await;
+            }
+            None => {}
+        }
+        self.confirm_message = None;
+    }
+
+    fn dismiss_confirm(&mut self) {
+        self.pending_confirm_action = None;
+        if let Some(ClipboardPrompt::Read { pane_id, selection }) = self.pending_clipboard.take() {
+            self.clipboard_reads.push((pane_id, selection, false));
+        }
+        self.confirm_message = None;
+    }
+
     async fn handle_terminal_event(
         &mut self,
         event: crate::event::AppEvent,
         tui: &Tui,
-        writer: &Arc<Mutex<tokio::net::unix::OwnedWriteHalf>>,
+        writer: &Arc<Mutex<ServerWriter>>,
     ) -> Result<()> {
         use crate::event::AppEvent;
         match event {
@@ -815,7 +1227,12 @@ impl Client {
                 )
                 .await;
             }
-            AppEvent::MouseDown { x, y } => {
+            AppEvent::MouseDown {
+                x,
+                y,
+                button: MouseButton::Left,
+                modifiers,
+            } => {
                 if self.focus == Focus::ContextMenu {
                     let size = tui.size()?;
                     let area = Rect::new(0, 0, size.width, size.height);
@@ -838,18 +1255,11 @@ impl Client {
                     if let Some(click) = ui::dialog::confirm_hit_test(area, msg, x, y) {
                         match click {
                             ui::ConfirmDialogClick::Confirm => {
-                                if let Some(action) = self.pending_confirm_action.take() {
-                                    if let Some(cmd) = action_to_command(&action) {
-                                        let mut w = writer.lock().await;
-                                        let _ = send_request(&mut w, &ClientRequest::Command(cmd)).await;
-                                    }
-                                }
-                                self.confirm_message = None;
+                                self.accept_confirm(writer).await;
                                 self.focus = Focus::Normal;
                             }
                             ui::ConfirmDialogClick::Cancel => {
-                                self.pending_confirm_action = None;
-                                self.confirm_message = None;
+                                self.dismiss_confirm();
                                 self.focus = Focus::Normal;
                             }
                         }
@@ -1138,43 +1548,163 @@ impl Client {
                         return Ok(());
                     }
 
+                    // Clicks on tab content select text, unless the tab takes
+                    // the mouse itself; shift selects anyway
+                    if let Some(tab) = self.tab_at(x, y) {
+                        if modifiers.shift || !self.tab_wants_mouse(x, y) {
+                            self.start_mouse_selection(tab, x, y, writer).await;
+                            return Ok(());
+                        }
+                    }
+
                     // Forward mouse to server (click on body clears workspace bar focus)
                     self.focus = Focus::Normal;
                     let mut w = writer.lock().await;
-                    let _ = send_request(&mut w, &ClientRequest::MouseDown { x, y }).await;
+                    let _ = send_request(
+                        &mut w,
+                        &ClientRequest::MouseDown {
+                            x,
+                            y,
+                            button: MouseButton::Left,
+                            modifiers,
+                        },
+                    )
+                    .await;
+                } else if self.focus == Focus::Copy {
+                    if let Some(tab) = self.tab_at(x, y) {
+                        self.start_mouse_selection(tab, x, y, writer).await;
+                    }
                 }
             }
-            AppEvent::MouseDrag { x, y } => {
-                let mut w = writer.lock().await;
-                let _ = send_request(&mut w, &ClientRequest::MouseDrag { x, y }).await;
+            AppEvent::MouseDrag { x, y, .. } if self.mouse_selection.is_some() => {
+                self.drag_mouse_selection(x, y);
             }
-            AppEvent::MouseUp { x, y } => {
+            AppEvent::MouseDrag {
+                x,
+                y,
+                button,
+                modifiers,
+            } => {
                 let mut w = writer.lock().await;
-                let _ = send_request(&mut w, &ClientRequest::MouseUp { x, y }).await;
+                let _ = send_request(
+                    &mut w,
+                    &ClientRequest::MouseDrag {
+                        x,
+                        y,
+                        button,
+                        modifiers,
+                    },
+                )
+                .await;
             }
-            AppEvent::MouseScroll { up } => {
+            AppEvent::MouseUp {
+                button: MouseButton::Left,
+                ..
+            } if self.mouse_selection.is_some() => {
+                self.finish_mouse_selection();
+            }
+            AppEvent::MouseUp {
+                x,
+                y,
+                button,
+                modifiers,
+            } => {
+                // Other buttons only reach the tab when their press did
+                if button == MouseButton::Left
+                    || matches!(self.focus, Focus::Normal | Focus::Interact)
+                {
+                    let mut w = writer.lock().await;
+                    let _ = send_request(
+                        &mut w,
+                        &ClientRequest::MouseUp {
+                            x,
+                            y,
+                            button,
+                            modifiers,
+                        },
+                    )
+                    .await;
+                }
+            }
+            AppEvent::MouseScroll {
+                x,
+                y,
+                direction,
+                modifiers,
+            } => {
+                let up = direction == WheelDirection::Up;
+                let vertical = matches!(direction, WheelDirection::Up | WheelDirection::Down);
                 if self.focus == Focus::NewWorkspace {
                     if let Some(ref mut state) = self.new_workspace_input {
-                        if matches!(state.stage, NewWorkspaceStage::Directory) {
+                        if vertical && matches!(state.stage, NewWorkspaceStage::Directory) {
                             if up { state.browser.move_up(); } else { state.browser.move_down(); }
                         }
                     }
                 } else if self.focus == Focus::TabPicker {
                     if let Some(ref mut tp) = self.tab_picker_state {
-                        if up { tp.move_up(); } else { tp.move_down(); }
+                        if vertical {
+                            if up {
+                                tp.move_up();
+                            } else {
+                                tp.move_down();
+                            }
+                        }
                     }
-                } else {
+                } else if matches!(
+                    self.focus,
+                    Focus::Normal | Focus::Interact | Focus::WorkspaceBar
+                ) {
+                    // The daemon sends it to the tab under the pointer
+                    let mut w = writer.lock().await;
+                    let _ = send_request(
+                        &mut w,
+                        &ClientRequest::MouseWheel {
+                            x,
+                            y,
+                            direction,
+                            modifiers,
+                        },
+                    )
+                    .await;
+                } else if vertical {
                     let mut w = writer.lock().await;
                     let _ = send_request(&mut w, &ClientRequest::MouseScroll { up }).await;
                 }
             }
-            AppEvent::MouseMove { x, y } => {
+            AppEvent::MouseMove { x, y, modifiers } => {
                 self.hover = Some((x, y));
                 let mut w = writer.lock().await;
-                let _ = send_request(&mut w, &ClientRequest::MouseMove { x, y }).await;
-            }
-            AppEvent::MouseRightDown { x, y } => {
-                if matches!(self.focus, Focus::Normal | Focus::Interact | Focus::WorkspaceBar) {
+                let _ = send_request(&mut w, &ClientRequest::MouseMove { x, y, modifiers }).await;
+            }
+            AppEvent::MouseDown {
+                x,
+                y,
+                button,
+                modifiers,
+            } => {
+                let body_focused = matches!(
+                    self.focus,
+                    Focus::Normal | Focus::Interact | Focus::WorkspaceBar
+                );
+                // Tabs reporting the mouse get middle and right clicks; shift
+                // right-click still opens the menu over them
+                let forward = body_focused
+                    && self.tab_wants_mouse(x, y)
+                    && !(button == MouseButton::Right && modifiers.shift);
+                if forward {
+                    self.focus = Focus::Normal;
+                    let mut w = writer.lock().await;
+                    let _ = send_request(
+                        &mut w,
+                        &ClientRequest::MouseDown {
+                            x,
+                            y,
+                            button,
+                            modifiers,
+                        },
+                    )
+                    .await;
+                } else if body_focused && button == MouseButton::Right {
                     let show_workspace_bar = !self.render_state.workspaces.is_empty();
 
                     if show_workspace_bar && y < crate::ui::workspace_bar::HEIGHT {
@@ -1210,7 +1740,16 @@ impl Client {
                     } else if let Some(TabBarHit::Tab { group_index, tab_index }) = self.hit_test_tab_bar(tui, x, y) {
                         // Right-click on a tab — select that tab first, then show tab bar menu
                         let mut w = writer.lock().await;
-                        let _ = send_request(&mut w, &ClientRequest::MouseDown { x, y }).await;
+                        let _ = send_request(
+                            &mut w,
+                            &ClientRequest::MouseDown {
+                                x,
+                                y,
+                                button: MouseButton::Left,
+                                modifiers: MouseModifiers::default(),
+                            },
+                        )
+                        .await;
                         drop(w);
                         // Update local render state to reflect the selected tab
                         if let Some(ws) = self.render_state.workspaces.get_mut(self.render_state.active_workspace) {
@@ -1232,8 +1771,16 @@ impl Client {
                     }
                 }
             }
+            AppEvent::FocusChanged { focused } => {
+                let mut w = writer.lock().await;
+                let _ = send_request(&mut w, &ClientRequest::FocusChanged { focused }).await;
+            }
             AppEvent::Tick => {}
-            AppEvent::PtyOutput { .. } | AppEvent::PtyExited { .. } | AppEvent::SystemStats(_) | AppEvent::ForegroundPoll => {
+            AppEvent::PtyOutput { .. }
+            | AppEvent::PtyExited { .. }
+            | AppEvent::SyncOutputTimeout { .. }
+            | AppEvent::SystemStats(_)
+            | AppEvent::ForegroundPoll => {
                 // These come from the server/daemon, not terminal
             }
         }
@@ -1244,13 +1791,27 @@ impl Client {
         &mut self,
         key: KeyEvent,
         tui: &Tui,
-        writer: &Arc<Mutex<tokio::net::unix::OwnedWriteHalf>>,
+        writer: &Arc<Mutex<ServerWriter>>,
     ) -> Result<()> {
+        // Releases only arrive when the outer terminal speaks the kitty
+        // keyboard protocol. They never trigger bindings, and are forwarded
+        // while interacting so tabs that asked for them get them.
+        if key.kind == KeyEventKind::Release {
+            let normalized = config::normalize_key(key);
+            if self.focus == Focus::Interact && self.config.keys.lookup(&normalized).is_none() {
+                let mut w = writer.lock().await;
+                let _ = send_request(&mut w, &ClientRequest::Key(SerializableKeyEvent::from(key)))
+                    .await;
+            }
+            return Ok(());
+        }
+
         // Modal modes handled client-side
         match &self.focus {
             Focus::Scroll => return self.handle_scroll_key(key, writer).await,
             Focus::Copy => self.handle_copy_mode_key(key),
             Focus::Palette => return self.handle_palette_key(key, tui, writer).await,
+            Focus::BufferPicker => return self.handle_buffer_picker_key(key, writer).await,
             Focus::TabPicker => return self.handle_tab_picker_key(key, writer).await,
             Focus::Confirm => return self.handle_confirm_key(key, writer).await,
             Focus::Leader => return self.handle_leader_key(key, tui, writer).await,
@@ -1271,7 +1832,7 @@ impl Client {
         &mut self,
         key: KeyEvent,
         _tui: &Tui,
-        writer: &Arc<Mutex<tokio::net::unix::OwnedWriteHalf>>,
+        writer: &Arc<Mutex<ServerWriter>>,
     ) -> Result<()> {
         let normalized = config::normalize_key(key);
 
@@ -1295,7 +1856,7 @@ impl Client {
         &mut self,
         key: KeyEvent,
         tui: &Tui,
-        writer: &Arc<Mutex<tokio::net::unix::OwnedWriteHalf>>,
+        writer: &Arc<Mutex<ServerWriter>>,
     ) -> Result<()> {
         let normalized = config::normalize_key(key);
 
@@ -1343,7 +1904,7 @@ impl Client {
         &mut self,
         label: &str,
         tui: &Tui,
-        writer: &Arc<Mutex<tokio::net::unix::OwnedWriteHalf>>,
+        writer: &Arc<Mutex<ServerWriter>>,
     ) -> Result<()> {
         match label {
             "leader" => self.enter_leader_mode(),
@@ -1394,7 +1955,7 @@ impl Client {
         &mut self,
         action: Action,
         _tui: &Tui,
-        writer: &Arc<Mutex<tokio::net::unix::OwnedWriteHalf>>,
+        writer: &Arc<Mutex<ServerWriter>>,
     ) -> Result<()> {
         // Workspace bar focus mode
         if self.focus == Focus::WorkspaceBar {
@@ -1518,15 +2079,10 @@ impl Client {
                     if let Some(group) = ws.groups.iter().find(|g| g.id == ws.active_group) {
                         if let Some(pane) = group.tabs.get(group.active_tab) {
                             if let Some(parser) = self.screens.get(&pane.id) {
-                                let screen = parser.screen();
-                                let (cursor_row, cursor_col) = screen.cursor_position();
-                                let (rows, cols) = screen.size();
-                                self.copy_mode_state = Some(CopyModeState::new(
-                                    rows as usize,
-                                    cols as usize,
-                                    cursor_row as usize,
-                                    cursor_col as usize,
-                                ));
+                                self.copy_mode_state = Some(
+                                    CopyModeState::for_screen(parser.screen())
+                                        .with_keys(self.config.behavior.copy_mode_keys),
+                                );
                                 self.push_focus();
                                 self.focus = Focus::Copy;
                             }
@@ -1535,6 +2091,12 @@ impl Client {
                 }
                 return Ok(());
             }
+            Action::ChooseBuffer => {
+                // The picker opens when the daemon answers with the buffers
+                let mut w = writer.lock().await;
+                let _ = send_request(&mut w, &ClientRequest::ListBuffers).await;
+                return Ok(());
+            }
             Action::CommandPalette => {
                 self.push_focus();
                 self.palette_state = Some(UnifiedPaletteState::new_full_search(&self.config.keys, &self.config.leader));
@@ -1705,7 +2267,7 @@ impl Client {
     async fn handle_tab_picker_key(
         &mut self,
         key: KeyEvent,
-        writer: &Arc<Mutex<tokio::net::unix::OwnedWriteHalf>>,
+        writer: &Arc<Mutex<ServerWriter>>,
     ) -> Result<()> {
         let state = match self.tab_picker_state.as_mut() {
             Some(s) => s,
@@ -1783,23 +2345,16 @@ impl Client {
     async fn handle_confirm_key(
         &mut self,
         key: KeyEvent,
-        writer: &Arc<Mutex<tokio::net::unix::OwnedWriteHalf>>,
+        writer: &Arc<Mutex<ServerWriter>>,
     ) -> Result<()> {
         match key.code {
             KeyCode::Enter | KeyCode::Char('y') => {
-                if let Some(action) = self.pending_confirm_action.take() {
-                    if let Some(cmd) = action_to_command(&action) {
-                        let mut w = writer.lock().await;
-                        let _ = send_request(&mut w, &ClientRequest::Command(cmd)).await;
-                    }
-                }
-                self.confirm_message = None;
+                self.accept_confirm(writer).await;
                 self.pop_focus();
                 self.focus = Focus::Normal;
             }
             KeyCode::Esc | KeyCode::Char('n') => {
-                self.pending_confirm_action = None;
-                self.confirm_message = None;
+                self.dismiss_confirm();
                 self.pop_focus();
                 self.focus = Focus::Normal;
             }
@@ -1811,7 +2366,7 @@ impl Client {
     async fn handle_rename_key(
         &mut self,
         key: KeyEvent,
-        writer: &Arc<Mutex<tokio::net::unix::OwnedWriteHalf>>,
+        writer: &Arc<Mutex<ServerWriter>>,
     ) -> Result<()> {
         match key.code {
             KeyCode::Esc => {
@@ -1843,7 +2398,7 @@ impl Client {
     async fn handle_new_workspace_key(
         &mut self,
         key: KeyEvent,
-        writer: &Arc<Mutex<tokio::net::unix::OwnedWriteHalf>>,
+        writer: &Arc<Mutex<ServerWriter>>,
     ) -> Result<()> {
         let state = match self.new_workspace_input.as_mut() {
             Some(s) => s,
@@ -1958,7 +2513,7 @@ impl Client {
         &mut self,
         key: KeyEvent,
         _tui: &Tui,
-        writer: &Arc<Mutex<tokio::net::unix::OwnedWriteHalf>>,
+        writer: &Arc<Mutex<ServerWriter>>,
     ) -> Result<()> {
         // Check global keybinds first (quit, etc.)
         if let Some(action) = self.config.keys.lookup(&key).cloned() {
@@ -2035,7 +2590,7 @@ impl Client {
     async fn handle_resize_key(
         &mut self,
         key: KeyEvent,
-        writer: &Arc<Mutex<tokio::net::unix::OwnedWriteHalf>>,
+        writer: &Arc<Mutex<ServerWriter>>,
     ) -> Result<()> {
         let state = match self.resize_state.as_mut() {
             Some(s) => s,
@@ -2146,7 +2701,7 @@ impl Client {
         &mut self,
         key: KeyEvent,
         tui: &Tui,
-        writer: &Arc<Mutex<tokio::net::unix::OwnedWriteHalf>>,
+        writer: &Arc<Mutex<ServerWriter>>,
     ) -> Result<()> {
         match key.code {
             KeyCode::Esc | KeyCode::Char('q') => {
@@ -2178,6 +2733,135 @@ impl Client {
         Ok(())
     }
 
+    /// Start selecting text in a tab with the mouse, in copy mode on it.
+    async fn start_mouse_selection(
+        &mut self,
+        tab: TabId,
+        x: u16,
+        y: u16,
+        writer: &Arc<Mutex<ServerWriter>>,
+    ) {
+        let clicks = self.count_click(x, y);
+        let Some(area) = self.visible_tabs.get(&tab).copied() else {
+            return;
+        };
+        let Some(window) = self.active_workspace().and_then(|ws| {
+            ws.groups
+                .iter()
+                .find(|g| g.tabs.get(g.active_tab).is_some_and(|t| t.id == tab))
+                .map(|g| (g.id, g.id == ws.active_group))
+        }) else {
+            return;
+        };
+        // Copy mode follows the focused window
+        if window.1 {
+            if self.focus == Focus::WorkspaceBar {
+                self.focus = Focus::Normal;
+            }
+        } else {
+            if self.focus == Focus::Copy {
+                self.copy_mode_state = None;
+                self.pop_focus();
+            }
+            self.focus = Focus::Normal;
+            let active = self.render_state.active_workspace;
+            if let Some(ws) = self.render_state.workspaces.get_mut(active) {
+                ws.active_group = window.0;
+            }
+            let mut w = writer.lock().await;
+            let _ = send_request(&mut w, &ClientRequest::FocusWindow { id: window.0 }).await;
+        }
+
+        if !self.screens.contains_key(&tab) {
+            return;
+        }
+        let started_copy_mode = self.focus != Focus::Copy || self.copy_mode_state.is_none();
+        if started_copy_mode {
+            self.push_focus();
+            self.focus = Focus::Copy;
+        }
+        let parser = &self.screens[&tab];
+        if started_copy_mode {
+            self.copy_mode_state = Some(
+                CopyModeState::for_screen(parser.screen())
+                    .with_keys(self.config.behavior.copy_mode_keys),
+            );
+        }
+        if let Some(cms) = self.copy_mode_state.as_mut() {
+            let (row, col) = cms.view_origin((area.height, area.width));
+            cms.mouse_press(row + y - area.y, col + x - area.x, clicks, parser.screen());
+        }
+        self.mouse_selection = Some(MouseSelection {
+            tab,
+            area,
+            last: (x, y),
+            started_copy_mode,
+        });
+    }
+
+    /// Count a left click at (x, y): 2 or 3 when it follows quickly on the
+    /// previous ones at the same spot.
+    fn count_click(&mut self, x: u16, y: u16) -> u8 {
+        let now = std::time::Instant::now();
+        let clicks = match self.last_click {
+            Some((at, px, py, n))
+                if (px, py) == (x, y) && now.duration_since(at).as_millis() < 400 =>
+            {
+                n % 3 + 1
+            }
+            _ => 1,
+        };
+        self.last_click = Some((now, x, y, clicks));
+        clicks
+    }
+
+    /// Extend the mouse selection to (x, y). Past the top or bottom of the
+    /// tab, each call scrolls a row further.
+    fn drag_mouse_selection(&mut self, x: u16, y: u16) {
+        let Some(sel) = self.mouse_selection.as_mut() else {
+            return;
+        };
+        sel.last = (x, y);
+        let area = sel.area;
+        let (Some(cms), Some(parser)) = (self.copy_mode_state.as_mut(), self.screens.get(&sel.tab))
+        else {
+            return;
+        };
+        // The tab's area may show only part of the viewport, which follows
+        // the cursor a cell at a time past its edges
+        let (origin_row, origin_col) = cms.view_origin((area.height, area.width));
+        let row = i32::from(origin_row) + i32::from(y.min(area.bottom())) - i32::from(area.y);
+        let col = if x < area.x {
+            origin_col.saturating_sub(1)
+        } else {
+            origin_col + x.min(area.right()) - area.x
+        };
+        cms.mouse_drag(row, col, parser.screen());
+    }
+
+    /// Copy the mouse selection to the clipboard and leave copy mode. A
+    /// click that selected nothing leaves copy mode only if it entered it.
+    fn finish_mouse_selection(&mut self) {
+        let Some(sel) = self.mouse_selection.take() else {
+            return;
+        };
+        let text = match (&self.copy_mode_state, self.screens.get(&sel.tab)) {
+            (Some(cms), Some(parser)) => cms.selected_text(parser.screen()),
+            _ => String::new(),
+        };
+        if !text.is_empty() {
+            let _ = clipboard::copy_to_clipboard(&text);
+            self.copied.push(text);
+        } else if !sel.started_copy_mode {
+            return;
+        }
+        if self.focus == Focus::Copy {
+            self.copy_mode_state = None;
+            self.pop_focus();
+            self.focus = Focus::Normal;
+        }
+    }
+
     fn handle_copy_mode_key(&mut self, key: KeyEvent) -> Result<()> {
         // Get the pane_id for the active pane so we can borrow screen and cms separately
         let pane_id = self
@@ -2211,6 +2895,7 @@ impl Client {
                 CopyModeAction::None => {}
                 CopyModeAction::YankSelection(text) => {
                     let _ = clipboard::copy_to_clipboard(&text);
+                    self.copied.push(text);
                     self.copy_mode_state = None;
                     self.pop_focus();
                     self.focus = Focus::Normal;
@@ -2228,7 +2913,7 @@ impl Client {
     async fn handle_scroll_key(
         &mut self,
         key: KeyEvent,
-        writer: &Arc<Mutex<tokio::net::unix::OwnedWriteHalf>>,
+        writer: &Arc<Mutex<ServerWriter>>,
     ) -> Result<()> {
         match key.code {
             KeyCode::Esc | KeyCode::Char('q') => {
@@ -2278,7 +2963,7 @@ impl Client {
         &mut self,
         key: KeyEvent,
         tui: &Tui,
-        writer: &Arc<Mutex<tokio::net::unix::OwnedWriteHalf>>,
+        writer: &Arc<Mutex<ServerWriter>>,
     ) -> Result<()> {
         if let Some(ref mut cp) = self.palette_state {
             match key.code {
@@ -2310,11 +2995,57 @@ impl Client {
         Ok(())
     }
 
+    async fn handle_buffer_picker_key(
+        &mut self,
+        key: KeyEvent,
+        writer: &Arc<Mutex<ServerWriter>>,
+    ) -> Result<()> {
+        let Some(ref mut bp) = self.buffer_picker_state else {
+            self.pop_focus();
+            return Ok(());
+        };
+        let selected_command = |bp: &BufferPickerState, command: &str| {
+            bp.selected_buffer().map(|b| {
+                let escaped = b.name.replace('\\', "\\\\").replace('"', "\\\"");
+                format!("{} -b \"{}\"", command, escaped)
+            })
+        };
+        match key.code {
+            KeyCode::Esc => {
+                self.buffer_picker_state = None;
+                self.pop_focus();
+            }
+            KeyCode::Enter => {
+                if let Some(cmd) = selected_command(bp, "paste-buffer -p") {
+                    self.buffer_picker_state = None;
+                    self.pop_focus();
+                    let mut w = writer.lock().await;
+                    let _ = send_request(&mut w, &ClientRequest::Command(cmd)).await;
+                }
+            }
+            KeyCode::Char('d') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                if let Some(cmd) = selected_command(bp, "delete-buffer") {
+                    bp.remove_selected();
+                    let mut w = writer.lock().await;
+                    let _ = send_request(&mut w, &ClientRequest::Command(cmd)).await;
+                }
+            }
+            KeyCode::Up => bp.move_up(),
+            KeyCode::Down => bp.move_down(),
+            _ => {
+                if ui::dialog::handle_text_input(key.code, &mut bp.input) {
+                    bp.update_filter();
+                }
+            }
+        }
+        Ok(())
+    }
+
     async fn handle_leader_key(
         &mut self,
         key: KeyEvent,
         tui: &Tui,
-        writer: &Arc<Mutex<tokio::net::unix::OwnedWriteHalf>>,
+        writer: &Arc<Mutex<ServerWriter>>,
     ) -> Result<()> {
         use pane_protocol::config::LeaderNode;
 
@@ -2410,7 +3141,7 @@ impl Client {
     async fn handle_widget_picker_key(
         &mut self,
         key: KeyEvent,
-        writer: &Arc<Mutex<tokio::net::unix::OwnedWriteHalf>>,
+        writer: &Arc<Mutex<ServerWriter>>,
     ) -> Result<()> {
         match key.code {
             KeyCode::Esc | KeyCode::Char('q') => {
@@ -2502,6 +3233,90 @@ impl Client {
         self.screens.get(&pane_id).map(|p| p.screen())
     }
 
+    /// Take the passthrough sequences to write, each with the
+    /// outer terminal position of its tab's cursor. Sequences from tabs
+    /// that aren't shown are dropped unless passthrough is allowed for all.
+    pub fn take_passthrough(&mut self) -> Vec<PassthroughWrite> {
+        let all = self.config.behavior.allow_passthrough == AllowPassthrough::All;
+        std::mem::take(&mut self.passthrough_pending)
+            .into_iter()
+            .filter_map(|(pane_id, data)| {
+                let position = self.tab_cursor_position(pane_id);
+                (position.is_some() || all).then_some((position, data))
+            })
+            .collect()
+    }
+
+    /// Take the bytes redrawing images on the outer terminal, if they changed
+    /// since the last draw.
+    pub fn take_image_output(&mut self) -> Option<ImageOutput> {
+        if self.image_draws == self.images_drawn {
+            return None;
+        }
+        // Sixel pixels stay until the cells under them are written again
+        let repaint = self
+            .images_drawn
+            .iter()
+            .filter(|d| d.protocol == vt100::ImageProtocol::Sixel && !self.image_draws.contains(d))
+            .map(|d| Rect::new(d.x, d.y, d.cols, d.rows))
+            .collect();
+        let mut bytes = Vec::new();
+        if self
+            .images_drawn
+            .iter()
+            .any(|d| d.protocol == vt100::ImageProtocol::Kitty)
+        {
+            bytes.extend_from_slice(graphics::DELETE_KITTY_IMAGES);
+        }
+        for draw in &self.image_draws {
+            let placement = self
+                .pane_screen(draw.tab)
+                .and_then(|screen| screen.images().into_iter().find(|p| p.id == draw.id));
+            if let Some(placement) = placement {
+                graphics::encode(draw, placement.image, &mut bytes);
+            }
+        }
+        self.images_drawn = self.image_draws.clone();
+        Some(ImageOutput { repaint, bytes })
+    }
+
+    /// Where a shown tab's cursor is on the outer terminal.
+    fn tab_cursor_position(&self, pane_id: TabId) -> Option<(u16, u16)> {
+        let area = self.visible_tabs.get(&pane_id)?;
+        ui::window_view::cursor_in(self.pane_screen(pane_id)?, *area)
+    }
+
+    /// The shown tab whose content is under (x, y), floating windows first.
+    pub fn tab_at(&self, x: u16, y: u16) -> Option<TabId> {
+        let ws = self.active_workspace()?;
+        let floating: Vec<TabId> = ws
+            .floating_windows
+            .iter()
+            .rev()
+            .filter_map(|fw| ws.groups.iter().find(|g| g.id == fw.id))
+            .filter_map(|g| g.tabs.get(g.active_tab).map(|t| t.id))
+            .collect();
+        let hit = |id: &TabId| {
+            self.visible_tabs
+                .get(id)
+                .is_some_and(|area| area.contains(ratatui::layout::Position { x, y }))
+        };
+        floating.iter().copied().find(hit).or_else(|| {
+            self.visible_tabs
+                .keys()
+                .copied()
+                .filter(|id| !floating.contains(id))
+                .find(hit)
+        })
+    }
+
+    /// Whether the tab under (x, y) has turned on mouse reporting.
+    pub fn tab_wants_mouse(&self, x: u16, y: u16) -> bool {
+        self.tab_at(x, y)
+            .and_then(|id| self.pane_screen(id))
+            .is_some_and(|screen| screen.mouse_protocol_mode() != vt100::MouseProtocolMode::None)
+    }
+
     /// Hit-test the tab bar across all visible windows.
     /// Returns which tab or + button was clicked, along with the window index.
     fn hit_test_tab_bar(&self, tui: &Tui, x: u16, y: u16) -> Option<TabBarHit> {
@@ -2649,18 +3464,48 @@ impl From<crate::event::AppEvent> for ServerEvent {
     }
 }
 
+/// Open the connection to the daemon. A spawned `via` command is returned
+/// so it lives as long as the connection.
+async fn connect(connection: Connection) -> Result<(ServerReader, ServerWriteHalf, Option<Child>)> {
+    match connection {
+        Connection::Local => {
+            let sock = daemon::socket_path();
+            if !sock.exists() {
+                anyhow::bail!("no running daemon. Start one with: pane");
+            }
+            let (read_half, write_half) = UnixStream::connect(&sock).await?.into_split();
+            Ok((Box::new(read_half), Box::new(write_half), None))
+        }
+        Connection::Via(via) => {
+            let mut child = tokio::process::Command::new("sh")
+                .arg("-c")
+                .arg(&via)
+                .stdin(std::process::Stdio::piped())
+                .stdout(std::process::Stdio::piped())
+                .stderr(std::process::Stdio::inherit())
+                .kill_on_drop(true)
+                .spawn()
+                .map_err(|e| anyhow::anyhow!("failed to run `{}`: {}", via, e))?;
+            let stdin = child.stdin.take().expect("stdin is piped");
+            let stdout = child.stdout.take().expect("stdout is piped");
+            Ok((Box::new(stdout), Box::new(stdin), Some(child)))
+        }
+        Connection::Tcp {
+            addr,
+            token,
+            tls_cert,
+        } => {
+            let (reader, writer) =
+                pane_daemon::server::tcp::connect(&addr, &token, tls_cert.as_deref()).await?;
+            Ok((reader, writer, None))
+        }
+    }
+}
+
 /// Send a client request using length-prefixed framing on the write half.
-async fn send_request(
-    writer: &mut tokio::net::unix::OwnedWriteHalf,
-    request: &ClientRequest,
-) -> Result<()> {
-    let json = serde_json::to_vec(request)?;
-    let len: u32 = json.len().try_into().map_err(|_| {
-        anyhow::anyhow!("request too large: {} bytes", json.len())
-    })?;
-    writer.write_all(&len.to_be_bytes()).await?;
-    writer.write_all(&json).await?;
-    writer.flush().await?;
+async fn send_request(writer: &mut ServerWriter, request: &ClientRequest) -> Result<()> {
+    let body = framing::encode(request, writer.encoding)?;
+    framing::write_frame(&mut writer.stream, &body).await?;
     Ok(())
 }
 
@@ -2708,6 +3553,7 @@ fn action_to_command(action: &Action) -> Option<String> {
         | Action::CopyMode
         | Action::CommandPalette
         | Action::PasteClipboard
+        | Action::ChooseBuffer
         | Action::EnterInteract
         | Action::EnterNormal
         | Action::Detach
@@ -2747,6 +3593,14 @@ fn titlecase_name(name: &str) -> String {
         .join(" ")
 }
 
+/// Keep the mirrored scrollback within the same memory budget the daemon
+/// enforces, trimming the oldest lines of the largest history first.
+fn enforce_scrollback_budget(screens: &mut HashMap<TabId, vt100::Parser>, budget: Option<usize>) {
+    if let Some(budget) = budget {
+        vt100::trim_scrollback_to_budget(screens.values_mut().map(|p| p.screen_mut()), budget);
+    }
+}
+
 fn print_detach_summary(state: &RenderState) {
     let total_tabs: usize = state.workspaces.iter()
         .flat_map(|ws| &ws.groups)
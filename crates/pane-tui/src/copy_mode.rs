@@ -1,4 +1,8 @@
+use std::collections::HashMap;
+
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use pane_protocol::config::CopyModeKeys;
+use pane_protocol::layout::viewport_origin;
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum SelectionMode {
@@ -14,8 +18,45 @@ pub enum CopyModeAction {
     Exit,
 }
 
+/// `f`/`F`/`t`/`T`: find a character on the current line.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum FindKind {
+    Forward,
+    Backward,
+    TillForward,
+    TillBackward,
+}
+
+impl FindKind {
+    fn reversed(self) -> Self {
+        match self {
+            FindKind::Forward => FindKind::Backward,
+            FindKind::Backward => FindKind::Forward,
+            FindKind::TillForward => FindKind::TillBackward,
+            FindKind::TillBackward => FindKind::TillForward,
+        }
+    }
+}
+
+/// A vi command waiting for its next key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Pending {
+    None,
+    G,
+    Find(FindKind),
+    SetMark,
+    /// `'` jumps to the mark's line, `` ` `` to its exact position.
+    JumpMark {
+        exact: bool,
+    },
+    TextObject {
+        inner: bool,
+    },
+}
+
 #[allow(dead_code)]
 pub struct CopyModeState {
+    /// Cursor row, counted from the oldest line in the scrollback.
     pub cursor_row: usize,
     pub cursor_col: usize,
     pub selection_start: Option<(usize, usize)>,
@@ -23,9 +64,30 @@ pub struct CopyModeState {
     pub search_query: String,
     pub search_matches: Vec<(usize, usize, usize)>, // (row, col_start, col_end)
     pub search_active: bool,
+    /// Direction of the last search: `/` searches forward, `?` backward.
+    pub search_forward: bool,
+    /// Cursor position when the search prompt was opened, restored on Esc.
+    search_origin: Option<(usize, usize)>,
+    /// Text of every row, read once while a query is typed instead of on
+    /// each keystroke.
+    search_lines: Vec<String>,
+    /// The query `search_matches` were found for.
+    searched_query: String,
     pub screen_rows: usize,
     pub screen_cols: usize,
-    pub scroll_offset: usize,
+    /// First history row shown in the viewport.
+    pub view_top: usize,
+    pub keys: CopyModeKeys,
+    /// Count typed before a vi motion (`5j`).
+    count: Option<usize>,
+    pending: Pending,
+    last_find: Option<(FindKind, char)>,
+    marks: HashMap<char, (usize, usize)>,
+    /// First and last cell of what a mouse selection started on: a cell,
+    /// a word or a line, depending on the number of clicks. `None` once a
+    /// selection is started from the keyboard.
+    mouse_anchor: Option<((usize, usize), (usize, usize))>,
+    mouse_clicks: u8,
 }
 
 impl CopyModeState {
@@ -43,18 +105,89 @@ impl CopyModeState {
             search_query: String::new(),
             search_matches: Vec::new(),
             search_active: false,
+            search_forward: true,
+            search_origin: None,
+            search_lines: Vec::new(),
+            searched_query: String::new(),
             screen_rows,
             screen_cols,
-            scroll_offset: 0,
+            view_top: 0,
+            keys: CopyModeKeys::Vi,
+            count: None,
+            pending: Pending::None,
+            last_find: None,
+            marks: HashMap::new(),
+            mouse_anchor: None,
+            mouse_clicks: 0,
         }
     }
 
+    pub fn with_keys(mut self, keys: CopyModeKeys) -> Self {
+        self.keys = keys;
+        self
+    }
+
+    /// Start copy mode on the live screen, with the cursor and viewport
+    /// placed below the scrollback history.
+    pub fn for_screen(screen: &vt100::Screen) -> Self {
+        let (cursor_row, cursor_col) = screen.cursor_position();
+        let (rows, cols) = screen.size();
+        let history = screen.history_len();
+        let mut state = Self::new(
+            rows as usize,
+            cols as usize,
+            history + cursor_row as usize,
+            cursor_col as usize,
+        );
+        state.view_top = history;
+        state
+    }
+
     pub fn handle_key(&mut self, key: KeyEvent, screen: &vt100::Screen) -> CopyModeAction {
-        if self.search_active {
-            return self.handle_search_key(key, screen);
-        }
+        let action = if self.search_active {
+            self.handle_search_key(key, screen)
+        } else {
+            match self.keys {
+                CopyModeKeys::Vi => self.handle_vi_key(key, screen),
+                CopyModeKeys::Emacs => self.handle_emacs_key(key, screen),
+            }
+        };
+        self.scroll_to_cursor(screen);
+        action
+    }
 
+    fn handle_vi_key(&mut self, key: KeyEvent, screen: &vt100::Screen) -> CopyModeAction {
         let mods = key.modifiers;
+        let plain = mods.is_empty() || mods == KeyModifiers::SHIFT;
+
+        if self.pending != Pending::None {
+            let pending = std::mem::replace(&mut self.pending, Pending::None);
+            let count = self.take_count();
+            if let KeyCode::Char(c) = key.code {
+                if plain {
+                    self.handle_pending(pending, c, count, screen);
+                }
+            }
+            return CopyModeAction::None;
+        }
+
+        // Counts: a leading 0 is the line-start motion, not a digit
+        if let KeyCode::Char(c @ '0'..='9') = key.code {
+            if mods.is_empty() && (c != '0' || self.count.is_some()) {
+                let digit = c.to_digit(10).unwrap_or(0) as usize;
+                let count = self
+                    .count
+                    .unwrap_or(0)
+                    .saturating_mul(10)
+                    .saturating_add(digit);
+                self.count = Some(count.min(MAX_COUNT));
+                return CopyModeAction::None;
+            }
+        }
+
+        let explicit_count = self.count.take();
+        let count = explicit_count.unwrap_or(1);
+
         match key.code {
             KeyCode::Esc => {
                 if self.selection_mode != SelectionMode::None {
@@ -69,29 +202,47 @@ impl CopyModeState {
 
             // Cursor movement
             KeyCode::Char('h') | KeyCode::Left if mods.is_empty() => {
-                self.move_left();
+                for _ in 0..count {
+                    self.move_left();
+                }
                 CopyModeAction::None
             }
             KeyCode::Char('j') | KeyCode::Down if mods.is_empty() => {
-                self.move_down(screen);
+                for _ in 0..count {
+                    self.move_down(screen);
+                }
                 CopyModeAction::None
             }
             KeyCode::Char('k') | KeyCode::Up if mods.is_empty() => {
-                self.move_up();
+                for _ in 0..count {
+                    self.move_up();
+                }
                 CopyModeAction::None
             }
             KeyCode::Char('l') | KeyCode::Right if mods.is_empty() => {
-                self.move_right(screen);
+                for _ in 0..count {
+                    self.move_right(screen);
+                }
                 CopyModeAction::None
             }
 
             // Word movement
             KeyCode::Char('w') if mods.is_empty() => {
-                self.move_word_forward(screen);
+                for _ in 0..count {
+                    self.move_word_forward(screen);
+                }
                 CopyModeAction::None
             }
             KeyCode::Char('b') if mods.is_empty() => {
-                self.move_word_backward(screen);
+                for _ in 0..count {
+                    self.move_word_backward(screen);
+                }
+                CopyModeAction::None
+            }
+            KeyCode::Char('e') if mods.is_empty() => {
+                for _ in 0..count {
+                    self.move_word_end(screen);
+                }
                 CopyModeAction::None
             }
 
@@ -100,34 +251,129 @@ impl CopyModeState {
                 self.cursor_col = 0;
                 CopyModeAction::None
             }
-            KeyCode::Char('$') if mods.is_empty() || mods == KeyModifiers::SHIFT => {
+            KeyCode::Char('^') if plain => {
+                self.cursor_col = self.first_non_blank(screen, self.cursor_row);
+                CopyModeAction::None
+            }
+            KeyCode::Char('$') if plain => {
+                for _ in 1..count {
+                    self.move_down(screen);
+                }
                 self.move_to_line_end(screen);
                 CopyModeAction::None
             }
 
-            // Top/bottom
+            // Top/bottom: `gg`, `G`, or a line number with a count
             KeyCode::Char('g') if mods.is_empty() => {
-                self.cursor_row = 0;
-                self.cursor_col = 0;
+                self.count = explicit_count;
+                self.pending = Pending::G;
                 CopyModeAction::None
             }
-            KeyCode::Char('G') if mods.is_empty() || mods == KeyModifiers::SHIFT => {
+            KeyCode::Char('G') if plain => {
                 let max_row = self.max_row(screen);
-                self.cursor_row = max_row;
+                self.cursor_row = explicit_count.map_or(max_row, |n| (n - 1).min(max_row));
                 self.cursor_col = 0;
                 CopyModeAction::None
             }
 
-            // Half-page movement
+            // Viewport top/middle/bottom
+            KeyCode::Char('H') if plain => {
+                let last = self.last_visible_row(screen);
+                self.cursor_row = (self.view_top + count - 1).min(last);
+                self.cursor_col = self.first_non_blank(screen, self.cursor_row);
+                CopyModeAction::None
+            }
+            KeyCode::Char('M') if plain => {
+                let last = self.last_visible_row(screen);
+                self.cursor_row = self.view_top + (last - self.view_top) / 2;
+                self.cursor_col = self.first_non_blank(screen, self.cursor_row);
+                CopyModeAction::None
+            }
+            KeyCode::Char('L') if plain => {
+                let last = self.last_visible_row(screen);
+                self.cursor_row = last.saturating_sub(count - 1).max(self.view_top);
+                self.cursor_col = self.first_non_blank(screen, self.cursor_row);
+                CopyModeAction::None
+            }
+
+            // Paragraphs and brackets
+            KeyCode::Char('}') if plain => {
+                for _ in 0..count {
+                    self.move_paragraph_forward(screen);
+                }
+                CopyModeAction::None
+            }
+            KeyCode::Char('{') if plain => {
+                for _ in 0..count {
+                    self.move_paragraph_backward(screen);
+                }
+                CopyModeAction::None
+            }
+            KeyCode::Char('%') if plain => {
+                if let Some((row, col)) = self.matching_bracket(screen) {
+                    self.cursor_row = row;
+                    self.cursor_col = col;
+                }
+                CopyModeAction::None
+            }
+
+            // Find a character on the line
+            KeyCode::Char(c @ ('f' | 'F' | 't' | 'T')) if plain => {
+                let kind = match c {
+                    'f' => FindKind::Forward,
+                    'F' => FindKind::Backward,
+                    't' => FindKind::TillForward,
+                    _ => FindKind::TillBackward,
+                };
+                self.count = explicit_count;
+                self.pending = Pending::Find(kind);
+                CopyModeAction::None
+            }
+            KeyCode::Char(';') if mods.is_empty() => {
+                if let Some((kind, c)) = self.last_find {
+                    self.find_char(screen, kind, c, count, true);
+                }
+                CopyModeAction::None
+            }
+            KeyCode::Char(',') if mods.is_empty() => {
+                if let Some((kind, c)) = self.last_find {
+                    self.find_char(screen, kind.reversed(), c, count, true);
+                }
+                CopyModeAction::None
+            }
+
+            // Marks
+            KeyCode::Char('m') if mods.is_empty() => {
+                self.pending = Pending::SetMark;
+                CopyModeAction::None
+            }
+            KeyCode::Char('\'') if plain => {
+                self.pending = Pending::JumpMark { exact: false };
+                CopyModeAction::None
+            }
+            KeyCode::Char('`') if plain => {
+                self.pending = Pending::JumpMark { exact: true };
+                CopyModeAction::None
+            }
+
+            // Half-page and full-page movement
             KeyCode::Char('u') if mods.contains(KeyModifiers::CONTROL) => {
                 let half = self.screen_rows / 2;
-                self.cursor_row = self.cursor_row.saturating_sub(half);
+                self.cursor_row = self.cursor_row.saturating_sub(half * count);
                 CopyModeAction::None
             }
             KeyCode::Char('d') if mods.contains(KeyModifiers::CONTROL) => {
                 let half = self.screen_rows / 2;
                 let max_row = self.max_row(screen);
-                self.cursor_row = (self.cursor_row + half).min(max_row);
+                self.cursor_row = (self.cursor_row + half * count).min(max_row);
+                CopyModeAction::None
+            }
+            KeyCode::Char('b') if mods.contains(KeyModifiers::CONTROL) => {
+                self.page_up(count);
+                CopyModeAction::None
+            }
+            KeyCode::Char('f') if mods.contains(KeyModifiers::CONTROL) => {
+                self.page_down(screen, count);
                 CopyModeAction::None
             }
 
@@ -136,7 +382,7 @@ impl CopyModeState {
                 self.toggle_selection(SelectionMode::Char);
                 CopyModeAction::None
             }
-            KeyCode::Char('V') if mods.is_empty() || mods == KeyModifiers::SHIFT => {
+            KeyCode::Char('V') if plain => {
                 self.toggle_selection(SelectionMode::Line);
                 CopyModeAction::None
             }
@@ -144,30 +390,228 @@ impl CopyModeState {
                 self.toggle_selection(SelectionMode::Block);
                 CopyModeAction::None
             }
+            KeyCode::Char('o') if mods.is_empty() => {
+                self.swap_selection_ends();
+                CopyModeAction::None
+            }
+            KeyCode::Char(c @ ('i' | 'a')) if mods.is_empty() => {
+                self.pending = Pending::TextObject { inner: c == 'i' };
+                CopyModeAction::None
+            }
 
             // Yank
-            KeyCode::Char('y') if mods.is_empty() => {
-                if self.selection_mode != SelectionMode::None {
-                    let text = self.selected_text(screen);
-                    if !text.is_empty() {
-                        return CopyModeAction::YankSelection(text);
+            KeyCode::Char('y') if mods.is_empty() => self.yank(screen),
+
+            // Search
+            KeyCode::Char('/') if mods.is_empty() => {
+                self.start_search(true);
+                CopyModeAction::None
+            }
+            KeyCode::Char('?') if plain => {
+                self.start_search(false);
+                CopyModeAction::None
+            }
+            KeyCode::Char('n') if mods.is_empty() => {
+                for _ in 0..count {
+                    self.repeat_search(self.search_forward);
+                }
+                CopyModeAction::None
+            }
+            KeyCode::Char('N') if plain => {
+                for _ in 0..count {
+                    self.repeat_search(!self.search_forward);
+                }
+                CopyModeAction::None
+            }
+
+            _ => CopyModeAction::None,
+        }
+    }
+
+    /// Complete a multi-key vi command with its final character.
+    fn handle_pending(&mut self, pending: Pending, c: char, count: usize, screen: &vt100::Screen) {
+        match pending {
+            Pending::None => {}
+            Pending::G => {
+                if c == 'g' {
+                    self.cursor_row = (count - 1).min(self.max_row(screen));
+                    self.cursor_col = 0;
+                }
+            }
+            Pending::Find(kind) => {
+                self.last_find = Some((kind, c));
+                self.find_char(screen, kind, c, count, false);
+            }
+            Pending::SetMark => {
+                self.marks.insert(c, (self.cursor_row, self.cursor_col));
+            }
+            Pending::JumpMark { exact } => {
+                if let Some(&(row, col)) = self.marks.get(&c) {
+                    self.cursor_row = row.min(self.max_row(screen));
+                    self.cursor_col = if exact {
+                        col
+                    } else {
+                        self.first_non_blank(screen, self.cursor_row)
+                    };
+                }
+            }
+            Pending::TextObject { inner } => {
+                if let Some(((sr, sc), (er, ec))) = self.text_object(screen, c, inner) {
+                    if self.selection_mode != SelectionMode::Char {
+                        self.selection_mode = SelectionMode::Char;
                     }
+                    self.selection_start = Some((sr, sc));
+                    self.mouse_anchor = None;
+                    self.cursor_row = er;
+                    self.cursor_col = ec;
                 }
+            }
+        }
+    }
+
+    /// Emacs-style bindings, following tmux's `mode-keys emacs` table.
+    fn handle_emacs_key(&mut self, key: KeyEvent, screen: &vt100::Screen) -> CopyModeAction {
+        let mods = key.modifiers;
+        let ctrl = mods.contains(KeyModifiers::CONTROL);
+        let alt = mods.contains(KeyModifiers::ALT);
+
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') if !ctrl && !alt => CopyModeAction::Exit,
+            KeyCode::Char('g') if ctrl => {
+                if self.selection_mode != SelectionMode::None {
+                    self.selection_start = None;
+                    self.selection_mode = SelectionMode::None;
+                    CopyModeAction::None
+                } else {
+                    CopyModeAction::Exit
+                }
+            }
+
+            KeyCode::Char('b') if ctrl => {
+                self.move_left();
+                CopyModeAction::None
+            }
+            KeyCode::Left => {
+                self.move_left();
+                CopyModeAction::None
+            }
+            KeyCode::Char('f') if ctrl => {
+                self.move_right(screen);
+                CopyModeAction::None
+            }
+            KeyCode::Right => {
+                self.move_right(screen);
+                CopyModeAction::None
+            }
+            KeyCode::Char('p') if ctrl => {
+                self.move_up();
+                CopyModeAction::None
+            }
+            KeyCode::Up => {
+                self.move_up();
+                CopyModeAction::None
+            }
+            KeyCode::Char('n') if ctrl => {
+                self.move_down(screen);
+                CopyModeAction::None
+            }
+            KeyCode::Down => {
+                self.move_down(screen);
+                CopyModeAction::None
+            }
+
+            KeyCode::Char('f') if alt => {
+                self.move_word_end(screen);
+                self.move_right(screen);
+                CopyModeAction::None
+            }
+            KeyCode::Char('b') if alt => {
+                self.move_word_backward(screen);
+                CopyModeAction::None
+            }
+            KeyCode::Char('a') if ctrl => {
+                self.cursor_col = 0;
+                CopyModeAction::None
+            }
+            KeyCode::Home => {
+                self.cursor_col = 0;
+                CopyModeAction::None
+            }
+            KeyCode::Char('e') if ctrl => {
+                self.move_to_line_end(screen);
+                CopyModeAction::None
+            }
+            KeyCode::End => {
+                self.move_to_line_end(screen);
+                CopyModeAction::None
+            }
+            KeyCode::Char('m') if alt => {
+                self.cursor_col = self.first_non_blank(screen, self.cursor_row);
+                CopyModeAction::None
+            }
+            KeyCode::Char('<') if alt => {
+                self.cursor_row = 0;
+                self.cursor_col = 0;
+                CopyModeAction::None
+            }
+            KeyCode::Char('>') if alt => {
+                self.cursor_row = self.max_row(screen);
+                self.cursor_col = 0;
+                CopyModeAction::None
+            }
+            KeyCode::Char('v') if alt => {
+                self.page_up(1);
+                CopyModeAction::None
+            }
+            KeyCode::PageUp => {
+                self.page_up(1);
+                CopyModeAction::None
+            }
+            KeyCode::Char('v') if ctrl => {
+                self.page_down(screen, 1);
+                CopyModeAction::None
+            }
+            KeyCode::PageDown => {
+                self.page_down(screen, 1);
+                CopyModeAction::None
+            }
+            KeyCode::Char('}') if alt => {
+                self.move_paragraph_forward(screen);
+                CopyModeAction::None
+            }
+            KeyCode::Char('{') if alt => {
+                self.move_paragraph_backward(screen);
+                CopyModeAction::None
+            }
+
+            // Mark and copy
+            KeyCode::Char(' ') if ctrl => {
+                self.selection_mode = SelectionMode::Char;
+                self.selection_start = Some((self.cursor_row, self.cursor_col));
+                self.mouse_anchor = None;
+                CopyModeAction::None
+            }
+            KeyCode::Char('x') if alt => {
+                self.swap_selection_ends();
                 CopyModeAction::None
             }
+            KeyCode::Char('w') if alt || ctrl => self.yank(screen),
 
             // Search
-            KeyCode::Char('/') if mods.is_empty() => {
-                self.search_active = true;
-                self.search_query.clear();
+            KeyCode::Char('s') if ctrl => {
+                self.start_search(true);
                 CopyModeAction::None
             }
-            KeyCode::Char('n') if mods.is_empty() => {
-                self.next_match();
+            KeyCode::Char('r') if ctrl => {
+                self.start_search(false);
+                CopyModeAction::None
+            }
+            KeyCode::Char('n') if !ctrl && !alt => {
+                self.repeat_search(self.search_forward);
                 CopyModeAction::None
             }
-            KeyCode::Char('N') if mods.is_empty() || mods == KeyModifiers::SHIFT => {
-                self.prev_match();
+            KeyCode::Char('N') if !ctrl && !alt => {
+                self.repeat_search(!self.search_forward);
                 CopyModeAction::None
             }
 
@@ -175,32 +619,95 @@ impl CopyModeState {
         }
     }
 
+    fn take_count(&mut self) -> usize {
+        self.count.take().unwrap_or(1)
+    }
+
+    fn yank(&self, screen: &vt100::Screen) -> CopyModeAction {
+        if self.selection_mode != SelectionMode::None {
+            let text = self.selected_text(screen);
+            if !text.is_empty() {
+                return CopyModeAction::YankSelection(text);
+            }
+        }
+        CopyModeAction::None
+    }
+
+    fn repeat_search(&mut self, forward: bool) {
+        if forward {
+            self.next_match();
+        } else {
+            self.prev_match();
+        }
+    }
+
     fn handle_search_key(&mut self, key: KeyEvent, screen: &vt100::Screen) -> CopyModeAction {
         match key.code {
             KeyCode::Esc => {
+                if let Some((row, col)) = self.search_origin.take() {
+                    self.cursor_row = row;
+                    self.cursor_col = col;
+                }
                 self.search_active = false;
                 self.search_query.clear();
                 self.search_matches.clear();
+                self.search_lines.clear();
                 CopyModeAction::None
             }
             KeyCode::Enter => {
                 self.search_active = false;
                 self.perform_search(screen);
-                self.next_match();
+                self.jump_from_origin();
+                self.search_origin = None;
                 CopyModeAction::None
             }
             KeyCode::Backspace => {
                 self.search_query.pop();
+                self.perform_search(screen);
+                self.jump_from_origin();
                 CopyModeAction::None
             }
             KeyCode::Char(c) => {
                 self.search_query.push(c);
+                self.perform_search(screen);
+                self.jump_from_origin();
                 CopyModeAction::None
             }
             _ => CopyModeAction::None,
         }
     }
 
+    fn start_search(&mut self, forward: bool) {
+        self.search_active = true;
+        self.search_forward = forward;
+        self.search_query.clear();
+        self.search_matches.clear();
+        self.search_lines.clear();
+        self.search_origin = Some((self.cursor_row, self.cursor_col));
+    }
+
+    /// Move the cursor to the nearest match from where the search started,
+    /// so matches are previewed while the query is being typed.
+    fn jump_from_origin(&mut self) {
+        if let Some((row, col)) = self.search_origin {
+            self.cursor_row = row;
+            self.cursor_col = col;
+        }
+        self.repeat_search(self.search_forward);
+    }
+
+    /// Scroll the viewport so the cursor stays on screen.
+    fn scroll_to_cursor(&mut self, screen: &vt100::Screen) {
+        let rows = self.screen_rows.max(1);
+        let max_top = (self.max_row(screen) + 1).saturating_sub(rows);
+        if self.cursor_row < self.view_top {
+            self.view_top = self.cursor_row;
+        } else if self.cursor_row >= self.view_top + rows {
+            self.view_top = self.cursor_row + 1 - rows;
+        }
+        self.view_top = self.view_top.min(max_top);
+    }
+
     fn move_left(&mut self) {
         self.cursor_col = self.cursor_col.saturating_sub(1);
     }
@@ -229,8 +736,7 @@ impl CopyModeState {
 
     fn move_word_forward(&mut self, screen: &vt100::Screen) {
         let max_row = self.max_row(screen);
-        let line = self.get_line_text(screen, self.cursor_row);
-        let chars: Vec<char> = line.chars().collect();
+        let chars = self.row_chars(screen, self.cursor_row);
         let mut col = self.cursor_col;
         let mut row = self.cursor_row;
 
@@ -247,8 +753,7 @@ impl CopyModeState {
             // Move to next line start
             row += 1;
             col = 0;
-            let next_line = self.get_line_text(screen, row);
-            let next_chars: Vec<char> = next_line.chars().collect();
+            let next_chars = self.row_chars(screen, row);
             while col < next_chars.len() && next_chars[col].is_whitespace() {
                 col += 1;
             }
@@ -259,21 +764,21 @@ impl CopyModeState {
     }
 
     fn move_word_backward(&mut self, screen: &vt100::Screen) {
-        let line = self.get_line_text(screen, self.cursor_row);
-        let chars: Vec<char> = line.chars().collect();
         let mut col = self.cursor_col;
         let mut row = self.cursor_row;
 
         if col == 0 && row > 0 {
             row -= 1;
-            let prev_line = self.get_line_text(screen, row);
-            col = prev_line.trim_end().len();
+            col = self
+                .row_chars(screen, row)
+                .iter()
+                .rposition(|c| !c.is_whitespace())
+                .map_or(0, |end| end + 1);
         }
 
         if col > 0 {
             col -= 1;
-            let ln = self.get_line_text(screen, row);
-            let cs: Vec<char> = ln.chars().collect();
+            let cs = self.row_chars(screen, row);
             // Skip spaces
             while col > 0 && col < cs.len() && cs[col].is_whitespace() {
                 col -= 1;
@@ -283,11 +788,335 @@ impl CopyModeState {
                 col -= 1;
             }
         }
-        let _ = chars; // used implicitly via col bounds
         self.cursor_row = row;
         self.cursor_col = col;
     }
 
+    fn move_word_end(&mut self, screen: &vt100::Screen) {
+        let max_row = self.max_row(screen);
+        let mut row = self.cursor_row;
+        let mut col = self.cursor_col + 1;
+        loop {
+            let chars = self.row_chars(screen, row);
+            // Skip spaces, then stop on the last char of the next word
+            while col < chars.len() && chars[col].is_whitespace() {
+                col += 1;
+            }
+            if col < chars.len() {
+                let class = char_class(chars[col]);
+                while col + 1 < chars.len() && char_class(chars[col + 1]) == class {
+                    col += 1;
+                }
+                self.cursor_row = row;
+                self.cursor_col = col;
+                return;
+            }
+            if row >= max_row {
+                return;
+            }
+            row += 1;
+            col = 0;
+        }
+    }
+
+    fn first_non_blank(&self, screen: &vt100::Screen, row: usize) -> usize {
+        self.row_chars(screen, row)
+            .iter()
+            .position(|c| !c.is_whitespace())
+            .unwrap_or(0)
+    }
+
+    fn last_visible_row(&self, screen: &vt100::Screen) -> usize {
+        (self.view_top + self.screen_rows.max(1) - 1).min(self.max_row(screen))
+    }
+
+    fn page_up(&mut self, count: usize) {
+        let page = self.screen_rows.max(1) * count;
+        self.cursor_row = self.cursor_row.saturating_sub(page);
+        self.view_top = self.view_top.saturating_sub(page);
+    }
+
+    fn page_down(&mut self, screen: &vt100::Screen, count: usize) {
+        let page = self.screen_rows.max(1) * count;
+        self.cursor_row = (self.cursor_row + page).min(self.max_row(screen));
+        self.view_top += page;
+    }
+
+    fn is_blank_row(&self, screen: &vt100::Screen, row: usize) -> bool {
+        self.get_line_text(screen, row).trim().is_empty()
+    }
+
+    /// `}`: move to the next blank line after the current paragraph.
+    fn move_paragraph_forward(&mut self, screen: &vt100::Screen) {
+        let max_row = self.max_row(screen);
+        let mut row = self.cursor_row;
+        while row < max_row && self.is_blank_row(screen, row) {
+            row += 1;
+        }
+        while row < max_row && !self.is_blank_row(screen, row) {
+            row += 1;
+        }
+        self.cursor_row = row;
+        self.cursor_col = 0;
+    }
+
+    /// `{`: move to the previous blank line before the current paragraph.
+    fn move_paragraph_backward(&mut self, screen: &vt100::Screen) {
+        let mut row = self.cursor_row;
+        while row > 0 && self.is_blank_row(screen, row) {
+            row -= 1;
+        }
+        while row > 0 && !self.is_blank_row(screen, row) {
+            row -= 1;
+        }
+        self.cursor_row = row;
+        self.cursor_col = 0;
+    }
+
+    /// `f`/`F`/`t`/`T` and their `;`/`,` repeats. A repeated `t`/`T` skips
+    /// the character directly next to the cursor so it doesn't get stuck.
+    fn find_char(
+        &mut self,
+        screen: &vt100::Screen,
+        kind: FindKind,
+        target: char,
+        count: usize,
+        repeat: bool,
+    ) {
+        let chars = self.row_chars(screen, self.cursor_row);
+        let mut col = self.cursor_col;
+        for _ in 0..count {
+            let found = match kind {
+                FindKind::Forward | FindKind::TillForward => {
+                    let skip = if kind == FindKind::TillForward && repeat {
+                        2
+                    } else {
+                        1
+                    };
+                    (col + skip..chars.len()).find(|&i| chars[i] == target)
+                }
+                FindKind::Backward | FindKind::TillBackward => {
+                    let skip = if kind == FindKind::TillBackward && repeat {
+                        2
+                    } else {
+                        1
+                    };
+                    (0..col.saturating_sub(skip - 1))
+                        .rev()
+                        .find(|&i| chars[i] == target)
+                }
+            };
+            let Some(i) = found else {
+                return;
+            };
+            col = i;
+        }
+        self.cursor_col = match kind {
+            FindKind::TillForward => col - 1,
+            FindKind::TillBackward => col + 1,
+            _ => col,
+        };
+    }
+
+    /// `%`: find the first bracket at or after the cursor on this line and
+    /// return the position of its partner.
+    fn matching_bracket(&self, screen: &vt100::Screen) -> Option<(usize, usize)> {
+        let chars = self.row_chars(screen, self.cursor_row);
+        let (col, &c) = chars
+            .iter()
+            .enumerate()
+            .skip(self.cursor_col)
+            .find(|(_, c)| percent_pair(**c).is_some())?;
+        let (open, close, forward) = percent_pair(c)?;
+        if forward {
+            self.scan_forward_for(screen, (self.cursor_row, col + 1), open, close)
+        } else {
+            self.scan_backward_for(screen, (self.cursor_row, col), open, close)
+        }
+    }
+
+    /// Scan forward from `from` for the `close` that balances one `open`.
+    fn scan_forward_for(
+        &self,
+        screen: &vt100::Screen,
+        from: (usize, usize),
+        open: char,
+        close: char,
+    ) -> Option<(usize, usize)> {
+        let mut depth = 0usize;
+        let (mut row, mut col) = from;
+        let max_row = self.max_row(screen);
+        loop {
+            let chars = self.row_chars(screen, row);
+            while col < chars.len() {
+                if chars[col] == open {
+                    depth += 1;
+                } else if chars[col] == close {
+                    if depth == 0 {
+                        return Some((row, col));
+                    }
+                    depth -= 1;
+                }
+                col += 1;
+            }
+            if row >= max_row {
+                return None;
+            }
+            row += 1;
+            col = 0;
+        }
+    }
+
+    /// Scan backward from just before `from` for the `open` that balances
+    /// one `close`.
+    fn scan_backward_for(
+        &self,
+        screen: &vt100::Screen,
+        from: (usize, usize),
+        open: char,
+        close: char,
+    ) -> Option<(usize, usize)> {
+        let mut depth = 0usize;
+        let (mut row, mut col) = from;
+        loop {
+            let chars = self.row_chars(screen, row);
+            let mut i = col.min(chars.len());
+            while i > 0 {
+                i -= 1;
+                if chars[i] == close {
+                    depth += 1;
+                } else if chars[i] == open {
+                    if depth == 0 {
+                        return Some((row, i));
+                    }
+                    depth -= 1;
+                }
+            }
+            if row == 0 {
+                return None;
+            }
+            row -= 1;
+            col = usize::MAX;
+        }
+    }
+
+    /// Range covered by a text object (`iw`, `aw`, `i"`, `a(`, ...), as
+    /// inclusive start and end positions.
+    fn text_object(
+        &self,
+        screen: &vt100::Screen,
+        object: char,
+        inner: bool,
+    ) -> Option<((usize, usize), (usize, usize))> {
+        let row = self.cursor_row;
+        match object {
+            'w' | 'W' => {
+                let chars = self.row_chars(screen, row);
+                let col = self.cursor_col.min(chars.len().checked_sub(1)?);
+                let same = |a: char, b: char| {
+                    if object == 'W' {
+                        a.is_whitespace() == b.is_whitespace()
+                    } else {
+                        char_class(a) == char_class(b)
+                    }
+                };
+                let mut start = col;
+                while start > 0 && same(chars[start - 1], chars[col]) {
+                    start -= 1;
+                }
+                let mut end = col;
+                while end + 1 < chars.len() && same(chars[end + 1], chars[col]) {
+                    end += 1;
+                }
+                if !inner {
+                    // Include trailing whitespace, or leading if there is none
+                    let mut trail = end;
+                    while trail + 1 < chars.len() && chars[trail + 1].is_whitespace() {
+                        trail += 1;
+                    }
+                    if trail > end {
+                        end = trail;
+                    } else {
+                        while start > 0 && chars[start - 1].is_whitespace() {
+                            start -= 1;
+                        }
+                    }
+                }
+                Some(((row, start), (row, end)))
+            }
+            '"' | '\'' | '`' => {
+                let chars = self.row_chars(screen, row);
+                let col = self.cursor_col.min(chars.len().checked_sub(1)?);
+                let quotes: Vec<usize> = (0..chars.len()).filter(|&i| chars[i] == object).collect();
+                // Pair quotes left to right and pick the pair around the
+                // cursor, or the first pair after it
+                let (open, close) = quotes
+                    .chunks_exact(2)
+                    .map(|p| (p[0], p[1]))
+                    .find(|&(_, close)| close >= col)?;
+                if inner {
+                    if close == open + 1 {
+                        return None;
+                    }
+                    Some(((row, open + 1), (row, close - 1)))
+                } else {
+                    Some(((row, open), (row, close)))
+                }
+            }
+            _ => {
+                // `b` and `B` are vim's aliases for `()` and `{}`
+                let object = match object {
+                    'b' => '(',
+                    'B' => '{',
+                    c => c,
+                };
+                let (open, close, _) = bracket_pair(object)?;
+                let chars = self.row_chars(screen, row);
+                let on_open = chars.get(self.cursor_col) == Some(&open);
+                let start = if on_open {
+                    (row, self.cursor_col)
+                } else {
+                    let from = if chars.get(self.cursor_col) == Some(&close) {
+                        self.cursor_col
+                    } else {
+                        self.cursor_col + 1
+                    };
+                    self.scan_backward_for(screen, (row, from), open, close)?
+                };
+                let end = self.scan_forward_for(screen, (start.0, start.1 + 1), open, close)?;
+                if inner {
+                    let inner_start = if start.1 + 1 < self.row_chars(screen, start.0).len() {
+                        (start.0, start.1 + 1)
+                    } else {
+                        (start.0 + 1, 0)
+                    };
+                    let inner_end = if end.1 > 0 {
+                        (end.0, end.1 - 1)
+                    } else {
+                        (
+                            end.0.saturating_sub(1),
+                            self.line_end_col(screen, end.0.saturating_sub(1)),
+                        )
+                    };
+                    if inner_start > inner_end {
+                        return None;
+                    }
+                    Some((inner_start, inner_end))
+                } else {
+                    Some((start, end))
+                }
+            }
+        }
+    }
+
+    /// `o`: move the cursor to the other end of the selection.
+    fn swap_selection_ends(&mut self) {
+        if let Some(start) = self.selection_start {
+            self.selection_start = Some((self.cursor_row, self.cursor_col));
+            (self.cursor_row, self.cursor_col) = start;
+        }
+    }
+
     fn toggle_selection(&mut self, mode: SelectionMode) {
         if self.selection_mode == mode {
             self.selection_mode = SelectionMode::None;
@@ -295,9 +1124,92 @@ impl CopyModeState {
         } else {
             self.selection_mode = mode;
             self.selection_start = Some((self.cursor_row, self.cursor_col));
+            self.mouse_anchor = None;
+        }
+    }
+
+    /// Start a mouse selection at a cell of the viewport. One click places
+    /// the cursor, two select the word under it and three the line.
+    pub fn mouse_press(&mut self, row: u16, col: u16, clicks: u8, screen: &vt100::Screen) {
+        let cell = self.view_cell(i32::from(row), col, screen);
+        (self.cursor_row, self.cursor_col) = cell;
+        self.mouse_clicks = clicks.clamp(1, 3);
+        let anchor = match self.mouse_clicks {
+            1 => (cell, cell),
+            2 => self.text_object(screen, 'w', true).unwrap_or((cell, cell)),
+            _ => ((cell.0, 0), (cell.0, self.line_end_col(screen, cell.0))),
+        };
+        self.mouse_anchor = Some(anchor);
+        if self.mouse_clicks == 1 {
+            self.selection_start = None;
+            self.selection_mode = SelectionMode::None;
+        } else {
+            self.select_from_anchor(anchor, cell, screen);
         }
     }
 
+    /// Extend a mouse selection to a cell of the viewport. Rows above or
+    /// below it scroll the viewport, so repeating a drag past an edge keeps
+    /// scrolling.
+    pub fn mouse_drag(&mut self, row: i32, col: u16, screen: &vt100::Screen) {
+        let Some(anchor) = self.mouse_anchor else {
+            return;
+        };
+        let cell = self.view_cell(row, col, screen);
+        self.select_from_anchor(anchor, cell, screen);
+        self.scroll_to_cursor(screen);
+    }
+
+    /// Select from the anchor to `cell`, keeping the whole anchor selected
+    /// and extending by words or lines after a double or triple click.
+    fn select_from_anchor(
+        &mut self,
+        (first, last): ((usize, usize), (usize, usize)),
+        cell: (usize, usize),
+        screen: &vt100::Screen,
+    ) {
+        let backward = cell < first;
+        (self.cursor_row, self.cursor_col) = cell;
+        if self.mouse_clicks == 2 {
+            if let Some((start, end)) = self.text_object(screen, 'w', true) {
+                (self.cursor_row, self.cursor_col) = if backward { start } else { end };
+            }
+        }
+        self.selection_mode = if self.mouse_clicks == 3 {
+            SelectionMode::Line
+        } else {
+            SelectionMode::Char
+        };
+        self.selection_start = Some(if backward { last } else { first });
+    }
+
+    /// The viewport cell shown at the top left of a `view` (rows, columns)
+    /// smaller than the screen: wherever keeps the copy mode cursor in view.
+    pub fn view_origin(&self, view: (u16, u16)) -> (u16, u16) {
+        let clamp = |n: usize| u16::try_from(n).unwrap_or(u16::MAX);
+        viewport_origin(
+            (clamp(self.screen_rows), clamp(self.screen_cols)),
+            (
+                clamp(self.cursor_row.saturating_sub(self.view_top)),
+                clamp(self.cursor_col),
+            ),
+            view,
+        )
+    }
+
+    /// History cell shown at a viewport position, clamped to the screen;
+    /// rows outside the viewport give the row just beyond its edge.
+    fn view_cell(&self, row: i32, col: u16, screen: &vt100::Screen) -> (usize, usize) {
+        let row = if row < 0 {
+            self.view_top.saturating_sub(1)
+        } else {
+            let row = (row as usize).min(self.screen_rows.max(1));
+            self.view_top + row
+        };
+        let col = usize::from(col).min(self.screen_cols.saturating_sub(1));
+        (row.min(self.max_row(screen)), col)
+    }
+
     pub fn selected_text(&self, screen: &vt100::Screen) -> String {
         let (start_row, start_col) = match self.selection_start {
             Some(s) => s,
@@ -322,21 +1234,18 @@ impl CopyModeState {
         let mut result = String::new();
 
         for row in sr..=er {
-            let line = self.get_line_text(screen, row);
-            let chars: Vec<char> = line.chars().collect();
             let from = if row == sr { sc } else { 0 };
-            let to = if row == er {
-                (ec + 1).min(chars.len())
-            } else {
-                chars.len()
-            };
-            let segment: String = chars[from.min(chars.len())..to.min(chars.len())]
-                .iter()
-                .collect();
+            let to = if row == er { ec + 1 } else { usize::MAX };
+            let segment = self.cols_text(screen, row, from, to);
             if row > sr {
                 result.push('\n');
             }
-            result.push_str(&segment);
+            // Lines a mouse selection runs past end at their last character
+            result.push_str(if row < er && self.mouse_anchor.is_some() {
+                segment.trim_end()
+            } else {
+                &segment
+            });
         }
         result
     }
@@ -364,12 +1273,7 @@ impl CopyModeState {
         let (sr, sc, er, ec) = self.normalize_range(start_row, start_col);
         let mut lines = Vec::new();
         for row in sr..=er {
-            let line = self.get_line_text(screen, row);
-            let chars: Vec<char> = line.chars().collect();
-            let from = sc.min(chars.len());
-            let to = (ec + 1).min(chars.len());
-            let segment: String = chars[from..to].iter().collect();
-            lines.push(segment);
+            lines.push(self.cols_text(screen, row, sc, ec + 1));
         }
         lines.join("\n")
     }
@@ -378,33 +1282,108 @@ impl CopyModeState {
         if (start_row, start_col) <= (self.cursor_row, self.cursor_col) {
             (start_row, start_col, self.cursor_row, self.cursor_col)
         } else {
-            (self.cursor_row, self.cursor_col, start_row, start_col)
+            (self.cursor_row, self.cursor_col, start_row, start_col)
+        }
+    }
+
+    /// Find every match of the query in the scrollback and the screen.
+    ///
+    /// The query is a regex; if it does not compile it is matched literally.
+    /// Matching is case-insensitive unless the query contains an uppercase
+    /// letter (smart-case).
+    ///
+    /// While the query is being typed the rows are read once and reused, and
+    /// a literal query that extends the last one only rechecks the rows that
+    /// matched it, so each keystroke doesn't rescan the whole scrollback.
+    fn perform_search(&mut self, screen: &vt100::Screen) {
+        let previous = std::mem::take(&mut self.search_matches);
+        let previous_query = std::mem::replace(&mut self.searched_query, self.search_query.clone());
+        if !self.search_active {
+            self.search_lines.clear();
+        }
+        if self.search_query.is_empty() {
+            return;
+        }
+        let smart_case = !has_uppercase(&self.search_query);
+        let regex = regex::RegexBuilder::new(&self.search_query)
+            .case_insensitive(smart_case)
+            .build()
+            .or_else(|_| {
+                regex::RegexBuilder::new(&regex::escape(&self.search_query))
+                    .case_insensitive(smart_case)
+                    .build()
+            });
+        let Ok(regex) = regex else {
+            return;
+        };
+
+        if !self.search_active {
+            for row in 0..=self.max_row(screen) {
+                let line = self.get_line_text(screen, row);
+                self.push_matches(screen, &regex, row, &line);
+            }
+            return;
+        }
+        if self.search_lines.is_empty() {
+            self.search_lines = (0..=self.max_row(screen))
+                .map(|row| self.get_line_text(screen, row))
+                .collect();
+        }
+        let literal = regex::escape(&self.search_query) == self.search_query;
+        let rows: Vec<usize> = if literal
+            && !previous_query.is_empty()
+            && self.search_query.starts_with(&previous_query)
+        {
+            let mut rows: Vec<usize> = previous.iter().map(|m| m.0).collect();
+            rows.dedup();
+            rows
+        } else {
+            (0..self.search_lines.len()).collect()
+        };
+        let lines = std::mem::take(&mut self.search_lines);
+        for row in rows {
+            if let Some(line) = lines.get(row) {
+                self.push_matches(screen, &regex, row, line);
+            }
         }
+        self.search_lines = lines;
     }
 
-    fn perform_search(&mut self, screen: &vt100::Screen) {
-        self.search_matches.clear();
-        if self.search_query.is_empty() {
+    /// Add the matches in one row. Its column offsets are only worked out
+    /// for a row that matches.
+    fn push_matches(
+        &mut self,
+        screen: &vt100::Screen,
+        regex: &regex::Regex,
+        row: usize,
+        line: &str,
+    ) {
+        if !regex.is_match(line) {
             return;
         }
-        let query = &self.search_query;
-        let query_char_len = query.chars().count();
-        let rows = screen.size().0 as usize;
-        for row in 0..rows {
-            let line = self.get_line_text(screen, row);
-            let mut byte_start = 0;
-            while let Some(pos) = line[byte_start..].find(query) {
-                let match_byte = byte_start + pos;
-                // Convert byte offset to char index for column-based highlighting
-                let col_start = line[..match_byte].chars().count();
-                let col_end = col_start + query_char_len.saturating_sub(1);
-                self.search_matches.push((row, col_start, col_end));
-                // Advance past the first byte of the match
-                byte_start = match_byte + line[match_byte..].chars().next().map_or(1, |c| c.len_utf8());
+        let (_, col_offsets) = self.get_line_with_cols(screen, row);
+        for m in regex.find_iter(line) {
+            if m.is_empty() {
+                continue;
             }
+            // Map byte offsets back to the cells they were read from
+            let col_start = col_offsets.partition_point(|&b| b <= m.start()) - 1;
+            let col_end = col_offsets.partition_point(|&b| b < m.end()) - 1;
+            self.search_matches.push((row, col_start, col_end));
         }
     }
 
+    /// Position of the match under the cursor and the total match count,
+    /// for the "N of M" counter in the search bar.
+    pub fn match_counter(&self) -> (Option<usize>, usize) {
+        let current = self
+            .search_matches
+            .iter()
+            .position(|&(row, col, _)| row == self.cursor_row && col == self.cursor_col)
+            .map(|i| i + 1);
+        (current, self.search_matches.len())
+    }
+
     fn next_match(&mut self) {
         if self.search_matches.is_empty() {
             return;
@@ -439,35 +1418,67 @@ impl CopyModeState {
     }
 
     fn get_line_text(&self, screen: &vt100::Screen, row: usize) -> String {
-        let cols = screen.size().1 as usize;
-        let mut line = String::with_capacity(cols);
-        for col in 0..cols {
-            if let Some(cell) = screen.cell(row as u16, col as u16) {
-                let contents = cell.contents();
-                if contents.is_empty() {
-                    line.push(' ');
-                } else {
-                    line.push_str(contents);
-                }
-            } else {
-                line.push(' ');
-            }
+        self.row_cells(screen, row).concat()
+    }
+
+    /// Text of each column of a history row: the cell's grapheme cluster, a
+    /// space for an empty cell, or nothing for the second half of a wide
+    /// character.
+    fn row_cells<'a>(&self, screen: &'a vt100::Screen, row: usize) -> Vec<&'a str> {
+        let cols = screen.size().1;
+        (0..cols)
+            .map(|col| match screen.history_cell(row, col) {
+                Some(cell) if cell.is_wide_continuation() => "",
+                Some(cell) if cell.has_contents() => cell.contents(),
+                _ => " ",
+            })
+            .collect()
+    }
+
+    /// One character per column, for word motions: the first character of
+    /// each cell, with the second half of a wide character repeating it.
+    fn row_chars(&self, screen: &vt100::Screen, row: usize) -> Vec<char> {
+        let mut chars: Vec<char> = Vec::with_capacity(screen.size().1 as usize);
+        for cell in self.row_cells(screen, row) {
+            let c = match cell.chars().next() {
+                Some(c) => c,
+                None => chars.last().copied().unwrap_or(' '),
+            };
+            chars.push(c);
+        }
+        chars
+    }
+
+    /// Text of a history row along with the byte offset at which each
+    /// column starts, so matches can be mapped back to cells.
+    fn get_line_with_cols(&self, screen: &vt100::Screen, row: usize) -> (String, Vec<usize>) {
+        let cells = self.row_cells(screen, row);
+        let mut line = String::with_capacity(cells.len());
+        let mut offsets = Vec::with_capacity(cells.len());
+        for cell in cells {
+            offsets.push(line.len());
+            line.push_str(cell);
         }
-        line
+        (line, offsets)
+    }
+
+    /// Text of the columns `from..to` of a history row.
+    fn cols_text(&self, screen: &vt100::Screen, row: usize, from: usize, to: usize) -> String {
+        let cells = self.row_cells(screen, row);
+        let to = to.min(cells.len());
+        cells[from.min(to)..to].concat()
     }
 
+    /// Column of the last non-blank cell of a row.
     fn line_end_col(&self, screen: &vt100::Screen, row: usize) -> usize {
-        let line = self.get_line_text(screen, row);
-        let trimmed = line.trim_end();
-        if trimmed.is_empty() {
-            0
-        } else {
-            trimmed.len().saturating_sub(1)
-        }
+        self.row_chars(screen, row)
+            .iter()
+            .rposition(|c| !c.is_whitespace())
+            .unwrap_or(0)
     }
 
     fn max_row(&self, screen: &vt100::Screen) -> usize {
-        screen.size().0 as usize - 1
+        screen.history_len() + screen.size().0 as usize - 1
     }
 
     /// Check if a cell is within the current selection.
@@ -510,12 +1521,64 @@ impl CopyModeState {
 
     /// Check if a cell is a search match.
     pub fn is_search_match(&self, row: usize, col: usize) -> bool {
-        self.search_matches
+        // Matches are sorted by row, so only scan the ones on this row
+        let start = self.search_matches.partition_point(|m| m.0 < row);
+        self.search_matches[start..]
             .iter()
-            .any(|&(mr, mc_start, mc_end)| row == mr && col >= mc_start && col <= mc_end)
+            .take_while(|m| m.0 == row)
+            .any(|&(_, mc_start, mc_end)| col >= mc_start && col <= mc_end)
+    }
+}
+
+/// Upper bound for vi counts, so a stray `99999j` stays cheap.
+const MAX_COUNT: usize = 10_000;
+
+/// Word, punctuation or whitespace, used for `e`, `iw` and `aw`.
+fn char_class(c: char) -> u8 {
+    if c.is_whitespace() {
+        0
+    } else if c.is_alphanumeric() || c == '_' {
+        1
+    } else {
+        2
+    }
+}
+
+/// Whether a search query has an uppercase letter of its own. Escaped
+/// characters don't count, so `\S` or `\W` leave the search smart-cased.
+fn has_uppercase(query: &str) -> bool {
+    let mut chars = query.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            chars.next();
+        } else if c.is_uppercase() {
+            return true;
+        }
+    }
+    false
+}
+
+/// Open and close characters for a bracket, and whether `c` is the opening
+/// one.
+fn bracket_pair(c: char) -> Option<(char, char, bool)> {
+    match c {
+        '(' => Some(('(', ')', true)),
+        ')' => Some(('(', ')', false)),
+        '[' => Some(('[', ']', true)),
+        ']' => Some(('[', ']', false)),
+        '{' => Some(('{', '}', true)),
+        '}' => Some(('{', '}', false)),
+        '<' => Some(('<', '>', true)),
+        '>' => Some(('<', '>', false)),
+        _ => None,
     }
 }
 
+/// The brackets `%` jumps between, which leave out `<>` like vim does.
+fn percent_pair(c: char) -> Option<(char, char, bool)> {
+    bracket_pair(c).filter(|&(open, _, _)| open != '<')
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -644,6 +1707,10 @@ mod tests {
             make_key(KeyCode::Char('g'), KeyModifiers::NONE),
             parser.screen(),
         );
+        state.handle_key(
+            make_key(KeyCode::Char('g'), KeyModifiers::NONE),
+            parser.screen(),
+        );
         assert_eq!(state.cursor_row, 0);
         assert_eq!(state.cursor_col, 0);
     }
@@ -1124,6 +2191,8 @@ mod tests {
                 assert!(text.contains('\n'), "should span multiple lines");
                 let lines: Vec<&str> = text.lines().collect();
                 assert_eq!(lines.len(), 3);
+                // Unlike a mouse selection, the padding is kept
+                assert_eq!(lines[0], format!("aa{}", " ".repeat(17)));
             }
             _ => panic!("Expected YankSelection"),
         }
@@ -1417,4 +2486,608 @@ mod tests {
         assert!(state.search_query.is_empty());
         assert!(state.search_matches.is_empty());
     }
+
+    // --- Scrollback / regex search ---
+
+    fn make_scrollback_screen(rows: u16, cols: u16, lines: usize) -> vt100::Parser {
+        let mut parser = vt100::Parser::new(rows, cols, 100);
+        let text: Vec<String> = (0..lines).map(|i| format!("line {}", i)).collect();
+        parser.process(text.join("\r\n").as_bytes());
+        parser
+    }
+
+    fn type_search(state: &mut CopyModeState, screen: &vt100::Screen, prompt: char, query: &str) {
+        state.handle_key(make_key(KeyCode::Char(prompt), KeyModifiers::NONE), screen);
+        for c in query.chars() {
+            state.handle_key(make_key(KeyCode::Char(c), KeyModifiers::NONE), screen);
+        }
+    }
+
+    #[test]
+    fn test_for_screen_starts_below_history() {
+        let parser = make_scrollback_screen(3, 20, 10);
+        let state = CopyModeState::for_screen(parser.screen());
+        assert_eq!(parser.screen().history_len(), 7);
+        assert_eq!(state.view_top, 7);
+        assert_eq!(state.cursor_row, 9);
+    }
+
+    #[test]
+    fn test_search_covers_scrollback() {
+        let parser = make_scrollback_screen(3, 20, 10);
+        let mut state = CopyModeState::for_screen(parser.screen());
+        state.search_query = "line 1".to_string();
+        state.perform_search(parser.screen());
+        // Only "line 1" itself; "line 10" and up don't exist
+        assert_eq!(state.search_matches, vec![(1, 0, 5)]);
+    }
+
+    #[test]
+    fn test_search_scrolls_viewport_to_match() {
+        let parser = make_scrollback_screen(3, 20, 10);
+        let screen = parser.screen();
+        let mut state = CopyModeState::for_screen(screen);
+        type_search(&mut state, screen, '/', "line 2");
+        state.handle_key(make_key(KeyCode::Enter, KeyModifiers::NONE), screen);
+        assert_eq!(state.cursor_row, 2);
+        assert!(state.view_top <= 2 && 2 < state.view_top + 3);
+    }
+
+    #[test]
+    fn test_search_regex() {
+        let parser = make_screen(5, 40, "foo1 bar foo22 baz");
+        let mut state = CopyModeState::new(5, 40, 0, 0);
+        state.search_query = r"foo\d+".to_string();
+        state.perform_search(parser.screen());
+        assert_eq!(state.search_matches, vec![(0, 0, 3), (0, 9, 13)]);
+    }
+
+    #[test]
+    fn test_search_invalid_regex_matches_literally() {
+        let parser = make_screen(5, 40, "call f(x");
+        let mut state = CopyModeState::new(5, 40, 0, 0);
+        state.search_query = "f(x".to_string();
+        state.perform_search(parser.screen());
+        assert_eq!(state.search_matches, vec![(0, 5, 7)]);
+    }
+
+    #[test]
+    fn test_search_smart_case() {
+        let parser = make_screen(5, 40, "Error error ERROR");
+        let mut state = CopyModeState::new(5, 40, 0, 0);
+        state.search_query = "error".to_string();
+        state.perform_search(parser.screen());
+        assert_eq!(state.search_matches.len(), 3);
+
+        state.search_query = "Error".to_string();
+        state.perform_search(parser.screen());
+        assert_eq!(state.search_matches, vec![(0, 0, 4)]);
+
+        // Escapes like \S aren't uppercase letters
+        state.search_query = r"error\S*".to_string();
+        state.perform_search(parser.screen());
+        assert_eq!(state.search_matches.len(), 3);
+        state.search_query = r"\bE\w+".to_string();
+        state.perform_search(parser.screen());
+        assert_eq!(state.search_matches, vec![(0, 0, 4), (0, 12, 16)]);
+    }
+
+    #[test]
+    fn test_search_highlights_incrementally() {
+        let parser = make_screen(5, 40, "abc abd abe");
+        let screen = parser.screen();
+        let mut state = CopyModeState::new(5, 40, 0, 0);
+        type_search(&mut state, screen, '/', "ab");
+        assert!(state.search_active);
+        assert_eq!(state.search_matches.len(), 3);
+        // Cursor previews the first match after the origin
+        assert_eq!(state.cursor_col, 4);
+
+        state.handle_key(make_key(KeyCode::Char('e'), KeyModifiers::NONE), screen);
+        assert_eq!(state.search_matches, vec![(0, 8, 10)]);
+        assert_eq!(state.cursor_col, 8);
+
+        state.handle_key(make_key(KeyCode::Backspace, KeyModifiers::NONE), screen);
+        assert_eq!(state.search_matches.len(), 3);
+        assert_eq!(state.cursor_col, 4);
+    }
+
+    #[test]
+    fn test_search_narrows_literal_queries() {
+        let parser = make_screen(5, 40, "abc abd\r\nxyz\r\nabe");
+        let screen = parser.screen();
+        let mut state = CopyModeState::new(5, 40, 0, 0);
+        type_search(&mut state, screen, '/', "ab");
+        assert_eq!(state.search_matches.len(), 3);
+        // Rows are read once for the whole query
+        assert_eq!(state.search_lines.len(), 5);
+
+        state.handle_key(make_key(KeyCode::Char('d'), KeyModifiers::NONE), screen);
+        assert_eq!(state.search_matches, vec![(0, 4, 6)]);
+
+        // A shorter or regex query searches every row again
+        state.handle_key(make_key(KeyCode::Backspace, KeyModifiers::NONE), screen);
+        state.handle_key(make_key(KeyCode::Backspace, KeyModifiers::NONE), screen);
+        assert_eq!(state.search_matches.len(), 3);
+        for c in "|x".chars() {
+            state.handle_key(make_key(KeyCode::Char(c), KeyModifiers::NONE), screen);
+        }
+        assert_eq!(state.search_matches.len(), 4);
+
+        state.handle_key(make_key(KeyCode::Enter, KeyModifiers::NONE), screen);
+        assert!(state.search_lines.is_empty());
+        assert_eq!(state.search_matches.len(), 4);
+    }
+
+    #[test]
+    fn test_search_esc_restores_cursor() {
+        let parser = make_screen(5, 40, "abc abd abe");
+        let screen = parser.screen();
+        let mut state = CopyModeState::new(5, 40, 0, 2);
+        type_search(&mut state, screen, '/', "abe");
+        assert_eq!(state.cursor_col, 8);
+        state.handle_key(make_key(KeyCode::Esc, KeyModifiers::NONE), screen);
+        assert_eq!((state.cursor_row, state.cursor_col), (0, 2));
+    }
+
+    #[test]
+    fn test_backward_search() {
+        let parser = make_screen(5, 40, "ab ab ab");
+        let screen = parser.screen();
+        let mut state = CopyModeState::new(5, 40, 0, 7);
+        type_search(&mut state, screen, '?', "ab");
+        state.handle_key(make_key(KeyCode::Enter, KeyModifiers::NONE), screen);
+        assert!(!state.search_forward);
+        assert_eq!(state.cursor_col, 6);
+
+        // n keeps going backward, N reverses
+        state.handle_key(make_key(KeyCode::Char('n'), KeyModifiers::NONE), screen);
+        assert_eq!(state.cursor_col, 3);
+        state.handle_key(make_key(KeyCode::Char('N'), KeyModifiers::SHIFT), screen);
+        assert_eq!(state.cursor_col, 6);
+    }
+
+    #[test]
+    fn test_match_counter() {
+        let parser = make_screen(5, 40, "ab ab ab");
+        let screen = parser.screen();
+        let mut state = CopyModeState::new(5, 40, 0, 0);
+        type_search(&mut state, screen, '/', "ab");
+        state.handle_key(make_key(KeyCode::Enter, KeyModifiers::NONE), screen);
+        assert_eq!(state.match_counter(), (Some(2), 3));
+
+        state.handle_key(make_key(KeyCode::Char('l'), KeyModifiers::NONE), screen);
+        assert_eq!(state.match_counter(), (None, 3));
+    }
+
+    #[test]
+    fn test_search_match_on_wide_chars() {
+        let parser = make_screen(5, 40, "日本 foo");
+        let mut state = CopyModeState::new(5, 40, 0, 0);
+        state.search_query = "foo".to_string();
+        state.perform_search(parser.screen());
+        // Each wide char takes two cells
+        assert_eq!(state.search_matches, vec![(0, 5, 7)]);
+    }
+
+    #[test]
+    fn test_grapheme_clusters_in_selection_and_motions() {
+        let parser = make_screen(5, 40, "a👍🏽b 🇯🇵 c");
+        let screen = parser.screen();
+        let mut state = CopyModeState::new(5, 40, 0, 0);
+        // The second half of a wide cell belongs to the same word
+        press(&mut state, screen, "w");
+        assert_eq!(state.cursor_col, 5);
+        press(&mut state, screen, "0v$");
+        assert_eq!(state.cursor_col, 8);
+        match state.handle_key(make_key(KeyCode::Char('y'), KeyModifiers::NONE), screen) {
+            CopyModeAction::YankSelection(text) => assert_eq!(text, "a👍🏽b 🇯🇵 c"),
+            _ => panic!("Expected YankSelection"),
+        }
+
+        state.search_query = "b".to_string();
+        state.perform_search(screen);
+        assert_eq!(state.search_matches, vec![(0, 3, 3)]);
+    }
+
+    #[test]
+    fn test_motion_scrolls_viewport_into_history() {
+        let parser = make_scrollback_screen(3, 20, 10);
+        let screen = parser.screen();
+        let mut state = CopyModeState::for_screen(screen);
+        press(&mut state, screen, "gg");
+        assert_eq!(state.cursor_row, 0);
+        assert_eq!(state.view_top, 0);
+        state.handle_key(make_key(KeyCode::Char('G'), KeyModifiers::SHIFT), screen);
+        assert_eq!(state.cursor_row, 9);
+        assert_eq!(state.view_top, 7);
+    }
+
+    // --- Vi motions, counts and text objects ---
+
+    fn press(state: &mut CopyModeState, screen: &vt100::Screen, keys: &str) {
+        for c in keys.chars() {
+            let mods = if c.is_ascii_uppercase() {
+                KeyModifiers::SHIFT
+            } else {
+                KeyModifiers::NONE
+            };
+            state.handle_key(make_key(KeyCode::Char(c), mods), screen);
+        }
+    }
+
+    fn lines_screen(rows: u16, cols: u16, lines: &[&str]) -> vt100::Parser {
+        make_screen(rows, cols, &lines.join("\r\n"))
+    }
+
+    #[test]
+    fn test_count_motions() {
+        let parser = make_screen(10, 40, "one two three four five");
+        let screen = parser.screen();
+        let mut state = CopyModeState::new(10, 40, 0, 0);
+        press(&mut state, screen, "5j");
+        assert_eq!(state.cursor_row, 5);
+        press(&mut state, screen, "3k");
+        assert_eq!(state.cursor_row, 2);
+        state.cursor_row = 0;
+        press(&mut state, screen, "3w");
+        assert_eq!(state.cursor_col, 14);
+        press(&mut state, screen, "2h");
+        assert_eq!(state.cursor_col, 12);
+    }
+
+    #[test]
+    fn test_count_with_zero_digit() {
+        let parser = make_screen(20, 40, "hello");
+        let screen = parser.screen();
+        let mut state = CopyModeState::new(20, 40, 0, 3);
+        press(&mut state, screen, "0");
+        assert_eq!(state.cursor_col, 0);
+        press(&mut state, screen, "10j");
+        assert_eq!(state.cursor_row, 10);
+    }
+
+    #[test]
+    fn test_word_end() {
+        let parser = make_screen(5, 40, "foo bar.baz");
+        let screen = parser.screen();
+        let mut state = CopyModeState::new(5, 40, 0, 0);
+        press(&mut state, screen, "e");
+        assert_eq!(state.cursor_col, 2);
+        press(&mut state, screen, "e");
+        assert_eq!(state.cursor_col, 6);
+        press(&mut state, screen, "e");
+        assert_eq!(state.cursor_col, 7);
+    }
+
+    #[test]
+    fn test_find_char_and_repeat() {
+        let parser = make_screen(5, 40, "a,b,c,d");
+        let screen = parser.screen();
+        let mut state = CopyModeState::new(5, 40, 0, 0);
+        press(&mut state, screen, "f,");
+        assert_eq!(state.cursor_col, 1);
+        press(&mut state, screen, "2;");
+        assert_eq!(state.cursor_col, 5);
+        // Not enough matches left for the count: stay put
+        press(&mut state, screen, "2;");
+        assert_eq!(state.cursor_col, 5);
+        press(&mut state, screen, ",");
+        assert_eq!(state.cursor_col, 3);
+        press(&mut state, screen, "Fa");
+        assert_eq!(state.cursor_col, 0);
+    }
+
+    #[test]
+    fn test_till_char_and_repeat() {
+        let parser = make_screen(5, 40, "a,b,c,d");
+        let screen = parser.screen();
+        let mut state = CopyModeState::new(5, 40, 0, 0);
+        press(&mut state, screen, "t,");
+        assert_eq!(state.cursor_col, 0);
+        // Repeating a till skips the adjacent match
+        press(&mut state, screen, ";");
+        assert_eq!(state.cursor_col, 2);
+        state.cursor_col = 6;
+        press(&mut state, screen, "T,");
+        assert_eq!(state.cursor_col, 6);
+        press(&mut state, screen, ";");
+        assert_eq!(state.cursor_col, 4);
+    }
+
+    #[test]
+    fn test_find_char_missing_stays() {
+        let parser = make_screen(5, 40, "abc");
+        let screen = parser.screen();
+        let mut state = CopyModeState::new(5, 40, 0, 1);
+        press(&mut state, screen, "fz");
+        assert_eq!(state.cursor_col, 1);
+    }
+
+    #[test]
+    fn test_matching_bracket() {
+        let parser = lines_screen(5, 40, &["fn f(a, (b)) {", "  x", "}"]);
+        let screen = parser.screen();
+        let mut state = CopyModeState::new(5, 40, 0, 0);
+        press(&mut state, screen, "%");
+        assert_eq!((state.cursor_row, state.cursor_col), (0, 11));
+        press(&mut state, screen, "%");
+        assert_eq!((state.cursor_row, state.cursor_col), (0, 4));
+        state.cursor_col = 13;
+        press(&mut state, screen, "%");
+        assert_eq!((state.cursor_row, state.cursor_col), (2, 0));
+        press(&mut state, screen, "%");
+        assert_eq!((state.cursor_row, state.cursor_col), (0, 13));
+    }
+
+    #[test]
+    fn test_matching_bracket_skips_letters() {
+        let parser = lines_screen(5, 40, &["(ab)x(y)", "debug {x} <B>"]);
+        let screen = parser.screen();
+        let mut state = CopyModeState::new(5, 40, 0, 2);
+        press(&mut state, screen, "%");
+        assert_eq!((state.cursor_row, state.cursor_col), (0, 0));
+        state.cursor_row = 1;
+        state.cursor_col = 0;
+        press(&mut state, screen, "%");
+        assert_eq!((state.cursor_row, state.cursor_col), (1, 8));
+        // Nor does it stop on `<>`
+        state.cursor_col = 9;
+        press(&mut state, screen, "%");
+        assert_eq!((state.cursor_row, state.cursor_col), (1, 9));
+    }
+
+    #[test]
+    fn test_paragraph_motions() {
+        let parser = lines_screen(10, 20, &["a", "b", "", "c", "d", "", "e"]);
+        let screen = parser.screen();
+        let mut state = CopyModeState::new(10, 20, 0, 0);
+        press(&mut state, screen, "}");
+        assert_eq!(state.cursor_row, 2);
+        press(&mut state, screen, "}");
+        assert_eq!(state.cursor_row, 5);
+        press(&mut state, screen, "{");
+        assert_eq!(state.cursor_row, 2);
+        press(&mut state, screen, "{");
+        assert_eq!(state.cursor_row, 0);
+    }
+
+    #[test]
+    fn test_viewport_high_middle_low() {
+        let parser = make_scrollback_screen(5, 20, 20);
+        let screen = parser.screen();
+        let mut state = CopyModeState::for_screen(screen);
+        assert_eq!(state.view_top, 15);
+        press(&mut state, screen, "H");
+        assert_eq!(state.cursor_row, 15);
+        press(&mut state, screen, "L");
+        assert_eq!(state.cursor_row, 19);
+        press(&mut state, screen, "M");
+        assert_eq!(state.cursor_row, 17);
+        press(&mut state, screen, "2H");
+        assert_eq!(state.cursor_row, 16);
+    }
+
+    #[test]
+    fn test_gg_and_g_with_count() {
+        let parser = make_scrollback_screen(3, 20, 10);
+        let screen = parser.screen();
+        let mut state = CopyModeState::for_screen(screen);
+        press(&mut state, screen, "4G");
+        assert_eq!(state.cursor_row, 3);
+        press(&mut state, screen, "G");
+        assert_eq!(state.cursor_row, 9);
+        press(&mut state, screen, "2gg");
+        assert_eq!(state.cursor_row, 1);
+        assert_eq!(state.view_top, 1);
+    }
+
+    #[test]
+    fn test_marks() {
+        let parser = make_scrollback_screen(3, 20, 10);
+        let screen = parser.screen();
+        let mut state = CopyModeState::for_screen(screen);
+        state.cursor_col = 3;
+        press(&mut state, screen, "ma");
+        press(&mut state, screen, "gg");
+        press(&mut state, screen, "'a");
+        assert_eq!((state.cursor_row, state.cursor_col), (9, 0));
+        press(&mut state, screen, "gg`a");
+        assert_eq!((state.cursor_row, state.cursor_col), (9, 3));
+        // Unknown marks leave the cursor alone
+        press(&mut state, screen, "'z");
+        assert_eq!((state.cursor_row, state.cursor_col), (9, 3));
+    }
+
+    #[test]
+    fn test_text_object_words() {
+        let parser = make_screen(5, 40, "foo bar_baz  qux");
+        let screen = parser.screen();
+        let mut state = CopyModeState::new(5, 40, 0, 6);
+        press(&mut state, screen, "viw");
+        assert_eq!(state.selected_text(screen), "bar_baz");
+        press(&mut state, screen, "v");
+        press(&mut state, screen, "vaw");
+        assert_eq!(state.selected_text(screen), "bar_baz  ");
+    }
+
+    #[test]
+    fn test_text_object_quotes() {
+        let parser = make_screen(5, 40, r#"say "hello there" now"#);
+        let screen = parser.screen();
+        let mut state = CopyModeState::new(5, 40, 0, 8);
+        press(&mut state, screen, "vi\"");
+        assert_eq!(state.selected_text(screen), "hello there");
+        press(&mut state, screen, "v");
+        state.cursor_col = 0;
+        press(&mut state, screen, "va\"");
+        assert_eq!(state.selected_text(screen), "\"hello there\"");
+    }
+
+    #[test]
+    fn test_text_object_brackets() {
+        let parser = make_screen(5, 40, "call(a, [b, c], d)");
+        let screen = parser.screen();
+        let mut state = CopyModeState::new(5, 40, 0, 9);
+        press(&mut state, screen, "vi[");
+        assert_eq!(state.selected_text(screen), "b, c");
+        press(&mut state, screen, "v");
+        state.cursor_col = 9;
+        press(&mut state, screen, "vab");
+        assert_eq!(state.selected_text(screen), "(a, [b, c], d)");
+    }
+
+    #[test]
+    fn test_text_object_starts_selection() {
+        let parser = make_screen(5, 40, "foo bar");
+        let screen = parser.screen();
+        let mut state = CopyModeState::new(5, 40, 0, 5);
+        press(&mut state, screen, "iw");
+        assert_eq!(state.selection_mode, SelectionMode::Char);
+        assert_eq!(state.selected_text(screen), "bar");
+    }
+
+    #[test]
+    fn test_swap_selection_ends() {
+        let parser = make_screen(5, 40, "hello world");
+        let screen = parser.screen();
+        let mut state = CopyModeState::new(5, 40, 0, 2);
+        press(&mut state, screen, "v3l");
+        assert_eq!(state.cursor_col, 5);
+        press(&mut state, screen, "o");
+        assert_eq!(state.cursor_col, 2);
+        assert_eq!(state.selection_start, Some((0, 5)));
+        press(&mut state, screen, "h");
+        assert_eq!(state.selected_text(screen), "ello ");
+    }
+
+    // --- Emacs keymap ---
+
+    fn emacs_key(
+        state: &mut CopyModeState,
+        screen: &vt100::Screen,
+        c: char,
+        mods: KeyModifiers,
+    ) -> CopyModeAction {
+        state.handle_key(make_key(KeyCode::Char(c), mods), screen)
+    }
+
+    #[test]
+    fn test_emacs_movement() {
+        let parser = lines_screen(5, 40, &["hello world", "second"]);
+        let screen = parser.screen();
+        let mut state = CopyModeState::new(5, 40, 0, 0).with_keys(CopyModeKeys::Emacs);
+        emacs_key(&mut state, screen, 'f', KeyModifiers::CONTROL);
+        assert_eq!(state.cursor_col, 1);
+        emacs_key(&mut state, screen, 'e', KeyModifiers::CONTROL);
+        assert_eq!(state.cursor_col, 10);
+        emacs_key(&mut state, screen, 'a', KeyModifiers::CONTROL);
+        assert_eq!(state.cursor_col, 0);
+        emacs_key(&mut state, screen, 'f', KeyModifiers::ALT);
+        assert_eq!(state.cursor_col, 5);
+        emacs_key(&mut state, screen, 'n', KeyModifiers::CONTROL);
+        assert_eq!(state.cursor_row, 1);
+        emacs_key(&mut state, screen, '<', KeyModifiers::ALT);
+        assert_eq!((state.cursor_row, state.cursor_col), (0, 0));
+        // vi keys are plain input in emacs mode
+        emacs_key(&mut state, screen, 'j', KeyModifiers::NONE);
+        assert_eq!(state.cursor_row, 0);
+    }
+
+    #[test]
+    fn test_emacs_mark_and_copy() {
+        let parser = make_screen(5, 40, "hello world");
+        let screen = parser.screen();
+        let mut state = CopyModeState::new(5, 40, 0, 0).with_keys(CopyModeKeys::Emacs);
+        emacs_key(&mut state, screen, ' ', KeyModifiers::CONTROL);
+        for _ in 0..4 {
+            emacs_key(&mut state, screen, 'f', KeyModifiers::CONTROL);
+        }
+        match emacs_key(&mut state, screen, 'w', KeyModifiers::ALT) {
+            CopyModeAction::YankSelection(text) => assert_eq!(text, "hello"),
+            _ => panic!("expected yank"),
+        }
+    }
+
+    #[test]
+    fn test_emacs_search_and_cancel() {
+        let parser = make_screen(5, 40, "foo bar foo");
+        let screen = parser.screen();
+        let mut state = CopyModeState::new(5, 40, 0, 0).with_keys(CopyModeKeys::Emacs);
+        emacs_key(&mut state, screen, 's', KeyModifiers::CONTROL);
+        assert!(state.search_active);
+        for c in "foo".chars() {
+            emacs_key(&mut state, screen, c, KeyModifiers::NONE);
+        }
+        state.handle_key(make_key(KeyCode::Enter, KeyModifiers::NONE), screen);
+        assert_eq!(state.cursor_col, 8);
+        assert!(matches!(
+            emacs_key(&mut state, screen, 'g', KeyModifiers::CONTROL),
+            CopyModeAction::Exit
+        ));
+    }
+
+    #[test]
+    fn test_view_origin_follows_copy_cursor() {
+        let parser = make_screen(10, 40, "");
+        let mut state = CopyModeState::for_screen(parser.screen());
+        assert_eq!(state.view_origin((4, 20)), (0, 0));
+        state.cursor_row = state.view_top + 8;
+        state.cursor_col = 30;
+        assert_eq!(state.view_origin((4, 20)), (5, 11));
+        // A view as big as the screen shows all of it
+        assert_eq!(state.view_origin((10, 40)), (0, 0));
+    }
+
+    #[test]
+    fn test_mouse_selection() {
+        let parser = make_screen(3, 20, "one two three\r\nfour five");
+        let screen = parser.screen();
+        let mut state = CopyModeState::for_screen(screen);
+
+        // A click alone selects nothing; dragging selects from it
+        state.mouse_press(0, 4, 1, screen);
+        assert_eq!(state.selected_text(screen), "");
+        state.mouse_drag(1, 3, screen);
+        assert_eq!(state.selected_text(screen), "two three\nfour");
+        state.mouse_drag(0, 0, screen);
+        assert_eq!(state.selected_text(screen), "one t");
+
+        // Double click selects a word and extends by words
+        state.mouse_press(0, 5, 2, screen);
+        assert_eq!(state.selected_text(screen), "two");
+        state.mouse_drag(1, 6, screen);
+        assert_eq!(state.selected_text(screen), "two three\nfour five");
+        state.mouse_drag(0, 1, screen);
+        assert_eq!(state.selected_text(screen), "one two");
+
+        // Triple click selects lines
+        state.mouse_press(1, 2, 3, screen);
+        assert_eq!(state.selected_text(screen), "four five");
+        state.mouse_drag(0, 0, screen);
+        assert_eq!(state.selected_text(screen), "one two three\nfour five");
+    }
+
+    #[test]
+    fn test_mouse_drag_past_edge_scrolls() {
+        let mut parser = vt100::Parser::new(3, 10, 100);
+        for i in 0..10 {
+            parser.process(format!("line {i}\r\n").as_bytes());
+        }
+        let screen = parser.screen();
+        let mut state = CopyModeState::for_screen(screen);
+        let top = state.view_top;
+        state.mouse_press(0, 0, 1, screen);
+        state.mouse_drag(-1, 0, screen);
+        assert_eq!(state.view_top, top - 1);
+        state.mouse_drag(-1, 0, screen);
+        assert_eq!(state.view_top, top - 2);
+        assert_eq!(state.selected_text(screen), "line 6\nline 7\nl");
+        // Below the bottom scrolls back down, stopping at the last row
+        for _ in 0..5 {
+            state.mouse_drag(5, 0, screen);
+        }
+        assert_eq!(state.view_top, top);
+        assert_eq!(state.cursor_row, state.max_row(screen));
+    }
 }
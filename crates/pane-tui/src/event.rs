@@ -1,10 +1,52 @@
 pub use pane_protocol::event::AppEvent;
 
-use crossterm::event::{Event, EventStream, MouseButton, MouseEventKind};
+use crossterm::event::{Event, EventStream, MouseButton, MouseEvent, MouseEventKind};
+use pane_protocol::protocol::{self, WheelDirection};
 use futures::StreamExt;
 use std::time::Duration;
 use tokio::sync::mpsc;
 
+fn mouse_event(m: MouseEvent) -> Option<AppEvent> {
+    let (x, y) = (m.column, m.row);
+    let modifiers = m.modifiers.into();
+    let button = |b: MouseButton| match b {
+        MouseButton::Left => protocol::MouseButton::Left,
+        MouseButton::Middle => protocol::MouseButton::Middle,
+        MouseButton::Right => protocol::MouseButton::Right,
+    };
+    let wheel = |direction| AppEvent::MouseScroll {
+        x,
+        y,
+        direction,
+        modifiers,
+    };
+    Some(match m.kind {
+        MouseEventKind::Down(b) => AppEvent::MouseDown {
+            x,
+            y,
+            button: button(b),
+            modifiers,
+        },
+        MouseEventKind::Drag(b) => AppEvent::MouseDrag {
+            x,
+            y,
+            button: button(b),
+            modifiers,
+        },
+        MouseEventKind::Up(b) => AppEvent::MouseUp {
+            x,
+            y,
+            button: button(b),
+            modifiers,
+        },
+        MouseEventKind::Moved => AppEvent::MouseMove { x, y, modifiers },
+        MouseEventKind::ScrollUp => wheel(WheelDirection::Up),
+        MouseEventKind::ScrollDown => wheel(WheelDirection::Down),
+        MouseEventKind::ScrollLeft => wheel(WheelDirection::Left),
+        MouseEventKind::ScrollRight => wheel(WheelDirection::Right),
+    })
+}
+
 pub fn start_event_loop(event_tx: mpsc::UnboundedSender<AppEvent>) {
     // Crossterm event reader
     let tx = event_tx.clone();
@@ -15,32 +57,13 @@ pub fn start_event_loop(event_tx: mpsc::UnboundedSender<AppEvent>) {
                 Some(Ok(event)) => {
                     let app_event = match event {
                         Event::Key(key) => AppEvent::Key(key),
-                        Event::Mouse(m) => match m.kind {
-                            MouseEventKind::Down(MouseButton::Left) => AppEvent::MouseDown {
-                                x: m.column,
-                                y: m.row,
-                            },
-                            MouseEventKind::Down(MouseButton::Right) => AppEvent::MouseRightDown {
-                                x: m.column,
-                                y: m.row,
-                            },
-                            MouseEventKind::Drag(MouseButton::Left) => AppEvent::MouseDrag {
-                                x: m.column,
-                                y: m.row,
-                            },
-                            MouseEventKind::Moved => AppEvent::MouseMove {
-                                x: m.column,
-                                y: m.row,
-                            },
-                            MouseEventKind::Up(_) => AppEvent::MouseUp {
-                                x: m.column,
-                                y: m.row,
-                            },
-                            MouseEventKind::ScrollUp => AppEvent::MouseScroll { up: true },
-                            MouseEventKind::ScrollDown => AppEvent::MouseScroll { up: false },
-                            _ => continue,
+                        Event::Mouse(m) => match mouse_event(m) {
+                            Some(event) => event,
+                            None => continue,
                         },
                         Event::Resize(w, h) => AppEvent::Resize(w, h),
+                        Event::FocusGained => AppEvent::FocusChanged { focused: true },
+                        Event::FocusLost => AppEvent::FocusChanged { focused: false },
                         _ => continue,
                     };
                     if tx.send(app_event).is_err() {
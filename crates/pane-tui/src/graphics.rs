@@ -0,0 +1,151 @@
+//! Re-emitting images shown inside tabs to the outer terminal.
+//!
+//! Tabs keep sixel and kitty graphics images anchored to cells of their
+//! screen. Every image gets a placeholder box when the UI is drawn; when the
+//! outer terminal speaks the image's protocol the image itself is written
+//! over the box afterwards, clipped to the tab.
+
+use pane_protocol::layout::TabId;
+use vt100::{Image, ImageProtocol};
+
+/// Kitty graphics payloads are sent in chunks of this many base64 bytes.
+const KITTY_CHUNK_LEN: usize = 4096;
+
+/// Removes every kitty image from the outer terminal.
+pub const DELETE_KITTY_IMAGES: &[u8] = b"\x1b_Ga=d,d=A,q=2\x1b\\";
+
+/// Image protocols the outer terminal understands.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct GraphicsSupport {
+    pub kitty: bool,
+    pub sixel: bool,
+}
+
+impl GraphicsSupport {
+    /// Guess from the environment the client runs in.
+    pub fn detect() -> Self {
+        Self::from_env(|name| std::env::var(name).ok())
+    }
+
+    fn from_env(var: impl Fn(&str) -> Option<String>) -> Self {
+        let term = var("TERM").unwrap_or_default();
+        let program = var("TERM_PROGRAM").unwrap_or_default();
+        let kitty = var("KITTY_WINDOW_ID").is_some()
+            || var("KONSOLE_VERSION").is_some()
+            || term.contains("kitty")
+            || term.contains("ghostty")
+            || matches!(program.as_str(), "WezTerm" | "ghostty");
+        let sixel = ["foot", "mlterm", "contour"].iter().any(|t| term.contains(t))
+            || program == "WezTerm";
+        Self { kitty, sixel }
+    }
+
+    /// Whether `image` can be shown with only part of it visible.
+    pub fn can_draw(&self, image: &Image, clipped: bool) -> bool {
+        match image.protocol() {
+            // Cropping needs the size in pixels
+            ImageProtocol::Kitty => self.kitty && (!clipped || image.pixel_size().is_some()),
+            // Sixel can't be cropped without decoding it
+            ImageProtocol::Sixel => self.sixel && !clipped,
+        }
+    }
+}
+
+/// An image to write over its placeholder.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ImageDraw {
+    pub tab: TabId,
+    /// Placement id within the tab's screen.
+    pub id: u32,
+    pub protocol: ImageProtocol,
+    /// Outer terminal cell of the visible top left corner.
+    pub x: u16,
+    pub y: u16,
+    /// Rows cut off the top of the image.
+    pub top: u16,
+    /// Rows and columns left visible.
+    pub rows: u16,
+    pub cols: u16,
+}
+
+/// Append the bytes drawing `image` as described by `draw`. The cursor is
+/// saved and restored around it.
+pub fn encode(draw: &ImageDraw, image: &Image, out: &mut Vec<u8>) {
+    out.extend_from_slice(format!("\x1b7\x1b[{};{}H", draw.y + 1, draw.x + 1).as_bytes());
+    match image.protocol() {
+        ImageProtocol::Sixel => out.extend_from_slice(image.data()),
+        ImageProtocol::Kitty => encode_kitty(draw, image, out),
+    }
+    out.extend_from_slice(b"\x1b8");
+}
+
+fn encode_kitty(draw: &ImageDraw, image: &Image, out: &mut Vec<u8>) {
+    let mut keys = format!(
+        "a=T,{},q=2,C=1,c={},r={}",
+        image.format(),
+        draw.cols,
+        draw.rows
+    );
+    let (rows, cols) = image.size();
+    if draw.top > 0 || draw.rows < rows || draw.cols < cols {
+        if let Some((width, height)) = image.pixel_size() {
+            let scale = |px: u32, n: u16, of: u16| {
+                (u64::from(px) * u64::from(n) / u64::from(of.max(1))) as u32
+            };
+            keys.push_str(&format!(
+                ",x=0,y={},w={},h={}",
+                scale(height, draw.top, rows),
+                scale(width, draw.cols, cols),
+                scale(height, draw.rows, rows),
+            ));
+        }
+    }
+
+    let mut chunks = image.data().chunks(KITTY_CHUNK_LEN).peekable();
+    let mut first = true;
+    loop {
+        let chunk = chunks.next().unwrap_or_default();
+        let more = u8::from(chunks.peek().is_some());
+        out.extend_from_slice(b"\x1b_G");
+        if first {
+            out.extend_from_slice(keys.as_bytes());
+            out.extend_from_slice(format!(",m={more};").as_bytes());
+        } else {
+            out.extend_from_slice(format!("m={more};").as_bytes());
+        }
+        out.extend_from_slice(chunk);
+        out.extend_from_slice(b"\x1b\\");
+        first = false;
+        if more == 0 {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn support(vars: &[(&str, &str)]) -> GraphicsSupport {
+        GraphicsSupport::from_env(|name| {
+            vars.iter()
+                .find(|(k, _)| *k == name)
+                .map(|(_, v)| v.to_string())
+        })
+    }
+
+    #[test]
+    fn detect_from_env() {
+        assert_eq!(support(&[("TERM", "xterm-256color")]), GraphicsSupport::default());
+        assert!(support(&[("TERM", "xterm-kitty")]).kitty);
+        assert!(support(&[("KITTY_WINDOW_ID", "1")]).kitty);
+        assert!(support(&[("TERM", "foot")]).sixel);
+        assert_eq!(
+            support(&[("TERM_PROGRAM", "WezTerm")]),
+            GraphicsSupport {
+                kitty: true,
+                sixel: true
+            }
+        );
+    }
+}
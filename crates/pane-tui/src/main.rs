@@ -2,11 +2,13 @@ mod client;
 mod clipboard;
 mod copy_mode;
 mod event;
+mod graphics;
 mod tui;
 mod ui;
 mod window;
 
 use clap::{Parser, Subcommand};
+use client::Connection;
 use pane_protocol::config::Config;
 
 #[derive(Parser)]
@@ -22,6 +24,25 @@ struct Cli {
 
 #[derive(Subcommand)]
 enum Commands {
+    /// Attach to the running daemon
+    Attach {
+        /// Watch without being able to type into tabs or run commands
+        #[arg(short, long)]
+        read_only: bool,
+        /// Reach the daemon through a command speaking the framed protocol
+        /// on stdin/stdout, e.g. 'ssh host pane proxy'
+        #[arg(long, value_name = "COMMAND", conflicts_with = "tcp")]
+        via: Option<String>,
+        /// Attach to a daemon's TCP listener. The token comes from
+        /// PANE_TOKEN, or from [server] token in the config
+        #[arg(long, value_name = "HOST:PORT")]
+        tcp: Option<String>,
+        /// Use TLS for --tcp, trusting this PEM certificate
+        #[arg(long, value_name = "PATH", requires = "tcp")]
+        tls_cert: Option<std::path::PathBuf>,
+    },
+    /// Bridge stdin/stdout to the daemon socket (the far end of `attach --via`)
+    Proxy,
     /// Kill the running daemon
     Kill,
     /// Send keys to a pane
@@ -50,20 +71,19 @@ fn start_and_connect(config: Config) -> anyhow::Result<()> {
     tui::install_panic_hook();
     let rt = tokio::runtime::Runtime::new()?;
 
-    match rt.block_on(client::Client::run(config.clone())) {
+    match rt.block_on(client::Client::run(
+        config.clone(),
+        false,
+        Connection::Local,
+    )) {
         Ok(()) => Ok(()),
         Err(e) => {
-            let msg = e.to_string();
-            if msg.contains("timed out")
-                || msg.contains("connection closed")
-                || msg.contains("Connection refused")
-                || msg.contains("Broken pipe")
-            {
+            if let Some(client::HandshakeError::NoResponse(msg)) = e.downcast_ref() {
                 // Daemon likely crashed — kill stale process and retry once
                 eprintln!("pane: daemon connection failed ({}), retrying...", msg);
                 pane_daemon::server::daemon::kill_daemon();
                 pane_daemon::server::daemon::start_daemon()?;
-                rt.block_on(client::Client::run(config))
+                rt.block_on(client::Client::run(config, false, Connection::Local))
             } else {
                 Err(e)
             }
@@ -85,6 +105,39 @@ fn main() -> anyhow::Result<()> {
                 start_and_connect(config)
             }
         }
+        Some(Commands::Attach {
+            read_only,
+            via,
+            tcp,
+            tls_cert,
+        }) => {
+            let connection = match (via, tcp) {
+                (Some(via), _) => Connection::Via(via),
+                (None, Some(addr)) => {
+                    let token = std::env::var("PANE_TOKEN")
+                        .ok()
+                        .filter(|t| !t.is_empty())
+                        .or_else(|| config.server.token.clone())
+                        .ok_or_else(|| {
+                            anyhow::anyhow!("--tcp needs a token: set PANE_TOKEN or [server] token")
+                        })?;
+                    Connection::Tcp {
+                        addr,
+                        token,
+                        tls_cert,
+                    }
+                }
+                (None, None) => Connection::Local,
+            };
+            tui::install_panic_hook();
+            let rt = tokio::runtime::Runtime::new()?;
+            rt.block_on(client::Client::run(config, read_only, connection))
+        }
+        Some(Commands::Proxy) => {
+            pane_daemon::server::daemon::start_daemon()?;
+            let rt = tokio::runtime::Runtime::new()?;
+            rt.block_on(pane_daemon::server::proxy::run())
+        }
         Some(Commands::Daemon) => {
             let rt = tokio::runtime::Runtime::new()?;
             rt.block_on(pane_daemon::server::daemon::run_server(config))
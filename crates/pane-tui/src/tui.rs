@@ -1,14 +1,30 @@
 use crossterm::{
-    event::{DisableMouseCapture, EnableMouseCapture},
+    cursor::{MoveTo, SetCursorStyle},
+    event::{
+        DisableFocusChange, DisableMouseCapture, EnableFocusChange, EnableMouseCapture,
+        KeyboardEnhancementFlags, PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+    },
     execute,
     terminal::{self, EnterAlternateScreen, LeaveAlternateScreen},
 };
-use ratatui::{backend::CrosstermBackend, Frame, Terminal};
+use ratatui::backend::{Backend, CrosstermBackend};
+use ratatui::layout::Rect;
+use ratatui::{Frame, Terminal};
+use unicode_width::UnicodeWidthStr;
 use std::io::{self, Stdout};
 
+/// Cursor shape and color of the focused tab, mirrored on the outer terminal.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CursorStyle {
+    pub shape: vt100::CursorShape,
+    pub color: Option<String>,
+}
+
 pub struct Tui {
     terminal: Terminal<CrosstermBackend<Stdout>>,
     entered: bool,
+    keyboard_enhanced: bool,
+    cursor_style: CursorStyle,
 }
 
 impl Tui {
@@ -18,6 +34,8 @@ impl Tui {
         Ok(Self {
             terminal,
             entered: false,
+            keyboard_enhanced: false,
+            cursor_style: CursorStyle::default(),
         })
     }
 
@@ -25,7 +43,25 @@ impl Tui {
         terminal::enable_raw_mode()?;
         // Save terminal title (xterm title stack)
         let _ = io::Write::write_all(&mut io::stdout(), b"\x1b[22;0t");
-        execute!(io::stdout(), EnterAlternateScreen, EnableMouseCapture,)?;
+        execute!(
+            io::stdout(),
+            EnterAlternateScreen,
+            EnableMouseCapture,
+            EnableFocusChange,
+        )?;
+        // Ask for kitty keyboard reporting so modifiers like shift+enter and
+        // key releases reach tabs that enable the protocol themselves.
+        if terminal::supports_keyboard_enhancement().unwrap_or(false) {
+            execute!(
+                io::stdout(),
+                PushKeyboardEnhancementFlags(
+                    KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES
+                        | KeyboardEnhancementFlags::REPORT_EVENT_TYPES
+                        | KeyboardEnhancementFlags::REPORT_ALTERNATE_KEYS
+                ),
+            )?;
+            self.keyboard_enhanced = true;
+        }
         self.terminal.clear()?;
         self.entered = true;
         Ok(())
@@ -35,7 +71,17 @@ impl Tui {
         if self.entered {
             self.entered = false;
             let _ = self.terminal.show_cursor();
-            let _ = execute!(io::stdout(), DisableMouseCapture, LeaveAlternateScreen,);
+            let _ = self.set_cursor_style(&CursorStyle::default());
+            if self.keyboard_enhanced {
+                self.keyboard_enhanced = false;
+                let _ = execute!(io::stdout(), PopKeyboardEnhancementFlags);
+            }
+            let _ = execute!(
+                io::stdout(),
+                DisableFocusChange,
+                DisableMouseCapture,
+                LeaveAlternateScreen,
+            );
             // Restore terminal title (xterm title stack)
             let _ = io::Write::write_all(&mut io::stdout(), b"\x1b[23;0t");
             let _ = io::Write::flush(&mut io::stdout());
@@ -48,6 +94,92 @@ impl Tui {
         Ok(())
     }
 
+    /// Switch the outer terminal's cursor to `style`, if it isn't already.
+    pub fn set_cursor_style(&mut self, style: &CursorStyle) -> anyhow::Result<()> {
+        if *style == self.cursor_style {
+            return Ok(());
+        }
+        if style.shape != self.cursor_style.shape {
+            let shape = match style.shape {
+                vt100::CursorShape::Default => SetCursorStyle::DefaultUserShape,
+                vt100::CursorShape::BlinkingBlock => SetCursorStyle::BlinkingBlock,
+                vt100::CursorShape::SteadyBlock => SetCursorStyle::SteadyBlock,
+                vt100::CursorShape::BlinkingUnderline => SetCursorStyle::BlinkingUnderScore,
+                vt100::CursorShape::SteadyUnderline => SetCursorStyle::SteadyUnderScore,
+                vt100::CursorShape::BlinkingBar => SetCursorStyle::BlinkingBar,
+                vt100::CursorShape::SteadyBar => SetCursorStyle::SteadyBar,
+            };
+            execute!(io::stdout(), shape)?;
+        }
+        if style.color != self.cursor_style.color {
+            let seq = match &style.color {
+                Some(color) => format!("\x1b]12;{color}\x1b\\"),
+                None => "\x1b]112\x1b\\".to_string(),
+            };
+            io::Write::write_all(&mut io::stdout(), seq.as_bytes())?;
+            io::Write::flush(&mut io::stdout())?;
+        }
+        self.cursor_style = style.clone();
+        Ok(())
+    }
+
+    /// Write a sequence a tab passed through to the outer terminal, with the
+    /// cursor at `position` (column, row) if given. The cursor is restored
+    /// afterwards so the next draw isn't disturbed.
+    pub fn write_passthrough(
+        &mut self,
+        position: Option<(u16, u16)>,
+        data: &[u8],
+    ) -> anyhow::Result<()> {
+        let mut out = io::stdout();
+        io::Write::write_all(&mut out, b"\x1b7")?;
+        if let Some((x, y)) = position {
+            execute!(out, MoveTo(x, y))?;
+        }
+        io::Write::write_all(&mut out, data)?;
+        io::Write::write_all(&mut out, b"\x1b8")?;
+        io::Write::flush(&mut out)?;
+        Ok(())
+    }
+
+    /// Write images over their placeholders. The bytes position the cursor
+    /// themselves.
+    pub fn write_images(&mut self, data: &[u8]) -> anyhow::Result<()> {
+        self.write_passthrough(None, data)
+    }
+
+    /// Write the cells in `areas` again, whatever the outer terminal is
+    /// thought to show there, to paint over sixel images left in them. `f`
+    /// draws the same frame as the last draw, so nothing else is written.
+    pub fn repaint(&mut self, areas: &[Rect], f: impl FnOnce(&mut Frame)) -> anyhow::Result<()> {
+        let buffer = self.terminal.draw(f)?.buffer;
+        let mut cells = Vec::new();
+        for area in areas {
+            let area = area.intersection(buffer.area);
+            for y in area.top()..area.bottom() {
+                // The cells after a wide character are covered by it
+                let mut covered = 0;
+                for x in area.left()..area.right() {
+                    let cell = &buffer[(x, y)];
+                    if covered > 0 {
+                        covered -= 1;
+                    } else if !cell.skip {
+                        covered = cell.symbol().width().saturating_sub(1);
+                        cells.push((x, y, cell.clone()));
+                    }
+                }
+            }
+        }
+        let mut out = io::stdout();
+        io::Write::write_all(&mut out, b"\x1b7")?;
+        self.terminal
+            .backend_mut()
+            .draw(cells.iter().map(|(x, y, cell)| (*x, *y, cell)))?;
+        io::Write::write_all(&mut out, b"\x1b8")?;
+        io::Write::flush(&mut out)?;
+        Ok(())
+    }
+
     pub fn size(&self) -> anyhow::Result<ratatui::layout::Rect> {
         let size = self.terminal.size()?;
         Ok(ratatui::layout::Rect::new(0, 0, size.width, size.height))
@@ -65,9 +197,17 @@ pub fn install_panic_hook() {
     let original_hook = std::panic::take_hook();
     std::panic::set_hook(Box::new(move |panic_info| {
         let _ = terminal::disable_raw_mode();
-        let _ = execute!(io::stdout(), DisableMouseCapture, LeaveAlternateScreen,);
-        // Restore terminal title
-        let _ = io::Write::write_all(&mut io::stdout(), b"\x1b[23;0t");
+        // Terminals without the kitty keyboard protocol ignore the pop
+        let _ = execute!(
+            io::stdout(),
+            SetCursorStyle::DefaultUserShape,
+            PopKeyboardEnhancementFlags,
+            DisableFocusChange,
+            DisableMouseCapture,
+            LeaveAlternateScreen,
+        );
+        // Reset cursor color and restore terminal title
+        let _ = io::Write::write_all(&mut io::stdout(), b"\x1b]112\x1b\\\x1b[23;0t");
         let _ = io::Write::flush(&mut io::stdout());
         original_hook(panic_info);
     }));
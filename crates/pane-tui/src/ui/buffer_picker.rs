@@ -0,0 +1,249 @@
+use ratatui::{
+    layout::{Constraint, Layout, Rect},
+    style::Style,
+    text::Line,
+    widgets::{Paragraph, Wrap},
+    Frame,
+};
+
+use pane_protocol::config::Theme;
+use pane_protocol::protocol::BufferSummary;
+
+use super::dialog;
+
+// ---------------------------------------------------------------------------
+// BufferPickerState
+// ---------------------------------------------------------------------------
+
+/// State for the `choose-buffer` picker: the paste buffers, newest first,
+/// filtered by a fuzzy query.
+pub struct BufferPickerState {
+    pub input: String,
+    pub selected: usize,
+    entries: Vec<BufferSummary>,
+    pub filtered: Vec<usize>,
+}
+
+impl BufferPickerState {
+    pub fn new(entries: Vec<BufferSummary>) -> Self {
+        let filtered = (0..entries.len()).collect();
+        Self {
+            input: String::new(),
+            selected: 0,
+            entries,
+            filtered,
+        }
+    }
+
+    pub fn update_filter(&mut self) {
+        let query = self.input.to_lowercase();
+        self.filtered = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter(|(_, e)| fuzzy_match(&query, &e.name) || fuzzy_match(&query, &e.preview))
+            .map(|(i, _)| i)
+            .collect();
+        if self.selected >= self.filtered.len() {
+            self.selected = 0;
+        }
+    }
+
+    pub fn move_up(&mut self) {
+        if !self.filtered.is_empty() {
+            self.selected = self
+                .selected
+                .checked_sub(1)
+                .unwrap_or(self.filtered.len() - 1);
+        }
+    }
+
+    pub fn move_down(&mut self) {
+        if !self.filtered.is_empty() {
+            self.selected = (self.selected + 1) % self.filtered.len();
+        }
+    }
+
+    pub fn selected_buffer(&self) -> Option<&BufferSummary> {
+        self.filtered
+            .get(self.selected)
+            .and_then(|&i| self.entries.get(i))
+    }
+
+    /// Drop the selected buffer from the list, after deleting it.
+    pub fn remove_selected(&mut self) {
+        let selected = self.selected;
+        if let Some(&idx) = self.filtered.get(selected) {
+            self.entries.remove(idx);
+            self.update_filter();
+            self.selected = selected.min(self.filtered.len().saturating_sub(1));
+        }
+    }
+}
+
+/// Whether every character of `query` appears in `text` in order, ignoring
+/// case. `query` must already be lowercase.
+fn fuzzy_match(query: &str, text: &str) -> bool {
+    let mut text = text.chars().flat_map(char::to_lowercase);
+    query.chars().all(|q| text.any(|c| c == q))
+}
+
+// ---------------------------------------------------------------------------
+// Rendering
+// ---------------------------------------------------------------------------
+
+pub fn render(state: &BufferPickerState, theme: &Theme, frame: &mut Frame, area: Rect) {
+    let popup_area = dialog::popup_rect(
+        dialog::PopupSize::Percent { width: 70, height: 70 },
+        dialog::PopupAnchor::Center,
+        area,
+    );
+    let inner = dialog::render_popup(frame, popup_area, "buffers", theme);
+
+    if inner.height < 5 {
+        return;
+    }
+
+    let [input_area, sep_area, list_area, preview_sep_area, preview_area] = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Length(1),
+        Constraint::Fill(1),
+        Constraint::Length(1),
+        Constraint::Percentage(50),
+    ])
+    .areas(inner);
+
+    dialog::render_filter_input_placeholder(
+        frame,
+        input_area,
+        &state.input,
+        Some("filter buffers"),
+        theme,
+    );
+    dialog::render_separator(frame, sep_area, theme);
+
+    if state.filtered.is_empty() {
+        let message = if state.entries.is_empty() {
+            "  No buffers"
+        } else {
+            "  No matches"
+        };
+        frame.render_widget(
+            Paragraph::new(message).style(Style::default().fg(theme.dim)),
+            list_area,
+        );
+        return;
+    }
+
+    let descriptions: Vec<String> = state
+        .filtered
+        .iter()
+        .map(|&i| {
+            let entry = &state.entries[i];
+            format!("{} bytes  {}", entry.size, first_line(&entry.preview))
+        })
+        .collect();
+    let items: Vec<dialog::ListItem> = state
+        .filtered
+        .iter()
+        .zip(&descriptions)
+        .map(|(&i, description)| dialog::ListItem {
+            label: &state.entries[i].name,
+            description,
+            section: None,
+            hint: None,
+        })
+        .collect();
+    dialog::render_select_list(frame, list_area, &items, state.selected, false, None, theme);
+
+    dialog::render_separator(frame, preview_sep_area, theme);
+    if let Some(entry) = state.selected_buffer() {
+        let lines: Vec<Line> = entry
+            .preview
+            .lines()
+            .take(preview_area.height as usize)
+            .map(|line| Line::raw(format!("  {}", line.replace('\t', "    "))))
+            .collect();
+        frame.render_widget(
+            Paragraph::new(lines)
+                .style(Style::default().fg(theme.fg))
+                .wrap(Wrap { trim: false }),
+            preview_area,
+        );
+    }
+}
+
+/// The first non-blank line of a buffer, for its list entry.
+fn first_line(text: &str) -> String {
+    let line = text.lines().find(|l| !l.trim().is_empty()).unwrap_or("");
+    line.trim().replace('\t', " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary(name: &str, preview: &str) -> BufferSummary {
+        BufferSummary {
+            name: name.to_string(),
+            size: preview.len(),
+            preview: preview.to_string(),
+        }
+    }
+
+    fn picker() -> BufferPickerState {
+        BufferPickerState::new(vec![
+            summary("buffer2", "cargo build --release"),
+            summary("notes", "meeting at noon"),
+            summary("buffer0", "git status"),
+        ])
+    }
+
+    #[test]
+    fn test_fuzzy_match() {
+        assert!(fuzzy_match("cbr", "cargo build --release"));
+        assert!(fuzzy_match("", "anything"));
+        assert!(fuzzy_match("gst", "Git STatus"));
+        assert!(!fuzzy_match("rc", "cargo"));
+    }
+
+    #[test]
+    fn test_filter_matches_names_and_contents() {
+        let mut state = picker();
+        state.input = "nts".to_string();
+        state.update_filter();
+        assert_eq!(state.selected_buffer().unwrap().name, "notes");
+
+        state.input = "gitst".to_string();
+        state.update_filter();
+        assert_eq!(state.filtered, vec![2]);
+
+        state.input = "zzz".to_string();
+        state.update_filter();
+        assert!(state.selected_buffer().is_none());
+    }
+
+    #[test]
+    fn test_navigation_wraps() {
+        let mut state = picker();
+        state.move_up();
+        assert_eq!(state.selected_buffer().unwrap().name, "buffer0");
+        state.move_down();
+        assert_eq!(state.selected_buffer().unwrap().name, "buffer2");
+    }
+
+    #[test]
+    fn test_remove_selected() {
+        let mut state = picker();
+        state.move_up();
+        state.remove_selected();
+        assert_eq!(state.filtered.len(), 2);
+        assert_eq!(state.selected_buffer().unwrap().name, "notes");
+    }
+
+    #[test]
+    fn test_first_line_skips_blank_lines() {
+        assert_eq!(first_line("\n  \n\tfn main() {\n}"), "fn main() {");
+        assert_eq!(first_line(""), "");
+    }
+}
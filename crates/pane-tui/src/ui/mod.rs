@@ -1,5 +1,6 @@
 #[cfg(test)]
 mod snapshot_tests;
+pub mod buffer_picker;
 #[cfg(test)]
 mod tests_context_menu;
 pub mod context_menu;
@@ -30,11 +31,17 @@ pub mod window_view;
 mod tests_workspace_bar;
 pub mod workspace_bar;
 
+use std::collections::HashMap;
+
 use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::widgets::{Block, Borders};
 use ratatui::Frame;
 use unicode_width::UnicodeWidthStr;
 
 use crate::client::{Client, Focus};
+use crate::graphics::ImageDraw;
+use crate::tui::CursorStyle;
+use pane_protocol::layout::TabId;
 
 /// Truncate a string to at most `max` display-width columns, adding "…" prefix.
 /// Used for path displays that should show the tail end.
@@ -84,10 +91,17 @@ fn truncate_end(s: &str, max: usize) -> String {
     format!("{}…", &s[..end])
 }
 
-/// Cursor X offset within a window: 1 (border) + 1 (padding).
-const WINDOW_CONTENT_X_OFFSET: u16 = 2;
-/// Cursor Y offset within a window: 1 (border) + 1 (tab bar).
-const WINDOW_CONTENT_Y_OFFSET: u16 = 2;
+/// Area a window's terminal content is drawn in: inside the border, the
+/// padding on each side and the tab bar.
+fn content_area(rect: Rect) -> Rect {
+    let inner = Block::default().borders(Borders::ALL).inner(rect);
+    Rect::new(
+        inner.x + 1,
+        inner.y + 1,
+        inner.width.saturating_sub(2),
+        inner.height.saturating_sub(1),
+    )
+}
 
 /// Render the TUI for a connected client (daemon mode).
 pub fn render_client(client: &mut Client, frame: &mut Frame) {
@@ -133,6 +147,14 @@ pub fn render_client(client: &mut Client, frame: &mut Frame) {
     // Status bar
     status_bar::render_client(client, theme, frame, footer);
 
+    // Outer terminal cursor follows the focused tab; default elsewhere
+    let mut cursor_style = CursorStyle::default();
+    let mut visible_tabs = HashMap::new();
+    // Tabs that may show images, with how many floating windows were drawn
+    // before them; any drawn later cover them
+    let mut image_areas = Vec::new();
+    let mut floating_rects = Vec::new();
+
     if client.focus == Focus::Overview {
         overview::render_overview(client, frame, body);
     } else if let Some(ws) = client.active_workspace() {
@@ -148,7 +170,10 @@ pub fn render_client(client: &mut Client, frame: &mut Frame) {
             if let Some(group) = ws.groups.iter().find(|g| g.id == zoomed_id) {
                 let pane = group.tabs.get(group.active_tab);
                 let screen = pane.and_then(|p| client.pane_screen(p.id));
-                window_view::render_group_from_snapshot(
+                if let Some(pane) = pane {
+                    visible_tabs.insert(pane.id, content_area(body));
+                }
+                let content = window_view::render_group_from_snapshot(
                     group,
                     screen,
                     !ws_bar_focused,
@@ -159,20 +184,18 @@ pub fn render_client(client: &mut Client, frame: &mut Frame) {
                     frame,
                     body,
                 );
+                if let (Some(pane), Some(content)) = (pane, content) {
+                    image_areas.push((pane.id, content, 0));
+                }
 
                 if !ws_bar_focused && matches!(client.focus, Focus::Normal | Focus::Interact) {
-                    if let Some(pane) = group.tabs.get(group.active_tab) {
+                    if let (Some(pane), Some(content)) = (group.tabs.get(group.active_tab), content)
+                    {
                         if let Some(screen) = client.pane_screen(pane.id) {
                             if !screen.hide_cursor() {
-                                let (vt_row, vt_col) = screen.cursor_position();
-                                let cursor_x = body.x + WINDOW_CONTENT_X_OFFSET + vt_col;
-                                let cursor_y = body.y + WINDOW_CONTENT_Y_OFFSET + vt_row;
-                                if cursor_x < body.x + body.width && cursor_y < body.y + body.height
-                                {
-                                    frame.set_cursor_position(ratatui::layout::Position {
-                                        x: cursor_x,
-                                        y: cursor_y,
-                                    });
+                                if let Some((x, y)) = window_view::cursor_in(screen, content) {
+                                    frame.set_cursor_position(ratatui::layout::Position { x, y });
+                                    cursor_style = window_view::cursor_style(screen);
                                 }
                             }
                         }
@@ -191,7 +214,10 @@ pub fn render_client(client: &mut Client, frame: &mut Frame) {
                         let is_active = *group_id == ws.active_group && !ws_bar_focused;
                         let pane = group.tabs.get(group.active_tab);
                         let screen = pane.and_then(|p| client.pane_screen(p.id));
-                        window_view::render_group_from_snapshot(
+                        if let Some(pane) = pane {
+                            visible_tabs.insert(pane.id, content_area(*rect));
+                        }
+                        let content = window_view::render_group_from_snapshot(
                             group,
                             screen,
                             is_active,
@@ -202,6 +228,9 @@ pub fn render_client(client: &mut Client, frame: &mut Frame) {
                             frame,
                             *rect,
                         );
+                        if let (Some(pane), Some(content)) = (pane, content) {
+                            image_areas.push((pane.id, content, 0));
+                        }
                     }
                 }
             }
@@ -230,26 +259,15 @@ pub fn render_client(client: &mut Client, frame: &mut Frame) {
                 if let Some(group) = ws.groups.iter().find(|g| g.id == ws.active_group) {
                     if let Some(pane) = group.tabs.get(group.active_tab) {
                         if let Some(screen) = client.pane_screen(pane.id) {
-                            if !screen.hide_cursor() {
-                                for rp in &resolved {
-                                    if let pane_protocol::layout::ResolvedPane::Visible { id, rect } = rp {
-                                        if *id == ws.active_group {
-                                            let (vt_row, vt_col) = screen.cursor_position();
-                                            let cursor_x = rect.x + WINDOW_CONTENT_X_OFFSET + vt_col;
-                                            let cursor_y = rect.y + WINDOW_CONTENT_Y_OFFSET + vt_row;
-                                            if cursor_x < rect.x + rect.width
-                                                && cursor_y < rect.y + rect.height
-                                            {
-                                                frame.set_cursor_position(
-                                                    ratatui::layout::Position {
-                                                        x: cursor_x,
-                                                        y: cursor_y,
-                                                    },
-                                                );
-                                            }
-                                            break;
-                                        }
-                                    }
+                            // Where the first pass drew the tab's content
+                            let content = image_areas
+                                .iter()
+                                .find(|(id, ..)| *id == pane.id)
+                                .map(|(_, area, _)| *area);
+                            if let Some(content) = content.filter(|_| !screen.hide_cursor()) {
+                                if let Some((x, y)) = window_view::cursor_in(screen, content) {
+                                    frame.set_cursor_position(ratatui::layout::Position { x, y });
+                                    cursor_style = window_view::cursor_style(screen);
                                 }
                             }
                         }
@@ -265,9 +283,12 @@ pub fn render_client(client: &mut Client, frame: &mut Frame) {
                 let pane = group.tabs.get(group.active_tab);
                 let screen = pane.and_then(|p| client.pane_screen(p.id));
                 let fw_rect = ratatui::layout::Rect::new(fw.x, fw.y, fw.width, fw.height);
+                if let Some(pane) = pane {
+                    visible_tabs.insert(pane.id, content_area(fw_rect));
+                }
                 use ratatui::widgets::Clear;
                 frame.render_widget(Clear, fw_rect);
-                window_view::render_group_from_snapshot(
+                let content = window_view::render_group_from_snapshot(
                     group,
                     screen,
                     is_active,
@@ -278,6 +299,10 @@ pub fn render_client(client: &mut Client, frame: &mut Frame) {
                     frame,
                     fw_rect,
                 );
+                floating_rects.push(fw_rect);
+                if let (Some(pane), Some(content)) = (pane, content) {
+                    image_areas.push((pane.id, content, floating_rects.len()));
+                }
             }
         }
     }
@@ -290,6 +315,12 @@ pub fn render_client(client: &mut Client, frame: &mut Frame) {
                 palette::render(palette_state, theme, frame, frame.area());
             }
         }
+        Focus::BufferPicker => {
+            if let Some(ref bp_state) = client.buffer_picker_state {
+                dialog::dim_background(frame, frame.area());
+                buffer_picker::render(bp_state, theme, frame, frame.area());
+            }
+        }
         Focus::Confirm => {
             dialog::dim_background(frame, frame.area());
             render_confirm_dialog(client, theme, frame, frame.area());
@@ -350,6 +381,52 @@ pub fn render_client(client: &mut Client, frame: &mut Frame) {
         }
         _ => {}
     }
+
+    client.cursor_style = cursor_style;
+    client.set_visible_tabs(visible_tabs);
+    // Images are left out while an overlay covers the tabs
+    client.image_draws = if matches!(client.focus, Focus::Normal | Focus::Interact | Focus::Copy) {
+        image_draws(client, &image_areas, &floating_rects)
+    } else {
+        Vec::new()
+    };
+}
+
+/// Images the outer terminal can draw over their placeholders. Images under
+/// a floating window are skipped, as the window would be drawn over.
+fn image_draws(
+    client: &Client,
+    image_areas: &[(TabId, Rect, usize)],
+    floating_rects: &[Rect],
+) -> Vec<ImageDraw> {
+    let mut draws = Vec::new();
+    for &(tab, area, covered_from) in image_areas {
+        let Some(screen) = client.pane_screen(tab) else {
+            continue;
+        };
+        for (placement, rect, top) in window_view::image_rects(screen, area) {
+            let (rows, cols) = placement.image.size();
+            let clipped = top > 0 || rect.height < rows || rect.width < cols;
+            if !client.graphics.can_draw(placement.image, clipped)
+                || floating_rects[covered_from..]
+                    .iter()
+                    .any(|fw| fw.intersects(rect))
+            {
+                continue;
+            }
+            draws.push(ImageDraw {
+                tab,
+                id: placement.id,
+                protocol: placement.image.protocol(),
+                x: rect.x,
+                y: rect.y,
+                top,
+                rows: rect.height,
+                cols: rect.width,
+            });
+        }
+    }
+    draws
 }
 
 /// Compute the body area (below workspace bar, above status bar).
@@ -166,6 +166,7 @@ fn window(id: WindowId, tabs: Vec<(&str, TabId)>, name: Option<&str>) -> WindowS
             cwd: String::new(),
             cols: 80,
             rows: 24,
+            history_limit: 1000,
         })
         .collect();
     WindowSnapshot {
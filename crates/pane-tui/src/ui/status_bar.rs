@@ -54,6 +54,12 @@ pub fn get_buttons(client: &Client) -> &'static [(&'static str, &'static str)] {
             ("Enter", "run"),
             ("Esc", "cancel"),
         ],
+        Focus::BufferPicker => &[
+            ("type", "filter"),
+            ("Enter", "paste"),
+            ("^d", "delete"),
+            ("Esc", "cancel"),
+        ],
         Focus::Confirm => &[
             ("Enter/y", "confirm"),
             ("Esc/n", "cancel"),
@@ -129,6 +135,22 @@ pub fn render_client(client: &Client, theme: &Theme, frame: &mut Frame, area: Re
     let buttons = get_buttons(client);
     let hovered = client.hover.and_then(|(hx, hy)| hit_test(buttons, area, hx, hy));
     render_button_bar(buttons, hovered, theme, frame, area);
+    if client.read_only {
+        let label = " read-only ";
+        let width = label.width() as u16;
+        if area.width > width {
+            let rect = Rect::new(area.right() - width, area.y, width, 1);
+            frame.render_widget(
+                Paragraph::new(Span::styled(
+                    label,
+                    Style::default()
+                        .fg(theme.accent)
+                        .add_modifier(Modifier::BOLD),
+                )),
+                rect,
+            );
+        }
+    }
 }
 
 #[allow(dead_code)]
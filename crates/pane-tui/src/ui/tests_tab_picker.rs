@@ -73,6 +73,7 @@ fn window(id: WindowId, tabs: Vec<(&str, uuid::Uuid)>, name: Option<&str>) -> Wi
             cwd: String::new(),
             cols: 80,
             rows: 24,
+            history_limit: 1000,
         })
         .collect();
     WindowSnapshot {
@@ -3,6 +3,7 @@
 use std::collections::HashSet;
 
 use ratatui::backend::TestBackend;
+use ratatui::layout::Rect;
 use ratatui::style::Color;
 use ratatui::Terminal;
 
@@ -16,6 +17,7 @@ use pane_protocol::window_types::{TabKind, WindowId};
 
 use crate::client::Client;
 use crate::ui;
+use crate::ui::window_view::{cursor_in, screen_origin};
 
 const COLS: u16 = 120;
 const ROWS: u16 = 36;
@@ -160,6 +162,7 @@ fn window(id: WindowId, tabs: Vec<(&str, TabId)>, name: Option<&str>) -> WindowS
             cwd: String::new(),
             cols: 80,
             rows: 24,
+            history_limit: 1000,
         })
         .collect();
     WindowSnapshot {
@@ -470,3 +473,219 @@ fn floating_window_overlay() {
     let output = render_to_string(&mut client, COLS, ROWS);
     insta::assert_snapshot!("floating_window_overlay", output);
 }
+
+/// Passthrough is written at the tab's cursor; hidden tabs need `all`.
+#[test]
+fn passthrough_positions() {
+    use pane_protocol::config::AllowPassthrough;
+
+    let mut config = Config::default();
+    config.behavior.allow_passthrough = AllowPassthrough::On;
+    let mut client = Client::for_test(config);
+    let w1 = new_id();
+    let w2 = new_id();
+    let t1 = new_id();
+    let t2 = new_id();
+    let hidden = new_id();
+
+    client.render_state = RenderState {
+        workspaces: vec![workspace(
+            "dev",
+            vec![
+                window(w1, vec![("left", t1)], None),
+                window(w2, vec![("right", t2), ("hidden", hidden)], None),
+            ],
+            hsplit(LayoutNode::Leaf(w1), LayoutNode::Leaf(w2)),
+        )],
+        active_workspace: 0,
+    };
+    for id in [t1, t2, hidden] {
+        client.screens.insert(id, vt100::Parser::new(24, 80, 0));
+    }
+    client.screens.get_mut(&t2).unwrap().process(b"\x1b[3;5H");
+    render_to_string(&mut client, COLS, ROWS);
+
+    let right = client.visible_tabs[&t2];
+    client.passthrough_pending = vec![
+        (t1, b"one".to_vec()),
+        (t2, b"two".to_vec()),
+        (hidden, b"three".to_vec()),
+    ];
+    let written = client.take_passthrough();
+    assert_eq!(written.len(), 2);
+    assert_eq!(
+        written[0].0,
+        Some((client.visible_tabs[&t1].x, client.visible_tabs[&t1].y))
+    );
+    assert_eq!(
+        written[1],
+        (Some((right.x + 4, right.y + 2)), b"two".to_vec())
+    );
+
+    client.config.behavior.allow_passthrough = AllowPassthrough::All;
+    client.passthrough_pending = vec![(hidden, b"three".to_vec())];
+    assert_eq!(client.take_passthrough(), vec![(None, b"three".to_vec())]);
+}
+
+/// Only tabs that turned on mouse reporting take clicks under the pointer.
+#[test]
+fn tab_under_pointer_wants_mouse() {
+    let mut client = Client::for_test(Config::default());
+    let w1 = new_id();
+    let w2 = new_id();
+    let t1 = new_id();
+    let t2 = new_id();
+
+    client.render_state = RenderState {
+        workspaces: vec![workspace(
+            "dev",
+            vec![
+                window(w1, vec![("left", t1)], None),
+                window(w2, vec![("right", t2)], None),
+            ],
+            hsplit(LayoutNode::Leaf(w1), LayoutNode::Leaf(w2)),
+        )],
+        active_workspace: 0,
+    };
+    for id in [t1, t2] {
+        client.screens.insert(id, vt100::Parser::new(24, 80, 0));
+    }
+    client.screens.get_mut(&t2).unwrap().process(b"\x1b[?1000h");
+    render_to_string(&mut client, COLS, ROWS);
+
+    let left = client.visible_tabs[&t1];
+    let right = client.visible_tabs[&t2];
+    assert_eq!(client.tab_at(left.x, left.y), Some(t1));
+    assert_eq!(client.tab_at(right.x + 1, right.y + 1), Some(t2));
+    assert_eq!(client.tab_at(0, 0), None);
+    assert!(!client.tab_wants_mouse(left.x, left.y));
+    assert!(client.tab_wants_mouse(right.x + 1, right.y + 1));
+}
+
+#[test]
+fn images_get_placeholders_and_draws() {
+    use crate::graphics::GraphicsSupport;
+
+    let mut client = Client::for_test(Config::default());
+    let w1 = new_id();
+    let t1 = new_id();
+    client.render_state = RenderState {
+        workspaces: vec![workspace(
+            "dev",
+            vec![window(w1, vec![("shell", t1)], None)],
+            LayoutNode::Leaf(w1),
+        )],
+        active_workspace: 0,
+    };
+    let mut parser = vt100::Parser::new(24, 80, 100);
+    parser.process(b"\r\n\x1b_Ga=T,f=24,s=100,v=60,c=10,r=3;AAAA\x1b\\");
+    client.screens.insert(t1, parser);
+
+    // Without image support only the placeholder is drawn
+    let output = render_to_string(&mut client, COLS, ROWS);
+    assert!(output.contains("┌ image ─┐"), "{output}");
+    assert!(client.image_draws.is_empty());
+    assert!(client.take_image_output().is_none());
+
+    client.graphics = GraphicsSupport {
+        kitty: true,
+        sixel: false,
+    };
+    render_to_string(&mut client, COLS, ROWS);
+    let area = client.visible_tabs[&t1];
+    assert_eq!(client.image_draws.len(), 1);
+    let draw = &client.image_draws[0];
+    assert_eq!((draw.x, draw.y), (area.x, area.y + 1));
+    assert_eq!((draw.rows, draw.cols, draw.top), (3, 10, 0));
+
+    let images = client.take_image_output().unwrap();
+    assert!(images.repaint.is_empty());
+    let expected = format!(
+        "\x1b7\x1b[{};{}H\x1b_Ga=T,f=24,s=100,v=60,q=2,C=1,c=10,r=3,m=0;AAAA\x1b\\\x1b8",
+        area.y + 2,
+        area.x + 1
+    );
+    assert_eq!(String::from_utf8(images.bytes).unwrap(), expected);
+    // Nothing changed, nothing to write
+    assert!(client.take_image_output().is_none());
+
+    // Scrolling half of it off the top crops it
+    client.screens.get_mut(&t1).unwrap().process(b"\x1b[2S");
+    render_to_string(&mut client, COLS, ROWS);
+    let images = client.take_image_output().unwrap();
+    let bytes = String::from_utf8(images.bytes).unwrap();
+    assert!(bytes.starts_with("\x1b_Ga=d,d=A,q=2\x1b\\"), "{bytes:?}");
+    assert!(bytes.contains("c=10,r=2,x=0,y=20,w=100,h=40"), "{bytes:?}");
+
+    // Overlays hide images
+    client.focus = Focus::Palette;
+    render_to_string(&mut client, COLS, ROWS);
+    assert!(client.image_draws.is_empty());
+    let images = client.take_image_output().unwrap();
+    assert_eq!(images.bytes, b"\x1b_Ga=d,d=A,q=2\x1b\\");
+}
+
+/// Only the cells a sixel image covered are written again when it moves.
+#[test]
+fn moved_sixel_repaints_its_old_cells() {
+    use crate::graphics::GraphicsSupport;
+
+    let mut client = Client::for_test(Config::default());
+    let w1 = new_id();
+    let t1 = new_id();
+    client.render_state = RenderState {
+        workspaces: vec![workspace(
+            "dev",
+            vec![window(w1, vec![("shell", t1)], None)],
+            LayoutNode::Leaf(w1),
+        )],
+        active_workspace: 0,
+    };
+    let mut parser = vt100::Parser::new(24, 80, 100);
+    parser.process(b"\r\n\x1bPq#0;2;0;0;0#0~~-!20~\x1b\\");
+    client.screens.insert(t1, parser);
+    client.graphics = GraphicsSupport {
+        kitty: false,
+        sixel: true,
+    };
+    render_to_string(&mut client, COLS, ROWS);
+    let images = client.take_image_output().unwrap();
+    assert!(images.repaint.is_empty());
+    let old = client.image_draws[0].clone();
+
+    client.screens.get_mut(&t1).unwrap().process(b"\x1b[S");
+    render_to_string(&mut client, COLS, ROWS);
+    let images = client.take_image_output().unwrap();
+    assert_eq!(
+        images.repaint,
+        vec![Rect::new(old.x, old.y, old.cols, old.rows)]
+    );
+    assert!(!images.bytes.is_empty());
+}
+
+fn screen_with_cursor(rows: u16, cols: u16, row: u16, col: u16) -> vt100::Parser {
+    let mut parser = vt100::Parser::new(rows, cols, 0);
+    parser.process(format!("\x1b[{};{}H", row + 1, col + 1).as_bytes());
+    parser
+}
+
+#[test]
+fn test_screen_origin_fits_without_offset() {
+    let parser = screen_with_cursor(24, 80, 23, 79);
+    assert_eq!(
+        screen_origin(parser.screen(), Rect::new(0, 0, 80, 24)),
+        (0, 0)
+    );
+}
+
+#[test]
+fn test_screen_origin_follows_cursor() {
+    let parser = screen_with_cursor(40, 120, 39, 5);
+    let area = Rect::new(10, 5, 80, 24);
+    assert_eq!(screen_origin(parser.screen(), area), (16, 0));
+    assert_eq!(cursor_in(parser.screen(), area), Some((15, 28)));
+
+    let parser = screen_with_cursor(40, 120, 0, 119);
+    assert_eq!(screen_origin(parser.screen(), area), (0, 40));
+    assert_eq!(cursor_in(parser.screen(), area), Some((89, 5)));
+}
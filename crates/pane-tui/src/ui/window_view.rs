@@ -2,13 +2,14 @@ use ratatui::{
     layout::{Constraint, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, BorderType, Borders, Paragraph},
+    widgets::{Block, BorderType, Borders, Clear, Paragraph},
     Frame,
 };
 
 use pane_protocol::config::{Config, Theme};
 use crate::copy_mode::CopyModeState;
-use pane_protocol::layout::SplitDirection;
+use crate::tui::CursorStyle;
+use pane_protocol::layout::{viewport_origin, SplitDirection};
 use crate::window::terminal::{render_screen, render_screen_copy_mode};
 
 pub(crate) fn render_content(
@@ -20,39 +21,143 @@ pub(crate) fn render_content(
     // Clamp render area to the VT screen dimensions so the process content
     // renders at its actual size. When a smaller client is connected the PTY
     // is sized to the minimum, and larger clients show only that region
-    // instead of stretching the content to fill.
+    // instead of stretching the content to fill. Smaller clients see the
+    // part around the cursor, or around the copy mode cursor.
+    let render_area = screen_area(screen, area);
+    let lines: Vec<Line<'static>> = match cms {
+        Some(cms) => {
+            let origin = cms.view_origin((area.height, area.width));
+            render_screen_copy_mode(screen, render_area, origin, cms)
+        }
+        None => render_screen(screen, render_area, screen_origin(screen, area)),
+    };
+    let paragraph = Paragraph::new(lines);
+    frame.render_widget(paragraph, render_area);
+}
+
+/// Part of `area` the screen's content covers.
+fn screen_area(screen: &vt100::Screen, area: Rect) -> Rect {
     let (vt_rows, vt_cols) = screen.size();
-    let render_area = Rect::new(
+    Rect::new(
         area.x,
         area.y,
         area.width.min(vt_cols),
         area.height.min(vt_rows),
-    );
-    let lines: Vec<Line<'static>> = match cms {
-        Some(cms) => render_screen_copy_mode(screen, render_area, cms),
-        None => render_screen(screen, render_area),
-    };
-    let paragraph = Paragraph::new(lines);
-    frame.render_widget(paragraph, render_area);
+    )
+}
+
+/// The screen cell shown at the top left of `area`, as a row and column.
+/// See [`viewport_origin`].
+pub fn screen_origin(screen: &vt100::Screen, area: Rect) -> (u16, u16) {
+    viewport_origin(
+        screen.size(),
+        screen.cursor_position(),
+        (area.height, area.width),
+    )
+}
+
+/// Where a tab's cursor is on the outer terminal, given the `area` its
+/// content was drawn in, if it's in view.
+pub fn cursor_in(screen: &vt100::Screen, area: Rect) -> Option<(u16, u16)> {
+    let (origin_row, origin_col) = screen_origin(screen, area);
+    let (row, col) = screen.cursor_position();
+    let x = area.x + col.checked_sub(origin_col)?;
+    let y = area.y + row.checked_sub(origin_row)?;
+    (x < area.right() && y < area.bottom()).then_some((x, y))
+}
+
+/// Images on a tab's screen with the cells they cover in `area`, clipped to
+/// it, and how many of their rows scrolled off the top.
+pub fn image_rects(
+    screen: &vt100::Screen,
+    area: Rect,
+) -> Vec<(vt100::ImagePlacement<'_>, Rect, u16)> {
+    let (origin_row, origin_col) = screen_origin(screen, area);
+    let area = screen_area(screen, area);
+    screen
+        .images()
+        .into_iter()
+        .filter_map(|placement| {
+            let (rows, cols) = placement.image.size();
+            let top_row = placement.row - i32::from(origin_row);
+            let top = u16::try_from(-top_row.min(0)).unwrap_or(u16::MAX);
+            let row = u16::try_from(top_row.max(0)).unwrap_or(u16::MAX);
+            // Images cut off on the left aren't shown
+            let col = placement.col.checked_sub(origin_col)?;
+            if top >= rows || row >= area.height || col >= area.width {
+                return None;
+            }
+            let rect = Rect::new(
+                area.x + col,
+                area.y + row,
+                cols.min(area.width - col),
+                (rows - top).min(area.height - row),
+            );
+            Some((placement, rect, top))
+        })
+        .collect()
+}
+
+/// Draw a box over each image in a tab. Outer terminals that can show the
+/// image draw it over the box afterwards.
+fn render_image_placeholders(screen: &vt100::Screen, theme: &Theme, frame: &mut Frame, area: Rect) {
+    for (placement, rect, _) in image_rects(screen, area) {
+        let label = match placement.image.protocol() {
+            vt100::ImageProtocol::Sixel => " sixel ",
+            vt100::ImageProtocol::Kitty => " image ",
+        };
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(theme.dim))
+            .title(Span::styled(label, Style::default().fg(theme.dim)));
+        frame.render_widget(Clear, rect);
+        frame.render_widget(block, rect);
+    }
+}
+
+/// Cursor shape and color a tab asked for, to show while it has focus.
+pub fn cursor_style(screen: &vt100::Screen) -> CursorStyle {
+    CursorStyle {
+        shape: screen.cursor_shape(),
+        color: screen.cursor_color().map(str::to_string),
+    }
 }
 
 fn render_search_bar(cms: &CopyModeState, theme: &Theme, frame: &mut Frame, area: Rect) {
-    let line = Line::from(vec![
+    let prompt = if cms.search_forward { "/" } else { "?" };
+    let mut spans = vec![
         Span::styled(
-            "/",
+            prompt,
             Style::default()
                 .fg(theme.accent)
                 .add_modifier(Modifier::BOLD),
         ),
         Span::styled(&cms.search_query, Style::default().fg(theme.fg)),
-        Span::styled("_", Style::default().fg(theme.dim)),
-    ]);
-    let paragraph = Paragraph::new(line);
+    ];
+    if cms.search_active {
+        spans.push(Span::styled("_", Style::default().fg(theme.dim)));
+    }
+
+    let counter = match cms.match_counter() {
+        (_, 0) if cms.search_query.is_empty() => String::new(),
+        (_, 0) => "no matches".to_string(),
+        (Some(current), total) => format!("{} of {}", current, total),
+        (None, total) => format!("{} matches", total),
+    };
+    let used: usize = spans.iter().map(|s| s.width()).sum();
+    let pad = (area.width as usize).saturating_sub(used + counter.len());
+    if !counter.is_empty() && pad > 0 {
+        spans.push(Span::raw(" ".repeat(pad)));
+        spans.push(Span::styled(counter, Style::default().fg(theme.dim)));
+    }
+
+    let paragraph = Paragraph::new(Line::from(spans));
     frame.render_widget(paragraph, area);
 }
 
 /// Render a pane group from a snapshot (used by the client).
 /// Receives the active tab's vt100 screen directly instead of accessing the Pane struct.
+/// Returns the area the tab's content was drawn in, unless it's in copy mode.
 #[allow(clippy::too_many_arguments)]
 pub fn render_group_from_snapshot(
     group: &pane_protocol::protocol::WindowSnapshot,
@@ -64,7 +169,7 @@ pub fn render_group_from_snapshot(
     hover: Option<(u16, u16)>,
     frame: &mut Frame,
     area: Rect,
-) {
+) -> Option<Rect> {
     let theme = &config.theme;
 
     // Check if the active pane's foreground process has a decoration
@@ -91,14 +196,14 @@ pub fn render_group_from_snapshot(
     frame.render_widget(block, area);
 
     if inner.width <= 2 || inner.height == 0 {
-        return;
+        return None;
     }
 
     // 1-cell padding inside the border on each side
     let padded = Rect::new(inner.x + 1, inner.y, inner.width - 2, inner.height);
 
     let cms = if is_active { copy_mode_state } else { None };
-    let show_search = cms.is_some_and(|c| c.search_active);
+    let show_search = cms.is_some_and(|c| c.search_active || !c.search_query.is_empty());
     let mut constraints = vec![Constraint::Length(1), Constraint::Fill(1)];
     if show_search {
         constraints.push(Constraint::Length(1));
@@ -111,6 +216,10 @@ pub fn render_group_from_snapshot(
 
     if let Some(screen) = screen {
         render_content(screen, cms, frame, content_area);
+        if cms.is_none() {
+            render_image_placeholders(screen, theme, frame, content_area);
+            render_viewport_indicator(screen, chrome_color, frame, area, content_area);
+        }
     }
 
     if show_search {
@@ -118,6 +227,41 @@ pub fn render_group_from_snapshot(
             render_search_bar(cms.unwrap(), theme, frame, search_area);
         }
     }
+    cms.is_none().then_some(content_area)
+}
+
+/// When a tab is bigger than the space it's drawn in, show its size and the
+/// part in view on the bottom border of the window.
+fn render_viewport_indicator(
+    screen: &vt100::Screen,
+    color: Color,
+    frame: &mut Frame,
+    area: Rect,
+    content_area: Rect,
+) {
+    let (rows, cols) = screen.size();
+    if rows <= content_area.height && cols <= content_area.width {
+        return;
+    }
+    let (row, col) = screen_origin(screen, content_area);
+    let label = format!(
+        " {}x{} at {},{} of {}x{} ",
+        content_area.width.min(cols),
+        content_area.height.min(rows),
+        col,
+        row,
+        cols,
+        rows
+    );
+    let width = label.len() as u16;
+    if area.height < 2 || width + 4 > area.width {
+        return;
+    }
+    let rect = Rect::new(area.right() - width - 2, area.bottom() - 1, width, 1);
+    frame.render_widget(
+        Paragraph::new(Span::styled(label, Style::default().fg(color))),
+        rect,
+    );
 }
 
 /// Maximum display width for a single tab title (excluding padding).
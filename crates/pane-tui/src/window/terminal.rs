@@ -5,24 +5,28 @@ use ratatui::{
 };
 use unicode_width::UnicodeWidthStr;
 
-/// Convert a vt100 screen to ratatui Lines for rendering.
-pub fn render_screen(screen: &vt100::Screen, area: Rect) -> Vec<Line<'static>> {
-    render_screen_inner(screen, area, None)
+/// Convert a vt100 screen to ratatui Lines for rendering, starting from the
+/// cell at `origin` (row, column) when the screen is bigger than `area`.
+pub fn render_screen(screen: &vt100::Screen, area: Rect, origin: (u16, u16)) -> Vec<Line<'static>> {
+    render_screen_inner(screen, area, origin, None)
 }
 
-/// Render screen with copy mode highlights overlaid.
-#[allow(dead_code)]
+/// Render the copy mode viewport, which may be scrolled into the history,
+/// with selection and search highlights overlaid. `origin` is the viewport
+/// cell shown at the top left of `area`.
 pub fn render_screen_copy_mode(
     screen: &vt100::Screen,
     area: Rect,
+    origin: (u16, u16),
     cms: &crate::copy_mode::CopyModeState,
 ) -> Vec<Line<'static>> {
-    render_screen_inner(screen, area, Some(cms))
+    render_screen_inner(screen, area, origin, Some(cms))
 }
 
 fn render_screen_inner(
     screen: &vt100::Screen,
     area: Rect,
+    (origin_row, origin_col): (u16, u16),
     cms: Option<&crate::copy_mode::CopyModeState>,
 ) -> Vec<Line<'static>> {
     let rows = area.height as usize;
@@ -35,8 +39,14 @@ fn render_screen_inner(
         let mut current_style = Style::default();
         let mut rendered_cols = 0usize;
 
-        for col in 0..cols {
-            let cell = screen.cell(row as u16, col as u16);
+        // In copy mode rows are addressed from the start of the scrollback
+        let abs_row = cms.map_or(row, |cms| cms.view_top + usize::from(origin_row) + row);
+
+        for col in (0..cols).map(|col| col + usize::from(origin_col)) {
+            let cell = match cms {
+                Some(_) => screen.history_cell(abs_row, col as u16),
+                None => screen.cell((row as u16).saturating_add(origin_row), col as u16),
+            };
 
             // Skip wide char continuation cells — the wide char already occupies 2 columns
             if cell.as_ref().is_some_and(|c| c.is_wide_continuation()) {
@@ -50,11 +60,11 @@ fn render_screen_inner(
 
             // Apply copy mode overlays
             if let Some(cms) = cms {
-                if (row == cms.cursor_row && col == cms.cursor_col)
-                    || cms.is_selected(row, col)
+                if (abs_row == cms.cursor_row && col == cms.cursor_col)
+                    || cms.is_selected(abs_row, col)
                 {
                     style = style.add_modifier(Modifier::REVERSED);
-                } else if cms.is_search_match(row, col) {
+                } else if cms.is_search_match(abs_row, col) {
                     style = style.bg(Color::Yellow).fg(Color::Black);
                 }
             }
@@ -172,7 +182,7 @@ mod tests {
     fn test_render_empty_screen() {
         let parser = make_screen(5, 10, b"");
         let area = Rect::new(0, 0, 10, 5);
-        let lines = render_screen(parser.screen(), area);
+        let lines = render_screen(parser.screen(), area, (0, 0));
 
         assert_eq!(lines.len(), 5);
         for line in &lines {
@@ -186,7 +196,7 @@ mod tests {
     fn test_render_plain_text() {
         let parser = make_screen(5, 20, b"Hello, world!");
         let area = Rect::new(0, 0, 20, 5);
-        let lines = render_screen(parser.screen(), area);
+        let lines = render_screen(parser.screen(), area, (0, 0));
 
         let first_line: String = lines[0].spans.iter().map(|s| s.content.as_ref()).collect();
         assert!(first_line.starts_with("Hello, world!"));
@@ -196,7 +206,7 @@ mod tests {
     fn test_render_multiline() {
         let parser = make_screen(5, 20, b"line one\r\nline two\r\nline three");
         let area = Rect::new(0, 0, 20, 5);
-        let lines = render_screen(parser.screen(), area);
+        let lines = render_screen(parser.screen(), area, (0, 0));
 
         let line0: String = lines[0].spans.iter().map(|s| s.content.as_ref()).collect();
         let line1: String = lines[1].spans.iter().map(|s| s.content.as_ref()).collect();
@@ -211,7 +221,7 @@ mod tests {
         // ESC[1m = bold on, ESC[0m = reset
         let parser = make_screen(3, 20, b"\x1b[1mbold\x1b[0m normal");
         let area = Rect::new(0, 0, 20, 3);
-        let lines = render_screen(parser.screen(), area);
+        let lines = render_screen(parser.screen(), area, (0, 0));
 
         // The bold span should have BOLD modifier
         let bold_span = &lines[0].spans[0];
@@ -224,7 +234,7 @@ mod tests {
         // ESC[31m = red foreground
         let parser = make_screen(3, 20, b"\x1b[31mred text\x1b[0m");
         let area = Rect::new(0, 0, 20, 3);
-        let lines = render_screen(parser.screen(), area);
+        let lines = render_screen(parser.screen(), area, (0, 0));
 
         let red_span = &lines[0].spans[0];
         assert!(red_span.content.starts_with("red text"));
@@ -236,7 +246,7 @@ mod tests {
         // ESC[38;2;255;128;0m = RGB foreground (255, 128, 0)
         let parser = make_screen(3, 30, b"\x1b[38;2;255;128;0morange\x1b[0m");
         let area = Rect::new(0, 0, 30, 3);
-        let lines = render_screen(parser.screen(), area);
+        let lines = render_screen(parser.screen(), area, (0, 0));
 
         let span = &lines[0].spans[0];
         assert!(span.content.starts_with("orange"));
@@ -248,7 +258,7 @@ mod tests {
         let parser = make_screen(10, 40, b"visible\r\nline2\r\nline3");
         // Only render 2 rows x 10 cols
         let area = Rect::new(0, 0, 10, 2);
-        let lines = render_screen(parser.screen(), area);
+        let lines = render_screen(parser.screen(), area, (0, 0));
 
         assert_eq!(lines.len(), 2);
         let line0: String = lines[0].spans.iter().map(|s| s.content.as_ref()).collect();
@@ -260,7 +270,7 @@ mod tests {
         // ESC[3m = italic, ESC[4m = underline
         let parser = make_screen(3, 30, b"\x1b[3mitalic\x1b[0m \x1b[4munderline\x1b[0m");
         let area = Rect::new(0, 0, 30, 3);
-        let lines = render_screen(parser.screen(), area);
+        let lines = render_screen(parser.screen(), area, (0, 0));
 
         let italic_span = &lines[0].spans[0];
         assert!(italic_span.content.starts_with("italic"));
@@ -272,7 +282,7 @@ mod tests {
         // CJK character "中" is a wide char occupying 2 columns
         let parser = make_screen(3, 20, "中文".as_bytes());
         let area = Rect::new(0, 0, 20, 3);
-        let lines = render_screen(parser.screen(), area);
+        let lines = render_screen(parser.screen(), area, (0, 0));
 
         let first_line: String = lines[0].spans.iter().map(|s| s.content.as_ref()).collect();
         // Wide chars should not have extra spaces from continuation cells
@@ -286,7 +296,7 @@ mod tests {
     fn test_render_zero_area() {
         let parser = make_screen(5, 10, b"hello");
         let area = Rect::new(0, 0, 0, 0);
-        let lines = render_screen(parser.screen(), area);
+        let lines = render_screen(parser.screen(), area, (0, 0));
         assert!(lines.is_empty());
     }
 
@@ -295,7 +305,7 @@ mod tests {
         // ESC[7m = inverse
         let parser = make_screen(3, 20, b"\x1b[7minverted\x1b[0m normal");
         let area = Rect::new(0, 0, 20, 3);
-        let lines = render_screen(parser.screen(), area);
+        let lines = render_screen(parser.screen(), area, (0, 0));
 
         let inv_span = &lines[0].spans[0];
         assert!(inv_span.content.starts_with("inverted"));
@@ -307,7 +317,7 @@ mod tests {
         // ESC[9m = strikethrough
         let parser = make_screen(3, 20, b"\x1b[9mstruck\x1b[0m rest");
         let area = Rect::new(0, 0, 20, 3);
-        let lines = render_screen(parser.screen(), area);
+        let lines = render_screen(parser.screen(), area, (0, 0));
 
         let struck_span = &lines[0].spans[0];
         assert!(struck_span.content.starts_with("struck"));
@@ -324,7 +334,7 @@ mod tests {
         // ESC[2m = dim
         let parser = make_screen(3, 20, b"\x1b[2mdimmed\x1b[0m bright");
         let area = Rect::new(0, 0, 20, 3);
-        let lines = render_screen(parser.screen(), area);
+        let lines = render_screen(parser.screen(), area, (0, 0));
 
         let dim_span = &lines[0].spans[0];
         assert!(dim_span.content.starts_with("dimmed"));
@@ -336,7 +346,7 @@ mod tests {
         // ESC[5m = blink
         let parser = make_screen(3, 20, b"\x1b[5mblink\x1b[0m");
         let area = Rect::new(0, 0, 20, 3);
-        let lines = render_screen(parser.screen(), area);
+        let lines = render_screen(parser.screen(), area, (0, 0));
 
         let blink_span = &lines[0].spans[0];
         assert!(blink_span.content.starts_with("blink"));
@@ -353,7 +363,7 @@ mod tests {
         // ESC[1;3;4m = bold + italic + underline
         let parser = make_screen(3, 30, b"\x1b[1;3;4mcombined\x1b[0m");
         let area = Rect::new(0, 0, 30, 3);
-        let lines = render_screen(parser.screen(), area);
+        let lines = render_screen(parser.screen(), area, (0, 0));
 
         let span = &lines[0].spans[0];
         assert!(span.content.starts_with("combined"));
@@ -367,7 +377,7 @@ mod tests {
         // Move cursor to row 2, col 5: ESC[3;6H (1-based)
         let parser = make_screen(5, 20, b"\x1b[3;6HX");
         let area = Rect::new(0, 0, 20, 5);
-        let lines = render_screen(parser.screen(), area);
+        let lines = render_screen(parser.screen(), area, (0, 0));
 
         // Row 2 (0-indexed) should have 'X' at col 5
         let row2: String = lines[2].spans.iter().map(|s| s.content.as_ref()).collect();
@@ -378,7 +388,7 @@ mod tests {
     fn test_render_cursor_at_origin() {
         let parser = make_screen(3, 10, b"A");
         let area = Rect::new(0, 0, 10, 3);
-        let lines = render_screen(parser.screen(), area);
+        let lines = render_screen(parser.screen(), area, (0, 0));
 
         let row0: String = lines[0].spans.iter().map(|s| s.content.as_ref()).collect();
         assert_eq!(row0.chars().next(), Some('A'));
@@ -389,7 +399,7 @@ mod tests {
         // Write text that fills to the last column
         let parser = make_screen(3, 5, b"ABCDE");
         let area = Rect::new(0, 0, 5, 3);
-        let lines = render_screen(parser.screen(), area);
+        let lines = render_screen(parser.screen(), area, (0, 0));
 
         let row0: String = lines[0].spans.iter().map(|s| s.content.as_ref()).collect();
         assert_eq!(row0, "ABCDE");
@@ -401,7 +411,7 @@ mod tests {
         let mut parser = vt100::Parser::new(3, 10, 10);
         parser.process(b"line1\r\nline2\r\nline3\r\nline4\r\nline5");
         let area = Rect::new(0, 0, 10, 3);
-        let lines = render_screen(parser.screen(), area);
+        let lines = render_screen(parser.screen(), area, (0, 0));
 
         // Should render only visible rows (last 3 lines)
         assert_eq!(lines.len(), 3);
@@ -418,7 +428,7 @@ mod tests {
         // Test with a mix of ASCII, wide chars, and empty cells
         let parser = make_screen(3, 20, "AB中CD".as_bytes());
         let area = Rect::new(0, 0, 20, 3);
-        let lines = render_screen(parser.screen(), area);
+        let lines = render_screen(parser.screen(), area, (0, 0));
 
         for line in &lines {
             let total_width: usize = line
@@ -437,7 +447,7 @@ mod tests {
     fn test_all_cells_display_width_plain_text() {
         let parser = make_screen(2, 15, b"hello world");
         let area = Rect::new(0, 0, 15, 2);
-        let lines = render_screen(parser.screen(), area);
+        let lines = render_screen(parser.screen(), area, (0, 0));
 
         for line in &lines {
             let total_width: usize = line
@@ -454,7 +464,7 @@ mod tests {
         // Bold red text followed by normal: both spans should sum to area width
         let parser = make_screen(2, 20, b"\x1b[1;31mred\x1b[0m normal");
         let area = Rect::new(0, 0, 20, 2);
-        let lines = render_screen(parser.screen(), area);
+        let lines = render_screen(parser.screen(), area, (0, 0));
 
         for line in &lines {
             let total_width: usize = line
@@ -471,7 +481,7 @@ mod tests {
         // ESC[41m = red background
         let parser = make_screen(3, 20, b"\x1b[41mhi\x1b[0m");
         let area = Rect::new(0, 0, 20, 3);
-        let lines = render_screen(parser.screen(), area);
+        let lines = render_screen(parser.screen(), area, (0, 0));
 
         let span = &lines[0].spans[0];
         assert!(span.content.starts_with("hi"));
@@ -483,7 +493,7 @@ mod tests {
         // Area is wider than the screen — cells beyond screen should be spaces
         let parser = make_screen(3, 5, b"AB");
         let area = Rect::new(0, 0, 10, 3);
-        let lines = render_screen(parser.screen(), area);
+        let lines = render_screen(parser.screen(), area, (0, 0));
 
         let row0: String = lines[0].spans.iter().map(|s| s.content.as_ref()).collect();
         // First 5 cols come from screen, remaining 5 are None → spaces
@@ -497,7 +507,7 @@ mod tests {
         // Screen width = 5, write "abc" then a wide char
         let parser = make_screen(3, 5, "abc中".as_bytes());
         let area = Rect::new(0, 0, 5, 3);
-        let lines = render_screen(parser.screen(), area);
+        let lines = render_screen(parser.screen(), area, (0, 0));
 
         let row0: String = lines[0].spans.iter().map(|s| s.content.as_ref()).collect();
         let total_width: usize = lines[0]
@@ -508,4 +518,24 @@ mod tests {
         assert_eq!(total_width, 5, "wide char at end should still fill width exactly");
         assert!(row0.contains("中") || row0.len() == 5);
     }
+
+    #[test]
+    fn test_render_screen_from_origin() {
+        let parser = make_screen(3, 6, b"abcdef\r\nghijkl\r\nmnopqr");
+        let lines = render_screen(parser.screen(), Rect::new(0, 0, 3, 2), (1, 2));
+        let rows: Vec<String> = lines
+            .iter()
+            .map(|l| l.spans.iter().map(|s| s.content.as_ref()).collect())
+            .collect();
+        assert_eq!(rows, vec!["ijk", "opq"]);
+    }
+
+    #[test]
+    fn test_grapheme_clusters_render_in_one_cell() {
+        let parser = make_screen(2, 10, "👨‍👩‍👧|🇯🇵|".as_bytes());
+        let lines = render_screen(parser.screen(), Rect::new(0, 0, 10, 2), (0, 0));
+        let row0: String = lines[0].spans.iter().map(|s| s.content.as_ref()).collect();
+        assert_eq!(row0, "👨‍👩‍👧|🇯🇵|    ");
+        assert_eq!(UnicodeWidthStr::width(row0.as_str()), 10);
+    }
 }
@@ -45,6 +45,14 @@ pub struct Attrs {
 }
 
 impl Attrs {
+    pub(crate) const DEFAULT: Self = Self {
+        fgcolor: Color::Default,
+        bgcolor: Color::Default,
+        mode: 0,
+        underline_style: UnderlineStyle::None,
+        underline_color: Color::Default,
+    };
+
     pub fn bold(&self) -> bool {
         self.mode & TEXT_MODE_BOLD != 0
     }
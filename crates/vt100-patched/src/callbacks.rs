@@ -34,8 +34,9 @@ pub trait Callbacks {
     /// This callback is called when the terminal requests data to be pasted
     /// from the system clipboard (typically with `\e]52;<ty>;?\a`).
     fn paste_from_clipboard(&mut self, _: &mut crate::Screen, _ty: &[u8]) {}
-    /// This callback is called when the terminal receives an escape sequence
-    /// which is otherwise not implemented.
+    /// This callback is called when the terminal receives a character which
+    /// is otherwise not implemented, or which continues a grapheme cluster
+    /// too long to store.
     fn unhandled_char(&mut self, _: &mut crate::Screen, _c: char) {}
     /// This callback is called when the terminal receives a control
     /// character which is otherwise not implemented.
@@ -64,6 +65,15 @@ pub trait Callbacks {
     /// This callback is called when the terminal receives a OSC sequence
     /// (`\e]`) which is otherwise not implemented.
     fn unhandled_osc(&mut self, _: &mut crate::Screen, _params: &[&[u8]]) {}
+    /// This callback is called when a kitty graphics command (`\e_G`) with
+    /// an image id asks for a reply: `OK`, or the given error.
+    fn kitty_graphics_reply(
+        &mut self,
+        _: &mut crate::Screen,
+        _id: u32,
+        _error: Option<&str>,
+    ) {
+    }
 }
 
 impl Callbacks for () {}
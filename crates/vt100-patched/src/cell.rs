@@ -1,48 +1,73 @@
-use unicode_width::UnicodeWidthChar as _;
+use unicode_width::{UnicodeWidthChar as _, UnicodeWidthStr as _};
 
 const CONTENT_BYTES: usize = 22;
 
+// longer grapheme clusters, such as emoji ZWJ sequences for families, are
+// kept on the heap up to this many bytes
+const MAX_CLUSTER_BYTES: usize = 256;
+
 const IS_WIDE: u8 = 0b1000_0000;
 const IS_WIDE_CONTINUATION: u8 = 0b0100_0000;
-const LEN_BITS: u8 = 0b0001_1111;
+
+#[derive(Clone, Debug)]
+enum Contents {
+    // the bytes of a grapheme cluster that fits in the cell, and how many
+    // of them are used
+    Inline([u8; CONTENT_BYTES], u8),
+    // a grapheme cluster too long to fit, owned by the cell so it goes
+    // away with it
+    Long(Box<str>),
+}
 
 /// Represents a single terminal cell.
-#[derive(Clone, Debug, Eq)]
+#[derive(Clone, Debug)]
 pub struct Cell {
-    contents: [u8; CONTENT_BYTES],
-    len: u8,
+    contents: Contents,
+    flags: u8,
     attrs: crate::attrs::Attrs,
 }
 
 impl PartialEq<Self> for Cell {
     fn eq(&self, other: &Self) -> bool {
-        if self.len != other.len {
-            return false;
-        }
-        if self.attrs != other.attrs {
-            return false;
-        }
-        let len = self.len();
-        self.contents[..len] == other.contents[..len]
+        self.flags == other.flags
+            && self.attrs == other.attrs
+            && self.contents() == other.contents()
     }
 }
 
+impl Eq for Cell {}
+
 impl Cell {
+    pub(crate) const BLANK: Self = Self {
+        contents: Contents::Inline([0; CONTENT_BYTES], 0),
+        flags: 0,
+        attrs: crate::attrs::Attrs::DEFAULT,
+    };
+
     pub(crate) fn new() -> Self {
-        Self {
-            contents: Default::default(),
-            len: 0,
-            attrs: crate::attrs::Attrs::default(),
-        }
+        Self::BLANK
     }
 
+    // bytes of contents
     fn len(&self) -> usize {
-        usize::from(self.len & LEN_BITS)
+        match &self.contents {
+            Contents::Inline(_, len) => usize::from(*len),
+            Contents::Long(cluster) => cluster.len(),
+        }
+    }
+
+    // bytes the cell holds on the heap
+    pub(crate) fn heap_bytes(&self) -> usize {
+        match &self.contents {
+            Contents::Inline(..) => 0,
+            Contents::Long(cluster) => cluster.len(),
+        }
     }
 
     pub(crate) fn set(&mut self, c: char, a: crate::attrs::Attrs) {
-        self.len = 0;
-        self.append_char(0, c);
+        self.contents = Contents::Inline([0; CONTENT_BYTES], 0);
+        self.flags = 0;
+        self.append_char(c);
         // strings in this context should always be an arbitrary character
         // followed by zero or more zero-width characters, so we should only
         // have to look at the first character
@@ -50,42 +75,88 @@ impl Cell {
         self.attrs = a;
     }
 
-    pub(crate) fn append(&mut self, c: char) {
-        let len = self.len();
-        if len >= CONTENT_BYTES - 4 {
-            return;
+    // adds c to the grapheme cluster in the cell. returns false if the
+    // cluster is too long to grow any more.
+    pub(crate) fn append(&mut self, c: char) -> bool {
+        if !self.has_contents() {
+            self.append_char(' ');
+        }
+        if self.len() + c.len_utf8() > MAX_CLUSTER_BYTES {
+            return false;
         }
-        if len == 0 {
-            self.contents[0] = b' ';
-            self.len += 1;
+        self.append_char(c);
+        true
+    }
+
+    // whether c continues the grapheme cluster in this cell rather than
+    // starting a new one
+    pub(crate) fn continues_cluster(&self, c: char) -> bool {
+        // nothing joins onto ascii except CR LF, which never reaches a cell
+        if !self.has_contents() || c.is_ascii() {
+            return false;
         }
+        let contents = self.contents();
+        let mut cluster = String::with_capacity(contents.len() + 4);
+        cluster.push_str(contents);
+        cluster.push(c);
+        let mut cursor = unicode_segmentation::GraphemeCursor::new(
+            contents.len(),
+            cluster.len(),
+            true,
+        );
+        // the whole string is one chunk, so no more context is ever needed
+        !cursor.is_boundary(&cluster, 0).unwrap_or(true)
+    }
 
-        // we already checked that we have space for another codepoint
-        self.append_char(self.len(), c);
+    // the number of columns the grapheme cluster in this cell takes up
+    pub(crate) fn cluster_width(&self) -> u16 {
+        if self.contents().width() > 1 {
+            2
+        } else {
+            1
+        }
     }
 
-    // Writes bytes representing c at start
-    // Requires caller to verify start <= CODEPOINTS_IN_CELL * 4
-    fn append_char(&mut self, start: usize, c: char) {
-        c.encode_utf8(&mut self.contents[start..]);
-        self.len += u8::try_from(c.len_utf8()).unwrap();
+    // adds c to the end of the contents, moving them to the heap once
+    // they outgrow the cell
+    fn append_char(&mut self, c: char) {
+        match &mut self.contents {
+            Contents::Inline(bytes, len)
+                if usize::from(*len) + c.len_utf8() <= CONTENT_BYTES =>
+            {
+                c.encode_utf8(&mut bytes[usize::from(*len)..]);
+                // at most CONTENT_BYTES, so it fits in a u8
+                *len += u8::try_from(c.len_utf8()).unwrap();
+            }
+            _ => {
+                let mut cluster = String::from(self.contents());
+                cluster.push(c);
+                self.contents = Contents::Long(cluster.into_boxed_str());
+            }
+        }
     }
 
     pub(crate) fn clear(&mut self, attrs: crate::attrs::Attrs) {
-        self.len = 0;
+        self.contents = Contents::Inline([0; CONTENT_BYTES], 0);
+        self.flags = 0;
         self.attrs = attrs;
     }
 
     /// Returns the text contents of the cell.
     ///
     /// Can include multiple unicode characters if combining characters are
-    /// used, but will contain at most one character with a non-zero character
-    /// width.
+    /// used. With grapheme clustering (mode 2027, on by default) this is a
+    /// whole grapheme cluster, such as an emoji ZWJ sequence or a flag.
     // Since contents has been constructed by appending chars encoded as UTF-8 it will be valid UTF-8
     #[allow(clippy::missing_panics_doc)]
     #[must_use]
     pub fn contents(&self) -> &str {
-        std::str::from_utf8(&self.contents[..self.len()]).unwrap()
+        match &self.contents {
+            Contents::Inline(bytes, len) => {
+                std::str::from_utf8(&bytes[..usize::from(*len)]).unwrap()
+            }
+            Contents::Long(cluster) => cluster,
+        }
     }
 
     /// Returns whether the cell contains any text data.
@@ -97,7 +168,7 @@ impl Cell {
     /// Returns whether the text data in the cell represents a wide character.
     #[must_use]
     pub fn is_wide(&self) -> bool {
-        self.len & IS_WIDE != 0
+        self.flags & IS_WIDE != 0
     }
 
     /// Returns whether the cell contains the second half of a wide character
@@ -105,22 +176,22 @@ impl Cell {
     /// character)
     #[must_use]
     pub fn is_wide_continuation(&self) -> bool {
-        self.len & IS_WIDE_CONTINUATION != 0
+        self.flags & IS_WIDE_CONTINUATION != 0
     }
 
-    fn set_wide(&mut self, wide: bool) {
+    pub(crate) fn set_wide(&mut self, wide: bool) {
         if wide {
-            self.len |= IS_WIDE;
+            self.flags |= IS_WIDE;
         } else {
-            self.len &= !IS_WIDE;
+            self.flags &= !IS_WIDE;
         }
     }
 
     pub(crate) fn set_wide_continuation(&mut self, wide: bool) {
         if wide {
-            self.len |= IS_WIDE_CONTINUATION;
+            self.flags |= IS_WIDE_CONTINUATION;
         } else {
-            self.len &= !IS_WIDE_CONTINUATION;
+            self.flags &= !IS_WIDE_CONTINUATION;
         }
     }
 
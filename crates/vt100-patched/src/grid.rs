@@ -13,6 +13,7 @@ pub struct Grid {
     scrollback: std::collections::VecDeque<crate::row::Row>,
     scrollback_len: usize,
     scrollback_offset: usize,
+    scrollback_bytes: usize,
 }
 
 impl Grid {
@@ -29,6 +30,7 @@ impl Grid {
             scrollback: std::collections::VecDeque::new(),
             scrollback_len,
             scrollback_offset: 0,
+            scrollback_bytes: 0,
         }
     }
 
@@ -76,7 +78,11 @@ impl Grid {
 
         self.size = size;
         for row in &mut self.rows {
-            row.resize(size.cols, crate::Cell::new());
+            // resizing also unwraps the row, so leave rows that are already
+            // the right width alone
+            if row.cols() != size.cols {
+                row.resize(size.cols, crate::Cell::new());
+            }
         }
         self.rows.resize(usize::from(size.rows), self.new_row());
 
@@ -99,6 +105,162 @@ impl Grid {
         }
     }
 
+    // like set_size, but soft-wrapped lines in the scrollback and the
+    // drawing area are rewrapped to the new width instead of being cut off
+    pub fn set_size_reflow(&mut self, size: Size) {
+        if size.cols != self.size.cols && !self.rows.is_empty() {
+            self.reflow(size.cols);
+        }
+        self.set_size(size);
+    }
+
+    // rewraps every line to `cols` columns, keeping the cursor on the same
+    // character. the number of drawing rows is left alone, set_size takes
+    // care of that afterwards.
+    fn reflow(&mut self, cols: u16) {
+        let cursor_row = self.scrollback.len() + usize::from(self.pos.row);
+        let cursor_col = usize::from(self.pos.col);
+        // the cursor sits past the last column after writing to it
+        let pending_wrap = self.pos.col >= self.size.cols;
+
+        let mut old: Vec<crate::row::Row> = self
+            .scrollback
+            .drain(..)
+            .chain(self.rows.drain(..))
+            .collect();
+        // blank rows below the cursor are just unused screen space
+        while old.len() > cursor_row + 1
+            && old.last().is_some_and(|row| {
+                row.content_len() == 0
+                    && !row.wrapped()
+                    && row.images().is_empty()
+            })
+        {
+            old.pop();
+        }
+
+        let mut rows = Vec::with_capacity(old.len());
+        let mut cursor = (0, 0);
+        let mut line: Vec<crate::Cell> = vec![];
+        let mut line_cursor = None;
+        // images in the line, by offset into it
+        let mut line_images = vec![];
+        for (i, row) in old.iter().enumerate() {
+            if i == cursor_row {
+                line_cursor = Some(line.len() + cursor_col);
+            }
+            line_images.extend(row.images().iter().map(|anchor| {
+                (line.len() + usize::from(anchor.col), anchor.clone())
+            }));
+            let wrapped = row.wrapped() && i + 1 < old.len();
+            let len = if wrapped {
+                row.cols()
+            } else {
+                row.content_len()
+            };
+            line.extend((0..len).filter_map(|col| row.get(col)).cloned());
+            if wrapped {
+                // drop the padding left by a previous reflow when a wide
+                // character didn't fit at the end of the row
+                if old[i + 1].get(0).is_some_and(crate::Cell::is_wide)
+                    && line.last().is_some_and(|cell| {
+                        !cell.has_contents() && !cell.is_wide_continuation()
+                    })
+                {
+                    line.pop();
+                }
+                continue;
+            }
+            let line_cursor = line_cursor.take().map(|c| (c, pending_wrap));
+            let first_row = rows.len();
+            if let Some(pos) = Self::rewrap_line(
+                &mut rows,
+                std::mem::take(&mut line),
+                cols,
+                line_cursor,
+            ) {
+                cursor = pos;
+            }
+            let width = usize::from(cols);
+            for (offset, mut anchor) in line_images.drain(..) {
+                let row = (first_row + offset / width).min(rows.len() - 1);
+                // the remainder is less than cols
+                anchor.col = (offset % width).try_into().unwrap();
+                rows[row].anchor_image(anchor);
+            }
+        }
+
+        let screen_rows = usize::from(self.size.rows);
+        let start = rows.len().saturating_sub(screen_rows).min(cursor.0);
+        let mut drawing = rows.split_off(start);
+        drawing.truncate(screen_rows);
+        drawing.resize_with(screen_rows, || crate::row::Row::new(cols));
+        self.rows = drawing;
+
+        // whatever doesn't fit on the screen goes back into the scrollback
+        self.scrollback_bytes = 0;
+        let excess = rows.len().saturating_sub(self.scrollback_len);
+        for mut row in rows.into_iter().skip(excess) {
+            row.compact();
+            self.scrollback_bytes += row.memory_usage();
+            self.scrollback.push_back(row);
+        }
+        self.scrollback_offset = 0;
+
+        self.size.cols = cols;
+        self.pos = Pos {
+            // both are within the screen size, which fits in a u16
+            row: (cursor.0 - start).try_into().unwrap(),
+            col: cursor.1.try_into().unwrap(),
+        };
+    }
+
+    // appends `line` to `rows`, wrapped at `cols` columns. if `cursor` is
+    // given as an offset into the line (and whether it is waiting to wrap),
+    // returns the row and column it ends up at.
+    fn rewrap_line(
+        rows: &mut Vec<crate::row::Row>,
+        line: Vec<crate::Cell>,
+        cols: u16,
+        cursor: Option<(usize, bool)>,
+    ) -> Option<(usize, usize)> {
+        let width = usize::from(cols);
+        let line_len = line.len();
+        let mut found = None;
+        let mut current = Vec::with_capacity(width);
+        for (i, cell) in line.into_iter().enumerate() {
+            let cell_width = if cell.is_wide() { 2 } else { 1 };
+            if !current.is_empty() && current.len() + cell_width > width {
+                rows.push(crate::row::Row::from_cells(
+                    std::mem::take(&mut current),
+                    cols,
+                    true,
+                ));
+            }
+            if cursor.is_some_and(|(offset, _)| offset == i) {
+                found = Some((rows.len(), current.len()));
+            }
+            current.push(cell);
+        }
+        let end = current.len();
+        rows.push(crate::row::Row::from_cells(current, cols, false));
+
+        let (offset, pending_wrap) = cursor?;
+        if found.is_some() {
+            return found;
+        }
+        // the cursor is past the end of the text
+        let mut col = end + (offset - line_len);
+        if col >= width && !(pending_wrap && col == width) {
+            if let Some(last) = rows.last_mut() {
+                last.wrap(true);
+            }
+            rows.push(crate::row::Row::new(cols));
+            col = (col - width).min(width - 1);
+        }
+        Some((rows.len() - 1, col))
+    }
+
     pub fn pos(&self) -> Pos {
         self.pos
     }
@@ -199,6 +361,68 @@ impl Grid {
         self.scrollback_offset = rows.min(self.scrollback.len());
     }
 
+    pub fn set_scrollback_len(&mut self, len: usize) {
+        self.scrollback_len = len;
+        let excess = self.scrollback.len().saturating_sub(len);
+        self.trim_scrollback(excess);
+    }
+
+    // drops up to `count` of the oldest rows in the scrollback, returning
+    // the number of rows actually dropped
+    pub fn trim_scrollback(&mut self, count: usize) -> usize {
+        let count = count.min(self.scrollback.len());
+        for row in self.scrollback.drain(..count) {
+            self.scrollback_bytes -= row.memory_usage();
+        }
+        self.scrollback_offset =
+            self.scrollback_offset.min(self.scrollback.len());
+        count
+    }
+
+    pub fn scrollback_bytes(&self) -> usize {
+        self.scrollback_bytes
+    }
+
+    pub fn history_len(&self) -> usize {
+        self.scrollback.len()
+    }
+
+    // rows are indexed from the oldest line in the scrollback, so row
+    // history_len() is the top of the drawing area regardless of the
+    // current scrollback offset
+    pub fn history_row(&self, row: usize) -> Option<&crate::row::Row> {
+        let scrollback_len = self.scrollback.len();
+        if row < scrollback_len {
+            self.scrollback.get(row)
+        } else {
+            self.rows.get(row - scrollback_len)
+        }
+    }
+
+    // images anchored in the visible rows, or in the `lookback` rows above
+    // them, with their row relative to the top of the visible screen
+    pub fn image_anchors(
+        &self,
+        lookback: usize,
+    ) -> Vec<(i32, &crate::row::ImageAnchor)> {
+        let top = self.scrollback.len() - self.scrollback_offset;
+        let start = top.saturating_sub(lookback);
+        let end = top + usize::from(self.size.rows);
+        let mut anchors = vec![];
+        for i in start..end {
+            let Some(row) = self.history_row(i) else {
+                break;
+            };
+            for anchor in row.images() {
+                // rows of history fit in an i32 by a wide margin
+                let row =
+                    i32::try_from(i).unwrap() - i32::try_from(top).unwrap();
+                anchors.push((row, anchor));
+            }
+        }
+        anchors
+    }
+
     pub fn write_contents(&self, contents: &mut String) {
         let mut wrapping = false;
         for row in self.visible_rows() {
@@ -251,6 +475,48 @@ impl Grid {
         prev_attrs
     }
 
+    // writes the scrollback from the top of a cleared screen, then scrolls
+    // it all off, so a fresh grid fed the result ends up with the same
+    // scrollback. every row is written as row 1 after row 0, which keeps
+    // the moves between rows relative, whatever the screen has scrolled to.
+    pub fn write_scrollback_formatted(&self, contents: &mut Vec<u8>) {
+        if self.scrollback.is_empty() {
+            return;
+        }
+        crate::term::ClearAttrs.write_buf(contents);
+        crate::term::ClearScreen.write_buf(contents);
+
+        let mut prev_attrs = crate::attrs::Attrs::default();
+        let mut prev_pos = Pos { row: 1, col: 0 };
+        let mut wrapping = false;
+        for (i, row) in self.scrollback.iter().enumerate() {
+            prev_pos = if wrapping {
+                Pos { row: 0, ..prev_pos }
+            } else {
+                if i > 0 {
+                    crate::term::Crlf.write_buf(contents);
+                }
+                Pos { row: 1, col: 0 }
+            };
+            let (new_pos, new_attrs) = row.write_contents_formatted(
+                contents,
+                0,
+                row.cols().min(self.size.cols),
+                1,
+                wrapping,
+                Some(prev_pos),
+                Some(prev_attrs),
+            );
+            prev_pos = new_pos;
+            prev_attrs = new_attrs;
+            wrapping = row.wrapped();
+        }
+        for _ in 0..self.size.rows {
+            crate::term::Crlf.write_buf(contents);
+        }
+        crate::term::ClearAttrs.write_buf(contents);
+    }
+
     pub fn write_contents_diff(
         &self,
         contents: &mut Vec<u8>,
@@ -562,11 +828,15 @@ impl Grid {
         for _ in 0..(count.min(self.size.rows - self.scroll_top)) {
             self.rows
                 .insert(usize::from(self.scroll_bottom) + 1, self.new_row());
-            let removed = self.rows.remove(usize::from(self.scroll_top));
+            let mut removed = self.rows.remove(usize::from(self.scroll_top));
             if self.scrollback_len > 0 && !self.scroll_region_active() {
+                removed.compact();
+                self.scrollback_bytes += removed.memory_usage();
                 self.scrollback.push_back(removed);
                 while self.scrollback.len() > self.scrollback_len {
-                    self.scrollback.pop_front();
+                    if let Some(row) = self.scrollback.pop_front() {
+                        self.scrollback_bytes -= row.memory_usage();
+                    }
                 }
                 if self.scrollback_offset > 0 {
                     self.scrollback_offset =
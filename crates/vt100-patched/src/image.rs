@@ -0,0 +1,377 @@
+/// Cell size in pixels assumed until the real one is known.
+pub const DEFAULT_CELL_PIXEL_SIZE: (u16, u16) = (10, 20);
+
+// kitty graphics payloads can be huge, but not unboundedly so
+pub(crate) const MAX_IMAGE_BYTES: usize = 32 * 1024 * 1024;
+
+/// The protocol an image was sent with.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ImageProtocol {
+    /// A sixel image (`DCS q`).
+    Sixel,
+
+    /// An image sent with the kitty graphics protocol (`APC G`).
+    Kitty,
+}
+
+/// An image shown in the terminal.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Image {
+    protocol: ImageProtocol,
+    rows: u16,
+    cols: u16,
+    pixel_size: Option<(u32, u32)>,
+    format: String,
+    data: Vec<u8>,
+    // the id the program gave a kitty image, or 0
+    kitty_id: u32,
+}
+
+impl Image {
+    /// Returns the protocol the image was sent with.
+    #[must_use]
+    pub fn protocol(&self) -> ImageProtocol {
+        self.protocol
+    }
+
+    /// Returns the number of cells the image covers.
+    ///
+    /// The return value will be (rows, cols).
+    #[must_use]
+    pub fn size(&self) -> (u16, u16) {
+        (self.rows, self.cols)
+    }
+
+    /// Returns the size of the image in pixels, if it is known.
+    ///
+    /// The return value will be (width, height).
+    #[must_use]
+    pub fn pixel_size(&self) -> Option<(u32, u32)> {
+        self.pixel_size
+    }
+
+    /// Returns the kitty graphics control keys describing the image data,
+    /// such as `f=100` or `f=32,s=16,v=16,o=z`. Empty for sixel images.
+    #[must_use]
+    pub fn format(&self) -> &str {
+        &self.format
+    }
+
+    /// Returns the image data. For sixel images this is the whole `DCS`
+    /// sequence, for kitty images the base64 encoded payload.
+    #[must_use]
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub(crate) fn sixel(
+        params: &[u8],
+        data: &[u8],
+        cell_size: (u16, u16),
+    ) -> Self {
+        let pixel_size = sixel_pixel_size(data);
+        let (rows, cols) = cells_for_pixels(pixel_size, cell_size);
+        let mut sequence = Vec::with_capacity(params.len() + data.len() + 5);
+        sequence.extend_from_slice(b"\x1bP");
+        sequence.extend_from_slice(params);
+        sequence.push(b'q');
+        sequence.extend_from_slice(data);
+        sequence.extend_from_slice(b"\x1b\\");
+        Self {
+            protocol: ImageProtocol::Sixel,
+            rows,
+            cols,
+            pixel_size: Some(pixel_size),
+            format: String::new(),
+            data: sequence,
+            kitty_id: 0,
+        }
+    }
+
+    // `first` is the command that started the transmission, holding the
+    // keys that describe the data
+    pub(crate) fn kitty(
+        first: &KittyCommand,
+        payload: Vec<u8>,
+        cell_size: (u16, u16),
+    ) -> Self {
+        let format = first.num(b'f').unwrap_or(32);
+        let pixel_size = match (first.num(b's'), first.num(b'v')) {
+            (Some(w), Some(h)) if w > 0 && h > 0 => Some((w, h)),
+            _ if format == 100 && first.get(b'o').is_none() => {
+                png_size(&payload)
+            }
+            _ => None,
+        };
+        let mut keys = format!("f={format}");
+        for key in [b's', b'v', b'o'] {
+            if let Some(value) = first.get(key) {
+                keys.push_str(&format!(",{}={value}", char::from(key)));
+            }
+        }
+        let mut image = Self {
+            protocol: ImageProtocol::Kitty,
+            rows: 1,
+            cols: 1,
+            pixel_size,
+            format: keys,
+            data: payload,
+            kitty_id: first.num(b'i').unwrap_or(0),
+        };
+        image.fit(first, cell_size);
+        image
+    }
+
+    pub(crate) fn kitty_id(&self) -> u32 {
+        self.kitty_id
+    }
+
+    // sets the cell size from the `c` and `r` keys, falling back to the
+    // pixel size
+    pub(crate) fn fit(&mut self, command: &KittyCommand, cell_size: (u16, u16)) {
+        let (rows, cols) = self
+            .pixel_size
+            .map_or((1, 1), |size| cells_for_pixels(size, cell_size));
+        let cap = |n: u32| u16::try_from(n).unwrap_or(u16::MAX).max(1);
+        self.cols = command.num(b'c').filter(|&c| c > 0).map_or(cols, cap);
+        self.rows = command.num(b'r').filter(|&r| r > 0).map_or(rows, cap);
+    }
+}
+
+/// An image and the cell its top left corner is anchored to.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ImagePlacement<'a> {
+    /// Identifies the placement for as long as it is on the screen.
+    pub id: u32,
+
+    /// Row of the top left corner, relative to the top of the visible
+    /// screen. Negative when the top of the image has scrolled out of view.
+    pub row: i32,
+
+    /// Column of the top left corner.
+    pub col: u16,
+
+    /// The image itself.
+    pub image: &'a Image,
+}
+
+// a parsed kitty graphics command: `G<key>=<value>,...;<payload>`
+#[derive(Clone, Debug, Default)]
+pub struct KittyCommand {
+    keys: Vec<(u8, String)>,
+    pub payload: Vec<u8>,
+}
+
+impl KittyCommand {
+    pub fn parse(data: &[u8]) -> Self {
+        let (control, payload) = match data.iter().position(|&b| b == b';')
+        {
+            Some(i) => (&data[..i], &data[i + 1..]),
+            None => (data, &[][..]),
+        };
+        let keys = control
+            .split(|&b| b == b',')
+            .filter_map(|pair| match pair {
+                [key, b'=', value @ ..] => Some((
+                    *key,
+                    String::from_utf8_lossy(value).into_owned(),
+                )),
+                _ => None,
+            })
+            .collect();
+        Self {
+            keys,
+            payload: payload.to_vec(),
+        }
+    }
+
+    pub fn get(&self, key: u8) -> Option<&str> {
+        self.keys
+            .iter()
+            .find(|(k, _)| *k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn num(&self, key: u8) -> Option<u32> {
+        self.get(key).and_then(|v| v.parse().ok())
+    }
+
+    pub fn action(&self) -> u8 {
+        self.get(b'a')
+            .and_then(|a| a.bytes().next())
+            .unwrap_or(b't')
+    }
+}
+
+// a kitty image being sent in several chunks
+#[derive(Clone, Debug)]
+pub struct KittyTransfer {
+    pub first: KittyCommand,
+    pub payload: Vec<u8>,
+}
+
+impl KittyTransfer {
+    pub fn new(mut first: KittyCommand) -> Self {
+        let payload = std::mem::take(&mut first.payload);
+        Self { first, payload }
+    }
+
+    // returns false once the transfer has grown too large to keep
+    pub fn push(&mut self, chunk: &[u8]) -> bool {
+        self.payload.extend_from_slice(chunk);
+        self.payload.len() <= MAX_IMAGE_BYTES
+    }
+}
+
+// rounds a pixel size up to whole cells
+fn cells_for_pixels(
+    (width, height): (u32, u32),
+    (cell_width, cell_height): (u16, u16),
+) -> (u16, u16) {
+    let cells = |px: u32, cell: u16| {
+        let cell = u32::from(cell.max(1));
+        u16::try_from(px.div_ceil(cell)).unwrap_or(u16::MAX).max(1)
+    };
+    (cells(height, cell_height), cells(width, cell_width))
+}
+
+// the size of a sixel image, from its raster attributes or by walking
+// the bands of pixels
+pub fn sixel_pixel_size(data: &[u8]) -> (u32, u32) {
+    let mut raster = (0, 0);
+    let (mut x, mut width, mut bands) = (0_u32, 0_u32, 0_u32);
+    let mut iter = data.iter().copied().peekable();
+    while let Some(b) = iter.next() {
+        match b {
+            b'"' => {
+                let mut values = [0; 4];
+                for value in &mut values {
+                    *value = sixel_number(&mut iter);
+                    if iter.peek() != Some(&b';') {
+                        break;
+                    }
+                    iter.next();
+                }
+                raster = (values[2], values[3]);
+            }
+            b'#' => {
+                // color selection or definition
+                loop {
+                    sixel_number(&mut iter);
+                    if iter.peek() != Some(&b';') {
+                        break;
+                    }
+                    iter.next();
+                }
+            }
+            b'!' => {
+                let count = sixel_number(&mut iter);
+                if iter.next_if(|b| (0x3f..=0x7e).contains(b)).is_some() {
+                    x = x.saturating_add(count);
+                }
+            }
+            0x3f..=0x7e => x += 1,
+            b'$' => {
+                width = width.max(x);
+                x = 0;
+            }
+            b'-' => {
+                width = width.max(x);
+                x = 0;
+                bands += 1;
+            }
+            _ => {}
+        }
+    }
+    width = width.max(x);
+    let height = if width > 0 { (bands + 1) * 6 } else { 0 };
+    (width.max(raster.0), height.max(raster.1))
+}
+
+fn sixel_number(
+    iter: &mut std::iter::Peekable<impl Iterator<Item = u8>>,
+) -> u32 {
+    let mut n = 0_u32;
+    while let Some(b) = iter.next_if(u8::is_ascii_digit) {
+        n = n.saturating_mul(10).saturating_add(u32::from(b - b'0'));
+    }
+    n
+}
+
+// reads the size from the header of a base64 encoded PNG
+fn png_size(base64: &[u8]) -> Option<(u32, u32)> {
+    // the IHDR chunk holds the size at bytes 16..24, which is the first 32
+    // base64 characters
+    let header = decode_base64(base64.get(..32)?)?;
+    if !header.starts_with(b"\x89PNG\r\n\x1a\n") {
+        return None;
+    }
+    let width = u32::from_be_bytes(header[16..20].try_into().ok()?);
+    let height = u32::from_be_bytes(header[20..24].try_into().ok()?);
+    Some((width, height))
+}
+
+fn decode_base64(data: &[u8]) -> Option<Vec<u8>> {
+    let value = |b: u8| match b {
+        b'A'..=b'Z' => Some(b - b'A'),
+        b'a'..=b'z' => Some(b - b'a' + 26),
+        b'0'..=b'9' => Some(b - b'0' + 52),
+        b'+' => Some(62),
+        b'/' => Some(63),
+        _ => None,
+    };
+    let mut out = Vec::with_capacity(data.len() / 4 * 3);
+    for chunk in data.chunks_exact(4) {
+        let mut n = 0_u32;
+        for &b in chunk {
+            n = (n << 6) | u32::from(value(b)?);
+        }
+        out.extend_from_slice(&n.to_be_bytes()[1..]);
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sixel_pixel_size() {
+        // two bands, the second one wider
+        assert_eq!(sixel_pixel_size(b"#0;2;0;0;0#0~~-!20~$~"), (20, 12));
+        // raster attributes can declare a larger image
+        assert_eq!(sixel_pixel_size(b"\"1;1;30;18#1~~"), (30, 18));
+        assert_eq!(sixel_pixel_size(b""), (0, 0));
+    }
+
+    #[test]
+    fn test_png_size() {
+        // signature and IHDR of a 300x120 PNG
+        let png = b"iVBORw0KGgoAAAANSUhEUgAAASwAAAB4CAYAAAA=";
+        assert_eq!(png_size(png), Some((300, 120)));
+        assert_eq!(png_size(b"AAAA"), None);
+    }
+
+    #[test]
+    fn test_kitty_command() {
+        let command = KittyCommand::parse(b"a=T,f=100,i=7;AAAA");
+        assert_eq!(command.action(), b'T');
+        assert_eq!(command.num(b'f'), Some(100));
+        assert_eq!(command.num(b'i'), Some(7));
+        assert_eq!(command.get(b'm'), None);
+        assert_eq!(command.payload, b"AAAA");
+        assert_eq!(KittyCommand::parse(b"i=1").action(), b't');
+    }
+
+    #[test]
+    fn test_kitty_image_size() {
+        let command = KittyCommand::parse(b"a=T,f=24,s=25,v=45");
+        let image = Image::kitty(&command, vec![], (10, 20));
+        assert_eq!(image.size(), (3, 3));
+        assert_eq!(image.format(), "f=24,s=25,v=45");
+        // columns and rows win over the pixel size
+        let command = KittyCommand::parse(b"a=T,f=24,s=25,v=45,c=8,r=2");
+        let image = Image::kitty(&command, vec![], (10, 20));
+        assert_eq!(image.size(), (2, 8));
+    }
+}
@@ -51,6 +51,7 @@ mod attrs;
 mod callbacks;
 mod cell;
 mod grid;
+mod image;
 mod parser;
 mod perform;
 mod row;
@@ -60,5 +61,9 @@ mod term;
 pub use attrs::Color;
 pub use callbacks::Callbacks;
 pub use cell::Cell;
+pub use image::{Image, ImagePlacement, ImageProtocol};
 pub use parser::Parser;
-pub use screen::{MouseProtocolEncoding, MouseProtocolMode, Screen};
+pub use screen::{
+    trim_scrollback_to_budget, CursorShape, MouseProtocolEncoding,
+    MouseProtocolMode, Screen,
+};
@@ -1,8 +1,22 @@
+// APC strings longer than this are dropped, rather than cut short
+const MAX_APC_BYTES: usize = 1024 * 1024;
+
 /// A parser for terminal output which produces an in-memory representation of
 /// the terminal contents.
 pub struct Parser<CB: crate::callbacks::Callbacks = ()> {
     parser: vte::Parser,
     screen: crate::perform::WrappedScreen<CB>,
+    // vte skips APC strings without passing them on, so they are picked out
+    // of the input before it gets there
+    apc: Option<Vec<u8>>,
+    // the APC string grew past MAX_APC_BYTES
+    apc_overflowed: bool,
+    // the last byte seen was an ESC inside an APC string, or one that
+    // cancelled it, which hasn't been parsed yet
+    pending_esc: bool,
+    // the previous input ended in an ESC, which was parsed already but
+    // might start an APC string
+    trailing_esc: bool,
 }
 
 impl Parser {
@@ -17,6 +31,10 @@ impl Parser {
                 cols,
                 scrollback_len,
             ),
+            apc: None,
+            apc_overflowed: false,
+            pending_esc: false,
+            trailing_esc: false,
         }
     }
 }
@@ -40,13 +58,85 @@ impl<CB: crate::callbacks::Callbacks> Parser<CB> {
                 scrollback_len,
                 callbacks,
             ),
+            apc: None,
+            apc_overflowed: false,
+            pending_esc: false,
+            trailing_esc: false,
         }
     }
 
     /// Processes the contents of the given byte string, and updates the
     /// in-memory terminal state.
-    pub fn process(&mut self, bytes: &[u8]) {
-        self.parser.advance(&mut self.screen, bytes);
+    pub fn process(&mut self, mut bytes: &[u8]) {
+        while !bytes.is_empty() {
+            if self.apc.is_some() {
+                bytes = self.process_apc(bytes);
+                continue;
+            }
+            if std::mem::take(&mut self.trailing_esc) && bytes[0] == b'_' {
+                // vte has to forget the ESC it was given
+                self.parser = vte::Parser::new();
+                self.apc = Some(vec![]);
+                bytes = &bytes[1..];
+                continue;
+            }
+            if std::mem::take(&mut self.pending_esc) {
+                if bytes[0] == b'_' {
+                    self.apc = Some(vec![]);
+                    bytes = &bytes[1..];
+                    continue;
+                }
+                self.parser.advance(&mut self.screen, b"\x1b");
+            }
+            let Some(i) = find_apc_start(bytes) else {
+                self.parser.advance(&mut self.screen, bytes);
+                break;
+            };
+            if i + 1 == bytes.len() {
+                // the ESC isn't held back waiting for more input, in case
+                // there is none for a while
+                self.parser.advance(&mut self.screen, bytes);
+                self.trailing_esc = true;
+                break;
+            }
+            self.parser.advance(&mut self.screen, &bytes[..i]);
+            self.apc = Some(vec![]);
+            bytes = &bytes[i + 2..];
+        }
+    }
+
+    // reads APC string contents up to the terminating ST, returning the
+    // rest of the input
+    fn process_apc<'a>(&mut self, bytes: &'a [u8]) -> &'a [u8] {
+        for (i, &b) in bytes.iter().enumerate() {
+            if std::mem::take(&mut self.pending_esc) {
+                let apc = self.apc.take().unwrap_or_default();
+                if b == b'\\' {
+                    if std::mem::take(&mut self.apc_overflowed) {
+                        // a kitty image missing a chunk would be corrupt
+                        self.screen.screen.cancel_kitty_transfer();
+                    } else {
+                        self.screen.apc_dispatch(&apc);
+                    }
+                    return &bytes[i + 1..];
+                }
+                self.apc_overflowed = false;
+                // any other escape sequence cancels the string, and is
+                // parsed as usual
+                self.pending_esc = true;
+                return &bytes[i..];
+            }
+            if b == 0x1b {
+                self.pending_esc = true;
+            } else if let Some(apc) = &mut self.apc {
+                if apc.len() < MAX_APC_BYTES {
+                    apc.push(b);
+                } else {
+                    self.apc_overflowed = true;
+                }
+            }
+        }
+        &[]
     }
 
     /// Returns a reference to a [`Screen`](crate::Screen) object containing
@@ -77,6 +167,20 @@ impl<CB: crate::callbacks::Callbacks> Parser<CB> {
     }
 }
 
+// finds the ESC starting an APC string, or an ESC at the very end that might
+// start one
+fn find_apc_start(bytes: &[u8]) -> Option<usize> {
+    let mut from = 0;
+    while let Some(i) = bytes[from..].iter().position(|&b| b == 0x1b) {
+        let i = from + i;
+        match bytes.get(i + 1) {
+            Some(b'_') | None => return Some(i),
+            Some(_) => from = i + 1,
+        }
+    }
+    None
+}
+
 impl Default for Parser {
     /// Returns a parser with dimensions 80x24 and no scrollback.
     fn default() -> Self {
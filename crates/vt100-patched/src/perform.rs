@@ -2,9 +2,14 @@ const BASE64: &[u8] =
     b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/=";
 const CLIPBOARD_SELECTOR: &[u8] = b"cpqs01234567";
 
+// sixel images larger than this are dropped
+const MAX_SIXEL_BYTES: usize = 32 * 1024 * 1024;
+
 pub struct WrappedScreen<CB: crate::callbacks::Callbacks = ()> {
     pub screen: crate::screen::Screen,
     pub callbacks: CB,
+    // parameters and data of the sixel image being received
+    sixel: Option<(Vec<u8>, Vec<u8>)>,
 }
 
 impl WrappedScreen<()> {
@@ -26,6 +31,20 @@ impl<CB: crate::callbacks::Callbacks> WrappedScreen<CB> {
                 scrollback_len,
             ),
             callbacks,
+            sixel: None,
+        }
+    }
+
+    // APC, which vte doesn't pass on, so the parser picks these out itself
+    pub fn apc_dispatch(&mut self, data: &[u8]) {
+        if let Some(command) = data.strip_prefix(b"G") {
+            if let Some((id, error)) = self.screen.kitty_graphics(command) {
+                self.callbacks.kitty_graphics_reply(
+                    &mut self.screen,
+                    id,
+                    error,
+                );
+            }
         }
     }
 }
@@ -34,8 +53,8 @@ impl<CB: crate::callbacks::Callbacks> vte::Perform for WrappedScreen<CB> {
     fn print(&mut self, c: char) {
         if c == '\u{fffd}' || ('\u{80}'..'\u{a0}').contains(&c) {
             self.callbacks.unhandled_char(&mut self.screen, c);
-        } else {
-            self.screen.text(c);
+        } else if !self.screen.text(c) {
+            self.callbacks.unhandled_char(&mut self.screen, c);
         }
     }
 
@@ -183,6 +202,18 @@ impl<CB: crate::callbacks::Callbacks> vte::Perform for WrappedScreen<CB> {
                     );
                 }
             },
+            Some(b' ') if c == 'q' => {
+                self.screen.decscusr(canonicalize_params_1(params, 0));
+            }
+            Some(b'>') if c == 'u' => self
+                .screen
+                .push_keyboard_flags(canonicalize_params_1(params, 0)),
+            Some(b'<') if c == 'u' => self
+                .screen
+                .pop_keyboard_flags(canonicalize_params_1(params, 1)),
+            Some(b'=') if c == 'u' => self
+                .screen
+                .set_keyboard_flags(canonicalize_params_2(params, 0, 1)),
             Some(i) => {
                 self.callbacks.unhandled_csi(
                     &mut self.screen,
@@ -195,6 +226,39 @@ impl<CB: crate::callbacks::Callbacks> vte::Perform for WrappedScreen<CB> {
         }
     }
 
+    fn hook(
+        &mut self,
+        params: &vte::Params,
+        intermediates: &[u8],
+        _ignore: bool,
+        action: char,
+    ) {
+        if action == 'q' && intermediates.is_empty() {
+            let params = params
+                .iter()
+                .map(|p| p.first().copied().unwrap_or(0).to_string())
+                .collect::<Vec<_>>()
+                .join(";");
+            self.sixel = Some((params.into_bytes(), vec![]));
+        }
+    }
+
+    fn put(&mut self, byte: u8) {
+        if let Some((_, data)) = &mut self.sixel {
+            if data.len() < MAX_SIXEL_BYTES {
+                data.push(byte);
+            } else {
+                self.sixel = None;
+            }
+        }
+    }
+
+    fn unhook(&mut self) {
+        if let Some((params, data)) = self.sixel.take() {
+            self.screen.sixel(&params, &data);
+        }
+    }
+
     fn osc_dispatch(&mut self, params: &[&[u8]], _bel_terminated: bool) {
         match params {
             [b"0", s] => {
@@ -209,6 +273,10 @@ impl<CB: crate::callbacks::Callbacks> vte::Perform for WrappedScreen<CB> {
                 self.screen.set_title(s);
                 self.callbacks.set_window_title(&mut self.screen, s);
             }
+            [b"12", color] if *color != b"?" => {
+                self.screen.set_cursor_color(color);
+            }
+            [b"112"] => self.screen.reset_cursor_color(),
             [b"52", ty, data] => {
                 match (
                     ty.iter().all(|c| CLIPBOARD_SELECTOR.contains(c)),
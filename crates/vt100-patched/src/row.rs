@@ -1,25 +1,110 @@
 use crate::term::BufWrite as _;
+use std::sync::Arc;
+
+static BLANK_CELL: crate::Cell = crate::Cell::BLANK;
 
 #[derive(Clone, Debug)]
 pub struct Row {
     cells: Vec<crate::Cell>,
+    // number of blank cells dropped from the end of `cells` by compact()
+    blank_tail: u16,
     wrapped: bool,
+    // the images whose top left corner is in this row. they are dropped
+    // along with the row or its contents.
+    images: Vec<ImageAnchor>,
+}
+
+// an image placed with its top left corner in a row
+#[derive(Clone, Debug)]
+pub struct ImageAnchor {
+    pub col: u16,
+    // placement id, in the order images were placed
+    pub id: u32,
+    pub image: Arc<crate::image::Image>,
 }
 
 impl Row {
     pub fn new(cols: u16) -> Self {
         Self {
             cells: vec![crate::Cell::new(); usize::from(cols)],
+            blank_tail: 0,
             wrapped: false,
+            images: vec![],
         }
     }
 
-    fn cols(&self) -> u16 {
-        self.cells
+    // builds a row of width `cols` from the leading cells of a reflowed
+    // line, padding the rest with blank cells
+    pub fn from_cells(
+        mut cells: Vec<crate::Cell>,
+        cols: u16,
+        wrapped: bool,
+    ) -> Self {
+        cells.resize(usize::from(cols), crate::Cell::new());
+        Self {
+            cells,
+            blank_tail: 0,
+            wrapped,
+            images: vec![],
+        }
+    }
+
+    pub fn cols(&self) -> u16 {
+        let stored: u16 = self
+            .cells
             .len()
             .try_into()
             // we limit the number of cols to a u16 (see Size)
-            .unwrap()
+            .unwrap();
+        stored + self.blank_tail
+    }
+
+    // drops trailing blank cells and any spare capacity. this is only
+    // meant for rows that have been pushed into the scrollback, since
+    // those are never written to again - reads past the stored cells see
+    // blank cells, but the mutating methods don't know about them.
+    pub fn compact(&mut self) {
+        let blank = crate::Cell::new();
+        let stored = self
+            .cells
+            .iter()
+            .rposition(|cell| cell != &blank)
+            .map_or(0, |i| i + 1);
+        // the removed cells all fit in a u16 row to begin with
+        self.blank_tail += u16::try_from(self.cells.len() - stored).unwrap();
+        self.cells.truncate(stored);
+        self.cells.shrink_to_fit();
+    }
+
+    // number of columns up to and including the last one with text in it,
+    // ignoring any trailing erased cells
+    pub fn content_len(&self) -> u16 {
+        self.cells
+            .iter()
+            .rposition(|cell| {
+                cell.has_contents() || cell.is_wide_continuation()
+            })
+            // we limit the number of cols to a u16 (see Size)
+            .map_or(0, |i| u16::try_from(i + 1).unwrap())
+    }
+
+    // approximate number of bytes of heap and inline storage used by this
+    // row, including long grapheme clusters and the data of the images
+    // anchored in it
+    pub fn memory_usage(&self) -> usize {
+        std::mem::size_of::<Self>()
+            + self.cells.capacity() * std::mem::size_of::<crate::Cell>()
+            + self
+                .cells
+                .iter()
+                .map(crate::Cell::heap_bytes)
+                .sum::<usize>()
+            + self.images.capacity() * std::mem::size_of::<ImageAnchor>()
+            + self
+                .images
+                .iter()
+                .map(|anchor| anchor.image.data().len())
+                .sum::<usize>()
     }
 
     pub fn clear(&mut self, attrs: crate::attrs::Attrs) {
@@ -27,14 +112,31 @@ impl Row {
             cell.clear(attrs);
         }
         self.wrapped = false;
+        self.images.clear();
+    }
+
+    pub fn images(&self) -> &[ImageAnchor] {
+        &self.images
+    }
+
+    pub fn anchor_image(&mut self, anchor: ImageAnchor) {
+        self.images.push(anchor);
+    }
+
+    pub fn retain_images(&mut self, f: impl FnMut(&ImageAnchor) -> bool) {
+        self.images.retain(f);
     }
 
     fn cells(&self) -> impl Iterator<Item = &crate::Cell> {
-        self.cells.iter()
+        self.cells.iter().chain(
+            std::iter::repeat(&BLANK_CELL).take(usize::from(self.blank_tail)),
+        )
     }
 
     pub fn get(&self, col: u16) -> Option<&crate::Cell> {
-        self.cells.get(usize::from(col))
+        self.cells
+            .get(usize::from(col))
+            .or_else(|| (col < self.cols()).then_some(&BLANK_CELL))
     }
 
     pub fn get_mut(&mut self, col: u16) -> Option<&mut crate::Cell> {
@@ -159,7 +261,7 @@ impl Row {
         });
         let mut prev_attrs = prev_attrs.unwrap_or_default();
 
-        let first_cell = &self.cells[usize::from(start)];
+        let first_cell = self.get(start).unwrap();
         if wrapping && first_cell == &default_cell {
             let default_attrs = default_cell.attrs();
             if &prev_attrs != default_attrs {
@@ -295,8 +397,8 @@ impl Row {
     ) -> (crate::grid::Pos, crate::attrs::Attrs) {
         let mut prev_was_wide = false;
 
-        let first_cell = &self.cells[usize::from(start)];
-        let prev_first_cell = &prev.cells[usize::from(start)];
+        let first_cell = self.get(start).unwrap();
+        let prev_first_cell = prev.get(start).unwrap();
         if wrapping
             && !prev_wrapping
             && first_cell == prev_first_cell
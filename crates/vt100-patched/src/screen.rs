@@ -1,11 +1,20 @@
 use crate::term::BufWrite as _;
+use std::sync::Arc;
 use unicode_width::UnicodeWidthChar as _;
 
+// kitty images kept around for placing again later, and the bytes of
+// image data they can hold between them
+const MAX_KITTY_IMAGES: usize = 32;
+const MAX_KITTY_IMAGE_BYTES: usize = crate::image::MAX_IMAGE_BYTES;
+
 const MODE_APPLICATION_KEYPAD: u8 = 0b0000_0001;
 const MODE_APPLICATION_CURSOR: u8 = 0b0000_0010;
 const MODE_HIDE_CURSOR: u8 = 0b0000_0100;
 const MODE_ALTERNATE_SCREEN: u8 = 0b0000_1000;
 const MODE_BRACKETED_PASTE: u8 = 0b0001_0000;
+const MODE_FOCUS_REPORTING: u8 = 0b0010_0000;
+const MODE_SYNCHRONIZED_OUTPUT: u8 = 0b0100_0000;
+const MODE_GRAPHEME_CLUSTERS: u8 = 0b1000_0000;
 
 /// The xterm mouse handling mode currently in use.
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Default)]
@@ -50,6 +59,74 @@ pub enum MouseProtocolEncoding {
     // Urxvt,
 }
 
+/// The cursor shape requested with DECSCUSR (`CSI Ps SP q`).
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Default)]
+pub enum CursorShape {
+    /// Whatever shape the user configured their terminal with.
+    #[default]
+    Default,
+
+    /// A blinking block.
+    BlinkingBlock,
+
+    /// A steady block.
+    SteadyBlock,
+
+    /// A blinking underline.
+    BlinkingUnderline,
+
+    /// A steady underline.
+    SteadyUnderline,
+
+    /// A blinking vertical bar.
+    BlinkingBar,
+
+    /// A steady vertical bar.
+    SteadyBar,
+}
+
+/// Maximum depth of a kitty keyboard flags stack. Pushing past it evicts the
+/// oldest entry, as in kitty.
+const KEYBOARD_STACK_LIMIT: usize = 16;
+
+/// Kitty keyboard protocol flags and the stack of flags saved by pushes.
+///
+/// The main and alternate screens each have their own.
+#[derive(Clone, Debug, Default)]
+struct KeyboardModes {
+    flags: u8,
+    stack: Vec<u8>,
+}
+
+impl KeyboardModes {
+    fn push(&mut self, flags: u8) {
+        if self.stack.len() == KEYBOARD_STACK_LIMIT {
+            self.stack.remove(0);
+        }
+        self.stack.push(self.flags);
+        self.flags = flags;
+    }
+
+    fn pop(&mut self, count: usize) {
+        for _ in 0..count {
+            let Some(flags) = self.stack.pop() else {
+                // Popping everything resets the flags
+                self.flags = 0;
+                return;
+            };
+            self.flags = flags;
+        }
+    }
+
+    fn set(&mut self, flags: u8, mode: u16) {
+        match mode {
+            2 => self.flags |= flags,
+            3 => self.flags &= !flags,
+            _ => self.flags = flags,
+        }
+    }
+}
+
 /// Represents the overall terminal state.
 #[derive(Clone, Debug)]
 pub struct Screen {
@@ -62,6 +139,20 @@ pub struct Screen {
     modes: u8,
     mouse_protocol_mode: MouseProtocolMode,
     mouse_protocol_encoding: MouseProtocolEncoding,
+    keyboard_modes: KeyboardModes,
+    alternate_keyboard_modes: KeyboardModes,
+    cursor_shape: CursorShape,
+    cursor_color: Option<String>,
+
+    cell_pixel_size: (u16, u16),
+    next_image_id: u32,
+    // rows of the tallest image placed, which is how far above the screen
+    // an image can start and still be in view
+    max_image_rows: u16,
+    // kitty images transmitted with an id, for later placements, oldest
+    // first
+    kitty_images: Vec<Arc<crate::image::Image>>,
+    kitty_transfer: Option<crate::image::KittyTransfer>,
 
     title: String,
 }
@@ -80,17 +171,32 @@ impl Screen {
             attrs: crate::attrs::Attrs::default(),
             saved_attrs: crate::attrs::Attrs::default(),
 
-            modes: 0,
+            modes: MODE_GRAPHEME_CLUSTERS,
             mouse_protocol_mode: MouseProtocolMode::default(),
             mouse_protocol_encoding: MouseProtocolEncoding::default(),
+            keyboard_modes: KeyboardModes::default(),
+            alternate_keyboard_modes: KeyboardModes::default(),
+            cursor_shape: CursorShape::default(),
+            cursor_color: None,
+
+            cell_pixel_size: crate::image::DEFAULT_CELL_PIXEL_SIZE,
+            next_image_id: 0,
+            max_image_rows: 0,
+            kitty_images: vec![],
+            kitty_transfer: None,
 
             title: String::new(),
         }
     }
 
     /// Resizes the terminal.
+    ///
+    /// When the width changes, soft-wrapped lines on the main screen and in
+    /// the scrollback are rewrapped to the new width, and the cursor stays on
+    /// the same character. The alternate screen is only resized, since the
+    /// programs using it redraw themselves.
     pub fn set_size(&mut self, rows: u16, cols: u16) {
-        self.grid.set_size(crate::grid::Size { rows, cols });
+        self.grid.set_size_reflow(crate::grid::Size { rows, cols });
         self.alternate_grid
             .set_size(crate::grid::Size { rows, cols });
     }
@@ -138,6 +244,171 @@ impl Screen {
         self.title = String::from_utf8_lossy(title).into_owned();
     }
 
+    // OSC 12
+    pub(crate) fn set_cursor_color(&mut self, color: &[u8]) {
+        // The color is echoed to other terminals, so only plain color
+        // specs like `#ff8800` or `rgb:ff/88/00` are kept.
+        if color.is_empty() || !color.iter().all(u8::is_ascii_graphic) {
+            return;
+        }
+        self.cursor_color = Some(String::from_utf8_lossy(color).into_owned());
+    }
+
+    // OSC 112
+    pub(crate) fn reset_cursor_color(&mut self) {
+        self.cursor_color = None;
+    }
+
+    // DCS q
+    pub(crate) fn sixel(&mut self, params: &[u8], data: &[u8]) {
+        let image =
+            crate::image::Image::sixel(params, data, self.cell_pixel_size);
+        let rows = image.size().0;
+        self.place_image(image);
+        // the text cursor ends up on the line below the image
+        self.grid_mut().row_inc_scroll(rows);
+    }
+
+    // APC G. returns the image id and error to reply with, if the program
+    // wants a reply.
+    pub(crate) fn kitty_graphics(
+        &mut self,
+        data: &[u8],
+    ) -> Option<(u32, Option<&'static str>)> {
+        let command = crate::image::KittyCommand::parse(data);
+        if let Some(transfer) = &mut self.kitty_transfer {
+            // later chunks of a transfer only say whether more will follow
+            if !transfer.push(&command.payload) {
+                self.kitty_transfer = None;
+                return None;
+            }
+            if command.num(b'm') == Some(1) {
+                return None;
+            }
+            let transfer = self.kitty_transfer.take()?;
+            return self.kitty_transmitted(transfer.first, transfer.payload);
+        }
+        match command.action() {
+            b't' | b'T' if command.num(b'm') == Some(1) => {
+                self.kitty_transfer =
+                    Some(crate::image::KittyTransfer::new(command));
+                None
+            }
+            b't' | b'T' => {
+                let payload = command.payload.clone();
+                self.kitty_transmitted(command, payload)
+            }
+            b'p' => {
+                let id = command.num(b'i').unwrap_or(0);
+                let Some(stored) =
+                    self.kitty_images.iter().find(|i| i.kitty_id() == id)
+                else {
+                    return kitty_reply(
+                        &command,
+                        Some("ENOENT:no such image"),
+                    );
+                };
+                let mut image = crate::image::Image::clone(stored);
+                image.fit(&command, self.cell_pixel_size);
+                self.place_kitty_image(&command, image);
+                kitty_reply(&command, None)
+            }
+            b'd' => {
+                self.delete_kitty_images(&command);
+                None
+            }
+            b'q' => kitty_reply(&command, kitty_medium_error(&command)),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn cancel_kitty_transfer(&mut self) {
+        self.kitty_transfer = None;
+    }
+
+    fn kitty_transmitted(
+        &mut self,
+        command: crate::image::KittyCommand,
+        payload: Vec<u8>,
+    ) -> Option<(u32, Option<&'static str>)> {
+        if let Some(error) = kitty_medium_error(&command) {
+            return kitty_reply(&command, Some(error));
+        }
+        let image = crate::image::Image::kitty(
+            &command,
+            payload,
+            self.cell_pixel_size,
+        );
+        if image.kitty_id() != 0 {
+            self.store_kitty_image(Arc::new(image.clone()));
+        }
+        if command.action() == b'T' {
+            self.place_kitty_image(&command, image);
+        }
+        kitty_reply(&command, None)
+    }
+
+    // keeps an image for later placements, replacing any with the same id
+    // and forgetting the oldest ones past the limits
+    fn store_kitty_image(&mut self, image: Arc<crate::image::Image>) {
+        self.kitty_images
+            .retain(|stored| stored.kitty_id() != image.kitty_id());
+        self.kitty_images.push(image);
+        let mut bytes: usize =
+            self.kitty_images.iter().map(|i| i.data().len()).sum();
+        while self.kitty_images.len() > MAX_KITTY_IMAGES
+            || bytes > MAX_KITTY_IMAGE_BYTES
+        {
+            bytes -= self.kitty_images.remove(0).data().len();
+        }
+    }
+
+    fn place_kitty_image(
+        &mut self,
+        command: &crate::image::KittyCommand,
+        image: crate::image::Image,
+    ) {
+        let (rows, cols) = image.size();
+        self.place_image(image);
+        // unless asked not to, the cursor moves to the cell after the
+        // bottom right corner of the image
+        if command.num(b'C') != Some(1) {
+            self.grid_mut().row_inc_scroll(rows - 1);
+            self.grid_mut().col_inc_clamp(cols);
+        }
+    }
+
+    fn delete_kitty_images(&mut self, command: &crate::image::KittyCommand) {
+        let id = command.num(b'i').unwrap_or(0);
+        let by_id = matches!(command.get(b'd'), Some("i" | "I"));
+        for row in self.grid_mut().drawing_rows_mut() {
+            row.retain_images(|anchor| {
+                by_id && anchor.image.kitty_id() != id
+            });
+        }
+        if matches!(command.get(b'd'), Some("A" | "I")) {
+            if by_id {
+                self.kitty_images.retain(|image| image.kitty_id() != id);
+            } else {
+                self.kitty_images.clear();
+            }
+        }
+    }
+
+    fn place_image(&mut self, image: crate::image::Image) {
+        let id = self.next_image_id;
+        self.next_image_id = self.next_image_id.wrapping_add(1);
+        let col = self.grid().pos().col.min(self.grid().size().cols - 1);
+        self.max_image_rows = self.max_image_rows.max(image.size().0);
+        self.grid_mut().current_row_mut().anchor_image(
+            crate::row::ImageAnchor {
+                col,
+                id,
+                image: Arc::new(image),
+            },
+        );
+    }
+
     /// Returns the text contents of the terminal.
     ///
     /// This will not include any formatting information, and will be in plain
@@ -243,11 +514,92 @@ impl Screen {
         if self.alternate_screen() {
             crate::term::AlternateScreen::new(true).write_buf(&mut contents);
         }
+        // Likewise the grapheme mode, which decides how the contents are
+        // split into cells
+        if !self.grapheme_clusters() {
+            crate::term::GraphemeClusters::new(false)
+                .write_buf(&mut contents);
+        }
         self.write_contents_formatted(&mut contents);
+        self.write_images_formatted(&mut contents);
         self.write_input_mode_formatted(&mut contents);
+        self.write_cursor_style_formatted(&mut contents);
         contents
     }
 
+    /// Like [`state_formatted`](Self::state_formatted), but first replays
+    /// the scrollback of the primary screen, so that a new parser of the
+    /// same size fed the result also ends up with the same history.
+    #[must_use]
+    pub fn state_formatted_with_scrollback(&self) -> Vec<u8> {
+        let mut contents = vec![];
+        if !self.grapheme_clusters() {
+            crate::term::GraphemeClusters::new(false)
+                .write_buf(&mut contents);
+        }
+        self.grid.write_scrollback_formatted(&mut contents);
+        contents.extend(self.state_formatted());
+        contents
+    }
+
+    // draws the images on the screen again, then sends the stored kitty
+    // images, which replace any placed with the same id, leaving the
+    // cursor where it was. images whose top has
+    // scrolled out of view can't be placed, and sixel images reaching the
+    // bottom row are left out, since drawing them would scroll the screen.
+    fn write_images_formatted(&self, contents: &mut Vec<u8>) {
+        let placements: Vec<_> = self
+            .images()
+            .into_iter()
+            .filter(|placement| placement.row >= 0)
+            .collect();
+        if placements.is_empty() && self.kitty_images.is_empty() {
+            return;
+        }
+        let screen_rows = i32::from(self.grid().size().rows);
+        for placement in placements {
+            let (rows, cols) = placement.image.size();
+            crate::term::MoveTo::new(crate::grid::Pos {
+                // checked to be on the screen above
+                row: u16::try_from(placement.row).unwrap(),
+                col: placement.col,
+            })
+            .write_buf(contents);
+            match placement.image.protocol() {
+                crate::ImageProtocol::Sixel => {
+                    if placement.row + i32::from(rows) < screen_rows {
+                        contents.extend_from_slice(placement.image.data());
+                    }
+                }
+                crate::ImageProtocol::Kitty => {
+                    let mut keys = format!(
+                        "a=T,{},c={cols},r={rows},C=1,q=2",
+                        placement.image.format()
+                    );
+                    let id = placement.image.kitty_id();
+                    if id != 0 {
+                        keys.push_str(&format!(",i={id}"));
+                    }
+                    write_kitty_image(
+                        contents,
+                        &keys,
+                        placement.image.data(),
+                    );
+                }
+            }
+        }
+        for image in &self.kitty_images {
+            let keys =
+                format!("a=t,i={},{},q=2", image.kitty_id(), image.format());
+            write_kitty_image(contents, &keys, image.data());
+        }
+        self.grid().write_cursor_position_formatted(
+            contents,
+            None,
+            Some(self.attrs),
+        );
+    }
+
     /// Return escape codes sufficient to turn the terminal state of the
     /// screen `prev` into the current terminal state. This is a convenience
     /// wrapper around [`contents_diff`](Self::contents_diff) and
@@ -258,11 +610,38 @@ impl Screen {
         // Emit alternate screen transition BEFORE contents so the consuming
         // parser targets the correct grid.
         self.write_alternate_screen_diff(&mut contents, prev);
+        if self.grapheme_clusters() != prev.grapheme_clusters() {
+            crate::term::GraphemeClusters::new(self.grapheme_clusters())
+                .write_buf(&mut contents);
+        }
         self.write_contents_diff(&mut contents, prev);
         self.write_input_mode_diff(&mut contents, prev);
+        self.write_cursor_style_diff(&mut contents, prev);
         contents
     }
 
+    fn write_cursor_style_formatted(&self, contents: &mut Vec<u8>) {
+        if self.cursor_shape != CursorShape::Default {
+            crate::term::CursorShape::new(self.cursor_shape)
+                .write_buf(contents);
+        }
+        if self.cursor_color.is_some() {
+            crate::term::CursorColor::new(self.cursor_color())
+                .write_buf(contents);
+        }
+    }
+
+    fn write_cursor_style_diff(&self, contents: &mut Vec<u8>, prev: &Self) {
+        if self.cursor_shape != prev.cursor_shape {
+            crate::term::CursorShape::new(self.cursor_shape)
+                .write_buf(contents);
+        }
+        if self.cursor_color != prev.cursor_color {
+            crate::term::CursorColor::new(self.cursor_color())
+                .write_buf(contents);
+        }
+    }
+
     /// Returns the formatted visible contents of the terminal.
     ///
     /// Formatting information will be included inline as terminal escape
@@ -580,6 +959,74 @@ impl Screen {
             .is_some_and(crate::row::Row::wrapped)
     }
 
+    /// Returns the maximum number of rows kept in the scrollback buffer.
+    #[must_use]
+    pub fn scrollback_limit(&self) -> usize {
+        self.grid.scrollback_len()
+    }
+
+    /// Changes the maximum number of rows kept in the scrollback buffer.
+    ///
+    /// If the scrollback currently holds more rows than the new limit, the
+    /// oldest rows are discarded.
+    pub fn set_scrollback_limit(&mut self, rows: usize) {
+        self.grid.set_scrollback_len(rows);
+    }
+
+    /// Discards up to `rows` of the oldest rows in the scrollback buffer,
+    /// returning the number of rows actually discarded. The scrollback limit
+    /// itself is left unchanged.
+    pub fn trim_scrollback(&mut self, rows: usize) -> usize {
+        self.grid.trim_scrollback(rows)
+    }
+
+    /// Returns the number of rows held in the scrollback buffer of the
+    /// primary screen, even while the alternate screen is active.
+    #[must_use]
+    pub fn scrollback_rows(&self) -> usize {
+        self.grid.history_len()
+    }
+
+    /// Returns the approximate number of bytes used to store the scrollback
+    /// buffer.
+    ///
+    /// Rows are compacted as they scroll off the screen, so this grows with
+    /// the amount of text in the history rather than with the screen width.
+    #[must_use]
+    pub fn scrollback_bytes(&self) -> usize {
+        self.grid.scrollback_bytes()
+    }
+
+    /// Returns the number of rows currently held in the scrollback buffer.
+    ///
+    /// Together with [`history_cell`](Self::history_cell), this allows
+    /// walking the full history of the terminal without changing the
+    /// scrollback position.
+    #[must_use]
+    pub fn history_len(&self) -> usize {
+        self.grid().history_len()
+    }
+
+    /// Returns the [`Cell`](crate::Cell) object at the given location in the
+    /// terminal history, if it exists.
+    ///
+    /// Rows are counted from the oldest line in the scrollback, so row
+    /// [`history_len`](Self::history_len) is the top row of the screen. The
+    /// current scrollback position is not taken into account.
+    #[must_use]
+    pub fn history_cell(&self, row: usize, col: u16) -> Option<&crate::Cell> {
+        self.grid().history_row(row).and_then(|r| r.get(col))
+    }
+
+    /// Returns whether the text in history row `row` should wrap to the next
+    /// line. Rows are counted as in [`history_cell`](Self::history_cell).
+    #[must_use]
+    pub fn history_row_wrapped(&self, row: usize) -> bool {
+        self.grid()
+            .history_row(row)
+            .is_some_and(crate::row::Row::wrapped)
+    }
+
     /// Returns whether the alternate screen is currently in use.
     #[must_use]
     pub fn alternate_screen(&self) -> bool {
@@ -610,6 +1057,85 @@ impl Screen {
         self.mode(MODE_BRACKETED_PASTE)
     }
 
+    /// Returns whether the program wants focus in/out events (`CSI I` and
+    /// `CSI O`).
+    #[must_use]
+    pub fn focus_reporting(&self) -> bool {
+        self.mode(MODE_FOCUS_REPORTING)
+    }
+
+    /// Returns whether the program is in the middle of drawing a
+    /// synchronized frame (DECSET 2026), which shouldn't be shown until it
+    /// ends.
+    #[must_use]
+    pub fn synchronized_output(&self) -> bool {
+        self.mode(MODE_SYNCHRONIZED_OUTPUT)
+    }
+
+    /// Returns whether text is split into cells by grapheme cluster (mode
+    /// 2027), so that emoji sequences and flags take up a single cell. This
+    /// is on unless the program turns it off.
+    #[must_use]
+    pub fn grapheme_clusters(&self) -> bool {
+        self.mode(MODE_GRAPHEME_CLUSTERS)
+    }
+
+    /// Returns the cursor shape the program asked for with DECSCUSR.
+    #[must_use]
+    pub fn cursor_shape(&self) -> CursorShape {
+        self.cursor_shape
+    }
+
+    /// Returns the cursor color the program set with OSC 12, as the color
+    /// spec it sent (for instance `#ff8800` or `rgb:ff/88/00`).
+    #[must_use]
+    pub fn cursor_color(&self) -> Option<&str> {
+        self.cursor_color.as_deref()
+    }
+
+    /// Returns the images on the screen, including ones whose top has
+    /// scrolled out of view, in the order they were placed.
+    #[must_use]
+    pub fn images(&self) -> Vec<crate::ImagePlacement<'_>> {
+        if self.max_image_rows == 0 {
+            return vec![];
+        }
+        let mut placements: Vec<_> = self
+            .grid()
+            .image_anchors(usize::from(self.max_image_rows))
+            .into_iter()
+            .filter_map(|(row, anchor)| {
+                // skip images that are entirely above the screen
+                (row + i32::from(anchor.image.size().0) > 0).then_some(
+                    crate::ImagePlacement {
+                        id: anchor.id,
+                        row,
+                        col: anchor.col,
+                        image: &anchor.image,
+                    },
+                )
+            })
+            .collect();
+        placements.sort_by_key(|placement| placement.id);
+        placements
+    }
+
+    /// Sets the size of a cell in pixels, which decides how many cells an
+    /// image covers. Zero sizes are ignored.
+    pub fn set_cell_pixel_size(&mut self, width: u16, height: u16) {
+        if width > 0 && height > 0 {
+            self.cell_pixel_size = (width, height);
+        }
+    }
+
+    /// Returns the size of a cell in pixels.
+    ///
+    /// The return value will be (width, height).
+    #[must_use]
+    pub fn cell_pixel_size(&self) -> (u16, u16) {
+        self.cell_pixel_size
+    }
+
     /// Returns the currently active [`MouseProtocolMode`].
     #[must_use]
     pub fn mouse_protocol_mode(&self) -> MouseProtocolMode {
@@ -622,6 +1148,13 @@ impl Screen {
         self.mouse_protocol_encoding
     }
 
+    /// Returns the kitty keyboard protocol flags enabled on the current
+    /// screen, or `0` if the program hasn't asked for enhanced key reporting.
+    #[must_use]
+    pub fn keyboard_flags(&self) -> u8 {
+        self.keyboard_modes().flags
+    }
+
     /// Returns the currently active foreground color.
     #[must_use]
     pub fn fgcolor(&self) -> crate::Color {
@@ -685,6 +1218,22 @@ impl Screen {
         }
     }
 
+    fn keyboard_modes(&self) -> &KeyboardModes {
+        if self.mode(MODE_ALTERNATE_SCREEN) {
+            &self.alternate_keyboard_modes
+        } else {
+            &self.keyboard_modes
+        }
+    }
+
+    fn keyboard_modes_mut(&mut self) -> &mut KeyboardModes {
+        if self.mode(MODE_ALTERNATE_SCREEN) {
+            &mut self.alternate_keyboard_modes
+        } else {
+            &mut self.keyboard_modes
+        }
+    }
+
     fn enter_alternate_grid(&mut self) {
         self.grid_mut().set_scrollback(0);
         self.set_mode(MODE_ALTERNATE_SCREEN);
@@ -739,7 +1288,15 @@ impl Screen {
 }
 
 impl Screen {
-    pub(crate) fn text(&mut self, c: char) {
+    // returns false if c was dropped because the grapheme cluster it
+    // continues is as long as a cell can hold
+    pub(crate) fn text(&mut self, c: char) -> bool {
+        if self.mode(MODE_GRAPHEME_CLUSTERS) {
+            if let Some(kept) = self.extend_cluster(c) {
+                return kept;
+            }
+        }
+
         let pos = self.grid().pos();
         let size = self.grid().size();
         let attrs = self.attrs;
@@ -747,7 +1304,7 @@ impl Screen {
         let width = c.width();
         if width.is_none() && (u32::from(c)) < 256 {
             // don't even try to draw control characters
-            return;
+            return true;
         }
         let width = width
             .unwrap_or(1)
@@ -783,75 +1340,13 @@ impl Screen {
         let pos = self.grid().pos();
 
         if width == 0 {
-            if pos.col > 0 {
-                let mut prev_cell = self
+            if let Some(prev_pos) = self.prev_cell_pos() {
+                return self
                     .grid_mut()
-                    .drawing_cell_mut(crate::grid::Pos {
-                        row: pos.row,
-                        col: pos.col - 1,
-                    })
-                    // pos.row is valid, since it comes directly from
-                    // self.grid().pos() which we assume to always have a
-                    // valid row value. pos.col - 1 is valid because we just
-                    // checked for pos.col > 0.
-                    .unwrap();
-                if prev_cell.is_wide_continuation() {
-                    prev_cell = self
-                        .grid_mut()
-                        .drawing_cell_mut(crate::grid::Pos {
-                            row: pos.row,
-                            col: pos.col - 2,
-                        })
-                        // pos.row is valid, since it comes directly from
-                        // self.grid().pos() which we assume to always have a
-                        // valid row value. we know pos.col - 2 is valid
-                        // because the cell at pos.col - 1 is a wide
-                        // continuation character, which means there must be
-                        // the first half of the wide character before it.
-                        .unwrap();
-                }
-                prev_cell.append(c);
-            } else if pos.row > 0 {
-                let prev_row = self
-                    .grid()
-                    .drawing_row(pos.row - 1)
-                    // pos.row is valid, since it comes directly from
-                    // self.grid().pos() which we assume to always have a
-                    // valid row value. pos.row - 1 is valid because we just
-                    // checked for pos.row > 0.
-                    .unwrap();
-                if prev_row.wrapped() {
-                    let mut prev_cell = self
-                        .grid_mut()
-                        .drawing_cell_mut(crate::grid::Pos {
-                            row: pos.row - 1,
-                            col: size.cols - 1,
-                        })
-                        // pos.row is valid, since it comes directly from
-                        // self.grid().pos() which we assume to always have a
-                        // valid row value. pos.row - 1 is valid because we
-                        // just checked for pos.row > 0. col of size.cols - 1
-                        // is always valid.
-                        .unwrap();
-                    if prev_cell.is_wide_continuation() {
-                        prev_cell = self
-                            .grid_mut()
-                            .drawing_cell_mut(crate::grid::Pos {
-                                row: pos.row - 1,
-                                col: size.cols - 2,
-                            })
-                            // pos.row is valid, since it comes directly from
-                            // self.grid().pos() which we assume to always
-                            // have a valid row value. pos.row - 1 is valid
-                            // because we just checked for pos.row > 0. col of
-                            // size.cols - 2 is valid because the cell at
-                            // size.cols - 1 is a wide continuation character,
-                            // so it must have the first half of the wide
-                            // character before it.
-                            .unwrap();
-                    }
-                    prev_cell.append(c);
-                }
+                    .drawing_cell_mut(prev_pos)
+                    // prev_cell_pos only returns valid positions
+                    .unwrap()
+                    .append(c);
             }
         } else {
             if self
@@ -976,6 +1471,107 @@ impl Screen {
                 self.grid_mut().col_inc(1);
             }
         }
+        true
+    }
+
+    // the cell holding the character before the cursor, which zero width
+    // characters are added to
+    fn prev_cell_pos(&self) -> Option<crate::grid::Pos> {
+        let pos = self.grid().pos();
+        let size = self.grid().size();
+        let prev = if pos.col > 0 {
+            crate::grid::Pos {
+                row: pos.row,
+                col: pos.col - 1,
+            }
+        } else if pos.row > 0
+            && self
+                .grid()
+                .drawing_row(pos.row - 1)
+                // pos.row is valid, since it comes directly from
+                // self.grid().pos() which we assume to always have a valid
+                // row value. pos.row - 1 is valid because we just checked
+                // for pos.row > 0.
+                .unwrap()
+                .wrapped()
+        {
+            crate::grid::Pos {
+                row: pos.row - 1,
+                col: size.cols - 1,
+            }
+        } else {
+            return None;
+        };
+        // prev.row is valid as above, and prev.col is at most pos.col - 1
+        // or size.cols - 1, both of which are valid columns
+        if self
+            .grid()
+            .drawing_cell(prev)
+            .unwrap()
+            .is_wide_continuation()
+        {
+            // the cell before a wide continuation character always holds
+            // the first half of the wide character
+            Some(crate::grid::Pos {
+                row: prev.row,
+                col: prev.col - 1,
+            })
+        } else {
+            Some(prev)
+        }
+    }
+
+    // adds c to the grapheme cluster before the cursor if it continues it,
+    // widening the cell when the cluster becomes wide (such as an emoji
+    // followed by VS16). returns whether c fit in the cluster, or None if it
+    // doesn't continue it.
+    fn extend_cluster(&mut self, c: char) -> Option<bool> {
+        let prev_pos = self.prev_cell_pos()?;
+        let cell = self
+            .grid_mut()
+            .drawing_cell_mut(prev_pos)
+            // prev_cell_pos only returns valid positions
+            .unwrap();
+        if !cell.continues_cluster(c) {
+            return None;
+        }
+        if !cell.append(c) {
+            return Some(false);
+        }
+        if cell.is_wide() || cell.cluster_width() < 2 {
+            return Some(true);
+        }
+
+        // only widen in place; a cluster ending a wrapped row or in the last
+        // column stays narrow
+        let pos = self.grid().pos();
+        let size = self.grid().size();
+        if pos.row != prev_pos.row || pos.col >= size.cols {
+            return Some(true);
+        }
+        let attrs = self.attrs;
+        self.grid_mut()
+            .drawing_cell_mut(prev_pos)
+            // prev_cell_pos only returns valid positions
+            .unwrap()
+            .set_wide(true);
+        // pos is valid because we just checked pos.col < size.cols
+        if self.grid().drawing_cell(pos).unwrap().is_wide() {
+            // pos.col + 1 holds the second half of the wide character at
+            // pos.col
+            self.grid_mut()
+                .drawing_cell_mut(crate::grid::Pos {
+                    row: pos.row,
+                    col: pos.col + 1,
+                })
+                .unwrap()
+                .clear(attrs);
+        }
+        let next_cell = self.grid_mut().drawing_cell_mut(pos).unwrap();
+        next_cell.clear(crate::attrs::Attrs::default());
+        next_cell.set_wide_continuation(true);
+        self.grid_mut().col_inc(1);
+        Some(true)
     }
 
     // control codes
@@ -1192,6 +1788,7 @@ impl Screen {
                     self.set_mouse_mode(MouseProtocolMode::ButtonMotion);
                 }
                 [1003] => self.set_mouse_mode(MouseProtocolMode::AnyMotion),
+                [1004] => self.set_mode(MODE_FOCUS_REPORTING),
                 [1005] => {
                     self.set_mouse_encoding(MouseProtocolEncoding::Utf8);
                 }
@@ -1204,6 +1801,8 @@ impl Screen {
                     self.enter_alternate_grid();
                 }
                 [2004] => self.set_mode(MODE_BRACKETED_PASTE),
+                [2026] => self.set_mode(MODE_SYNCHRONIZED_OUTPUT),
+                [2027] => self.set_mode(MODE_GRAPHEME_CLUSTERS),
                 _ => unhandled(self),
             }
         }
@@ -1233,6 +1832,7 @@ impl Screen {
                 [1003] => {
                     self.clear_mouse_mode(MouseProtocolMode::AnyMotion);
                 }
+                [1004] => self.clear_mode(MODE_FOCUS_REPORTING),
                 [1005] => {
                     self.clear_mouse_encoding(MouseProtocolEncoding::Utf8);
                 }
@@ -1244,6 +1844,8 @@ impl Screen {
                     self.decrc();
                 }
                 [2004] => self.clear_mode(MODE_BRACKETED_PASTE),
+                [2026] => self.clear_mode(MODE_SYNCHRONIZED_OUTPUT),
+                [2027] => self.clear_mode(MODE_GRAPHEME_CLUSTERS),
                 _ => unhandled(self),
             }
         }
@@ -1419,6 +2021,123 @@ impl Screen {
     pub(crate) fn decstbm(&mut self, (top, bottom): (u16, u16)) {
         self.grid_mut().set_scroll_region(top - 1, bottom - 1);
     }
+
+    // CSI SP q
+    pub(crate) fn decscusr(&mut self, ps: u16) {
+        self.cursor_shape = match ps {
+            0 => CursorShape::Default,
+            1 => CursorShape::BlinkingBlock,
+            2 => CursorShape::SteadyBlock,
+            3 => CursorShape::BlinkingUnderline,
+            4 => CursorShape::SteadyUnderline,
+            5 => CursorShape::BlinkingBar,
+            6 => CursorShape::SteadyBar,
+            _ => return,
+        };
+    }
+
+    // CSI > u
+    pub(crate) fn push_keyboard_flags(&mut self, flags: u16) {
+        self.keyboard_modes_mut().push(keyboard_flags(flags));
+    }
+
+    // CSI < u
+    pub(crate) fn pop_keyboard_flags(&mut self, count: u16) {
+        self.keyboard_modes_mut().pop(count.into());
+    }
+
+    // CSI = u
+    pub(crate) fn set_keyboard_flags(&mut self, (flags, mode): (u16, u16)) {
+        self.keyboard_modes_mut().set(keyboard_flags(flags), mode);
+    }
+}
+
+/// Trims scrollback until the screens together hold at most `budget`
+/// bytes of it. The screen with the most history gives up its oldest rows
+/// first, so short histories survive a single noisy screen. Returns the
+/// number of rows discarded.
+pub fn trim_scrollback_to_budget<'a>(
+    screens: impl IntoIterator<Item = &'a mut Screen>,
+    budget: usize,
+) -> usize {
+    let mut screens: Vec<&mut Screen> = screens.into_iter().collect();
+    let mut total: usize = screens.iter().map(|s| s.scrollback_bytes()).sum();
+    let mut dropped = 0;
+    while total > budget {
+        let Some(screen) =
+            screens.iter_mut().max_by_key(|s| s.scrollback_bytes())
+        else {
+            break;
+        };
+        let before = screen.scrollback_bytes();
+        let rows = screen.scrollback_rows();
+        if rows == 0 {
+            break;
+        }
+        // Drop roughly enough rows to cover the excess in one pass
+        let per_row = (before / rows).max(1);
+        let count = (total - budget).div_ceil(per_row).clamp(1, rows);
+        dropped += screen.trim_scrollback(count);
+        total -= before - screen.scrollback_bytes();
+    }
+    dropped
+}
+
+/// Keeps only the flags defined by the kitty keyboard protocol.
+fn keyboard_flags(flags: u16) -> u8 {
+    (flags & 0b1_1111) as u8
+}
+
+// the reply a kitty graphics command asks for, going by its `i` and `q`
+// keys
+fn kitty_reply(
+    command: &crate::image::KittyCommand,
+    error: Option<&'static str>,
+) -> Option<(u32, Option<&'static str>)> {
+    let id = command.num(b'i').unwrap_or(0);
+    let quiet = command.num(b'q').unwrap_or(0);
+    let wanted = match quiet {
+        0 => true,
+        1 => error.is_some(),
+        _ => false,
+    };
+    (id != 0 && wanted).then_some((id, error))
+}
+
+// writes a kitty graphics command in chunks small enough for any parser
+fn write_kitty_image(contents: &mut Vec<u8>, keys: &str, data: &[u8]) {
+    const CHUNK_BYTES: usize = 4096;
+    let chunks = data.chunks(CHUNK_BYTES).count().max(1);
+    let mut chunks_left = data.chunks(CHUNK_BYTES);
+    for i in 0..chunks {
+        contents.extend_from_slice(b"\x1b_G");
+        if i == 0 {
+            contents.extend_from_slice(keys.as_bytes());
+            if chunks > 1 {
+                contents.extend_from_slice(b",m=1");
+            }
+        } else {
+            contents.extend_from_slice(if i + 1 < chunks {
+                b"m=1"
+            } else {
+                b"m=0"
+            });
+        }
+        contents.push(b';');
+        contents.extend_from_slice(chunks_left.next().unwrap_or_default());
+        contents.extend_from_slice(b"\x1b\\");
+    }
+}
+
+// images can only be sent inline, since the outer terminal may not be able
+// to read the program's files
+fn kitty_medium_error(
+    command: &crate::image::KittyCommand,
+) -> Option<&'static str> {
+    match command.get(b't') {
+        None | Some("d") => None,
+        Some(_) => Some("EINVAL:only direct transmission is supported"),
+    }
 }
 
 #[cfg(test)]
@@ -1510,6 +2229,42 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_state_formatted_with_scrollback_round_trip() {
+        let mut p = crate::Parser::new(4, 10, 100);
+        // A line wrapping into the next row, and one indented by a move
+        p.process(b"abcdefghijklmno\r\n\x1b[4Cend\r\n");
+        for i in 0..12 {
+            p.process(format!("\x1b[3{}mline {i}\r\n", i % 8).as_bytes());
+        }
+        assert!(p.screen().history_row_wrapped(0));
+
+        let mut p2 = crate::Parser::new(4, 10, 100);
+        p2.process(&p.screen().state_formatted_with_scrollback());
+
+        let (s1, s2) = (p.screen(), p2.screen());
+        assert_eq!(s1.history_len(), s2.history_len());
+        for row in 0..s1.history_len() + 4 {
+            assert_eq!(
+                s1.history_row_wrapped(row),
+                s2.history_row_wrapped(row),
+                "wrap of row {row}"
+            );
+            for col in 0..10 {
+                let (c1, c2) =
+                    (s1.history_cell(row, col), s2.history_cell(row, col));
+                let (c1, c2) = (c1.unwrap(), c2.unwrap());
+                assert_eq!(
+                    c1.contents(),
+                    c2.contents(),
+                    "row {row} col {col}"
+                );
+                assert_eq!(c1.fgcolor(), c2.fgcolor(), "row {row} col {col}");
+            }
+        }
+        assert_eq!(s1.cursor_position(), s2.cursor_position());
+    }
+
     #[test]
     fn test_state_formatted_round_trip_main_screen() {
         let mut p = parser(24, 80);
@@ -1674,6 +2429,178 @@ mod tests {
         assert_eq!(client2.screen().scrollback(), 0);
     }
 
+    #[test]
+    fn test_history_cell_reads_scrollback_and_screen() {
+        let mut p = crate::Parser::new(3, 10, 10);
+        p.process(b"one\r\ntwo\r\nthree\r\nfour\r\nfive");
+        let screen = p.screen();
+        assert_eq!(screen.history_len(), 2);
+        assert_eq!(screen.history_cell(0, 0).unwrap().contents(), "o");
+        assert_eq!(screen.history_cell(1, 0).unwrap().contents(), "t");
+        assert_eq!(screen.history_cell(2, 0).unwrap().contents(), "t");
+        assert_eq!(screen.history_cell(4, 0).unwrap().contents(), "f");
+        assert!(screen.history_cell(5, 0).is_none());
+
+        // Independent of the scrollback position
+        p.screen_mut().set_scrollback(2);
+        assert_eq!(p.screen().history_cell(4, 1).unwrap().contents(), "i");
+    }
+
+    #[test]
+    fn test_scrollback_rows_are_compacted() {
+        let mut p = crate::Parser::new(3, 200, 10);
+        p.process(b"ab\r\n\r\n\r\n\r\n");
+        let screen = p.screen();
+        assert_eq!(screen.history_len(), 2);
+        // Trailing blanks are still readable up to the row width
+        assert_eq!(screen.history_cell(0, 1).unwrap().contents(), "b");
+        assert_eq!(screen.history_cell(0, 199).unwrap().contents(), "");
+        assert!(screen.history_cell(0, 200).is_none());
+        assert!(
+            screen.scrollback_bytes()
+                < 200 * std::mem::size_of::<crate::Cell>()
+        );
+
+        // Scrolled-back rendering still covers the full width
+        let prev = p.screen().clone();
+        p.screen_mut().set_scrollback(2);
+        assert_eq!(p.screen().contents(), "ab");
+        assert!(!p.screen().contents_formatted().is_empty());
+        assert!(!p.screen().contents_diff(&prev).is_empty());
+    }
+
+    #[test]
+    fn test_scrollback_limit_and_trim() {
+        let mut p = crate::Parser::new(2, 10, 10);
+        for i in 0..8 {
+            p.process(format!("line{i}\r\n").as_bytes());
+        }
+        assert_eq!(p.screen().history_len(), 7);
+        let bytes = p.screen().scrollback_bytes();
+
+        p.screen_mut().set_scrollback_limit(4);
+        assert_eq!(p.screen().scrollback_limit(), 4);
+        assert_eq!(p.screen().history_len(), 4);
+        assert_eq!(p.screen().history_cell(0, 4).unwrap().contents(), "3");
+        assert!(p.screen().scrollback_bytes() < bytes);
+
+        assert_eq!(p.screen_mut().trim_scrollback(3), 3);
+        assert_eq!(p.screen().history_cell(0, 4).unwrap().contents(), "6");
+        // Trimming applies to the primary screen's scrollback even while
+        // the alternate screen is active
+        p.process(b"\x1b[?1049h");
+        assert_eq!(p.screen().scrollback_rows(), 1);
+        assert_eq!(p.screen_mut().trim_scrollback(3), 1);
+        p.process(b"\x1b[?1049l");
+        assert_eq!(p.screen().history_len(), 0);
+        assert_eq!(p.screen().scrollback_bytes(), 0);
+    }
+
+    #[test]
+    fn test_trim_scrollback_to_budget() {
+        let mut small = crate::Parser::new(2, 10, 1000);
+        let mut big = crate::Parser::new(2, 10, 1000);
+        for i in 0..5 {
+            small.process(format!("s{i}\r\n").as_bytes());
+        }
+        for i in 0..500 {
+            big.process(format!("b{i}\r\n").as_bytes());
+        }
+        let budget = small.screen().scrollback_bytes() * 10;
+        let screens = [small.screen_mut(), big.screen_mut()];
+        assert!(super::trim_scrollback_to_budget(screens, budget) > 0);
+        assert_eq!(small.screen().scrollback_rows(), 4);
+        assert!(
+            small.screen().scrollback_bytes()
+                + big.screen().scrollback_bytes()
+                <= budget
+        );
+        let screens = [small.screen_mut(), big.screen_mut()];
+        assert_eq!(super::trim_scrollback_to_budget(screens, budget), 0);
+    }
+
+    fn screen_rows(p: &crate::Parser) -> Vec<String> {
+        let (_, cols) = p.screen().size();
+        p.screen().rows(0, cols).collect()
+    }
+
+    #[test]
+    fn test_resize_reflows_wrapped_lines() {
+        let mut p = parser(5, 10);
+        p.process(b"abcdefghij12345\r\nxyz");
+        assert_eq!(screen_rows(&p), ["abcdefghij", "12345", "xyz", "", ""]);
+
+        p.screen_mut().set_size(5, 5);
+        assert_eq!(screen_rows(&p), ["abcde", "fghij", "12345", "xyz", ""]);
+        assert!(p.screen().row_wrapped(0));
+        assert!(p.screen().row_wrapped(1));
+        assert!(!p.screen().row_wrapped(2));
+        assert_eq!(p.screen().cursor_position(), (3, 3));
+
+        p.screen_mut().set_size(5, 20);
+        assert_eq!(screen_rows(&p), ["abcdefghij12345", "xyz", "", "", ""]);
+        assert_eq!(p.screen().cursor_position(), (1, 3));
+        assert_eq!(p.screen().contents(), "abcdefghij12345\nxyz");
+    }
+
+    #[test]
+    fn test_resize_reflow_keeps_cursor_on_character() {
+        let mut p = parser(3, 20);
+        p.process(b"$ hello world\x1b[5D");
+        assert_eq!(p.screen().cursor_position(), (0, 8));
+
+        p.screen_mut().set_size(3, 5);
+        assert_eq!(screen_rows(&p), ["$ hel", "lo wo", "rld"]);
+        assert_eq!(p.screen().cursor_position(), (1, 3));
+
+        // Typing continues at the same place in the line
+        p.process(b"W");
+        p.screen_mut().set_size(3, 20);
+        assert_eq!(screen_rows(&p)[0], "$ hello World");
+    }
+
+    #[test]
+    fn test_resize_reflow_moves_lines_through_scrollback() {
+        let mut p = crate::Parser::new(2, 6, 100);
+        p.process(b"aaaaaabbbbbb\r\ncc");
+        assert_eq!(p.screen().history_len(), 1);
+
+        // Narrower: more rows than fit, the top goes into the scrollback
+        p.screen_mut().set_size(2, 3);
+        assert_eq!(screen_rows(&p), ["bbb", "cc"]);
+        assert_eq!(p.screen().history_len(), 3);
+        assert!(p.screen().history_row_wrapped(0));
+        assert_eq!(p.screen().cursor_position(), (1, 2));
+
+        // Wider: lines come back out of the scrollback
+        p.screen_mut().set_size(2, 12);
+        assert_eq!(screen_rows(&p), ["aaaaaabbbbbb", "cc"]);
+        assert_eq!(p.screen().history_len(), 0);
+    }
+
+    #[test]
+    fn test_resize_reflow_wide_chars() {
+        let mut p = parser(3, 6);
+        p.process("ab\u{4f60}\u{597d}".as_bytes());
+        p.screen_mut().set_size(3, 3);
+        // The second wide character doesn't fit after "ab" + one cell
+        assert_eq!(screen_rows(&p), ["ab", "\u{4f60}", "\u{597d}"]);
+        assert!(p.screen().row_wrapped(0));
+
+        p.screen_mut().set_size(3, 6);
+        assert_eq!(screen_rows(&p)[0], "ab\u{4f60}\u{597d}");
+    }
+
+    #[test]
+    fn test_resize_reflows_main_screen_under_alternate() {
+        let mut p = parser(3, 10);
+        p.process(b"abcdefghij12\x1b[?1049hALT");
+        p.screen_mut().set_size(3, 6);
+        assert_eq!(screen_rows(&p), ["ALT", "", ""]);
+        p.process(b"\x1b[?1049l");
+        assert_eq!(screen_rows(&p), ["abcdef", "ghij12", ""]);
+    }
+
     #[test]
     fn test_vim_like_alt_screen_with_scroll_region() {
         let mut p = parser(10, 20);
@@ -1694,6 +2621,399 @@ mod tests {
         assert_eq!(p.screen().cell(1, 0).unwrap().contents(), "l");
         assert_eq!(p.screen().cell(2, 0).unwrap().contents(), "l");
     }
+
+    #[test]
+    fn test_focus_reporting_mode() {
+        let mut p = parser(24, 80);
+        assert!(!p.screen().focus_reporting());
+        p.process(b"\x1b[?1004h");
+        assert!(p.screen().focus_reporting());
+        p.process(b"\x1b[?1004l");
+        assert!(!p.screen().focus_reporting());
+    }
+
+    #[test]
+    fn test_synchronized_output_mode() {
+        let mut p = parser(24, 80);
+        p.process(b"\x1b[?2026h");
+        assert!(p.screen().synchronized_output());
+        p.process(b"frame\x1b[?2026l");
+        assert!(!p.screen().synchronized_output());
+    }
+
+    // 20x12 pixels: 2 columns and a row at the default cell size
+    const SIXEL: &[u8] = b"\x1bPq#0;2;0;0;0#0~~-!20~\x1b\\";
+
+    #[test]
+    fn test_sixel_image_is_placed_at_cursor() {
+        let mut p = parser(24, 80);
+        p.process(b"\x1b[3;5H");
+        p.process(SIXEL);
+        let images = p.screen().images();
+        assert_eq!(images.len(), 1);
+        assert_eq!((images[0].row, images[0].col), (2, 4));
+        assert_eq!(images[0].image.protocol(), crate::ImageProtocol::Sixel);
+        assert_eq!(images[0].image.size(), (1, 2));
+        assert_eq!(images[0].image.pixel_size(), Some((20, 12)));
+        assert_eq!(
+            images[0].image.data(),
+            b"\x1bP0q#0;2;0;0;0#0~~-!20~\x1b\\"
+        );
+        // the cursor moves below the image
+        assert_eq!(p.screen().cursor_position(), (3, 4));
+    }
+
+    #[test]
+    fn test_images_scroll_with_text() {
+        let mut p = crate::Parser::new(5, 80, 10);
+        p.process(SIXEL);
+        p.process(b"\r\n\r\n\r\n\r\n\r\n");
+        // the top of the image is one line into the scrollback
+        let images = p.screen().images();
+        assert_eq!(images.len(), 0);
+        p.screen_mut().set_scrollback(2);
+        assert_eq!(p.screen().images()[0].row, 0);
+
+        // the anchor of a partly visible image is kept in the scrollback
+        let mut p = crate::Parser::new(5, 80, 10);
+        p.process(b"\x1b_Ga=T,f=24,s=10,v=60,C=1;AAAA\x1b\\");
+        p.process(b"\x1b[5H\n\n");
+        // two lines of the three row image are left
+        assert_eq!(p.screen().images()[0].row, -2);
+        p.process(b"\n");
+        assert!(p.screen().images().is_empty());
+    }
+
+    #[test]
+    fn test_images_are_cleared_with_the_screen() {
+        let mut p = parser(24, 80);
+        p.process(SIXEL);
+        assert_eq!(p.screen().images().len(), 1);
+        let image = Arc::downgrade(
+            &p.screen().grid().visible_rows().next().unwrap().images()[0]
+                .image,
+        );
+        p.process(b"\x1b[2J");
+        assert!(p.screen().images().is_empty());
+        // nothing else holds on to the image
+        assert_eq!(image.strong_count(), 0);
+    }
+
+    #[test]
+    fn test_images_follow_reflow() {
+        let mut p = parser(24, 80);
+        p.process(b"\x1b[1;61H");
+        p.process(SIXEL);
+        p.screen_mut().set_size(24, 40);
+        let images = p.screen().images();
+        assert_eq!((images[0].row, images[0].col), (0, 20));
+    }
+
+    #[test]
+    fn test_kitty_image_in_chunks() {
+        let mut p = parser(24, 80);
+        p.process(b"\x1b_Ga=T,f=24,s=30,v=40,m=1;AAAA\x1b\\");
+        assert!(p.screen().images().is_empty());
+        // a chunk can be split across reads too
+        p.process(b"\x1b_Gm=0;BB");
+        p.process(b"BB\x1b");
+        p.process(b"\\");
+        let images = p.screen().images();
+        assert_eq!(images.len(), 1);
+        assert_eq!(images[0].image.protocol(), crate::ImageProtocol::Kitty);
+        assert_eq!(images[0].image.data(), b"AAAABBBB");
+        assert_eq!(images[0].image.format(), "f=24,s=30,v=40");
+        assert_eq!(images[0].image.size(), (2, 3));
+        // the cursor moves past the bottom right corner
+        assert_eq!(p.screen().cursor_position(), (1, 3));
+    }
+
+    #[test]
+    fn test_kitty_place_and_delete() {
+        let mut p = parser(24, 80);
+        p.process(b"\x1b_Ga=t,i=3,f=24,s=10,v=20;AAAA\x1b\\");
+        assert!(p.screen().images().is_empty());
+        p.process(b"\x1b_Ga=p,i=3,c=4,r=2,C=1\x1b\\");
+        p.process(b"\x1b[5H\x1b_Ga=p,i=3,C=1\x1b\\");
+        let images = p.screen().images();
+        assert_eq!(images.len(), 2);
+        assert_eq!(images[0].image.size(), (2, 4));
+        assert_eq!(images[1].image.size(), (1, 1));
+        assert_eq!(images[1].row, 4);
+        assert_eq!(p.screen().cursor_position(), (4, 0));
+
+        p.process(b"\x1b_Ga=d,d=i,i=3\x1b\\");
+        assert!(p.screen().images().is_empty());
+        // the image data is kept unless deleted with d=I
+        p.process(b"\x1b_Ga=p,i=3\x1b\\");
+        assert_eq!(p.screen().images().len(), 1);
+        p.process(b"\x1b_Ga=d,d=I,i=3\x1b\\\x1b_Ga=p,i=3\x1b\\");
+        assert!(p.screen().images().is_empty());
+    }
+
+    #[test]
+    fn test_apc_does_not_reach_the_screen() {
+        let mut p = parser(24, 80);
+        p.process(b"a\x1b_Gi=1;AAAA\x1b\\b\x1b_other\x1b\\c");
+        assert_eq!(p.screen().contents(), "abc");
+        // an unterminated APC is cancelled by the next escape sequence
+        p.process(b"\x1b_Gi=1\x1b[31md");
+        assert_eq!(p.screen().contents(), "abcd");
+        assert_eq!(
+            p.screen().cell(0, 3).unwrap().fgcolor(),
+            crate::Color::Idx(1)
+        );
+    }
+
+    #[test]
+    fn test_images_round_trip_through_state() {
+        let mut p = parser(24, 80);
+        let data = "AAAA".repeat(2500);
+        p.process(
+            format!("\x1b_Ga=t,i=5,f=24,s=10,v=20;{data}\x1b\\").as_bytes(),
+        );
+        p.process(b"\x1b_Ga=p,i=5,c=4,r=2\x1b\\");
+        p.process(b"\x1b[4;3H");
+        p.process(SIXEL);
+        p.process(b"text");
+
+        let mut copy = parser(24, 80);
+        copy.process(&p.screen().state_formatted());
+        let placed = |p: &crate::Parser| {
+            p.screen()
+                .images()
+                .iter()
+                .map(|i| (i.row, i.col, i.image.clone()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(placed(&copy), placed(&p));
+        assert_eq!(copy.screen().cursor_position(), (4, 6));
+        assert_eq!(copy.screen().contents(), p.screen().contents());
+        // the stored image can be placed again
+        copy.process(b"\x1b_Ga=p,i=5,C=1\x1b\\");
+        assert_eq!(copy.screen().images().len(), 3);
+    }
+
+    #[test]
+    fn test_apc_split_after_esc() {
+        let mut p = parser(24, 80);
+        p.process(b"a\x1b");
+        p.process(b"_Gi=1;AAAA\x1b\\b\x1b");
+        p.process(b"[31mc");
+        assert_eq!(p.screen().contents(), "abc");
+        assert_eq!(
+            p.screen().cell(0, 2).unwrap().fgcolor(),
+            crate::Color::Idx(1)
+        );
+    }
+
+    #[test]
+    fn test_oversized_apc_is_dropped() {
+        let mut p = parser(24, 80);
+        let mut apc = b"\x1b_Ga=T,f=24,s=10,v=20;".to_vec();
+        apc.resize(apc.len() + 2 * 1024 * 1024, b'A');
+        apc.extend_from_slice(b"\x1b\\x");
+        p.process(&apc);
+        assert!(p.screen().images().is_empty());
+        assert_eq!(p.screen().contents(), "x");
+    }
+
+    #[test]
+    fn test_kitty_store_forgets_oldest() {
+        let mut p = parser(24, 80);
+        for id in (1..=MAX_KITTY_IMAGES + 1).rev() {
+            p.process(
+                format!("\x1b_Ga=t,i={id},f=24,s=1,v=1;AAAA\x1b\\")
+                    .as_bytes(),
+            );
+        }
+        // the first one sent had the highest id
+        let sent_first = MAX_KITTY_IMAGES + 1;
+        p.process(format!("\x1b_Ga=p,i={sent_first},C=1\x1b\\").as_bytes());
+        assert!(p.screen().images().is_empty());
+        p.process(b"\x1b_Ga=p,i=1,C=1\x1b\\");
+        assert_eq!(p.screen().images().len(), 1);
+    }
+
+    #[test]
+    fn test_cursor_shape() {
+        let mut p = parser(24, 80);
+        assert_eq!(p.screen().cursor_shape(), CursorShape::Default);
+        p.process(b"\x1b[6 q");
+        assert_eq!(p.screen().cursor_shape(), CursorShape::SteadyBar);
+        p.process(b"\x1b[1 q");
+        assert_eq!(p.screen().cursor_shape(), CursorShape::BlinkingBlock);
+        // Unknown shapes are ignored
+        p.process(b"\x1b[9 q");
+        assert_eq!(p.screen().cursor_shape(), CursorShape::BlinkingBlock);
+        p.process(b"\x1b[ q");
+        assert_eq!(p.screen().cursor_shape(), CursorShape::Default);
+    }
+
+    #[test]
+    fn test_cursor_color() {
+        let mut p = parser(24, 80);
+        p.process(b"\x1b]12;#ff8800\x07");
+        assert_eq!(p.screen().cursor_color(), Some("#ff8800"));
+        // Queries don't change the color
+        p.process(b"\x1b]12;?\x07");
+        assert_eq!(p.screen().cursor_color(), Some("#ff8800"));
+        p.process(b"\x1b]112\x07");
+        assert_eq!(p.screen().cursor_color(), None);
+    }
+
+    #[test]
+    fn test_cursor_style_in_state_formatted() {
+        let mut p = parser(24, 80);
+        let prev = p.screen().clone();
+        p.process(b"\x1b[5 q\x1b]12;red\x1b\\");
+        let state = p.screen().state_formatted();
+        assert!(state.ends_with(b"\x1b[5 q\x1b]12;red\x1b\\"));
+        assert_eq!(
+            p.screen().state_diff(&prev),
+            b"\x1b[5 q\x1b]12;red\x1b\\"
+        );
+
+        let mut copy = parser(24, 80);
+        copy.process(&state);
+        assert_eq!(copy.screen().cursor_shape(), CursorShape::BlinkingBar);
+        assert_eq!(copy.screen().cursor_color(), Some("red"));
+    }
+
+    #[test]
+    fn test_keyboard_flags_push_pop() {
+        let mut p = parser(24, 80);
+        assert_eq!(p.screen().keyboard_flags(), 0);
+        p.process(b"\x1b[>1u");
+        assert_eq!(p.screen().keyboard_flags(), 1);
+        p.process(b"\x1b[>11u");
+        assert_eq!(p.screen().keyboard_flags(), 11);
+        p.process(b"\x1b[<u");
+        assert_eq!(p.screen().keyboard_flags(), 1);
+        // Popping more entries than were pushed resets the flags
+        p.process(b"\x1b[>3u\x1b[<5u");
+        assert_eq!(p.screen().keyboard_flags(), 0);
+    }
+
+    #[test]
+    fn test_keyboard_flags_set_modes() {
+        let mut p = parser(24, 80);
+        p.process(b"\x1b[=5u");
+        assert_eq!(p.screen().keyboard_flags(), 5);
+        p.process(b"\x1b[=2;2u");
+        assert_eq!(p.screen().keyboard_flags(), 7);
+        p.process(b"\x1b[=4;3u");
+        assert_eq!(p.screen().keyboard_flags(), 3);
+        // Undefined bits are dropped
+        p.process(b"\x1b[=255u");
+        assert_eq!(p.screen().keyboard_flags(), 31);
+    }
+
+    #[test]
+    fn test_keyboard_flags_per_screen() {
+        let mut p = parser(24, 80);
+        p.process(b"\x1b[>1u");
+        p.process(b"\x1b[?1049h");
+        assert_eq!(p.screen().keyboard_flags(), 0);
+        p.process(b"\x1b[>15u");
+        assert_eq!(p.screen().keyboard_flags(), 15);
+        p.process(b"\x1b[?1049l");
+        assert_eq!(p.screen().keyboard_flags(), 1);
+    }
+
+    #[test]
+    fn test_keyboard_flags_stack_is_bounded() {
+        let mut p = parser(24, 80);
+        for flags in 1..=20 {
+            p.process(format!("\x1b[>{flags}u").as_bytes());
+        }
+        p.process(b"\x1b[<16u");
+        assert_eq!(p.screen().keyboard_flags(), 4);
+        p.process(b"\x1b[<u");
+        assert_eq!(p.screen().keyboard_flags(), 0);
+    }
+
+    #[test]
+    fn test_grapheme_clusters_share_a_cell() {
+        let mut p = parser(24, 80);
+        // thumbs up with a skin tone, a family ZWJ sequence and a flag
+        p.process("👍🏽|👨‍👩‍👧‍👦|🇯🇵|".as_bytes());
+        let screen = p.screen();
+        assert_eq!(screen.cell(0, 0).unwrap().contents(), "👍🏽");
+        assert!(screen.cell(0, 0).unwrap().is_wide());
+        assert!(screen.cell(0, 1).unwrap().is_wide_continuation());
+        assert_eq!(screen.cell(0, 2).unwrap().contents(), "|");
+        assert_eq!(screen.cell(0, 3).unwrap().contents(), "👨‍👩‍👧‍👦");
+        assert_eq!(screen.cell(0, 5).unwrap().contents(), "|");
+        assert_eq!(screen.cell(0, 6).unwrap().contents(), "🇯🇵");
+        assert_eq!(screen.cell(0, 8).unwrap().contents(), "|");
+        assert_eq!(screen.cursor_position(), (0, 9));
+        assert_eq!(screen.contents(), "👍🏽|👨‍👩‍👧‍👦|🇯🇵|");
+    }
+
+    #[test]
+    fn test_long_grapheme_clusters() {
+        struct Dropped(usize);
+        impl crate::callbacks::Callbacks for Dropped {
+            fn unhandled_char(&mut self, _: &mut Screen, _: char) {
+                self.0 += 1;
+            }
+        }
+
+        let mut p = crate::Parser::new_with_callbacks(24, 80, 0, Dropped(0));
+        p.process("👨‍👩‍👧‍👦👨‍👩‍👧‍👦".as_bytes());
+        let screen = p.screen();
+        assert_eq!(screen.cell(0, 0), screen.cell(0, 2));
+        assert_eq!(screen.cell(0, 2).unwrap().contents(), "👨‍👩‍👧‍👦");
+
+        // a cluster stops growing at MAX_CLUSTER_BYTES, and the rest is
+        // reported
+        p.process(format!("\re{}x", "\u{301}".repeat(200)).as_bytes());
+        let contents = p.screen().cell(0, 0).unwrap().contents();
+        assert_eq!(contents.len(), 255);
+        assert_eq!(p.callbacks().0, 73);
+        assert_eq!(p.screen().cell(0, 1).unwrap().contents(), "x");
+
+        // each cell owns its long cluster, so any number of distinct ones
+        // are kept in full
+        for i in 0..2000 {
+            let cluster = format!("e{}", "\u{301}".repeat(12 + i % 100));
+            p.process(format!("\r{cluster}").as_bytes());
+            assert_eq!(p.screen().cell(0, 0).unwrap().contents(), cluster);
+        }
+        assert!(std::mem::size_of::<crate::Cell>() <= 40);
+    }
+
+    #[test]
+    fn test_variation_selector_widens_cell() {
+        let mut p = parser(24, 80);
+        // a heart is narrow until VS16 asks for emoji presentation
+        p.process("\u{2764}x\r\u{2764}\u{fe0f}x".as_bytes());
+        let screen = p.screen();
+        assert_eq!(screen.cell(0, 0).unwrap().contents(), "\u{2764}\u{fe0f}");
+        assert!(screen.cell(0, 0).unwrap().is_wide());
+        assert!(screen.cell(0, 1).unwrap().is_wide_continuation());
+        assert_eq!(screen.cell(0, 2).unwrap().contents(), "x");
+        assert_eq!(screen.cursor_position(), (0, 3));
+    }
+
+    #[test]
+    fn test_grapheme_mode_can_be_turned_off() {
+        let mut p = parser(24, 80);
+        assert!(p.screen().grapheme_clusters());
+        p.process(b"\x1b[?2027l");
+        assert!(!p.screen().grapheme_clusters());
+        p.process("🇯🇵".as_bytes());
+        assert_eq!(p.screen().cell(0, 0).unwrap().contents(), "🇯");
+        assert_eq!(p.screen().cursor_position(), (0, 2));
+
+        // the mode is carried over so a copy splits cells the same way
+        let formatted = p.screen().state_formatted();
+        let mut copy = parser(24, 80);
+        copy.process(&formatted);
+        assert_eq!(copy.screen().cell(0, 0).unwrap().contents(), "🇯");
+        assert!(!copy.screen().grapheme_clusters());
+    }
 }
 
 fn u16_to_u8(i: u16) -> Option<u8> {
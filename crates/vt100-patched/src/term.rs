@@ -557,6 +557,28 @@ impl BufWrite for BracketedPaste {
     }
 }
 
+#[derive(Default, Debug)]
+#[must_use = "this struct does nothing unless you call write_buf"]
+pub struct GraphemeClusters {
+    state: bool,
+}
+
+impl GraphemeClusters {
+    pub fn new(state: bool) -> Self {
+        Self { state }
+    }
+}
+
+impl BufWrite for GraphemeClusters {
+    fn write_buf(&self, buf: &mut Vec<u8>) {
+        if self.state {
+            buf.extend_from_slice(b"\x1b[?2027h");
+        } else {
+            buf.extend_from_slice(b"\x1b[?2027l");
+        }
+    }
+}
+
 #[derive(Default, Debug)]
 #[must_use = "this struct does nothing unless you call write_buf"]
 pub struct MouseProtocolMode {
@@ -657,3 +679,56 @@ fn extend_itoa<I: itoa::Integer>(buf: &mut Vec<u8>, i: I) {
     let mut itoa_buf = itoa::Buffer::new();
     buf.extend_from_slice(itoa_buf.format(i).as_bytes());
 }
+
+#[derive(Default, Debug)]
+#[must_use = "this struct does nothing unless you call write_buf"]
+pub struct CursorShape {
+    shape: crate::CursorShape,
+}
+
+impl CursorShape {
+    pub fn new(shape: crate::CursorShape) -> Self {
+        Self { shape }
+    }
+}
+
+impl BufWrite for CursorShape {
+    fn write_buf(&self, buf: &mut Vec<u8>) {
+        let ps: &[u8] = match self.shape {
+            crate::CursorShape::Default => b"0",
+            crate::CursorShape::BlinkingBlock => b"1",
+            crate::CursorShape::SteadyBlock => b"2",
+            crate::CursorShape::BlinkingUnderline => b"3",
+            crate::CursorShape::SteadyUnderline => b"4",
+            crate::CursorShape::BlinkingBar => b"5",
+            crate::CursorShape::SteadyBar => b"6",
+        };
+        buf.extend_from_slice(b"\x1b[");
+        buf.extend_from_slice(ps);
+        buf.extend_from_slice(b" q");
+    }
+}
+
+#[derive(Default, Debug)]
+#[must_use = "this struct does nothing unless you call write_buf"]
+pub struct CursorColor<'a> {
+    color: Option<&'a str>,
+}
+
+impl<'a> CursorColor<'a> {
+    pub fn new(color: Option<&'a str>) -> Self {
+        Self { color }
+    }
+}
+
+impl BufWrite for CursorColor<'_> {
+    fn write_buf(&self, buf: &mut Vec<u8>) {
+        if let Some(color) = self.color {
+            buf.extend_from_slice(b"\x1b]12;");
+            buf.extend_from_slice(color.as_bytes());
+            buf.extend_from_slice(b"\x1b\\");
+        } else {
+            buf.extend_from_slice(b"\x1b]112\x1b\\");
+        }
+    }
+}